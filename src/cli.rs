@@ -0,0 +1,245 @@
+//! 命令行参数解析
+//!
+//! 与 redis-server 一样使用 `--name value` 形式，便于沿用现有的启动脚本。解析结果通过
+//! [`CliConfig::env_overrides`] 映射到服务端读取的环境变量，命令行优先于已有的环境变量。
+//! 无法识别的参数或非法取值只记录警告，不会中止启动。
+
+/// 命令行解析结果
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CliConfig {
+    /// `--bind <addr>`：完整监听地址（`host:port`）或只有 host
+    pub bind: Option<String>,
+    /// `--port <port>`：只替换监听地址中的端口
+    pub port: Option<u16>,
+    /// `--maxmemory-bytes <value>`，写法与 `REDUST_MAXMEMORY_BYTES` 相同
+    pub maxmemory_bytes: Option<String>,
+    /// `--requirepass <password>`
+    pub requirepass: Option<String>,
+    /// `--dir <path>`：工作目录，相对路径的 RDB/AOF 文件都放在这里
+    pub dir: Option<String>,
+    /// `--dbfilename <name>`
+    pub dbfilename: Option<String>,
+    /// `--appendonly yes|no`
+    pub appendonly: Option<bool>,
+    /// `--unixsocket <path>`：目前只解析，不监听 Unix socket
+    pub unixsocket: Option<String>,
+    /// 第一个位置参数：配置文件路径（见 [`crate::config`]）
+    pub config_file: Option<String>,
+    /// 未知参数、缺少取值或取值非法时的警告信息
+    pub warnings: Vec<String>,
+}
+
+/// 解析命令行参数（不含程序名）
+pub fn parse_args<I>(args: I) -> CliConfig
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut config = CliConfig::default();
+    let mut iter = args.into_iter().peekable();
+    let mut first = true;
+
+    while let Some(arg) = iter.next() {
+        let is_first = std::mem::take(&mut first);
+        let Some(name) = arg.strip_prefix("--") else {
+            // 与 redis-server 一样，第一个位置参数是配置文件路径
+            if is_first {
+                config.config_file = Some(arg);
+            } else {
+                config
+                    .warnings
+                    .push(format!("ignoring unexpected argument '{}'", arg));
+            }
+            continue;
+        };
+        let name = name.to_ascii_lowercase();
+        // 取值不能以 `--` 开头，否则视为缺少取值，留给下一轮作为参数名解析
+        let value = match iter.peek() {
+            Some(v) if !v.starts_with("--") => iter.next(),
+            _ => None,
+        };
+        let Some(value) = value else {
+            config
+                .warnings
+                .push(format!("missing value for '--{}'", name));
+            continue;
+        };
+
+        match name.as_str() {
+            "bind" => config.bind = Some(value),
+            "port" => match value.parse::<u16>() {
+                Ok(port) => config.port = Some(port),
+                Err(_) => config.warnings.push(format!("invalid port '{}'", value)),
+            },
+            "maxmemory-bytes" => config.maxmemory_bytes = Some(value),
+            "requirepass" => config.requirepass = Some(value),
+            "dir" => config.dir = Some(value),
+            "dbfilename" => config.dbfilename = Some(value),
+            "appendonly" => match value.to_ascii_lowercase().as_str() {
+                "yes" => config.appendonly = Some(true),
+                "no" => config.appendonly = Some(false),
+                _ => config.warnings.push(format!(
+                    "invalid value for '--appendonly': '{}' (expected yes or no)",
+                    value
+                )),
+            },
+            "unixsocket" => config.unixsocket = Some(value),
+            _ => config
+                .warnings
+                .push(format!("ignoring unknown option '--{}'", name)),
+        }
+    }
+
+    config
+}
+
+impl CliConfig {
+    /// 最终的监听地址：`--bind` 覆盖 `base`（通常来自 `REDUST_ADDR`），`--port` 只替换端口；
+    /// `--bind` 只给出 host 时沿用 `base` 的端口
+    pub fn bind_addr(&self, base: &str) -> String {
+        let (base_host, base_port) = split_host_port(base);
+        let (host, port) = match &self.bind {
+            Some(bind) => match split_host_port(bind) {
+                (host, Some(port)) => (host, Some(port)),
+                (host, None) => (host, base_port),
+            },
+            None => (base_host, base_port),
+        };
+        let port = self
+            .port
+            .map(|p| p.to_string())
+            .or(port.map(str::to_string));
+        match port {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        }
+    }
+
+    /// 需要写入的环境变量（名称, 取值），服务端启动时从这些变量读取配置
+    pub fn env_overrides(&self) -> Vec<(&'static str, String)> {
+        let mut out = Vec::new();
+        if let Some(m) = &self.maxmemory_bytes {
+            out.push(("REDUST_MAXMEMORY_BYTES", m.clone()));
+        }
+        if let Some(pw) = &self.requirepass {
+            out.push(("REDUST_AUTH_PASSWORD", pw.clone()));
+        }
+        if let Some(name) = &self.dbfilename {
+            out.push(("REDUST_RDB_PATH", name.clone()));
+        }
+        if let Some(enabled) = self.appendonly {
+            out.push((
+                "REDUST_AOF_ENABLED",
+                if enabled { "yes" } else { "no" }.to_string(),
+            ));
+        }
+        out
+    }
+}
+
+/// 拆分 `host:port`；IPv6 地址需要写成 `[::1]:6379`，不带端口时返回 None
+fn split_host_port(addr: &str) -> (&str, Option<&str>) {
+    if let Some(rest) = addr.strip_prefix('[') {
+        return match rest.split_once(']') {
+            Some((_, tail)) => {
+                let host_len = addr.len() - tail.len();
+                (&addr[..host_len], tail.strip_prefix(':'))
+            }
+            None => (addr, None),
+        };
+    }
+    match addr.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') => (host, Some(port)),
+        _ => (addr, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_redis_server_style_flags() {
+        let config = parse_args(args(&[
+            "--port",
+            "7000",
+            "--requirepass",
+            "secret",
+            "--dir",
+            "/tmp/redust",
+            "--dbfilename",
+            "dump.rdb",
+            "--appendonly",
+            "yes",
+            "--unixsocket",
+            "/tmp/redust.sock",
+            "--maxmemory-bytes",
+            "10mb",
+            "--daemonize",
+            "no",
+        ]));
+        assert_eq!(config.port, Some(7000));
+        assert_eq!(config.dir.as_deref(), Some("/tmp/redust"));
+        assert_eq!(config.unixsocket.as_deref(), Some("/tmp/redust.sock"));
+        assert_eq!(config.bind_addr("127.0.0.1:6379"), "127.0.0.1:7000");
+        assert_eq!(
+            config.env_overrides(),
+            vec![
+                ("REDUST_MAXMEMORY_BYTES", "10mb".to_string()),
+                ("REDUST_AUTH_PASSWORD", "secret".to_string()),
+                ("REDUST_RDB_PATH", "dump.rdb".to_string()),
+                ("REDUST_AOF_ENABLED", "yes".to_string()),
+            ]
+        );
+        // 未知参数只产生警告，其取值被一并跳过
+        assert_eq!(
+            config.warnings,
+            vec!["ignoring unknown option '--daemonize'"]
+        );
+    }
+
+    #[test]
+    fn invalid_values_warn_without_aborting() {
+        let config = parse_args(args(&["--port", "abc", "--appendonly", "maybe", "--bind"]));
+        assert_eq!(config.port, None);
+        assert_eq!(config.appendonly, None);
+        assert_eq!(config.bind, None);
+        assert_eq!(config.warnings.len(), 3);
+    }
+
+    #[test]
+    fn bind_and_port_combine() {
+        let bind_only = parse_args(args(&["--bind", "0.0.0.0"]));
+        assert_eq!(bind_only.bind_addr("127.0.0.1:6379"), "0.0.0.0:6379");
+
+        let both = parse_args(args(&["--bind", "0.0.0.0:6380", "--port", "7001"]));
+        assert_eq!(both.bind_addr("127.0.0.1:6379"), "0.0.0.0:7001");
+
+        let ipv6 = parse_args(args(&["--bind", "[::1]", "--port", "7002"]));
+        assert_eq!(ipv6.bind_addr("127.0.0.1:6379"), "[::1]:7002");
+
+        assert_eq!(
+            CliConfig::default().bind_addr("127.0.0.1:6379"),
+            "127.0.0.1:6379"
+        );
+    }
+
+    #[test]
+    fn first_positional_argument_is_config_file() {
+        let config = parse_args(args(&["/etc/redust.conf", "--port", "7000", "extra"]));
+        assert_eq!(config.config_file.as_deref(), Some("/etc/redust.conf"));
+        assert_eq!(config.port, Some(7000));
+        assert_eq!(
+            config.warnings,
+            vec!["ignoring unexpected argument 'extra'"]
+        );
+
+        // 只有出现在最前面的位置参数才是配置文件
+        let late = parse_args(args(&["--port", "7000", "/etc/redust.conf"]));
+        assert_eq!(late.config_file, None);
+        assert_eq!(late.warnings.len(), 1);
+    }
+}
@@ -1,7 +1,9 @@
 use std::fmt;
 use tokio::io::{self, BufReader};
 
-use crate::resp::read_resp_array;
+use crate::command_table;
+use crate::resp::{read_resp_array, read_resp_array_with_limit, ClientReader};
+use crate::slowlog::summarize_args;
 
 pub type Binary = Vec<u8>;
 
@@ -11,6 +13,9 @@ pub enum CommandError {
     Io(io::Error),
     /// Represents a Redis-like command error, e.g., "ERR wrong number of arguments".
     RedisError(String),
+    /// RESP 帧格式错误（如非法的 multibulk 长度）：字节流已失去同步，
+    /// 与 Redis 一致，回复错误后必须关闭连接
+    Protocol(String),
 }
 
 impl From<io::Error> for CommandError {
@@ -24,6 +29,7 @@ impl fmt::Display for CommandError {
         match self {
             CommandError::Io(err) => write!(f, "IO Error: {}", err),
             CommandError::RedisError(msg) => write!(f, "{}", msg),
+            CommandError::Protocol(msg) => write!(f, "{}", msg),
         }
     }
 }
@@ -36,6 +42,8 @@ pub enum Command {
     Ping,
     PingWithPayload(Binary),
     Echo(Binary),
+    Time,
+    Lolwut,
     Quit,
     Set {
         key: String,
@@ -74,6 +82,15 @@ pub enum Command {
         key: String,
         value: Binary,
     },
+    Setbit {
+        key: String,
+        offset: u64,
+        bit: bool,
+    },
+    Getbit {
+        key: String,
+        offset: u64,
+    },
     Strlen {
         key: String,
     },
@@ -84,6 +101,10 @@ pub enum Command {
     Del {
         keys: Vec<String>,
     },
+    /// 与 DEL 相同；Redust 没有后台释放线程，内存同步回收
+    Unlink {
+        keys: Vec<String>,
+    },
     Exists {
         keys: Vec<String>,
     },
@@ -109,6 +130,8 @@ pub enum Command {
         cursor: u64,
         pattern: Option<String>,
         count: Option<u64>,
+        /// `TYPE <type>`，已转为小写
+        type_filter: Option<String>,
     },
     Sscan {
         key: String,
@@ -121,6 +144,8 @@ pub enum Command {
         cursor: u64,
         pattern: Option<String>,
         count: Option<u64>,
+        /// NOVALUES：只返回 field
+        novalues: bool,
     },
     Zscan {
         key: String,
@@ -131,17 +156,28 @@ pub enum Command {
     Type {
         key: String,
     },
+    ObjectIdletime {
+        key: String,
+    },
+    /// OBJECT ENCODING key：返回 value 的编码名；list 按当前元素数在 listpack/quicklist 间切换
+    ObjectEncoding {
+        key: String,
+    },
+    /// MEMORY USAGE key [SAMPLES count]：key 长度加上序列化后的 value 长度
+    MemoryUsage {
+        key: String,
+    },
     Keys {
         pattern: String,
     },
     Dbsize,
     Lpush {
         key: String,
-        values: Vec<String>,
+        values: Vec<Binary>,
     },
     Rpush {
         key: String,
-        values: Vec<String>,
+        values: Vec<Binary>,
     },
     Lrange {
         key: String,
@@ -164,7 +200,25 @@ pub enum Command {
     Lrem {
         key: String,
         count: isize,
-        value: String,
+        value: Binary,
+    },
+    /// LPOS key element [RANK rank] [COUNT num-matches] [MAXLEN len]
+    Lpos {
+        key: String,
+        element: Binary,
+        /// 非零；负数表示从表尾开始查找
+        rank: i64,
+        /// None 表示未指定 COUNT（回复单个整数），Some(0) 表示返回全部匹配
+        count: Option<i64>,
+        /// 最多比较的元素个数，0 表示不限
+        maxlen: i64,
+    },
+    /// LINSERT key BEFORE|AFTER pivot element
+    Linsert {
+        key: String,
+        before: bool,
+        pivot: Binary,
+        value: Binary,
     },
     Ltrim {
         key: String,
@@ -173,11 +227,11 @@ pub enum Command {
     },
     Sadd {
         key: String,
-        members: Vec<String>,
+        members: Vec<Binary>,
     },
     Srem {
         key: String,
-        members: Vec<String>,
+        members: Vec<Binary>,
     },
     Smembers {
         key: String,
@@ -187,7 +241,7 @@ pub enum Command {
     },
     Sismember {
         key: String,
-        member: String,
+        member: Binary,
     },
     Spop {
         key: String,
@@ -221,7 +275,7 @@ pub enum Command {
     Hset {
         key: String,
         field: String,
-        value: String,
+        value: Binary,
     },
     Hget {
         key: String,
@@ -278,10 +332,40 @@ pub enum Command {
     Persist {
         key: String,
     },
-    Info,
+    Info {
+        section: Option<String>,
+    },
+    /// ROLE：没有复制功能，始终报告为 master
+    Role,
+    /// REPLICAOF / SLAVEOF：只接受 `NO ONE`
+    Replicaof {
+        host: String,
+        port: String,
+    },
+    /// WAIT：没有副本可等待，立即返回 0
+    Wait {
+        numreplicas: i64,
+        timeout_ms: i64,
+    },
+    /// FAILOVER：没有副本，总是报错
+    Failover,
+    /// CLUSTER 子命令：未启用集群，只提供客户端探测用的只读桩
+    Cluster {
+        subcommand: String,
+        args: Vec<String>,
+    },
     Auth {
+        /// `AUTH <username> <password>` 形式中的用户名；单参数形式为 None
+        username: Option<String>,
         password: String,
     },
+    /// `HELLO [protover [AUTH username password] [SETNAME clientname]]`
+    Hello {
+        protover: Option<i64>,
+        /// (username, password)
+        auth: Option<(String, String)>,
+        setname: Option<String>,
+    },
     Select {
         db: u8,
     },
@@ -302,6 +386,14 @@ pub enum Command {
         key: String,
         newkey: String,
     },
+    /// `COPY source destination [DB destination-db] [REPLACE]`
+    Copy {
+        source: String,
+        destination: String,
+        /// 目标 DB；未指定时为当前 DB
+        db: Option<u8>,
+        replace: bool,
+    },
     Flushdb,
     Flushall,
     Setnx {
@@ -361,6 +453,10 @@ pub enum Command {
     Save,
     Bgsave,
     Lastsave,
+    /// SHUTDOWN [NOSAVE|SAVE]；None 表示按是否启用持久化决定
+    Shutdown {
+        save: Option<bool>,
+    },
     // 事务命令
     Multi,
     Exec,
@@ -369,9 +465,16 @@ pub enum Command {
         keys: Vec<String>,
     },
     Unwatch,
+    /// ZADD key [NX|XX] [GT|LT] [CH] [INCR] score member [score member ...]
     Zadd {
         key: String,
-        entries: Vec<(f64, String)>,
+        entries: Vec<(f64, Binary)>,
+        nx: bool,
+        xx: bool,
+        gt: bool,
+        lt: bool,
+        ch: bool,
+        incr: bool,
     },
     Zcard {
         key: String,
@@ -385,16 +488,16 @@ pub enum Command {
     },
     Zscore {
         key: String,
-        member: String,
+        member: Binary,
     },
     Zrem {
         key: String,
-        members: Vec<String>,
+        members: Vec<Binary>,
     },
     Zincrby {
         key: String,
         increment: f64,
-        member: String,
+        member: Binary,
     },
     // HyperLogLog 命令
     Pfadd {
@@ -408,16 +511,24 @@ pub enum Command {
         destkey: String,
         sourcekeys: Vec<String>,
     },
-    // Lua 脚本命令
+    PfdebugGetreg {
+        key: String,
+    },
+    PfdebugEncoding {
+        key: String,
+    },
+    // Lua 脚本命令；read_only 对应 EVAL_RO / EVALSHA_RO
     Eval {
         script: String,
         keys: Vec<String>,
         args: Vec<String>,
+        read_only: bool,
     },
     Evalsha {
         sha1: String,
         keys: Vec<String>,
         args: Vec<String>,
+        read_only: bool,
     },
     ScriptLoad {
         script: String,
@@ -426,6 +537,23 @@ pub enum Command {
         sha1s: Vec<String>,
     },
     ScriptFlush,
+    ScriptKill,
+    // Redis Functions；read_only 对应 FCALL_RO
+    FunctionLoad {
+        code: String,
+        replace: bool,
+    },
+    FunctionList {
+        library_pattern: Option<String>,
+        with_code: bool,
+    },
+    FunctionFlush,
+    Fcall {
+        function: String,
+        keys: Vec<String>,
+        args: Vec<String>,
+        read_only: bool,
+    },
     // 运维命令
     ConfigGet {
         pattern: String,
@@ -435,6 +563,7 @@ pub enum Command {
         value: String,
     },
     ClientList,
+    ClientInfo,
     ClientId,
     ClientSetname {
         name: String,
@@ -442,14 +571,259 @@ pub enum Command {
     ClientGetname,
     SlowlogGet {
         count: Option<usize>,
+        /// Redust 扩展：WITHREPLYBYTES 时每条记录追加回复字节数
+        with_reply_bytes: bool,
     },
     SlowlogReset,
     SlowlogLen,
+    /// DEBUG SLEEP <seconds>：让当前连接休眠指定秒数（用于测试慢查询等）
+    DebugSleep {
+        seconds: f64,
+    },
+    /// DEBUG RELOAD：同步保存 RDB、清空内存后重新加载，用于验证持久化往返
+    DebugReload,
+    /// DEBUG SET-USED-MEMORY <bytes>：伪造内存用量，便于确定性地测试淘汰
+    DebugSetUsedMemory {
+        bytes: u64,
+    },
+    /// DEBUG OBJECT <key>：返回 key 的 serializedlength、空闲时长等调试信息
+    DebugObject {
+        key: String,
+    },
+    /// DEBUG LISTPACK-ENTRIES <n>（别名 QUICKLIST-PACKED-THRESHOLD）：调整 list 从 listpack
+    /// 切换为 quicklist 的元素数阈值，便于测试编码转换
+    DebugListpackEntries {
+        entries: usize,
+    },
+    /// 不带参数的 COMMAND，返回完整命令表
+    CommandAll,
+    CommandCount,
+    CommandInfo {
+        names: Vec<String>,
+    },
+    /// ACL 子命令（仅支持单一 default 用户的只读查询）
+    Acl {
+        subcommand: String,
+        args: Vec<String>,
+    },
     Unknown(Vec<Binary>),
     /// Represents an error that should be sent back to the client.
     Error(String),
 }
 
+impl Command {
+    /// 读命令查找的 key（INFO keyspace_hits/keyspace_misses 统计用）；
+    /// 写命令以及不按 key 查找的命令返回空
+    pub fn lookup_keys(&self) -> Vec<&str> {
+        match self {
+            Command::Get { key }
+            | Command::Getdel { key }
+            | Command::Getex { key, .. }
+            | Command::Getrange { key, .. }
+            | Command::Getbit { key, .. }
+            | Command::Strlen { key }
+            | Command::Type { key }
+            | Command::Ttl { key }
+            | Command::Pttl { key }
+            | Command::Hget { key, .. }
+            | Command::Hmget { key, .. }
+            | Command::Hexists { key, .. }
+            | Command::Hgetall { key }
+            | Command::Hkeys { key }
+            | Command::Hvals { key }
+            | Command::Hlen { key }
+            | Command::Lrange { key, .. }
+            | Command::Llen { key }
+            | Command::Lindex { key, .. }
+            | Command::Lpos { key, .. }
+            | Command::Smembers { key }
+            | Command::Scard { key }
+            | Command::Sismember { key, .. }
+            | Command::Srandmember { key, .. }
+            | Command::Zrange { key, .. }
+            | Command::Zscore { key, .. }
+            | Command::Zcard { key } => vec![key.as_str()],
+            Command::Mget { keys }
+            | Command::Exists { keys }
+            | Command::Sunion { keys }
+            | Command::Sinter { keys }
+            | Command::Sdiff { keys }
+            | Command::Pfcount { keys } => keys.iter().map(String::as_str).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// 返回命令的小写名称（容器命令使用 `client|list` 形式），用于命令统计与 CLIENT INFO。
+    /// 未知命令与解析错误返回 `None`，不计入统计。
+    pub fn name(&self) -> Option<&'static str> {
+        let name = match self {
+            Command::Ping => "ping",
+            Command::PingWithPayload(_) => "ping",
+            Command::Echo(_) => "echo",
+            Command::Time => "time",
+            Command::Lolwut => "lolwut",
+            Command::Quit => "quit",
+            Command::Set { .. } => "set",
+            Command::Get { .. } => "get",
+            Command::Getdel { .. } => "getdel",
+            Command::Getex { .. } => "getex",
+            Command::Getrange { .. } => "getrange",
+            Command::Setrange { .. } => "setrange",
+            Command::Append { .. } => "append",
+            Command::Setbit { .. } => "setbit",
+            Command::Getbit { .. } => "getbit",
+            Command::Strlen { .. } => "strlen",
+            Command::Getset { .. } => "getset",
+            Command::Del { .. } => "del",
+            Command::Unlink { .. } => "unlink",
+            Command::Exists { .. } => "exists",
+            Command::Incr { .. } => "incr",
+            Command::Decr { .. } => "decr",
+            Command::Incrby { .. } => "incrby",
+            Command::Decrby { .. } => "decrby",
+            Command::Incrbyfloat { .. } => "incrbyfloat",
+            Command::Scan { .. } => "scan",
+            Command::Sscan { .. } => "sscan",
+            Command::Hscan { .. } => "hscan",
+            Command::Zscan { .. } => "zscan",
+            Command::Type { .. } => "type",
+            Command::ObjectIdletime { .. } => "object|idletime",
+            Command::ObjectEncoding { .. } => "object|encoding",
+            Command::MemoryUsage { .. } => "memory|usage",
+            Command::Keys { .. } => "keys",
+            Command::Dbsize => "dbsize",
+            Command::Lpush { .. } => "lpush",
+            Command::Rpush { .. } => "rpush",
+            Command::Lrange { .. } => "lrange",
+            Command::Lpop { .. } => "lpop",
+            Command::Rpop { .. } => "rpop",
+            Command::Llen { .. } => "llen",
+            Command::Lindex { .. } => "lindex",
+            Command::Lrem { .. } => "lrem",
+            Command::Lpos { .. } => "lpos",
+            Command::Linsert { .. } => "linsert",
+            Command::Ltrim { .. } => "ltrim",
+            Command::Sadd { .. } => "sadd",
+            Command::Srem { .. } => "srem",
+            Command::Smembers { .. } => "smembers",
+            Command::Scard { .. } => "scard",
+            Command::Sismember { .. } => "sismember",
+            Command::Spop { .. } => "spop",
+            Command::Srandmember { .. } => "srandmember",
+            Command::Sunion { .. } => "sunion",
+            Command::Sinter { .. } => "sinter",
+            Command::Sdiff { .. } => "sdiff",
+            Command::Sunionstore { .. } => "sunionstore",
+            Command::Sinterstore { .. } => "sinterstore",
+            Command::Sdiffstore { .. } => "sdiffstore",
+            Command::Hset { .. } => "hset",
+            Command::Hget { .. } => "hget",
+            Command::Hdel { .. } => "hdel",
+            Command::Hexists { .. } => "hexists",
+            Command::Hgetall { .. } => "hgetall",
+            Command::Hkeys { .. } => "hkeys",
+            Command::Hvals { .. } => "hvals",
+            Command::Hmget { .. } => "hmget",
+            Command::Hincrby { .. } => "hincrby",
+            Command::Hincrbyfloat { .. } => "hincrbyfloat",
+            Command::Hlen { .. } => "hlen",
+            Command::Expire { .. } => "expire",
+            Command::Pexpire { .. } => "pexpire",
+            Command::Ttl { .. } => "ttl",
+            Command::Pttl { .. } => "pttl",
+            Command::Persist { .. } => "persist",
+            Command::Info { .. } => "info",
+            Command::Role => "role",
+            Command::Replicaof { .. } => "replicaof",
+            Command::Wait { .. } => "wait",
+            Command::Failover => "failover",
+            Command::Cluster { .. } => "cluster",
+            Command::Auth { .. } => "auth",
+            Command::Hello { .. } => "hello",
+            Command::Select { .. } => "select",
+            Command::Mget { .. } => "mget",
+            Command::Mset { .. } => "mset",
+            Command::Msetnx { .. } => "msetnx",
+            Command::Rename { .. } => "rename",
+            Command::Renamenx { .. } => "renamenx",
+            Command::Copy { .. } => "copy",
+            Command::Flushdb => "flushdb",
+            Command::Flushall => "flushall",
+            Command::Setnx { .. } => "setnx",
+            Command::Setex { .. } => "setex",
+            Command::Psetex { .. } => "psetex",
+            Command::Subscribe { .. } => "subscribe",
+            Command::Unsubscribe { .. } => "unsubscribe",
+            Command::Ssubscribe { .. } => "ssubscribe",
+            Command::Sunsubscribe { .. } => "sunsubscribe",
+            Command::Psubscribe { .. } => "psubscribe",
+            Command::Punsubscribe { .. } => "punsubscribe",
+            Command::Publish { .. } => "publish",
+            Command::Spublish { .. } => "spublish",
+            Command::PubsubChannels { .. } => "pubsub|channels",
+            Command::PubsubNumsub { .. } => "pubsub|numsub",
+            Command::PubsubNumpat => "pubsub|numpat",
+            Command::PubsubShardchannels { .. } => "pubsub|shardchannels",
+            Command::PubsubShardnumsub { .. } => "pubsub|shardnumsub",
+            Command::PubsubHelp => "pubsub|help",
+            Command::Save => "save",
+            Command::Shutdown { .. } => "shutdown",
+            Command::Bgsave => "bgsave",
+            Command::Lastsave => "lastsave",
+            Command::Multi => "multi",
+            Command::Exec => "exec",
+            Command::Discard => "discard",
+            Command::Watch { .. } => "watch",
+            Command::Unwatch => "unwatch",
+            Command::Zadd { .. } => "zadd",
+            Command::Zcard { .. } => "zcard",
+            Command::Zrange { rev: true, .. } => "zrevrange",
+            Command::Zrange { .. } => "zrange",
+            Command::Zscore { .. } => "zscore",
+            Command::Zrem { .. } => "zrem",
+            Command::Zincrby { .. } => "zincrby",
+            Command::Pfadd { .. } => "pfadd",
+            Command::Pfcount { .. } => "pfcount",
+            Command::Pfmerge { .. } => "pfmerge",
+            Command::PfdebugGetreg { .. } | Command::PfdebugEncoding { .. } => "pfdebug",
+            Command::Eval { read_only: true, .. } => "eval_ro",
+            Command::Eval { .. } => "eval",
+            Command::Evalsha { read_only: true, .. } => "evalsha_ro",
+            Command::Evalsha { .. } => "evalsha",
+            Command::ScriptLoad { .. } => "script|load",
+            Command::ScriptExists { .. } => "script|exists",
+            Command::ScriptFlush => "script|flush",
+            Command::ScriptKill => "script|kill",
+            Command::FunctionLoad { .. } => "function|load",
+            Command::FunctionList { .. } => "function|list",
+            Command::FunctionFlush => "function|flush",
+            Command::Fcall { read_only: true, .. } => "fcall_ro",
+            Command::Fcall { .. } => "fcall",
+            Command::ConfigGet { .. } => "config|get",
+            Command::ConfigSet { .. } => "config|set",
+            Command::ClientList => "client|list",
+            Command::ClientInfo => "client|info",
+            Command::ClientId => "client|id",
+            Command::ClientSetname { .. } => "client|setname",
+            Command::ClientGetname => "client|getname",
+            Command::SlowlogGet { .. } => "slowlog|get",
+            Command::SlowlogReset => "slowlog|reset",
+            Command::SlowlogLen => "slowlog|len",
+            Command::DebugSleep { .. }
+            | Command::DebugReload
+            | Command::DebugSetUsedMemory { .. }
+            | Command::DebugObject { .. }
+            | Command::DebugListpackEntries { .. } => "debug",
+            Command::CommandAll => "command",
+            Command::CommandInfo { .. } => "command|info",
+            Command::CommandCount => "command|count",
+            Command::Acl { .. } => "acl",
+            Command::Unknown(_) | Command::Error(_) => return None,
+        };
+        Some(name)
+    }
+}
+
 fn err_wrong_args(cmd: &str) -> Command {
     // Redis 错误消息中命令名通常是小写形式
     Command::Error(format!(
@@ -462,6 +836,12 @@ fn err_not_integer() -> Command {
     Command::Error("ERR value is not an integer or out of range".to_string())
 }
 
+/// SET/GETEX 的 EX/PX/EXAT/PXAT 以及 SETEX/PSETEX 的过期时间必须为正数；
+/// EXPIRE/PEXPIRE 不同，非正数表示立刻删除 key
+fn err_invalid_expire(cmd: &str) -> Command {
+    Command::Error(format!("ERR invalid expire time in '{}' command", cmd))
+}
+
 fn err_not_float() -> Command {
     Command::Error("ERR value is not a valid float".to_string())
 }
@@ -490,6 +870,31 @@ fn parse_i64_from_bulk(bytes: Vec<u8>) -> Result<i64, Command> {
     s.parse::<i64>().map_err(|_| err_not_integer())
 }
 
+/// SETBIT/GETBIT 的位偏移：与 Redis 一样限制在 512MB（2^32 位）以内
+fn parse_bit_offset(bytes: Vec<u8>) -> Result<u64, Command> {
+    std::str::from_utf8(&bytes)
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .filter(|v| *v < (1u64 << 32))
+        .ok_or_else(|| {
+            Command::Error("ERR bit offset is not an integer or out of range".to_string())
+        })
+}
+
+/// EVAL/EVALSHA/FCALL 的 numkeys：不能为负，也不能超过其后剩余的参数个数（与 Redis 报错一致）
+fn parse_eval_numkeys(bytes: Vec<u8>, remaining: usize) -> Result<usize, Command> {
+    let n = parse_i64_from_bulk(bytes)?;
+    if n < 0 {
+        return Err(Command::Error("ERR Number of keys can't be negative".to_string()));
+    }
+    if n as u64 > remaining as u64 {
+        return Err(Command::Error(
+            "ERR Number of keys can't be greater than number of args".to_string(),
+        ));
+    }
+    Ok(n as usize)
+}
+
 fn parse_isize_from_bulk(bytes: Vec<u8>) -> Result<isize, Command> {
     let s = parse_bulk_string(bytes)?;
     s.parse::<isize>().map_err(|_| err_not_integer())
@@ -504,13 +909,56 @@ fn parse_f64_from_bulk(bytes: Vec<u8>) -> Result<f64, Command> {
     Ok(v)
 }
 
+/// `read_resp_array` 以 `InvalidData` 表示帧格式错误，其余 IO 错误（含 EOF）原样返回
+fn map_read_error(err: io::Error) -> CommandError {
+    if err.kind() == io::ErrorKind::InvalidData {
+        CommandError::Protocol(format!("ERR Protocol error: {}", err))
+    } else {
+        CommandError::Io(err)
+    }
+}
+
 pub async fn read_command(
-    reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>,
+    reader: &mut BufReader<ClientReader>,
 ) -> Result<Option<Command>, CommandError> {
-    let Some(parts) = read_resp_array(reader).await? else {
+    let Some(parts) = read_resp_array(reader).await.map_err(map_read_error)? else {
         return Ok(None);
     };
+    parse_command(parts)
+}
+
+/// 读取并解析一条命令，同时返回按 SLOWLOG 规则截断后的原始参数；
+/// `max_args` 为单条命令允许的最大参数个数（含命令名）
+pub async fn read_command_with_args(
+    reader: &mut BufReader<ClientReader>,
+    max_args: usize,
+) -> Result<Option<(Command, Vec<Binary>)>, CommandError> {
+    let Some(parts) = read_resp_array_with_limit(reader, max_args)
+        .await
+        .map_err(map_read_error)?
+    else {
+        return Ok(None);
+    };
+    let args = summarize_args(&parts);
+    Ok(parse_command(parts)?.map(|cmd| (cmd, args)))
+}
 
+/// 按命令表中的 arity 做第一轮参数个数检查（含命令名本身）；
+/// 未登记的命令交给各自的解析分支处理
+fn arity_matches(name: &str, argc: usize) -> bool {
+    let Some(spec) = command_table::lookup(name) else {
+        return true;
+    };
+    let argc = argc as i64;
+    if spec.arity >= 0 {
+        argc == spec.arity
+    } else {
+        argc >= -spec.arity
+    }
+}
+
+fn parse_command(parts: Vec<Binary>) -> Result<Option<Command>, CommandError> {
+    let argc = parts.len();
     let mut iter = parts.into_iter();
     let Some(command_bytes) = iter.next() else {
         return Ok(None);
@@ -520,6 +968,9 @@ pub async fn read_command(
         Ok(s) => s.to_ascii_uppercase(),
         Err(_) => return Ok(Some(err_invalid_bulk())),
     };
+    if !arity_matches(&upper, argc) {
+        return Ok(Some(err_wrong_args(&upper)));
+    }
     let cmd = match upper.as_str() {
         "PING" => {
             if let Some(payload) = iter.next() {
@@ -540,6 +991,76 @@ pub async fn read_command(
             }
             Command::Echo(value)
         }
+        "TIME" => {
+            if iter.next().is_some() {
+                return Ok(Some(err_wrong_args("time")));
+            }
+            Command::Time
+        }
+        "ROLE" => Command::Role,
+        "REPLICAOF" | "SLAVEOF" => {
+            let name = upper.to_ascii_lowercase();
+            let (Some(host), Some(port), None) = (iter.next(), iter.next(), iter.next()) else {
+                return Ok(Some(err_wrong_args(&name)));
+            };
+            let host = match parse_bulk_string(host) {
+                Ok(s) => s,
+                Err(e) => return Ok(Some(e)),
+            };
+            let port = match parse_bulk_string(port) {
+                Ok(s) => s,
+                Err(e) => return Ok(Some(e)),
+            };
+            Command::Replicaof { host, port }
+        }
+        "WAIT" => {
+            let (Some(numreplicas), Some(timeout), None) = (iter.next(), iter.next(), iter.next())
+            else {
+                return Ok(Some(err_wrong_args("wait")));
+            };
+            let numreplicas = match parse_i64_from_bulk(numreplicas) {
+                Ok(v) => v,
+                Err(e) => return Ok(Some(e)),
+            };
+            let timeout_ms = match parse_i64_from_bulk(timeout) {
+                Ok(v) => v,
+                Err(_) => {
+                    return Ok(Some(Command::Error(
+                        "ERR timeout is not an integer or out of range".to_string(),
+                    )))
+                }
+            };
+            if timeout_ms < 0 {
+                return Ok(Some(Command::Error("ERR timeout is negative".to_string())));
+            }
+            Command::Wait {
+                numreplicas,
+                timeout_ms,
+            }
+        }
+        // TO/FORCE/ABORT/TIMEOUT 等参数一律忽略，没有副本可供切换
+        "FAILOVER" => Command::Failover,
+        "CLUSTER" => {
+            let Some(subcmd_bytes) = iter.next() else {
+                return Ok(Some(err_wrong_args("cluster")));
+            };
+            let subcommand = match parse_bulk_string(subcmd_bytes) {
+                Ok(s) => s.to_lowercase(),
+                Err(e) => return Ok(Some(e)),
+            };
+            let mut args = Vec::new();
+            for bytes in iter.by_ref() {
+                match parse_bulk_string(bytes) {
+                    Ok(s) => args.push(s),
+                    Err(e) => return Ok(Some(e)),
+                }
+            }
+            Command::Cluster { subcommand, args }
+        }
+        "LOLWUT" => {
+            // 可选的 VERSION <n> 等参数被忽略，只返回版本横幅
+            Command::Lolwut
+        }
         "QUIT" => {
             if iter.next().is_some() {
                 return Ok(Some(err_wrong_args("quit")));
@@ -586,8 +1107,8 @@ pub async fn read_command(
                             Ok(v) => v,
                             Err(e) => return Ok(Some(e)),
                         };
-                        if sec < 0 {
-                            return Ok(Some(err_not_integer()));
+                        if sec <= 0 {
+                            return Ok(Some(err_invalid_expire("set")));
                         }
                         expire_millis = Some(sec.saturating_mul(1000));
                         has_expire = true;
@@ -603,8 +1124,8 @@ pub async fn read_command(
                             Ok(v) => v,
                             Err(e) => return Ok(Some(e)),
                         };
-                        if ms < 0 {
-                            return Ok(Some(err_not_integer()));
+                        if ms <= 0 {
+                            return Ok(Some(err_invalid_expire("set")));
                         }
                         expire_millis = Some(ms);
                         has_expire = true;
@@ -620,8 +1141,8 @@ pub async fn read_command(
                             Ok(v) => v,
                             Err(e) => return Ok(Some(e)),
                         };
-                        if sec < 0 {
-                            return Ok(Some(err_not_integer()));
+                        if sec <= 0 {
+                            return Ok(Some(err_invalid_expire("set")));
                         }
                         // 转为毫秒级绝对时间戳
                         expire_at_millis = Some(sec.saturating_mul(1000));
@@ -638,8 +1159,8 @@ pub async fn read_command(
                             Ok(v) => v,
                             Err(e) => return Ok(Some(e)),
                         };
-                        if ms < 0 {
-                            return Ok(Some(err_not_integer()));
+                        if ms <= 0 {
+                            return Ok(Some(err_invalid_expire("set")));
                         }
                         expire_at_millis = Some(ms);
                         has_expire = true;
@@ -736,8 +1257,8 @@ pub async fn read_command(
                             Ok(v) => v,
                             Err(e) => return Ok(Some(e)),
                         };
-                        if sec < 0 {
-                            return Ok(Some(err_not_integer()));
+                        if sec <= 0 {
+                            return Ok(Some(err_invalid_expire("getex")));
                         }
                         expire_millis = Some(sec.saturating_mul(1000));
                         has_ex = true;
@@ -753,8 +1274,8 @@ pub async fn read_command(
                             Ok(v) => v,
                             Err(e) => return Ok(Some(e)),
                         };
-                        if ms < 0 {
-                            return Ok(Some(err_not_integer()));
+                        if ms <= 0 {
+                            return Ok(Some(err_invalid_expire("getex")));
                         }
                         expire_millis = Some(ms);
                         has_px = true;
@@ -849,6 +1370,47 @@ pub async fn read_command(
             }
             Command::Append { key, value }
         }
+        "SETBIT" => {
+            let (Some(key_bytes), Some(offset_bytes), Some(bit_bytes), None) =
+                (iter.next(), iter.next(), iter.next(), iter.next())
+            else {
+                return Ok(Some(err_wrong_args("setbit")));
+            };
+            let key = match parse_bulk_string(key_bytes) {
+                Ok(k) => k,
+                Err(e) => return Ok(Some(e)),
+            };
+            let offset = match parse_bit_offset(offset_bytes) {
+                Ok(v) => v,
+                Err(e) => return Ok(Some(e)),
+            };
+            let bit = match bit_bytes.as_slice() {
+                b"0" => false,
+                b"1" => true,
+                _ => {
+                    return Ok(Some(Command::Error(
+                        "ERR bit is not an integer or out of range".to_string(),
+                    )))
+                }
+            };
+            Command::Setbit { key, offset, bit }
+        }
+        "GETBIT" => {
+            let (Some(key_bytes), Some(offset_bytes), None) =
+                (iter.next(), iter.next(), iter.next())
+            else {
+                return Ok(Some(err_wrong_args("getbit")));
+            };
+            let key = match parse_bulk_string(key_bytes) {
+                Ok(k) => k,
+                Err(e) => return Ok(Some(e)),
+            };
+            let offset = match parse_bit_offset(offset_bytes) {
+                Ok(v) => v,
+                Err(e) => return Ok(Some(e)),
+            };
+            Command::Getbit { key, offset }
+        }
         "STRLEN" => {
             let Some(key_bytes) = iter.next() else {
                 return Ok(Some(err_wrong_args("strlen")));
@@ -964,7 +1526,7 @@ pub async fn read_command(
             };
             Command::Decrby { key, delta }
         }
-        "DEL" => {
+        "DEL" | "UNLINK" => {
             let mut keys = Vec::new();
             for b in iter {
                 match parse_bulk_string(b) {
@@ -972,10 +1534,17 @@ pub async fn read_command(
                     Err(e) => return Ok(Some(e)),
                 }
             }
-            if keys.is_empty() {
-                return Ok(Some(err_wrong_args("del")));
+            if upper == "UNLINK" {
+                if keys.is_empty() {
+                    return Ok(Some(err_wrong_args("unlink")));
+                }
+                Command::Unlink { keys }
+            } else {
+                if keys.is_empty() {
+                    return Ok(Some(err_wrong_args("del")));
+                }
+                Command::Del { keys }
             }
-            Command::Del { keys }
         }
         "EXISTS" => {
             let mut keys = Vec::new();
@@ -990,6 +1559,79 @@ pub async fn read_command(
             }
             Command::Exists { keys }
         }
+        "OBJECT" => {
+            // 目前只支持 OBJECT IDLETIME key 与 OBJECT ENCODING key
+            let Some(subcmd_bytes) = iter.next() else {
+                return Ok(Some(err_wrong_args("object")));
+            };
+            let subcmd = match parse_bulk_string(subcmd_bytes) {
+                Ok(s) => s.to_uppercase(),
+                Err(e) => return Ok(Some(e)),
+            };
+            match subcmd.as_str() {
+                "IDLETIME" => {
+                    let (Some(key_bytes), None) = (iter.next(), iter.next()) else {
+                        return Ok(Some(err_wrong_args("object|idletime")));
+                    };
+                    match parse_bulk_string(key_bytes) {
+                        Ok(key) => Command::ObjectIdletime { key },
+                        Err(e) => return Ok(Some(e)),
+                    }
+                }
+                "ENCODING" => {
+                    let (Some(key_bytes), None) = (iter.next(), iter.next()) else {
+                        return Ok(Some(err_wrong_args("object|encoding")));
+                    };
+                    match parse_bulk_string(key_bytes) {
+                        Ok(key) => Command::ObjectEncoding { key },
+                        Err(e) => return Ok(Some(e)),
+                    }
+                }
+                _ => {
+                    Command::Error(format!("ERR Unknown subcommand or wrong number of arguments for 'object|{}'", subcmd.to_lowercase()))
+                }
+            }
+        }
+        "MEMORY" => {
+            // 目前只支持 MEMORY USAGE key [SAMPLES count]，SAMPLES 仅做校验
+            let Some(subcmd_bytes) = iter.next() else {
+                return Ok(Some(err_wrong_args("memory")));
+            };
+            let subcmd = match parse_bulk_string(subcmd_bytes) {
+                Ok(s) => s.to_uppercase(),
+                Err(e) => return Ok(Some(e)),
+            };
+            match subcmd.as_str() {
+                "USAGE" => {
+                    let Some(key_bytes) = iter.next() else {
+                        return Ok(Some(err_wrong_args("memory|usage")));
+                    };
+                    let key = match parse_bulk_string(key_bytes) {
+                        Ok(k) => k,
+                        Err(e) => return Ok(Some(e)),
+                    };
+                    match (iter.next(), iter.next(), iter.next()) {
+                        (None, _, _) => Command::MemoryUsage { key },
+                        (Some(opt), Some(count), None) => {
+                            match parse_bulk_string(opt) {
+                                Ok(o) if o.eq_ignore_ascii_case("SAMPLES") => {}
+                                Ok(_) => return Ok(Some(Command::Error("ERR syntax error".to_string()))),
+                                Err(e) => return Ok(Some(e)),
+                            }
+                            match parse_i64_from_bulk(count) {
+                                Ok(n) if n >= 0 => Command::MemoryUsage { key },
+                                Ok(_) => Command::Error("ERR value is out of range, must be positive".to_string()),
+                                Err(e) => return Ok(Some(e)),
+                            }
+                        }
+                        _ => Command::Error("ERR syntax error".to_string()),
+                    }
+                }
+                _ => {
+                    Command::Error(format!("ERR Unknown subcommand or wrong number of arguments for 'memory|{}'", subcmd.to_lowercase()))
+                }
+            }
+        }
         "TYPE" => {
             let Some(key_bytes) = iter.next() else {
                 return Ok(Some(err_wrong_args("type")));
@@ -1022,6 +1664,7 @@ pub async fn read_command(
             }
             let mut pattern: Option<String> = None;
             let mut count: Option<u64> = None;
+            let mut type_filter: Option<String> = None;
 
             while let Some(opt) = iter.next() {
                 let opt_upper = match std::str::from_utf8(&opt) {
@@ -1029,6 +1672,15 @@ pub async fn read_command(
                     Err(_) => return Ok(Some(err_syntax())),
                 };
                 match opt_upper.as_str() {
+                    "TYPE" => {
+                        let Some(type_bytes) = iter.next() else {
+                            return Ok(Some(err_syntax()));
+                        };
+                        type_filter = match parse_bulk_string(type_bytes) {
+                            Ok(t) => Some(t.to_ascii_lowercase()),
+                            Err(e) => return Ok(Some(e)),
+                        };
+                    }
                     "MATCH" => {
                         if pattern.is_some() {
                             return Ok(Some(err_syntax()));
@@ -1053,8 +1705,9 @@ pub async fn read_command(
                             Ok(v) => v,
                             Err(e) => return Ok(Some(e)),
                         };
-                        if c_i64 < 0 {
-                            return Ok(Some(err_not_integer()));
+                        // 与 Redis 一致：COUNT 必须 >= 1，否则扫描无法前进
+                        if c_i64 < 1 {
+                            return Ok(Some(err_syntax()));
                         }
                         count = Some(c_i64 as u64);
                     }
@@ -1068,6 +1721,7 @@ pub async fn read_command(
                 cursor: cursor_i64 as u64,
                 pattern,
                 count,
+                type_filter,
             }
         }
         "SSCAN" => {
@@ -1159,6 +1813,7 @@ pub async fn read_command(
             }
             let mut pattern: Option<String> = None;
             let mut count: Option<u64> = None;
+            let mut novalues = false;
 
             while let Some(opt) = iter.next() {
                 let opt_upper = match std::str::from_utf8(&opt) {
@@ -1195,6 +1850,9 @@ pub async fn read_command(
                         }
                         count = Some(c_i64 as u64);
                     }
+                    "NOVALUES" => {
+                        novalues = true;
+                    }
                     _ => {
                         return Ok(Some(err_syntax()));
                     }
@@ -1206,6 +1864,7 @@ pub async fn read_command(
                 cursor: cursor_i64 as u64,
                 pattern,
                 count,
+                novalues,
             }
         }
         "ZSCAN" => {
@@ -1286,27 +1945,66 @@ pub async fn read_command(
                 Err(e) => return Ok(Some(e)),
             };
 
-            let mut entries: Vec<(f64, String)> = Vec::new();
-            while let Some(score_bytes) = iter.next() {
+            // 选项位于 score/member 之前，遇到第一个非选项参数即停止
+            let mut args = iter.peekable();
+            let (mut nx, mut xx, mut gt, mut lt, mut ch, mut incr) =
+                (false, false, false, false, false, false);
+            while let Some(arg) = args.peek() {
+                let flag = match arg.to_ascii_uppercase().as_slice() {
+                    b"NX" => &mut nx,
+                    b"XX" => &mut xx,
+                    b"GT" => &mut gt,
+                    b"LT" => &mut lt,
+                    b"CH" => &mut ch,
+                    b"INCR" => &mut incr,
+                    _ => break,
+                };
+                *flag = true;
+                args.next();
+            }
+            if nx && xx {
+                return Ok(Some(Command::Error(
+                    "ERR XX and NX options at the same time are not compatible".to_string(),
+                )));
+            }
+            if (gt && lt) || (nx && (gt || lt)) {
+                return Ok(Some(Command::Error(
+                    "ERR GT, LT, and/or NX options at the same time are not compatible".to_string(),
+                )));
+            }
+
+            let mut entries: Vec<(f64, Binary)> = Vec::new();
+            while let Some(score_bytes) = args.next() {
                 let score = match parse_f64_from_bulk(score_bytes) {
                     Ok(v) => v,
                     Err(e) => return Ok(Some(e)),
                 };
-                let Some(member_bytes) = iter.next() else {
+                // member 按原始字节保存
+                let Some(member) = args.next() else {
                     return Ok(Some(err_wrong_args("zadd")));
                 };
-                let member = match parse_bulk_string(member_bytes) {
-                    Ok(m) => m,
-                    Err(e) => return Ok(Some(e)),
-                };
                 entries.push((score, member));
             }
 
             if entries.is_empty() {
                 return Ok(Some(err_wrong_args("zadd")));
             }
+            if incr && entries.len() > 1 {
+                return Ok(Some(Command::Error(
+                    "ERR INCR option supports a single increment-element pair".to_string(),
+                )));
+            }
 
-            Command::Zadd { key, entries }
+            Command::Zadd {
+                key,
+                entries,
+                nx,
+                xx,
+                gt,
+                lt,
+                ch,
+                incr,
+            }
         }
         "ZCARD" => {
             let Some(key_bytes) = iter.next() else {
@@ -1380,13 +2078,9 @@ pub async fn read_command(
                 Ok(k) => k,
                 Err(e) => return Ok(Some(e)),
             };
-            let Some(member_bytes) = iter.next() else {
+            let Some(member) = iter.next() else {
                 return Ok(Some(err_wrong_args("zscore")));
             };
-            let member = match parse_bulk_string(member_bytes) {
-                Ok(m) => m,
-                Err(e) => return Ok(Some(e)),
-            };
 
             if iter.next().is_some() {
                 return Ok(Some(err_wrong_args("zscore")));
@@ -1403,14 +2097,7 @@ pub async fn read_command(
                 Err(e) => return Ok(Some(e)),
             };
 
-            let mut members: Vec<String> = Vec::new();
-            for member_bytes in iter {
-                let m = match parse_bulk_string(member_bytes) {
-                    Ok(v) => v,
-                    Err(e) => return Ok(Some(e)),
-                };
-                members.push(m);
-            }
+            let members: Vec<Binary> = iter.collect();
 
             if members.is_empty() {
                 return Ok(Some(err_wrong_args("zrem")));
@@ -1433,13 +2120,9 @@ pub async fn read_command(
                 Ok(v) => v,
                 Err(e) => return Ok(Some(e)),
             };
-            let Some(member_bytes) = iter.next() else {
+            let Some(member) = iter.next() else {
                 return Ok(Some(err_wrong_args("zincrby")));
             };
-            let member = match parse_bulk_string(member_bytes) {
-                Ok(m) => m,
-                Err(e) => return Ok(Some(e)),
-            };
 
             if iter.next().is_some() {
                 return Ok(Some(err_wrong_args("zincrby")));
@@ -1515,12 +2198,46 @@ pub async fn read_command(
                 sourcekeys,
             }
         }
+        "PFDEBUG" => {
+            let (Some(subcmd_bytes), Some(key_bytes), None) = (iter.next(), iter.next(), iter.next())
+            else {
+                return Ok(Some(err_wrong_args("pfdebug")));
+            };
+            let subcmd = match parse_bulk_string(subcmd_bytes) {
+                Ok(s) => s.to_uppercase(),
+                Err(e) => return Ok(Some(e)),
+            };
+            let key = match parse_bulk_string(key_bytes) {
+                Ok(k) => k,
+                Err(e) => return Ok(Some(e)),
+            };
+            match subcmd.as_str() {
+                "GETREG" => Command::PfdebugGetreg { key },
+                "ENCODING" => Command::PfdebugEncoding { key },
+                _ => Command::Error(format!("ERR Unknown PFDEBUG subcommand '{}'", subcmd.to_lowercase())),
+            }
+        }
         "SAVE" => {
             if iter.next().is_some() {
                 return Ok(Some(err_wrong_args("save")));
             }
             Command::Save
         }
+        "SHUTDOWN" => {
+            let mut save = None;
+            for b in iter {
+                let opt = match parse_bulk_string(b) {
+                    Ok(s) => s.to_uppercase(),
+                    Err(e) => return Ok(Some(e)),
+                };
+                match opt.as_str() {
+                    "NOSAVE" if save != Some(true) => save = Some(false),
+                    "SAVE" if save != Some(false) => save = Some(true),
+                    _ => return Ok(Some(Command::Error("ERR syntax error".to_string()))),
+                }
+            }
+            Command::Shutdown { save }
+        }
         "BGSAVE" => {
             if iter.next().is_some() {
                 return Ok(Some(err_wrong_args("bgsave")));
@@ -1591,13 +2308,8 @@ pub async fn read_command(
                 Ok(k) => k,
                 Err(e) => return Ok(Some(e)),
             };
-            let mut values: Vec<String> = Vec::new();
-            for b in iter {
-                match parse_bulk_string(b) {
-                    Ok(v) => values.push(v),
-                    Err(e) => return Ok(Some(e)),
-                }
-            }
+            // 列表元素按原始字节保存
+            let values: Vec<Binary> = iter.collect();
             if values.is_empty() {
                 return Ok(Some(err_wrong_args("lpush")));
             }
@@ -1611,13 +2323,8 @@ pub async fn read_command(
                 Ok(k) => k,
                 Err(e) => return Ok(Some(e)),
             };
-            let mut values: Vec<String> = Vec::new();
-            for b in iter {
-                match parse_bulk_string(b) {
-                    Ok(v) => values.push(v),
-                    Err(e) => return Ok(Some(e)),
-                }
-            }
+            // 列表元素按原始字节保存
+            let values: Vec<Binary> = iter.collect();
             if values.is_empty() {
                 return Ok(Some(err_wrong_args("rpush")));
             }
@@ -1720,13 +2427,9 @@ pub async fn read_command(
             let Some(count_bytes) = iter.next() else {
                 return Ok(Some(err_wrong_args("lrem")));
             };
-            let Some(value_bytes) = iter.next() else {
+            let Some(value) = iter.next() else {
                 return Ok(Some(err_wrong_args("lrem")));
             };
-            let value = match parse_bulk_string(value_bytes) {
-                Ok(v) => v,
-                Err(e) => return Ok(Some(e)),
-            };
             if iter.next().is_some() {
                 return Ok(Some(err_wrong_args("lrem")));
             }
@@ -1736,6 +2439,105 @@ pub async fn read_command(
             };
             Command::Lrem { key, count, value }
         }
+        "LPOS" => {
+            let Some(key_bytes) = iter.next() else {
+                return Ok(Some(err_wrong_args("lpos")));
+            };
+            let key = match parse_bulk_string(key_bytes) {
+                Ok(k) => k,
+                Err(e) => return Ok(Some(e)),
+            };
+            let Some(element) = iter.next() else {
+                return Ok(Some(err_wrong_args("lpos")));
+            };
+
+            // 选项可以重复出现，以最后一次为准
+            let mut rank: i64 = 1;
+            let mut count: Option<i64> = None;
+            let mut maxlen: i64 = 0;
+            while let Some(opt) = iter.next() {
+                let Some(arg) = iter.next() else {
+                    return Ok(Some(err_syntax()));
+                };
+                let value = match parse_i64_from_bulk(arg) {
+                    Ok(v) => v,
+                    Err(e) => return Ok(Some(e)),
+                };
+                if opt.eq_ignore_ascii_case(b"RANK") {
+                    if value == 0 {
+                        return Ok(Some(Command::Error(
+                            "ERR RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the end of the list".to_string(),
+                        )));
+                    }
+                    if value == i64::MIN {
+                        // 取反会溢出，与 Redis 一样拒绝
+                        return Ok(Some(Command::Error(format!(
+                            "ERR value is out of range, value must between {} and {}",
+                            -i64::MAX,
+                            i64::MAX
+                        ))));
+                    }
+                    rank = value;
+                } else if opt.eq_ignore_ascii_case(b"COUNT") {
+                    if value < 0 {
+                        return Ok(Some(Command::Error(
+                            "ERR COUNT can't be negative".to_string(),
+                        )));
+                    }
+                    count = Some(value);
+                } else if opt.eq_ignore_ascii_case(b"MAXLEN") {
+                    if value < 0 {
+                        return Ok(Some(Command::Error(
+                            "ERR MAXLEN can't be negative".to_string(),
+                        )));
+                    }
+                    maxlen = value;
+                } else {
+                    return Ok(Some(err_syntax()));
+                }
+            }
+            Command::Lpos {
+                key,
+                element,
+                rank,
+                count,
+                maxlen,
+            }
+        }
+        "LINSERT" => {
+            let Some(key_bytes) = iter.next() else {
+                return Ok(Some(err_wrong_args("linsert")));
+            };
+            let key = match parse_bulk_string(key_bytes) {
+                Ok(k) => k,
+                Err(e) => return Ok(Some(e)),
+            };
+            let Some(where_bytes) = iter.next() else {
+                return Ok(Some(err_wrong_args("linsert")));
+            };
+            let Some(pivot) = iter.next() else {
+                return Ok(Some(err_wrong_args("linsert")));
+            };
+            let Some(value) = iter.next() else {
+                return Ok(Some(err_wrong_args("linsert")));
+            };
+            if iter.next().is_some() {
+                return Ok(Some(err_wrong_args("linsert")));
+            }
+            let before = if where_bytes.eq_ignore_ascii_case(b"BEFORE") {
+                true
+            } else if where_bytes.eq_ignore_ascii_case(b"AFTER") {
+                false
+            } else {
+                return Ok(Some(err_syntax()));
+            };
+            Command::Linsert {
+                key,
+                before,
+                pivot,
+                value,
+            }
+        }
         "LTRIM" => {
             let Some(key_bytes) = iter.next() else {
                 return Ok(Some(err_wrong_args("ltrim")));
@@ -1771,13 +2573,8 @@ pub async fn read_command(
                 Ok(k) => k,
                 Err(e) => return Ok(Some(e)),
             };
-            let mut members: Vec<String> = Vec::new();
-            for b in iter {
-                match parse_bulk_string(b) {
-                    Ok(m) => members.push(m),
-                    Err(e) => return Ok(Some(e)),
-                }
-            }
+            // 集合成员按原始字节保存
+            let members: Vec<Binary> = iter.collect();
             if members.is_empty() {
                 return Ok(Some(err_wrong_args("sadd")));
             }
@@ -1791,13 +2588,8 @@ pub async fn read_command(
                 Ok(k) => k,
                 Err(e) => return Ok(Some(e)),
             };
-            let mut members: Vec<String> = Vec::new();
-            for b in iter {
-                match parse_bulk_string(b) {
-                    Ok(m) => members.push(m),
-                    Err(e) => return Ok(Some(e)),
-                }
-            }
+            // 集合成员按原始字节保存
+            let members: Vec<Binary> = iter.collect();
             if members.is_empty() {
                 return Ok(Some(err_wrong_args("srem")));
             }
@@ -1884,13 +2676,9 @@ pub async fn read_command(
                 Ok(k) => k,
                 Err(e) => return Ok(Some(e)),
             };
-            let Some(member_bytes) = iter.next() else {
+            let Some(member) = iter.next() else {
                 return Ok(Some(err_wrong_args("sismember")));
             };
-            let member = match parse_bulk_string(member_bytes) {
-                Ok(m) => m,
-                Err(e) => return Ok(Some(e)),
-            };
             if iter.next().is_some() {
                 return Ok(Some(err_wrong_args("sismember")));
             }
@@ -2010,13 +2798,10 @@ pub async fn read_command(
                 Ok(f) => f,
                 Err(e) => return Ok(Some(e)),
             };
-            let Some(value_bytes) = iter.next() else {
+            // value 按原始字节保存，允许任意二进制内容
+            let Some(value) = iter.next() else {
                 return Ok(Some(err_wrong_args("hset")));
             };
-            let value = match parse_bulk_string(value_bytes) {
-                Ok(v) => v,
-                Err(e) => return Ok(Some(e)),
-            };
             if iter.next().is_some() {
                 return Ok(Some(err_wrong_args("hset")));
             }
@@ -2288,23 +3073,107 @@ pub async fn read_command(
             Command::Persist { key }
         }
         "INFO" => {
+            let section = match iter.next() {
+                Some(bytes) => match parse_bulk_string(bytes) {
+                    Ok(s) => Some(s.to_lowercase()),
+                    Err(e) => return Ok(Some(e)),
+                },
+                None => None,
+            };
             if iter.next().is_some() {
                 return Ok(Some(err_wrong_args("info")));
             }
-            Command::Info
+            Command::Info { section }
         }
         "AUTH" => {
-            let Some(password_bytes) = iter.next() else {
+            let Some(first_bytes) = iter.next() else {
                 return Ok(Some(err_wrong_args("auth")));
             };
-            let password = match parse_bulk_string(password_bytes) {
+            let first = match parse_bulk_string(first_bytes) {
                 Ok(p) => p,
                 Err(e) => return Ok(Some(e)),
             };
+            let second = match iter.next() {
+                Some(bytes) => match parse_bulk_string(bytes) {
+                    Ok(p) => Some(p),
+                    Err(e) => return Ok(Some(e)),
+                },
+                None => None,
+            };
             if iter.next().is_some() {
                 return Ok(Some(err_wrong_args("auth")));
             }
-            Command::Auth { password }
+            // 两参数形式为 AUTH <username> <password>
+            match second {
+                Some(password) => Command::Auth {
+                    username: Some(first),
+                    password,
+                },
+                None => Command::Auth {
+                    username: None,
+                    password: first,
+                },
+            }
+        }
+        "HELLO" => {
+            let protover = match iter.next() {
+                Some(bytes) => match std::str::from_utf8(&bytes).ok().and_then(|s| s.parse::<i64>().ok()) {
+                    Some(v) => Some(v),
+                    None => {
+                        return Ok(Some(Command::Error(
+                            "ERR Protocol version is not an integer or out of range".to_string(),
+                        )))
+                    }
+                },
+                None => None,
+            };
+            let mut auth = None;
+            let mut setname = None;
+            while let Some(opt_bytes) = iter.next() {
+                let opt = String::from_utf8_lossy(&opt_bytes).to_string();
+                match opt.to_ascii_uppercase().as_str() {
+                    "AUTH" => {
+                        let (Some(user_bytes), Some(pass_bytes)) = (iter.next(), iter.next()) else {
+                            return Ok(Some(Command::Error(format!(
+                                "ERR Syntax error in HELLO option '{}'",
+                                opt
+                            ))));
+                        };
+                        let user = match parse_bulk_string(user_bytes) {
+                            Ok(s) => s,
+                            Err(e) => return Ok(Some(e)),
+                        };
+                        let pass = match parse_bulk_string(pass_bytes) {
+                            Ok(s) => s,
+                            Err(e) => return Ok(Some(e)),
+                        };
+                        auth = Some((user, pass));
+                    }
+                    "SETNAME" => {
+                        let Some(name_bytes) = iter.next() else {
+                            return Ok(Some(Command::Error(format!(
+                                "ERR Syntax error in HELLO option '{}'",
+                                opt
+                            ))));
+                        };
+                        setname = match parse_bulk_string(name_bytes) {
+                            Ok(s) => Some(s),
+                            Err(e) => return Ok(Some(e)),
+                        };
+                    }
+                    _ => {
+                        return Ok(Some(Command::Error(format!(
+                            "ERR Syntax error in HELLO option '{}'",
+                            opt
+                        ))))
+                    }
+                }
+            }
+            Command::Hello {
+                protover,
+                auth,
+                setname,
+            }
         }
         "SELECT" => {
             let Some(db_bytes) = iter.next() else {
@@ -2367,6 +3236,55 @@ pub async fn read_command(
             }
             Command::Renamenx { key, newkey }
         }
+        "COPY" => {
+            let Some(source_bytes) = iter.next() else {
+                return Ok(Some(err_wrong_args("copy")));
+            };
+            let source = match parse_bulk_string(source_bytes) {
+                Ok(k) => k,
+                Err(e) => return Ok(Some(e)),
+            };
+            let Some(dest_bytes) = iter.next() else {
+                return Ok(Some(err_wrong_args("copy")));
+            };
+            let destination = match parse_bulk_string(dest_bytes) {
+                Ok(k) => k,
+                Err(e) => return Ok(Some(e)),
+            };
+            let mut db = None;
+            let mut replace = false;
+            while let Some(opt_bytes) = iter.next() {
+                let opt = match parse_bulk_string(opt_bytes) {
+                    Ok(s) => s.to_ascii_uppercase(),
+                    Err(e) => return Ok(Some(e)),
+                };
+                match opt.as_str() {
+                    "REPLACE" => replace = true,
+                    "DB" => {
+                        let Some(db_bytes) = iter.next() else {
+                            return Ok(Some(Command::Error("ERR syntax error".to_string())));
+                        };
+                        let db_idx = match parse_i64_from_bulk(db_bytes) {
+                            Ok(v) => v,
+                            Err(e) => return Ok(Some(e)),
+                        };
+                        if !(0..16).contains(&db_idx) {
+                            return Ok(Some(Command::Error(
+                                "ERR DB index is out of range".to_string(),
+                            )));
+                        }
+                        db = Some(db_idx as u8);
+                    }
+                    _ => return Ok(Some(Command::Error("ERR syntax error".to_string()))),
+                }
+            }
+            Command::Copy {
+                source,
+                destination,
+                db,
+                replace,
+            }
+        }
         "FLUSHDB" => match (iter.next(), iter.next()) {
             (None, None) => Command::Flushdb,
             (Some(arg_bytes), None) => {
@@ -2665,8 +3583,8 @@ pub async fn read_command(
                 Ok(v) => v,
                 Err(e) => return Ok(Some(e)),
             };
-            if seconds < 0 {
-                return Ok(Some(err_not_integer()));
+            if seconds <= 0 {
+                return Ok(Some(err_invalid_expire("setex")));
             }
             Command::Setex {
                 key,
@@ -2695,31 +3613,33 @@ pub async fn read_command(
                 Ok(v) => v,
                 Err(e) => return Ok(Some(e)),
             };
-            if millis < 0 {
-                return Ok(Some(err_not_integer()));
+            if millis <= 0 {
+                return Ok(Some(err_invalid_expire("psetex")));
             }
             Command::Psetex { key, millis, value }
         }
-        "EVAL" => {
+        "EVAL" | "EVAL_RO" => {
             // EVAL script numkeys [key ...] [arg ...]
+            let read_only = upper == "EVAL_RO";
+            let err_cmd = if read_only { "eval_ro" } else { "eval" };
             let Some(script_bytes) = iter.next() else {
-                return Ok(Some(err_wrong_args("eval")));
+                return Ok(Some(err_wrong_args(err_cmd)));
             };
             let script = match parse_bulk_string(script_bytes) {
                 Ok(s) => s,
                 Err(e) => return Ok(Some(e)),
             };
             let Some(numkeys_bytes) = iter.next() else {
-                return Ok(Some(err_wrong_args("eval")));
+                return Ok(Some(err_wrong_args(err_cmd)));
             };
-            let numkeys = match parse_i64_from_bulk(numkeys_bytes) {
-                Ok(n) if n >= 0 => n as usize,
-                _ => return Ok(Some(err_not_integer())),
+            let numkeys = match parse_eval_numkeys(numkeys_bytes, iter.len()) {
+                Ok(n) => n,
+                Err(e) => return Ok(Some(e)),
             };
             let mut keys = Vec::with_capacity(numkeys);
             for _ in 0..numkeys {
                 let Some(key_bytes) = iter.next() else {
-                    return Ok(Some(err_wrong_args("eval")));
+                    return Ok(Some(err_wrong_args(err_cmd)));
                 };
                 match parse_bulk_string(key_bytes) {
                     Ok(k) => keys.push(k),
@@ -2733,28 +3653,35 @@ pub async fn read_command(
                     Err(e) => return Ok(Some(e)),
                 }
             }
-            Command::Eval { script, keys, args }
+            Command::Eval {
+                script,
+                keys,
+                args,
+                read_only,
+            }
         }
-        "EVALSHA" => {
+        "EVALSHA" | "EVALSHA_RO" => {
             // EVALSHA sha1 numkeys [key ...] [arg ...]
+            let read_only = upper == "EVALSHA_RO";
+            let err_cmd = if read_only { "evalsha_ro" } else { "evalsha" };
             let Some(sha1_bytes) = iter.next() else {
-                return Ok(Some(err_wrong_args("evalsha")));
+                return Ok(Some(err_wrong_args(err_cmd)));
             };
             let sha1 = match parse_bulk_string(sha1_bytes) {
                 Ok(s) => s,
                 Err(e) => return Ok(Some(e)),
             };
             let Some(numkeys_bytes) = iter.next() else {
-                return Ok(Some(err_wrong_args("evalsha")));
+                return Ok(Some(err_wrong_args(err_cmd)));
             };
-            let numkeys = match parse_i64_from_bulk(numkeys_bytes) {
-                Ok(n) if n >= 0 => n as usize,
-                _ => return Ok(Some(err_not_integer())),
+            let numkeys = match parse_eval_numkeys(numkeys_bytes, iter.len()) {
+                Ok(n) => n,
+                Err(e) => return Ok(Some(e)),
             };
             let mut keys = Vec::with_capacity(numkeys);
             for _ in 0..numkeys {
                 let Some(key_bytes) = iter.next() else {
-                    return Ok(Some(err_wrong_args("evalsha")));
+                    return Ok(Some(err_wrong_args(err_cmd)));
                 };
                 match parse_bulk_string(key_bytes) {
                     Ok(k) => keys.push(k),
@@ -2768,10 +3695,15 @@ pub async fn read_command(
                     Err(e) => return Ok(Some(e)),
                 }
             }
-            Command::Evalsha { sha1, keys, args }
+            Command::Evalsha {
+                sha1,
+                keys,
+                args,
+                read_only,
+            }
         }
         "SCRIPT" => {
-            // SCRIPT LOAD script | SCRIPT EXISTS sha1 [sha1 ...] | SCRIPT FLUSH
+            // SCRIPT LOAD script | SCRIPT EXISTS sha1 [sha1 ...] | SCRIPT FLUSH | SCRIPT KILL
             let Some(subcmd_bytes) = iter.next() else {
                 return Ok(Some(err_wrong_args("script")));
             };
@@ -2810,11 +3742,136 @@ pub async fn read_command(
                     // 忽略可选的 ASYNC/SYNC 参数
                     Command::ScriptFlush
                 }
+                "KILL" => {
+                    if iter.next().is_some() {
+                        return Ok(Some(err_wrong_args("script|kill")));
+                    }
+                    Command::ScriptKill
+                }
                 _ => {
                     Command::Error(format!("ERR Unknown SCRIPT subcommand or wrong number of arguments for '{}'", subcmd))
                 }
             }
         }
+        "FUNCTION" => {
+            // FUNCTION LOAD [REPLACE] code | FUNCTION LIST [LIBRARYNAME pattern] [WITHCODE] | FUNCTION FLUSH
+            let Some(subcmd_bytes) = iter.next() else {
+                return Ok(Some(err_wrong_args("function")));
+            };
+            let subcmd = match parse_bulk_string(subcmd_bytes) {
+                Ok(s) => s.to_uppercase(),
+                Err(e) => return Ok(Some(e)),
+            };
+            match subcmd.as_str() {
+                "LOAD" => {
+                    let mut rest = Vec::new();
+                    for b in iter {
+                        match parse_bulk_string(b) {
+                            Ok(s) => rest.push(s),
+                            Err(e) => return Ok(Some(e)),
+                        }
+                    }
+                    let Some(code) = rest.pop() else {
+                        return Ok(Some(err_wrong_args("function|load")));
+                    };
+                    let mut replace = false;
+                    for opt in rest {
+                        if opt.eq_ignore_ascii_case("REPLACE") {
+                            replace = true;
+                        } else {
+                            return Ok(Some(Command::Error(format!(
+                                "ERR Unknown option given: {}",
+                                opt
+                            ))));
+                        }
+                    }
+                    Command::FunctionLoad { code, replace }
+                }
+                "LIST" => {
+                    let mut library_pattern = None;
+                    let mut with_code = false;
+                    while let Some(opt_bytes) = iter.next() {
+                        let opt = match parse_bulk_string(opt_bytes) {
+                            Ok(s) => s.to_uppercase(),
+                            Err(e) => return Ok(Some(e)),
+                        };
+                        match opt.as_str() {
+                            "WITHCODE" => with_code = true,
+                            "LIBRARYNAME" => {
+                                let Some(pattern_bytes) = iter.next() else {
+                                    return Ok(Some(Command::Error(
+                                        "ERR library name argument was not given".to_string(),
+                                    )));
+                                };
+                                match parse_bulk_string(pattern_bytes) {
+                                    Ok(p) => library_pattern = Some(p),
+                                    Err(e) => return Ok(Some(e)),
+                                }
+                            }
+                            _ => {
+                                return Ok(Some(Command::Error(format!(
+                                    "ERR Unknown argument {}",
+                                    opt
+                                ))))
+                            }
+                        }
+                    }
+                    Command::FunctionList {
+                        library_pattern,
+                        with_code,
+                    }
+                }
+                "FLUSH" => {
+                    // 与 SCRIPT FLUSH 一样忽略可选的 ASYNC/SYNC 参数
+                    Command::FunctionFlush
+                }
+                _ => {
+                    Command::Error(format!("ERR Unknown FUNCTION subcommand or wrong number of arguments for '{}'", subcmd))
+                }
+            }
+        }
+        "FCALL" | "FCALL_RO" => {
+            // FCALL function numkeys [key ...] [arg ...]
+            let read_only = upper == "FCALL_RO";
+            let err_cmd = if read_only { "fcall_ro" } else { "fcall" };
+            let Some(function_bytes) = iter.next() else {
+                return Ok(Some(err_wrong_args(err_cmd)));
+            };
+            let function = match parse_bulk_string(function_bytes) {
+                Ok(s) => s,
+                Err(e) => return Ok(Some(e)),
+            };
+            let Some(numkeys_bytes) = iter.next() else {
+                return Ok(Some(err_wrong_args(err_cmd)));
+            };
+            let numkeys = match parse_eval_numkeys(numkeys_bytes, iter.len()) {
+                Ok(n) => n,
+                Err(e) => return Ok(Some(e)),
+            };
+            let mut keys = Vec::with_capacity(numkeys);
+            for _ in 0..numkeys {
+                let Some(key_bytes) = iter.next() else {
+                    return Ok(Some(err_wrong_args(err_cmd)));
+                };
+                match parse_bulk_string(key_bytes) {
+                    Ok(k) => keys.push(k),
+                    Err(e) => return Ok(Some(e)),
+                }
+            }
+            let mut args = Vec::new();
+            for b in iter {
+                match parse_bulk_string(b) {
+                    Ok(a) => args.push(a),
+                    Err(e) => return Ok(Some(e)),
+                }
+            }
+            Command::Fcall {
+                function,
+                keys,
+                args,
+                read_only,
+            }
+        }
         "CONFIG" => {
             let Some(subcmd_bytes) = iter.next() else {
                 return Ok(Some(err_wrong_args("config")));
@@ -2875,6 +3932,9 @@ pub async fn read_command(
                     // CLIENT LIST 可能有可选参数，但我们简化处理
                     Command::ClientList
                 }
+                "INFO" => {
+                    Command::ClientInfo
+                }
                 "ID" => {
                     Command::ClientId
                 }
@@ -2906,19 +3966,26 @@ pub async fn read_command(
             };
             match subcmd.as_str() {
                 "GET" => {
-                    let count = if let Some(count_bytes) = iter.next() {
-                        let count_str = match parse_bulk_string(count_bytes) {
+                    // SLOWLOG GET [count] [WITHREPLYBYTES]
+                    let mut count = None;
+                    let mut with_reply_bytes = false;
+                    for arg_bytes in iter {
+                        let arg = match parse_bulk_string(arg_bytes) {
                             Ok(s) => s,
                             Err(e) => return Ok(Some(e)),
                         };
-                        match count_str.parse::<usize>() {
-                            Ok(n) => Some(n),
-                            Err(_) => return Ok(Some(err_not_integer())),
+                        if arg.eq_ignore_ascii_case("WITHREPLYBYTES") && !with_reply_bytes {
+                            with_reply_bytes = true;
+                        } else if count.is_none() && !with_reply_bytes {
+                            match arg.parse::<usize>() {
+                                Ok(n) => count = Some(n),
+                                Err(_) => return Ok(Some(err_not_integer())),
+                            }
+                        } else {
+                            return Ok(Some(err_syntax()));
                         }
-                    } else {
-                        None
-                    };
-                    Command::SlowlogGet { count }
+                    }
+                    Command::SlowlogGet { count, with_reply_bytes }
                 }
                 "RESET" => {
                     Command::SlowlogReset
@@ -2931,6 +3998,113 @@ pub async fn read_command(
                 }
             }
         }
+        "DEBUG" => {
+            let Some(subcmd_bytes) = iter.next() else {
+                return Ok(Some(err_wrong_args("debug")));
+            };
+            let subcmd = match parse_bulk_string(subcmd_bytes) {
+                Ok(s) => s.to_uppercase(),
+                Err(e) => return Ok(Some(e)),
+            };
+            match subcmd.as_str() {
+                "SLEEP" => {
+                    let (Some(seconds_bytes), None) = (iter.next(), iter.next()) else {
+                        return Ok(Some(err_wrong_args("debug|sleep")));
+                    };
+                    let seconds = match parse_f64_from_bulk(seconds_bytes) {
+                        Ok(v) => v.max(0.0),
+                        Err(e) => return Ok(Some(e)),
+                    };
+                    Command::DebugSleep { seconds }
+                }
+                "RELOAD" => {
+                    if iter.next().is_some() {
+                        return Ok(Some(err_wrong_args("debug|reload")));
+                    }
+                    Command::DebugReload
+                }
+                "SET-USED-MEMORY" => {
+                    let (Some(bytes), None) = (iter.next(), iter.next()) else {
+                        return Ok(Some(err_wrong_args("debug|set-used-memory")));
+                    };
+                    match parse_i64_from_bulk(bytes) {
+                        Ok(n) if n >= 0 => Command::DebugSetUsedMemory { bytes: n as u64 },
+                        Ok(_) => Command::Error("ERR value is out of range, must be positive".to_string()),
+                        Err(e) => return Ok(Some(e)),
+                    }
+                }
+                "OBJECT" => {
+                    let (Some(key_bytes), None) = (iter.next(), iter.next()) else {
+                        return Ok(Some(err_wrong_args("debug|object")));
+                    };
+                    match parse_bulk_string(key_bytes) {
+                        Ok(key) => Command::DebugObject { key },
+                        Err(e) => return Ok(Some(e)),
+                    }
+                }
+                "LISTPACK-ENTRIES" | "QUICKLIST-PACKED-THRESHOLD" => {
+                    let (Some(bytes), None) = (iter.next(), iter.next()) else {
+                        return Ok(Some(err_wrong_args(&format!("debug|{}", subcmd.to_lowercase()))));
+                    };
+                    match parse_i64_from_bulk(bytes) {
+                        Ok(n) if n >= 0 => Command::DebugListpackEntries { entries: n as usize },
+                        Ok(_) => Command::Error("ERR value is out of range, must be positive".to_string()),
+                        Err(e) => return Ok(Some(e)),
+                    }
+                }
+                _ => {
+                    Command::Error(format!("ERR Unknown subcommand or wrong number of arguments for 'debug|{}'", subcmd.to_lowercase()))
+                }
+            }
+        }
+        "COMMAND" => {
+            let Some(subcmd_bytes) = iter.next() else {
+                return Ok(Some(Command::CommandAll));
+            };
+            let subcmd = match parse_bulk_string(subcmd_bytes.clone()) {
+                Ok(s) => s.to_uppercase(),
+                Err(e) => return Ok(Some(e)),
+            };
+            match subcmd.as_str() {
+                "INFO" => {
+                    let mut names = Vec::new();
+                    for name_bytes in iter {
+                        match parse_bulk_string(name_bytes) {
+                            Ok(s) => names.push(s),
+                            Err(e) => return Ok(Some(e)),
+                        }
+                    }
+                    Command::CommandInfo { names }
+                }
+                "COUNT" => {
+                    if iter.next().is_some() {
+                        return Ok(Some(err_wrong_args("command|count")));
+                    }
+                    Command::CommandCount
+                }
+                // 其他子命令（DOCS/GETKEYS 等）暂未实现
+                _ => Command::Unknown(
+                    [command_bytes, subcmd_bytes].into_iter().chain(iter).collect(),
+                ),
+            }
+        }
+        "ACL" => {
+            let Some(subcmd_bytes) = iter.next() else {
+                return Ok(Some(err_wrong_args("acl")));
+            };
+            let subcommand = match parse_bulk_string(subcmd_bytes) {
+                Ok(s) => s.to_uppercase(),
+                Err(e) => return Ok(Some(e)),
+            };
+            let mut args = Vec::new();
+            for arg in iter {
+                match parse_bulk_string(arg) {
+                    Ok(s) => args.push(s),
+                    Err(e) => return Ok(Some(e)),
+                }
+            }
+            Command::Acl { subcommand, args }
+        }
         _ => Command::Unknown(std::iter::once(command_bytes).chain(iter).collect()),
     };
 
@@ -2940,6 +4114,7 @@ pub async fn read_command(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::resp::Counted;
     use tokio::io::{AsyncWriteExt, BufReader};
     use tokio::net::TcpListener;
 
@@ -2981,7 +4156,7 @@ mod tests {
 
         let (stream, _) = listener.accept().await.unwrap();
         let (read_half, _) = stream.into_split();
-        let mut reader = BufReader::new(read_half);
+        let mut reader = BufReader::new(Counted::new(read_half, Default::default()));
 
         // PING
         if let Some(cmd) = read_command(&mut reader).await.unwrap() {
@@ -3104,14 +4279,14 @@ mod tests {
 
         let (stream, _) = listener.accept().await.unwrap();
         let (read_half, _) = stream.into_split();
-        let mut reader = BufReader::new(read_half);
+        let mut reader = BufReader::new(Counted::new(read_half, Default::default()));
 
         // LPUSH mylist a b
         if let Some(cmd) = read_command(&mut reader).await.unwrap() {
             match cmd {
                 Command::Lpush { key, values } => {
                     assert_eq!(key, "mylist");
-                    assert_eq!(values, vec!["a".to_string(), "b".to_string()]);
+                    assert_eq!(values, vec![b"a".to_vec(), b"b".to_vec()]);
                 }
                 _ => panic!("expected LPUSH"),
             }
@@ -3124,7 +4299,7 @@ mod tests {
             match cmd {
                 Command::Rpush { key, values } => {
                     assert_eq!(key, "mylist");
-                    assert_eq!(values, vec!["c".to_string()]);
+                    assert_eq!(values, vec![b"c".to_vec()]);
                 }
                 _ => panic!("expected RPUSH"),
             }
@@ -3220,14 +4395,14 @@ mod tests {
 
         let (stream, _) = listener.accept().await.unwrap();
         let (read_half, _) = stream.into_split();
-        let mut reader = BufReader::new(read_half);
+        let mut reader = BufReader::new(Counted::new(read_half, Default::default()));
 
         // SADD myset a b
         if let Some(cmd) = read_command(&mut reader).await.unwrap() {
             match cmd {
                 Command::Sadd { key, members } => {
                     assert_eq!(key, "myset");
-                    assert_eq!(members, vec!["a".to_string(), "b".to_string()]);
+                    assert_eq!(members, vec![b"a".to_vec(), b"b".to_vec()]);
                 }
                 _ => panic!("expected SADD"),
             }
@@ -3252,7 +4427,7 @@ mod tests {
             match cmd {
                 Command::Sismember { key, member } => {
                     assert_eq!(key, "myset");
-                    assert_eq!(member, "a");
+                    assert_eq!(member, b"a");
                 }
                 _ => panic!("expected SISMEMBER"),
             }
@@ -3295,4 +4470,38 @@ mod tests {
 
         client.await.unwrap();
     }
+
+    fn parse_args(args: &[&str]) -> Option<Command> {
+        parse_command(args.iter().map(|a| a.as_bytes().to_vec()).collect()).unwrap()
+    }
+
+    fn assert_wrong_args(args: &[&str], name: &str) {
+        match parse_args(args) {
+            Some(Command::Error(msg)) => assert_eq!(
+                msg,
+                format!("ERR wrong number of arguments for '{}' command", name)
+            ),
+            _ => panic!("expected wrong-args error for {:?}", args),
+        }
+    }
+
+    #[test]
+    fn arity_table_rejects_malformed_forms() {
+        // 固定 arity：多或少都拒绝
+        assert_wrong_args(&["GET"], "get");
+        assert_wrong_args(&["GET", "k", "extra"], "get");
+        assert_wrong_args(&["LRANGE", "k", "0"], "lrange");
+        assert_wrong_args(&["PUBLISH", "ch"], "publish");
+        assert_wrong_args(&["TYPE", "k", "extra"], "type");
+        // 负 arity：至少 N 个参数
+        assert_wrong_args(&["SUBSCRIBE"], "subscribe");
+        assert_wrong_args(&["SADD", "k"], "sadd");
+        assert_wrong_args(&["ZADD", "k", "1"], "zadd");
+        // 合法形式不受影响
+        assert!(matches!(parse_args(&["GET", "k"]), Some(Command::Get { .. })));
+        assert!(matches!(
+            parse_args(&["SUBSCRIBE", "a", "b"]),
+            Some(Command::Subscribe { .. })
+        ));
+    }
 }
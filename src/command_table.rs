@@ -0,0 +1,282 @@
+//! 静态命令元信息表（COMMAND / COMMAND INFO 使用）
+//!
+//! 字段含义与 Redis `COMMAND INFO` 前 6 项一致：名称、arity、flags、
+//! 第一个 key 位置、最后一个 key 位置、key 步长。arity 为负数表示「至少 |arity| 个参数」
+//! （含命令名本身）。解析命令前也会按 arity 做第一轮参数个数检查，
+//! 因此新增命令时需同步在此登记，且 arity 要与解析逻辑一致。
+
+/// 单个命令的元信息
+#[derive(Debug, Clone, Copy)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub arity: i64,
+    pub flags: &'static [&'static str],
+    pub first_key: i64,
+    pub last_key: i64,
+    pub step: i64,
+}
+
+const fn spec(
+    name: &'static str,
+    arity: i64,
+    flags: &'static [&'static str],
+    first_key: i64,
+    last_key: i64,
+    step: i64,
+) -> CommandSpec {
+    CommandSpec {
+        name,
+        arity,
+        flags,
+        first_key,
+        last_key,
+        step,
+    }
+}
+
+const READ: &[&str] = &["readonly"];
+const READ_FAST: &[&str] = &["readonly", "fast"];
+const WRITE: &[&str] = &["write"];
+const WRITE_FAST: &[&str] = &["write", "fast"];
+const WRITE_OOM: &[&str] = &["write", "denyoom"];
+const WRITE_OOM_FAST: &[&str] = &["write", "denyoom", "fast"];
+const PUBSUB: &[&str] = &["pubsub", "noscript", "loading", "stale"];
+const TX: &[&str] = &["noscript", "loading", "stale", "fast"];
+const ADMIN: &[&str] = &["admin", "noscript"];
+const CONTAINER: &[&str] = &[];
+
+/// 当前已实现的全部命令，按名称排序
+pub static COMMAND_TABLE: &[CommandSpec] = &[
+    spec("acl", -2, CONTAINER, 0, 0, 0),
+    spec("append", 3, WRITE_OOM_FAST, 1, 1, 1),
+    spec(
+        "auth",
+        -2,
+        &["noscript", "loading", "stale", "fast", "no_auth"],
+        0,
+        0,
+        0,
+    ),
+    spec("bgsave", -1, ADMIN, 0, 0, 0),
+    spec("client", -2, CONTAINER, 0, 0, 0),
+    spec("cluster", -2, CONTAINER, 0, 0, 0),
+    spec("command", -1, &["loading", "stale"], 0, 0, 0),
+    spec("config", -2, CONTAINER, 0, 0, 0),
+    spec("copy", -3, WRITE_OOM, 1, 2, 1),
+    spec("dbsize", 1, READ_FAST, 0, 0, 0),
+    spec(
+        "debug",
+        -2,
+        &["admin", "noscript", "loading", "stale"],
+        0,
+        0,
+        0,
+    ),
+    spec("decr", 2, WRITE_OOM_FAST, 1, 1, 1),
+    spec("decrby", 3, WRITE_OOM_FAST, 1, 1, 1),
+    spec("del", -2, WRITE, 1, -1, 1),
+    spec("discard", 1, TX, 0, 0, 0),
+    spec("echo", 2, &["fast"], 0, 0, 0),
+    spec("eval", -3, &["noscript", "stale"], 0, 0, 0),
+    spec("eval_ro", -3, &["readonly", "noscript", "stale"], 0, 0, 0),
+    spec("evalsha", -3, &["noscript", "stale"], 0, 0, 0),
+    spec(
+        "evalsha_ro",
+        -3,
+        &["readonly", "noscript", "stale"],
+        0,
+        0,
+        0,
+    ),
+    spec("exec", 1, &["noscript", "loading", "stale"], 0, 0, 0),
+    spec("exists", -2, READ_FAST, 1, -1, 1),
+    spec("expire", -3, WRITE_FAST, 1, 1, 1),
+    spec("failover", -1, &["admin", "noscript", "stale"], 0, 0, 0),
+    spec("fcall", -3, &["noscript", "stale"], 0, 0, 0),
+    spec("fcall_ro", -3, &["readonly", "noscript", "stale"], 0, 0, 0),
+    spec("flushall", -1, WRITE, 0, 0, 0),
+    spec("flushdb", -1, WRITE, 0, 0, 0),
+    spec("function", -2, CONTAINER, 0, 0, 0),
+    spec("get", 2, READ_FAST, 1, 1, 1),
+    spec("getbit", 3, READ_FAST, 1, 1, 1),
+    spec("getdel", 2, WRITE_FAST, 1, 1, 1),
+    spec("getex", -2, WRITE_FAST, 1, 1, 1),
+    spec("getrange", 4, READ, 1, 1, 1),
+    spec("getset", 3, WRITE_OOM_FAST, 1, 1, 1),
+    spec("hdel", -3, WRITE_FAST, 1, 1, 1),
+    spec(
+        "hello",
+        -1,
+        &["noscript", "loading", "stale", "fast", "no_auth"],
+        0,
+        0,
+        0,
+    ),
+    spec("hexists", 3, READ_FAST, 1, 1, 1),
+    spec("hget", 3, READ_FAST, 1, 1, 1),
+    spec("hgetall", 2, READ, 1, 1, 1),
+    spec("hincrby", 4, WRITE_OOM_FAST, 1, 1, 1),
+    spec("hincrbyfloat", 4, WRITE_OOM_FAST, 1, 1, 1),
+    spec("hkeys", 2, READ, 1, 1, 1),
+    spec("hlen", 2, READ_FAST, 1, 1, 1),
+    spec("hmget", -3, READ_FAST, 1, 1, 1),
+    spec("hscan", -3, READ, 1, 1, 1),
+    spec("hset", -4, WRITE_OOM_FAST, 1, 1, 1),
+    spec("hvals", 2, READ, 1, 1, 1),
+    spec("incr", 2, WRITE_OOM_FAST, 1, 1, 1),
+    spec("incrby", 3, WRITE_OOM_FAST, 1, 1, 1),
+    spec("incrbyfloat", 3, WRITE_OOM_FAST, 1, 1, 1),
+    spec("info", -1, &["loading", "stale"], 0, 0, 0),
+    spec("keys", 2, READ, 0, 0, 0),
+    spec("lastsave", 1, &["loading", "stale", "fast"], 0, 0, 0),
+    spec("lindex", 3, READ, 1, 1, 1),
+    spec("linsert", 5, WRITE_OOM, 1, 1, 1),
+    spec("llen", 2, READ_FAST, 1, 1, 1),
+    spec("lolwut", -1, &["readonly", "fast"], 0, 0, 0),
+    spec("lpop", -2, WRITE_FAST, 1, 1, 1),
+    spec("lpos", -3, READ, 1, 1, 1),
+    spec("lpush", -3, WRITE_OOM_FAST, 1, 1, 1),
+    spec("lrange", 4, READ, 1, 1, 1),
+    spec("lrem", 4, WRITE, 1, 1, 1),
+    spec("ltrim", 4, WRITE, 1, 1, 1),
+    spec("memory", -2, CONTAINER, 0, 0, 0),
+    spec("mget", -2, READ_FAST, 1, -1, 1),
+    spec("mset", -3, WRITE_OOM, 1, -1, 2),
+    spec("msetnx", -3, WRITE_OOM, 1, -1, 2),
+    spec("multi", 1, TX, 0, 0, 0),
+    spec("object", -2, CONTAINER, 0, 0, 0),
+    spec("persist", 2, WRITE_FAST, 1, 1, 1),
+    spec("pexpire", -3, WRITE_FAST, 1, 1, 1),
+    spec("pfadd", -2, WRITE_OOM_FAST, 1, 1, 1),
+    spec("pfcount", -2, READ, 1, -1, 1),
+    spec("pfdebug", 3, &["readonly", "admin"], 2, 2, 1),
+    spec("pfmerge", -2, WRITE_OOM, 1, -1, 1),
+    spec("ping", -1, &["fast"], 0, 0, 0),
+    spec("psetex", 4, WRITE_OOM, 1, 1, 1),
+    spec("psubscribe", -2, PUBSUB, 0, 0, 0),
+    spec("pttl", 2, READ_FAST, 1, 1, 1),
+    spec(
+        "publish",
+        3,
+        &["pubsub", "loading", "stale", "fast"],
+        0,
+        0,
+        0,
+    ),
+    spec("pubsub", -2, CONTAINER, 0, 0, 0),
+    spec("punsubscribe", -1, PUBSUB, 0, 0, 0),
+    spec("quit", -1, &["fast"], 0, 0, 0),
+    spec("rename", 3, WRITE, 1, 2, 1),
+    spec("renamenx", 3, WRITE_FAST, 1, 2, 1),
+    spec("replicaof", 3, &["admin", "noscript", "stale"], 0, 0, 0),
+    spec(
+        "role",
+        1,
+        &["noscript", "loading", "stale", "fast"],
+        0,
+        0,
+        0,
+    ),
+    spec("rpop", -2, WRITE_FAST, 1, 1, 1),
+    spec("rpush", -3, WRITE_OOM_FAST, 1, 1, 1),
+    spec("sadd", -3, WRITE_OOM_FAST, 1, 1, 1),
+    spec("save", 1, ADMIN, 0, 0, 0),
+    spec("scan", -2, READ, 0, 0, 0),
+    spec("scard", 2, READ_FAST, 1, 1, 1),
+    spec("script", -2, CONTAINER, 0, 0, 0),
+    spec("sdiff", -2, READ, 1, -1, 1),
+    spec("sdiffstore", -3, WRITE_OOM, 1, -1, 1),
+    spec("select", 2, &["loading", "stale", "fast"], 0, 0, 0),
+    spec("set", -3, WRITE_OOM, 1, 1, 1),
+    spec("setbit", 4, WRITE_OOM, 1, 1, 1),
+    spec("setex", 4, WRITE_OOM, 1, 1, 1),
+    spec("setnx", 3, WRITE_OOM_FAST, 1, 1, 1),
+    spec("setrange", 4, WRITE_OOM, 1, 1, 1),
+    spec(
+        "shutdown",
+        -1,
+        &["admin", "noscript", "loading", "stale"],
+        0,
+        0,
+        0,
+    ),
+    spec("sinter", -2, READ, 1, -1, 1),
+    spec("sinterstore", -3, WRITE_OOM, 1, -1, 1),
+    spec("sismember", 3, READ_FAST, 1, 1, 1),
+    spec("slaveof", 3, &["admin", "noscript", "stale"], 0, 0, 0),
+    spec("slowlog", -2, CONTAINER, 0, 0, 0),
+    spec("smembers", 2, READ, 1, 1, 1),
+    spec("spop", -2, WRITE_FAST, 1, 1, 1),
+    spec(
+        "spublish",
+        3,
+        &["pubsub", "loading", "stale", "fast"],
+        1,
+        1,
+        1,
+    ),
+    spec("srandmember", -2, READ, 1, 1, 1),
+    spec("srem", -3, WRITE_FAST, 1, 1, 1),
+    spec("sscan", -3, READ, 1, 1, 1),
+    spec("ssubscribe", -2, PUBSUB, 1, -1, 1),
+    spec("strlen", 2, READ_FAST, 1, 1, 1),
+    spec("subscribe", -2, PUBSUB, 0, 0, 0),
+    spec("sunion", -2, READ, 1, -1, 1),
+    spec("sunionstore", -3, WRITE_OOM, 1, -1, 1),
+    spec("sunsubscribe", -1, PUBSUB, 1, -1, 1),
+    spec("time", 1, &["random", "loading", "stale", "fast"], 0, 0, 0),
+    spec("ttl", 2, READ_FAST, 1, 1, 1),
+    spec("type", 2, READ_FAST, 1, 1, 1),
+    spec("unlink", -2, WRITE_FAST, 1, -1, 1),
+    spec("unsubscribe", -1, PUBSUB, 0, 0, 0),
+    spec("unwatch", 1, TX, 0, 0, 0),
+    spec("wait", 3, &["noscript"], 0, 0, 0),
+    spec("watch", -2, TX, 1, -1, 1),
+    spec("zadd", -4, WRITE_OOM_FAST, 1, 1, 1),
+    spec("zcard", 2, READ_FAST, 1, 1, 1),
+    spec("zincrby", 4, WRITE_OOM_FAST, 1, 1, 1),
+    spec("zrange", -4, READ, 1, 1, 1),
+    spec("zrem", -3, WRITE_FAST, 1, 1, 1),
+    spec("zrevrange", -4, READ, 1, 1, 1),
+    spec("zscan", -3, READ, 1, 1, 1),
+    spec("zscore", 3, READ_FAST, 1, 1, 1),
+];
+
+/// 支持的命令数：COMMAND COUNT 与 INFO 的 `supported_commands` 都取自命令表
+pub fn command_count() -> usize {
+    COMMAND_TABLE.len()
+}
+
+/// 按名称（大小写不敏感）查找命令元信息
+pub fn lookup(name: &str) -> Option<&'static CommandSpec> {
+    let lower = name.to_ascii_lowercase();
+    COMMAND_TABLE
+        .binary_search_by(|spec| spec.name.cmp(lower.as_str()))
+        .ok()
+        .map(|idx| &COMMAND_TABLE[idx])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_is_sorted_and_unique() {
+        for pair in COMMAND_TABLE.windows(2) {
+            assert!(
+                pair[0].name < pair[1].name,
+                "{} >= {}",
+                pair[0].name,
+                pair[1].name
+            );
+        }
+    }
+
+    #[test]
+    fn lookup_is_case_insensitive() {
+        let get = lookup("GET").expect("get registered");
+        assert_eq!(get.arity, 2);
+        assert_eq!((get.first_key, get.last_key, get.step), (1, 1, 1));
+        assert!(lookup("nosuchcommand").is_none());
+    }
+}
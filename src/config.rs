@@ -0,0 +1,277 @@
+//! 配置文件加载（redis.conf 的子集）
+//!
+//! 与 redis-server 一样，配置文件路径作为第一个位置参数传入。每行是 `directive arg...`，
+//! `#` 开头的行是注释，参数可以用双引号包住（`save ""`）。只识别 Redust 支持的指令，
+//! 其余指令记录警告后忽略。解析结果通过 [`ServerConfig::env_defaults`] 映射到环境变量，
+//! 只在对应环境变量未设置时生效：配置文件 < 环境变量 < 命令行参数。
+
+use std::io;
+use std::path::Path;
+
+/// 配置文件解析结果
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ServerConfig {
+    /// `bind`：可以列出多个地址，目前只监听第一个
+    pub bind: Option<String>,
+    pub port: Option<u16>,
+    /// `maxmemory`，写法与 `REDUST_MAXMEMORY_BYTES` 相同（如 `100mb`）
+    pub maxmemory: Option<String>,
+    pub maxmemory_policy: Option<String>,
+    pub requirepass: Option<String>,
+    /// 所有 `save` 行合并后的保存点（如 `"900 1 300 10"`）；`save ""` 表示关闭
+    pub save: Option<String>,
+    pub appendonly: Option<bool>,
+    pub dir: Option<String>,
+    pub dbfilename: Option<String>,
+    pub slowlog_log_slower_than: Option<i64>,
+    /// 未知指令或非法取值的警告信息（带行号）
+    pub warnings: Vec<String>,
+}
+
+/// 读取并解析配置文件
+pub fn load_config_file<P: AsRef<Path>>(path: P) -> io::Result<ServerConfig> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(parse_config(&text))
+}
+
+/// 解析配置文件内容
+pub fn parse_config(text: &str) -> ServerConfig {
+    let mut config = ServerConfig::default();
+
+    for (idx, line) in text.lines().enumerate() {
+        let lineno = idx + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some(args) = split_args(line) else {
+            config
+                .warnings
+                .push(format!("line {}: unbalanced quotes", lineno));
+            continue;
+        };
+        let Some((directive, values)) = args.split_first() else {
+            continue;
+        };
+        let directive = directive.to_ascii_lowercase();
+        let single = || match values {
+            [v] => Some(v.clone()),
+            _ => None,
+        };
+
+        let ok = match directive.as_str() {
+            "bind" => {
+                // redis.conf 中的 `-` 前缀表示地址不可用时忽略，这里去掉前缀
+                let first = values
+                    .first()
+                    .map(|v| v.trim_start_matches('-').to_string());
+                config.bind = first.clone().or(config.bind.take());
+                first.is_some()
+            }
+            "port" => match single().and_then(|v| v.parse::<u16>().ok()) {
+                Some(port) => {
+                    config.port = Some(port);
+                    true
+                }
+                None => false,
+            },
+            "maxmemory" => set(&mut config.maxmemory, single()),
+            "maxmemory-policy" => set(&mut config.maxmemory_policy, single()),
+            "requirepass" => set(&mut config.requirepass, single()),
+            "dir" => set(&mut config.dir, single()),
+            "dbfilename" => set(&mut config.dbfilename, single()),
+            "save" => {
+                let spec = values.join(" ");
+                let spec = spec.trim();
+                // 多条 save 累加；`save ""` 清空之前的保存点
+                config.save = Some(match config.save.take() {
+                    Some(prev) if !prev.is_empty() && !spec.is_empty() => {
+                        format!("{} {}", prev, spec)
+                    }
+                    _ => spec.to_string(),
+                });
+                true
+            }
+            "appendonly" => match single().map(|v| v.to_ascii_lowercase()).as_deref() {
+                Some("yes") => {
+                    config.appendonly = Some(true);
+                    true
+                }
+                Some("no") => {
+                    config.appendonly = Some(false);
+                    true
+                }
+                _ => false,
+            },
+            "slowlog-log-slower-than" => match single().and_then(|v| v.parse::<i64>().ok()) {
+                Some(us) => {
+                    config.slowlog_log_slower_than = Some(us);
+                    true
+                }
+                None => false,
+            },
+            _ => {
+                config.warnings.push(format!(
+                    "line {}: ignoring unsupported directive '{}'",
+                    lineno, directive
+                ));
+                continue;
+            }
+        };
+        if !ok {
+            config.warnings.push(format!(
+                "line {}: invalid arguments for '{}'",
+                lineno, directive
+            ));
+        }
+    }
+
+    config
+}
+
+fn set(slot: &mut Option<String>, value: Option<String>) -> bool {
+    match value {
+        Some(v) => {
+            *slot = Some(v);
+            true
+        }
+        None => false,
+    }
+}
+
+/// 按空白拆分参数，支持双引号包住的参数（可以为空）；引号不成对时返回 None
+fn split_args(line: &str) -> Option<Vec<String>> {
+    let mut args = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(&c) = chars.peek() else {
+            return Some(args);
+        };
+        let mut arg = String::new();
+        if c == '"' {
+            chars.next();
+            loop {
+                match chars.next()? {
+                    '"' => break,
+                    '\\' => arg.push(chars.next()?),
+                    other => arg.push(other),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                arg.push(c);
+            }
+        }
+        args.push(arg);
+    }
+}
+
+impl ServerConfig {
+    /// 监听地址 `host:port`；都未配置时返回 None，`bind` 与 `port` 只配置其一时另一半取默认值
+    pub fn listen_addr(&self) -> Option<String> {
+        if self.bind.is_none() && self.port.is_none() {
+            return None;
+        }
+        let host = self.bind.as_deref().unwrap_or("127.0.0.1");
+        let host = if host.contains(':') && !host.starts_with('[') {
+            format!("[{}]", host)
+        } else {
+            host.to_string()
+        };
+        Some(format!("{}:{}", host, self.port.unwrap_or(6379)))
+    }
+
+    /// 配置对应的环境变量（名称, 取值）；调用方只在环境变量未设置时写入
+    pub fn env_defaults(&self) -> Vec<(&'static str, String)> {
+        let mut out = Vec::new();
+        if let Some(addr) = self.listen_addr() {
+            out.push(("REDUST_ADDR", addr));
+        }
+        if let Some(m) = &self.maxmemory {
+            out.push(("REDUST_MAXMEMORY_BYTES", m.clone()));
+        }
+        if let Some(p) = &self.maxmemory_policy {
+            out.push(("REDUST_MAXMEMORY_POLICY", p.clone()));
+        }
+        if let Some(pw) = &self.requirepass {
+            out.push(("REDUST_AUTH_PASSWORD", pw.clone()));
+        }
+        if let Some(save) = &self.save {
+            out.push(("REDUST_RDB_SAVE", save.clone()));
+        }
+        if let Some(enabled) = self.appendonly {
+            out.push((
+                "REDUST_AOF_ENABLED",
+                if enabled { "yes" } else { "no" }.to_string(),
+            ));
+        }
+        if let Some(name) = &self.dbfilename {
+            out.push(("REDUST_RDB_PATH", name.clone()));
+        }
+        if let Some(us) = self.slowlog_log_slower_than {
+            out.push(("REDUST_SLOWLOG_LOG_SLOWER_THAN", us.to_string()));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_supported_directives_from_file() {
+        let path = std::env::temp_dir().join(format!("redust-config-{}.conf", std::process::id()));
+        std::fs::write(
+            &path,
+            "# 示例配置\n\
+             bind 0.0.0.0 -::1\n\
+             port 7000\n\
+             maxmemory 100mb\n\
+             maxmemory-policy noeviction\n\
+             requirepass \"s3cret pw\"\n\
+             save 900 1\n\
+             save 300 10\n\
+             appendonly yes\n\
+             dir /var/lib/redust\n\
+             dbfilename dump.rdb\n\
+             slowlog-log-slower-than 5000\n\
+             daemonize yes\n",
+        )
+        .unwrap();
+        let config = load_config_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            config,
+            ServerConfig {
+                bind: Some("0.0.0.0".to_string()),
+                port: Some(7000),
+                maxmemory: Some("100mb".to_string()),
+                maxmemory_policy: Some("noeviction".to_string()),
+                requirepass: Some("s3cret pw".to_string()),
+                save: Some("900 1 300 10".to_string()),
+                appendonly: Some(true),
+                dir: Some("/var/lib/redust".to_string()),
+                dbfilename: Some("dump.rdb".to_string()),
+                slowlog_log_slower_than: Some(5000),
+                warnings: vec!["line 13: ignoring unsupported directive 'daemonize'".to_string()],
+            }
+        );
+        assert_eq!(config.listen_addr().as_deref(), Some("0.0.0.0:7000"));
+        assert!(config
+            .env_defaults()
+            .contains(&("REDUST_RDB_SAVE", "900 1 300 10".to_string())));
+    }
+
+    #[test]
+    fn empty_save_and_invalid_values() {
+        let config = parse_config("save 900 1\nsave \"\"\nport abc\nappendonly maybe\n");
+        assert_eq!(config.save.as_deref(), Some(""));
+        assert_eq!(config.port, None);
+        assert_eq!(config.appendonly, None);
+        assert_eq!(config.warnings.len(), 2);
+        assert_eq!(config.listen_addr(), None);
+    }
+}
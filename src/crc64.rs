@@ -0,0 +1,49 @@
+//! CRC-64/Jones 校验实现（与 Redis RDB 使用的 CRC64 算法一致）
+//!
+//! 参数：反射输入/输出，多项式 0xad93d23594c935a9（反射形式 0x95ac9329ac4bc9b5），
+//! 初值 0，结果不取反。用于 RDB 文件尾部的完整性校验。
+
+/// 反射形式的多项式
+const POLY: u64 = 0x95ac_9329_ac4b_c9b5;
+
+const fn build_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u64;
+        let mut bit = 0;
+        while bit < 8 {
+            if crc & 1 == 1 {
+                crc = (crc >> 1) ^ POLY;
+            } else {
+                crc >>= 1;
+            }
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+static TABLE: [u64; 256] = build_table();
+
+/// 计算 `data` 的 CRC64 校验值
+pub fn crc64(data: &[u8]) -> u64 {
+    let mut crc = 0u64;
+    for &b in data {
+        crc = TABLE[((crc ^ b as u64) & 0xff) as usize] ^ (crc >> 8);
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_redis_reference_value() {
+        // 与 Redis crc64.c 自带测试向量一致
+        assert_eq!(crc64(b"123456789"), 0xe9c6_d914_c4b8_d9ca);
+    }
+}
@@ -2,13 +2,16 @@
 ///
 /// 基于 Redis 的 HyperLogLog 实现，使用 16384 个寄存器（2^14），
 /// 每个寄存器 6 位精度，标准误差约 0.81%。
+/// 小基数时使用稀疏表示，超过 `hll-sparse-max-bytes` 后升级为密集表示。
 ///
 /// 参考：
 /// - Redis hyperloglog.c: https://github.com/redis/redis/blob/unstable/src/hyperloglog.c
 /// - HyperLogLog 论文: http://algo.inria.fr/flajolet/Publications/FlFuGaMe07.pdf
 
+use std::borrow::Cow;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 
 /// HyperLogLog 寄存器数量 (2^14 = 16384)
 const HLL_REGISTERS: usize = 16384;
@@ -19,24 +22,123 @@ const HLL_P: u32 = 14;
 /// 每个寄存器的最大值 (6 bits = 63)
 const HLL_REGISTER_MAX: u8 = 63;
 
+/// 用于计算前导零的哈希位数 (64 - 14)，寄存器取值范围为 0..=HLL_Q + 1
+const HLL_Q: usize = 50;
+
+/// Ertl 估算器中的常数 1 / (2 ln 2)
+const HLL_ALPHA_INF: f64 = 0.721_347_520_444_481_7;
+
+/// 密集表示占用的字节数：16384 * 6 / 8
+pub const HLL_DENSE_SIZE: usize = HLL_REGISTERS * 6 / 8;
+
+/// 缓存的基数估算无效时的取值（与 Redis HLL 头部的 invalid 位作用相同）
+const HLL_CACHE_INVALID: u64 = u64::MAX;
+
+/// 稀疏表示中每个非零寄存器的开销：u16 索引 + u8 值
+const HLL_SPARSE_ENTRY_SIZE: usize = 3;
+
+/// 与 Redis `hll-sparse-max-bytes` 默认值一致
+pub const DEFAULT_SPARSE_MAX_BYTES: usize = 3000;
+
+/// 稀疏表示超过该字节数时升级为密集表示，可通过 `set_sparse_max_bytes` 调整
+static SPARSE_MAX_BYTES: AtomicUsize = AtomicUsize::new(DEFAULT_SPARSE_MAX_BYTES);
+
+pub fn sparse_max_bytes() -> usize {
+    SPARSE_MAX_BYTES.load(Ordering::Relaxed)
+}
+
+/// 设置稀疏表示的上限（字节）；超过密集表示大小没有意义，会被截断到 `HLL_DENSE_SIZE`
+pub fn set_sparse_max_bytes(bytes: usize) {
+    SPARSE_MAX_BYTES.store(bytes.min(HLL_DENSE_SIZE), Ordering::Relaxed);
+}
+
+fn sparse_max_entries() -> usize {
+    sparse_max_bytes() / HLL_SPARSE_ENTRY_SIZE
+}
+
+/// Ertl 估算器的 σ 函数，处理值为 0 的寄存器
+fn hll_sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+    let mut y = 1.0;
+    let mut z = x;
+    loop {
+        x *= x;
+        let z_prev = z;
+        z += x * y;
+        y += y;
+        if z_prev == z {
+            return z;
+        }
+    }
+}
+
+/// Ertl 估算器的 τ 函数，处理取到最大值 q + 1 的寄存器
+fn hll_tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+    let mut y = 1.0;
+    let mut z = 1.0 - x;
+    loop {
+        x = x.sqrt();
+        let z_prev = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+        if z_prev == z {
+            return z / 3.0;
+        }
+    }
+}
+
+/// 寄存器的两种存储方式，两者表示的寄存器值完全相同，估算结果也完全一致
+#[derive(Debug, Clone)]
+enum Registers {
+    /// 只保存非零寄存器，按索引升序排列；适合大量小基数 key
+    Sparse(Vec<(u16, u8)>),
+    /// 16384 个寄存器，每个元素存储一个 6-bit 值 (0-63)
+    Dense(Vec<u8>),
+}
+
 /// HyperLogLog 数据结构
 ///
-/// 使用密集表示：16384 个 6-bit 寄存器
-/// 总内存占用：16384 * 6 / 8 = 12288 bytes ≈ 12KB
-#[derive(Debug, Clone)]
+/// 新建的 HLL 使用稀疏表示，非零寄存器数超过 `hll-sparse-max-bytes` 对应的条目数后
+/// 升级为密集表示（约 12KB），升级后不再降级
+#[derive(Debug)]
 pub struct HyperLogLog {
-    /// 寄存器数组，每个元素存储一个 6-bit 值 (0-63)
-    registers: Vec<u8>,
+    registers: Registers,
+    /// 上一次 `count` 的结果；寄存器被修改时失效。
+    /// PFCOUNT 只持有读锁，因此用原子量做内部可变
+    cached_count: AtomicU64,
+}
+
+impl Clone for HyperLogLog {
+    fn clone(&self) -> Self {
+        HyperLogLog {
+            registers: self.registers.clone(),
+            cached_count: AtomicU64::new(self.cached_count.load(Ordering::Relaxed)),
+        }
+    }
 }
 
 impl HyperLogLog {
-    /// 创建一个新的空 HyperLogLog
+    /// 创建一个新的空 HyperLogLog（稀疏表示）
     pub fn new() -> Self {
+        HyperLogLog::with_registers(Registers::Sparse(Vec::new()))
+    }
+
+    fn with_registers(registers: Registers) -> Self {
         HyperLogLog {
-            registers: vec![0; HLL_REGISTERS],
+            registers,
+            cached_count: AtomicU64::new(HLL_CACHE_INVALID),
         }
     }
 
+    fn invalidate_cache(&self) {
+        self.cached_count.store(HLL_CACHE_INVALID, Ordering::Relaxed);
+    }
+
     /// 添加一个元素到 HyperLogLog
     ///
     /// 返回 true 如果寄存器被更新（基数可能改变）
@@ -53,73 +155,142 @@ impl HyperLogLog {
 
         // 计算前导零数量 + 1（至少为 1）
         let leading_zeros = if remaining == 0 {
-            51 // 50 bits 全为 0，加上隐含的 1 位
+            (HLL_Q + 1) as u8 // 50 bits 全为 0，加上隐含的 1 位
         } else {
-            (remaining.leading_zeros() as u8) - (64 - 50) + 1
+            (remaining.leading_zeros() as u8) - (64 - HLL_Q as u8) + 1
         };
 
         // 限制在 6 bits 范围内
         let count = leading_zeros.min(HLL_REGISTER_MAX);
 
         // 更新寄存器（取最大值）
-        let old_value = self.registers[index];
-        if count > old_value {
-            self.registers[index] = count;
-            true
-        } else {
-            false
+        let changed = match &mut self.registers {
+            Registers::Dense(registers) => {
+                if count > registers[index] {
+                    registers[index] = count;
+                    true
+                } else {
+                    false
+                }
+            }
+            Registers::Sparse(entries) => {
+                match entries.binary_search_by_key(&(index as u16), |&(i, _)| i) {
+                    Ok(pos) => {
+                        if count > entries[pos].1 {
+                            entries[pos].1 = count;
+                            true
+                        } else {
+                            false
+                        }
+                    }
+                    Err(pos) => {
+                        entries.insert(pos, (index as u16, count));
+                        true
+                    }
+                }
+            }
+        };
+        if changed {
+            self.invalidate_cache();
         }
+        self.promote_if_needed();
+        changed
     }
 
     /// 估算基数
     ///
-    /// 使用 HyperLogLog 算法计算不同元素的估计数量
+    /// 与 Redis 5+ 相同，使用 Ertl 的改进估算器（"New cardinality estimation
+    /// algorithms for HyperLogLog sketches"）：只依赖寄存器值的直方图，
+    /// 在整个基数范围内都无需线性计数或偏差修正表，小基数与过渡区间的误差也保持在标准误差附近
+    ///
+    /// 结果会被缓存，直到下一次修改寄存器的 add/merge；寄存器未变的 PFADD 不会让缓存失效
     pub fn count(&self) -> u64 {
-        // 计算调和平均数的倒数
-        let mut sum = 0.0;
-        let mut zeros = 0;
-
-        for &register in &self.registers {
-            if register == 0 {
-                zeros += 1;
-            }
-            sum += 1.0 / (1u64 << register) as f64;
+        let cached = self.cached_count.load(Ordering::Relaxed);
+        if cached != HLL_CACHE_INVALID {
+            return cached;
         }
+        let estimate = self.estimate();
+        self.cached_count.store(estimate, Ordering::Relaxed);
+        estimate
+    }
 
-        // 标准 HyperLogLog 估算公式
-        let m = HLL_REGISTERS as f64;
-        let alpha = 0.7213 / (1.0 + 1.079 / m);
-        let raw_estimate = alpha * m * m / sum;
-
-        // 小范围修正（使用线性计数）
-        if raw_estimate <= 2.5 * m {
-            if zeros > 0 {
-                let linear_count = m * (m / zeros as f64).ln();
-                if linear_count <= 2.5 * m {
-                    return linear_count as u64;
+    /// 根据寄存器重新计算估算值（不读写缓存）
+    fn estimate(&self) -> u64 {
+        // 寄存器值直方图；稀疏表示中未出现的寄存器都是 0
+        let mut histogram = [0u32; HLL_Q + 2];
+        match &self.registers {
+            Registers::Dense(registers) => {
+                for &register in registers {
+                    histogram[register as usize] += 1;
+                }
+            }
+            Registers::Sparse(entries) => {
+                histogram[0] = (HLL_REGISTERS - entries.len()) as u32;
+                for &(_, register) in entries {
+                    histogram[register as usize] += 1;
                 }
             }
         }
 
-        // 大范围修正（2^32 附近）
-        if raw_estimate <= (1u64 << 32) as f64 / 30.0 {
-            raw_estimate as u64
-        } else {
-            let two_pow_32 = (1u64 << 32) as f64;
-            let corrected = -two_pow_32 * (1.0 - raw_estimate / two_pow_32).ln();
-            corrected as u64
+        let m = HLL_REGISTERS as f64;
+        let mut z = m * hll_tau((m - histogram[HLL_Q + 1] as f64) / m);
+        for k in (1..=HLL_Q).rev() {
+            z += histogram[k] as f64;
+            z *= 0.5;
         }
+        z += m * hll_sigma(histogram[0] as f64 / m);
+        (HLL_ALPHA_INF * m * m / z).round() as u64
     }
 
     /// 合并另一个 HyperLogLog 到当前实例
     ///
-    /// 对每个寄存器取最大值
+    /// 对每个寄存器取最大值；两边表示方式可以不同，结果按需升级为密集表示
     pub fn merge(&mut self, other: &HyperLogLog) {
-        for i in 0..HLL_REGISTERS {
-            if other.registers[i] > self.registers[i] {
-                self.registers[i] = other.registers[i];
+        self.invalidate_cache();
+        match (&mut self.registers, &other.registers) {
+            (Registers::Dense(mine), Registers::Dense(theirs)) => {
+                for (a, &b) in mine.iter_mut().zip(theirs) {
+                    if b > *a {
+                        *a = b;
+                    }
+                }
+            }
+            (Registers::Dense(mine), Registers::Sparse(theirs)) => {
+                for &(index, value) in theirs {
+                    let slot = &mut mine[index as usize];
+                    if value > *slot {
+                        *slot = value;
+                    }
+                }
+            }
+            (Registers::Sparse(_), Registers::Dense(_)) => {
+                self.promote_to_dense();
+                self.merge(other);
+                return;
+            }
+            (Registers::Sparse(mine), Registers::Sparse(theirs)) => {
+                let mut merged = Vec::with_capacity(mine.len().max(theirs.len()));
+                let (mut i, mut j) = (0, 0);
+                while i < mine.len() && j < theirs.len() {
+                    let (a, b) = (mine[i], theirs[j]);
+                    if a.0 < b.0 {
+                        merged.push(a);
+                        i += 1;
+                    } else if b.0 < a.0 {
+                        merged.push(b);
+                        j += 1;
+                    } else {
+                        merged.push((a.0, a.1.max(b.1)));
+                        i += 1;
+                        j += 1;
+                    }
+                }
+                merged.extend_from_slice(&mine[i..]);
+                merged.extend_from_slice(&theirs[j..]);
+                *mine = merged;
             }
         }
+        self.promote_if_needed();
     }
 
     /// 计算 64-bit 哈希值
@@ -132,12 +303,44 @@ impl HyperLogLog {
         hasher.finish()
     }
 
-    /// 获取寄存器数据（用于序列化）
-    pub fn registers(&self) -> &[u8] {
-        &self.registers
+    /// 稀疏条目数超过上限时升级为密集表示
+    fn promote_if_needed(&mut self) {
+        if let Registers::Sparse(entries) = &self.registers {
+            if entries.len() > sparse_max_entries() {
+                self.promote_to_dense();
+            }
+        }
+    }
+
+    fn promote_to_dense(&mut self) {
+        if let Registers::Sparse(entries) = &self.registers {
+            let mut registers = vec![0u8; HLL_REGISTERS];
+            for &(index, value) in entries {
+                registers[index as usize] = value;
+            }
+            self.registers = Registers::Dense(registers);
+        }
+    }
+
+    /// 获取完整的 16384 个寄存器（用于序列化与 PFDEBUG GETREG）
+    ///
+    /// 密集表示直接借用，稀疏表示会展开成一份新的数组
+    pub fn registers(&self) -> Cow<'_, [u8]> {
+        match &self.registers {
+            Registers::Dense(registers) => Cow::Borrowed(registers),
+            Registers::Sparse(entries) => {
+                let mut registers = vec![0u8; HLL_REGISTERS];
+                for &(index, value) in entries {
+                    registers[index as usize] = value;
+                }
+                Cow::Owned(registers)
+            }
+        }
     }
 
     /// 从寄存器数据创建 HyperLogLog（用于反序列化）
+    ///
+    /// 非零寄存器足够少时恢复为稀疏表示，保持与写入前相同的内存占用
     pub fn from_registers(registers: Vec<u8>) -> Option<Self> {
         if registers.len() != HLL_REGISTERS {
             return None;
@@ -146,12 +349,63 @@ impl HyperLogLog {
         if registers.iter().any(|&r| r > HLL_REGISTER_MAX) {
             return None;
         }
-        Some(HyperLogLog { registers })
+        let nonzero = registers.iter().filter(|&&r| r != 0).count();
+        if nonzero <= sparse_max_entries() {
+            let entries = registers
+                .iter()
+                .enumerate()
+                .filter(|(_, &r)| r != 0)
+                .map(|(i, &r)| (i as u16, r))
+                .collect();
+            return Some(HyperLogLog::with_registers(Registers::Sparse(entries)));
+        }
+        Some(HyperLogLog::with_registers(Registers::Dense(registers)))
+    }
+
+    /// 稀疏表示下的非零寄存器 `(索引, 值)`，按索引升序；密集表示返回 None（用于序列化）
+    pub fn sparse_entries(&self) -> Option<&[(u16, u8)]> {
+        match &self.registers {
+            Registers::Sparse(entries) => Some(entries),
+            Registers::Dense(_) => None,
+        }
+    }
+
+    /// 从稀疏条目创建 HyperLogLog（用于反序列化），保持稀疏表示
+    ///
+    /// 索引必须严格升序且小于寄存器数，值必须在 1..=63 之间
+    pub fn from_sparse_entries(entries: Vec<(u16, u8)>) -> Option<Self> {
+        let in_range = entries.iter().all(|&(index, value)| {
+            (index as usize) < HLL_REGISTERS && value != 0 && value <= HLL_REGISTER_MAX
+        });
+        let ascending = entries.windows(2).all(|w| w[0].0 < w[1].0);
+        if !in_range || !ascending {
+            return None;
+        }
+        Some(HyperLogLog::with_registers(Registers::Sparse(entries)))
+    }
+
+    /// 当前的存储编码（PFDEBUG ENCODING 使用）
+    pub fn encoding(&self) -> &'static str {
+        match self.registers {
+            Registers::Sparse(_) => "sparse",
+            Registers::Dense(_) => "dense",
+        }
+    }
+
+    /// 估算寄存器部分占用的字节数（used_memory 统计使用）
+    pub fn memory_usage(&self) -> usize {
+        match &self.registers {
+            Registers::Sparse(entries) => entries.len() * HLL_SPARSE_ENTRY_SIZE,
+            Registers::Dense(_) => HLL_DENSE_SIZE,
+        }
     }
 
     /// 检查 HyperLogLog 是否为空（所有寄存器都为 0）
     pub fn is_empty(&self) -> bool {
-        self.registers.iter().all(|&r| r == 0)
+        match &self.registers {
+            Registers::Sparse(entries) => entries.is_empty(),
+            Registers::Dense(registers) => registers.iter().all(|&r| r == 0),
+        }
     }
 }
 
@@ -268,7 +522,7 @@ mod tests {
             hll.add(format!("element_{}", i).as_bytes());
         }
         
-        let registers = hll.registers().to_vec();
+        let registers = hll.registers().into_owned();
         let restored = HyperLogLog::from_registers(registers).unwrap();
         
         assert_eq!(hll.count(), restored.count());
@@ -287,6 +541,23 @@ mod tests {
         assert!(HyperLogLog::from_registers(invalid).is_none());
     }
 
+    #[test]
+    fn test_sparse_entries_roundtrip() {
+        let mut hll = HyperLogLog::new();
+        for i in 0..50 {
+            hll.add(format!("element_{}", i).as_bytes());
+        }
+
+        let entries = hll.sparse_entries().unwrap().to_vec();
+        let restored = HyperLogLog::from_sparse_entries(entries).unwrap();
+        assert_eq!(restored.encoding(), "sparse");
+        assert_eq!(hll.count(), restored.count());
+
+        assert!(HyperLogLog::from_sparse_entries(vec![(3, 1), (2, 1)]).is_none());
+        assert!(HyperLogLog::from_sparse_entries(vec![(16384, 1)]).is_none());
+        assert!(HyperLogLog::from_sparse_entries(vec![(0, 64)]).is_none());
+    }
+
     #[test]
     fn test_duplicate_elements() {
         let mut hll = HyperLogLog::new();
@@ -303,6 +574,169 @@ mod tests {
         assert!(count >= 8 && count <= 12, "count = {}", count);
     }
 
+    #[test]
+    fn test_registers_match_hashed_indices() {
+        let mut hll = HyperLogLog::new();
+        let mut indices = std::collections::HashSet::new();
+        for i in 0..200 {
+            let element = format!("element_{}", i);
+            hll.add(element.as_bytes());
+            indices.insert((hll.hash64(element.as_bytes()) & ((1 << HLL_P) - 1)) as usize);
+        }
+
+        // 非零寄存器恰好是被元素哈希命中的那些索引
+        let nonzero: std::collections::HashSet<usize> = hll
+            .registers()
+            .iter()
+            .enumerate()
+            .filter(|(_, &r)| r != 0)
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(nonzero, indices);
+        assert_eq!(hll.encoding(), "sparse");
+    }
+
+    /// 标准误差 1.04 / sqrt(16384) ≈ 0.81%，单次估算按 3 倍标准误差判断
+    fn assert_within_standard_error(n: usize, estimate: u64) {
+        let tolerance = (n as f64 * 0.0081 * 3.0).max(2.0);
+        let diff = (estimate as f64 - n as f64).abs();
+        assert!(
+            diff <= tolerance,
+            "n = {}, estimate = {}, error = {:.3}%",
+            n,
+            estimate,
+            diff / n as f64 * 100.0
+        );
+    }
+
+    #[test]
+    fn test_estimate_within_standard_error_at_scales() {
+        for n in [10, 100, 1_000, 5_000, 20_000, 40_000, 60_000, 100_000, 500_000] {
+            let mut hll = HyperLogLog::new();
+            for i in 0..n {
+                hll.add(format!("scale:{}", i).as_bytes());
+            }
+            assert_within_standard_error(n, hll.count());
+        }
+    }
+
+    #[test]
+    fn test_sparse_promotes_to_dense_past_threshold() {
+        let mut hll = HyperLogLog::new();
+        assert_eq!(hll.encoding(), "sparse");
+
+        let mut added = 0;
+        while hll.encoding() == "sparse" {
+            if let Registers::Sparse(entries) = &hll.registers {
+                assert!(entries.len() <= sparse_max_entries());
+            }
+            // 过渡边界两侧的估算同样要在标准误差内，且与密集表示完全一致
+            let mut dense = hll.clone();
+            dense.promote_to_dense();
+            assert_eq!(dense.count(), hll.count());
+            if added % 100 == 0 {
+                assert_within_standard_error(added, hll.count());
+            }
+            hll.add(format!("boundary:{}", added).as_bytes());
+            added += 1;
+        }
+        assert_eq!(hll.encoding(), "dense");
+        assert_within_standard_error(added, hll.count());
+        assert!(hll.memory_usage() > DEFAULT_SPARSE_MAX_BYTES);
+
+        // 升级后继续添加不会回到稀疏表示
+        hll.add(b"one-more");
+        assert_eq!(hll.encoding(), "dense");
+    }
+
+    #[test]
+    fn test_merge_mixed_representations() {
+        let mut small = HyperLogLog::new();
+        for i in 0..200 {
+            small.add(format!("mixed:{}", i).as_bytes());
+        }
+        let mut large = HyperLogLog::new();
+        for i in 100..20_000 {
+            large.add(format!("mixed:{}", i).as_bytes());
+        }
+        assert_eq!(small.encoding(), "sparse");
+        assert_eq!(large.encoding(), "dense");
+
+        let mut all = HyperLogLog::new();
+        for i in 0..20_000 {
+            all.add(format!("mixed:{}", i).as_bytes());
+        }
+
+        // 稀疏合并密集：升级为密集
+        let mut a = small.clone();
+        a.merge(&large);
+        assert_eq!(a.encoding(), "dense");
+        assert_eq!(a.registers(), all.registers());
+
+        // 密集合并稀疏
+        let mut b = large.clone();
+        b.merge(&small);
+        assert_eq!(b.registers(), all.registers());
+        assert_eq!(a.count(), b.count());
+
+        // 稀疏合并稀疏：结果足够大时同样升级
+        let mut left = HyperLogLog::new();
+        let mut right = HyperLogLog::new();
+        for i in 0..800 {
+            left.add(format!("left:{}", i).as_bytes());
+            right.add(format!("right:{}", i).as_bytes());
+        }
+        assert_eq!(left.encoding(), "sparse");
+        assert_eq!(right.encoding(), "sparse");
+        left.merge(&right);
+        assert_eq!(left.encoding(), "dense");
+        assert_within_standard_error(1_600, left.count());
+    }
+
+    #[test]
+    fn test_from_registers_restores_sparse_encoding() {
+        let mut hll = HyperLogLog::new();
+        for i in 0..50 {
+            hll.add(format!("restore:{}", i).as_bytes());
+        }
+        let restored = HyperLogLog::from_registers(hll.registers().into_owned()).unwrap();
+        assert_eq!(restored.encoding(), "sparse");
+        assert_eq!(restored.count(), hll.count());
+    }
+
+    #[test]
+    fn test_count_cache_survives_noop_adds() {
+        let mut hll = HyperLogLog::new();
+        for i in 0..5_000 {
+            hll.add(format!("cache:{}", i).as_bytes());
+        }
+        assert_eq!(hll.cached_count.load(Ordering::Relaxed), HLL_CACHE_INVALID);
+        let count = hll.count();
+        assert_eq!(hll.cached_count.load(Ordering::Relaxed), count);
+
+        // 重复元素不修改寄存器，缓存保持有效
+        for i in 0..5_000 {
+            assert!(!hll.add(format!("cache:{}", i).as_bytes()));
+        }
+        assert_eq!(hll.cached_count.load(Ordering::Relaxed), count);
+        assert_eq!(hll.count(), count);
+
+        // 修改寄存器后缓存失效，重新计算的结果与直接估算一致
+        let mut i = 5_000;
+        while !hll.add(format!("cache:{}", i).as_bytes()) {
+            i += 1;
+        }
+        assert_eq!(hll.cached_count.load(Ordering::Relaxed), HLL_CACHE_INVALID);
+        assert_eq!(hll.count(), hll.estimate());
+
+        // merge 同样使缓存失效；被合并的一方不受影响
+        let other = HyperLogLog::new();
+        let other_count = other.count();
+        hll.merge(&other);
+        assert_eq!(hll.cached_count.load(Ordering::Relaxed), HLL_CACHE_INVALID);
+        assert_eq!(other.cached_count.load(Ordering::Relaxed), other_count);
+    }
+
     #[test]
     fn test_binary_data() {
         let mut hll = HyperLogLog::new();
@@ -1,8 +1,14 @@
+pub mod cli;
 pub mod command;
+pub mod command_table;
+pub mod config;
+pub mod crc64;
 pub mod hyperloglog;
+pub mod notify;
 pub mod resp;
 pub mod scripting;
 pub mod server;
+pub mod slowlog;
 pub mod storage;
 
 pub use server::run_server;
@@ -2,48 +2,53 @@ use std::env;
 use tokio::io;
 use tokio::signal; // Import the signal module
 
-use log::info;
-use redust::run_server;
+use log::{info, warn};
+use redust::{cli, config, run_server};
 
 #[tokio::main]
 async fn main() -> io::Result<()> {
     // 初始化日志（仅在 main 中调用一次），默认 info 级别，可被 RUST_LOG 覆盖
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
-    // 解析简单的命令行参数：支持 --bind 和 --maxmemory-bytes
-    let args: Vec<String> = env::args().skip(1).collect();
-
-    let mut bind_from_cli: Option<String> = None;
-    let mut maxmemory_from_cli: Option<String> = None;
-
-    let mut i = 0;
-    while i < args.len() {
-        match args[i].as_str() {
-            "--bind" => {
-                if i + 1 < args.len() {
-                    bind_from_cli = Some(args[i + 1].clone());
-                    i += 1;
-                }
+    // 解析 redis-server 风格的命令行参数，未知参数只警告不退出
+    let cli = cli::parse_args(env::args().skip(1));
+    for warning in &cli.warnings {
+        warn!("[cli] {}", warning);
+    }
+    if cli.unixsocket.is_some() {
+        warn!("[cli] unix sockets are not supported yet, ignoring --unixsocket");
+    }
+
+    // 配置文件优先级最低：只填充尚未设置的环境变量，--dir 覆盖文件中的 dir
+    let file_config = match &cli.config_file {
+        Some(path) => {
+            let file_config = config::load_config_file(path).map_err(|e| {
+                io::Error::new(e.kind(), format!("failed to read config file '{}': {}", path, e))
+            })?;
+            for warning in &file_config.warnings {
+                warn!("[config] {}: {}", path, warning);
             }
-            "--maxmemory-bytes" => {
-                if i + 1 < args.len() {
-                    maxmemory_from_cli = Some(args[i + 1].clone());
-                    i += 1;
+            for (name, value) in file_config.env_defaults() {
+                if env::var_os(name).is_none() {
+                    env::set_var(name, value);
                 }
             }
-            _ => {}
+            file_config
         }
-        i += 1;
+        None => config::ServerConfig::default(),
+    };
+
+    // --dir 与 redis-server 一样切换工作目录，相对路径的 RDB/AOF 文件都写在这里
+    if let Some(dir) = cli.dir.as_ref().or(file_config.dir.as_ref()) {
+        env::set_current_dir(dir)?;
     }
 
     // 命令行优先覆盖环境变量
-    if let Some(b) = bind_from_cli {
-        env::set_var("REDUST_ADDR", &b);
-    }
-    if let Some(m) = maxmemory_from_cli {
-        env::set_var("REDUST_MAXMEMORY_BYTES", &m);
+    for (name, value) in cli.env_overrides() {
+        env::set_var(name, value);
     }
 
-    let bind_addr = env::var("REDUST_ADDR").unwrap_or_else(|_| "127.0.0.1:6379".to_string());
+    let default_addr = env::var("REDUST_ADDR").unwrap_or_else(|_| "127.0.0.1:6379".to_string());
+    let bind_addr = cli.bind_addr(&default_addr);
 
     // Create a future that resolves when Ctrl+C is received
     let shutdown_future = async {
@@ -0,0 +1,177 @@
+//! 键空间通知（notify-keyspace-events）
+//!
+//! 配置字符串与 Redis 一致：`K` 发布到 `__keyspace@<db>__:<key>`（消息为事件名），
+//! `E` 发布到 `__keyevent@<db>__:<event>`（消息为 key），其余字符选择事件类别，
+//! `A` 是 `g$lshzxe` 的别名。K 与 E 至少要有一个，且至少选中一个类别，才会真正发布。
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+pub const NOTIFY_KEYSPACE: u32 = 1 << 0;
+pub const NOTIFY_KEYEVENT: u32 = 1 << 1;
+pub const NOTIFY_GENERIC: u32 = 1 << 2;
+pub const NOTIFY_STRING: u32 = 1 << 3;
+pub const NOTIFY_LIST: u32 = 1 << 4;
+pub const NOTIFY_SET: u32 = 1 << 5;
+pub const NOTIFY_HASH: u32 = 1 << 6;
+pub const NOTIFY_ZSET: u32 = 1 << 7;
+pub const NOTIFY_EXPIRED: u32 = 1 << 8;
+pub const NOTIFY_EVICTED: u32 = 1 << 9;
+pub const NOTIFY_ALL: u32 = NOTIFY_GENERIC
+    | NOTIFY_STRING
+    | NOTIFY_LIST
+    | NOTIFY_SET
+    | NOTIFY_HASH
+    | NOTIFY_ZSET
+    | NOTIFY_EXPIRED
+    | NOTIFY_EVICTED;
+
+/// 存储层产生的键空间事件，由 server 转交给 pub/sub 按配置发布；`key` 带 db 前缀
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyspaceEvent {
+    pub class: u32,
+    pub event: &'static str,
+    pub key: String,
+}
+
+/// 解析配置字符串；包含未知字符时返回 None
+pub fn parse_flags(s: &str) -> Option<u32> {
+    let mut flags = 0;
+    for c in s.chars() {
+        flags |= match c {
+            'A' => NOTIFY_ALL,
+            'g' => NOTIFY_GENERIC,
+            '$' => NOTIFY_STRING,
+            'l' => NOTIFY_LIST,
+            's' => NOTIFY_SET,
+            'h' => NOTIFY_HASH,
+            'z' => NOTIFY_ZSET,
+            'x' => NOTIFY_EXPIRED,
+            'e' => NOTIFY_EVICTED,
+            'K' => NOTIFY_KEYSPACE,
+            'E' => NOTIFY_KEYEVENT,
+            _ => return None,
+        };
+    }
+    Some(flags)
+}
+
+/// 将标志位还原为规范化的配置字符串（CONFIG GET 使用）
+pub fn format_flags(flags: u32) -> String {
+    let mut out = String::new();
+    if flags & NOTIFY_ALL == NOTIFY_ALL {
+        out.push('A');
+    } else {
+        for (bit, c) in [
+            (NOTIFY_GENERIC, 'g'),
+            (NOTIFY_STRING, '$'),
+            (NOTIFY_LIST, 'l'),
+            (NOTIFY_SET, 's'),
+            (NOTIFY_HASH, 'h'),
+            (NOTIFY_ZSET, 'z'),
+            (NOTIFY_EXPIRED, 'x'),
+            (NOTIFY_EVICTED, 'e'),
+        ] {
+            if flags & bit != 0 {
+                out.push(c);
+            }
+        }
+    }
+    if flags & NOTIFY_KEYSPACE != 0 {
+        out.push('K');
+    }
+    if flags & NOTIFY_KEYEVENT != 0 {
+        out.push('E');
+    }
+    out
+}
+
+/// 运行时可修改的通知配置
+#[derive(Debug, Default)]
+pub struct NotifyConfig {
+    flags: AtomicU32,
+}
+
+impl NotifyConfig {
+    pub fn new(flags: u32) -> Self {
+        NotifyConfig {
+            flags: AtomicU32::new(flags),
+        }
+    }
+
+    /// 从 `REDUST_NOTIFY_KEYSPACE_EVENTS` 读取初始配置，默认关闭
+    pub fn from_env() -> Self {
+        let flags = std::env::var("REDUST_NOTIFY_KEYSPACE_EVENTS")
+            .ok()
+            .and_then(|v| parse_flags(v.trim()))
+            .unwrap_or(0);
+        NotifyConfig::new(flags)
+    }
+
+    pub fn flags(&self) -> u32 {
+        self.flags.load(Ordering::Relaxed)
+    }
+
+    pub fn set_flags(&self, flags: u32) {
+        self.flags.store(flags, Ordering::Relaxed);
+    }
+
+    /// 计算某类事件需要发布的频道与消息：返回 (channel, payload) 列表
+    pub fn channels_for(
+        &self,
+        class: u32,
+        event: &str,
+        db: &str,
+        key: &str,
+    ) -> Vec<(String, Vec<u8>)> {
+        let flags = self.flags();
+        if flags & class == 0 {
+            return Vec::new();
+        }
+        let mut out = Vec::new();
+        if flags & NOTIFY_KEYSPACE != 0 {
+            out.push((
+                format!("__keyspace@{}__:{}", db, key),
+                event.as_bytes().to_vec(),
+            ));
+        }
+        if flags & NOTIFY_KEYEVENT != 0 {
+            out.push((
+                format!("__keyevent@{}__:{}", db, event),
+                key.as_bytes().to_vec(),
+            ));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_format_roundtrip() {
+        assert_eq!(parse_flags("Ex"), Some(NOTIFY_KEYEVENT | NOTIFY_EXPIRED));
+        assert_eq!(format_flags(parse_flags("xE").unwrap()), "xE");
+        assert_eq!(format_flags(parse_flags("KEA").unwrap()), "AKE");
+        assert_eq!(parse_flags(""), Some(0));
+        assert!(parse_flags("Q").is_none());
+    }
+
+    #[test]
+    fn channels_require_class_and_target() {
+        let cfg = NotifyConfig::new(parse_flags("Kx").unwrap());
+        let out = cfg.channels_for(NOTIFY_EXPIRED, "expired", "0", "foo");
+        assert_eq!(
+            out,
+            vec![("__keyspace@0__:foo".to_string(), b"expired".to_vec())]
+        );
+        assert!(cfg
+            .channels_for(NOTIFY_GENERIC, "del", "0", "foo")
+            .is_empty());
+        // 只选类别、不选 K/E 时不发布
+        let cfg = NotifyConfig::new(NOTIFY_EXPIRED);
+        assert!(cfg
+            .channels_for(NOTIFY_EXPIRED, "expired", "0", "foo")
+            .is_empty());
+    }
+}
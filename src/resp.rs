@@ -1,51 +1,173 @@
-use tokio::io::{self, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{
+    self, AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf,
+};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
 
 // Redis's default max bulk string size is 512MB.
-const MAX_BULK_STRING_SIZE: usize = 512 * 1024 * 1024;
+pub(crate) const MAX_BULK_STRING_SIZE: usize = 512 * 1024 * 1024;
 // We'll also limit array sizes to something reasonable, e.g., 1MB elements for an array.
-const MAX_ARRAY_SIZE: usize = 1024 * 1024;
+// 这是单条命令参数个数的默认上限，服务端可通过 REDUST_MAX_MULTIBULK_LEN 按实例调整
+pub(crate) const MAX_ARRAY_SIZE: usize = 1024 * 1024;
+// 按数组头预分配时的上限，避免只发一个巨大的长度就让服务端分配大块内存
+const MAX_ARRAY_PREALLOC: usize = 1024;
+// bulk 数据同理，超过该长度的部分随数据到达逐步扩容
+const MAX_BULK_PREALLOC: usize = 64 * 1024;
+// inline 命令与 `*` / `$` 头部行的最大长度，与 Redis 的 PROTO_INLINE_MAX_SIZE 一致
+const MAX_INLINE_LEN: usize = 64 * 1024;
+
+/// 累计读写字节数的连接读写端，用于 INFO 的 total_net_input_bytes / total_net_output_bytes
+pub struct Counted<T> {
+    inner: T,
+    bytes: Arc<AtomicU64>,
+    /// 本连接自建立以来读写的字节数，用于计算单条命令的回复大小
+    transferred: u64,
+    /// 为 Some 时写入先暂存在内存中，由 `flush_buffered` 统一写出；
+    /// 持有全局锁期间使用，避免读取缓慢的客户端让锁一直得不到释放
+    buffered: Option<Vec<u8>>,
+}
 
-pub async fn read_resp_array(
-    reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>,
-) -> io::Result<Option<Vec<Vec<u8>>>> {
-    let mut header = String::new();
-    let read = reader.read_line(&mut header).await?;
-    if read == 0 {
-        return Ok(None);
+impl<T> Counted<T> {
+    pub fn new(inner: T, bytes: Arc<AtomicU64>) -> Self {
+        Counted {
+            inner,
+            bytes,
+            transferred: 0,
+            buffered: None,
+        }
     }
 
-    let header = header.trim_end();
-    if !header.starts_with('*') {
-        return Ok(Some(vec![header.as_bytes().to_vec()]));
+    pub fn transferred(&self) -> u64 {
+        self.transferred
     }
+}
 
-    let array_len: usize = header[1..].parse().map_err(|e| {
-        io::Error::new(
-            io::ErrorKind::InvalidData,
-            format!("Invalid array length: {}", e),
-        )
-    })?;
+impl<T: AsyncRead + Unpin> AsyncRead for Counted<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        let read = buf.filled().len() - before;
+        self.bytes.fetch_add(read as u64, Ordering::Relaxed);
+        self.transferred += read as u64;
+        poll
+    }
+}
+
+impl<T: AsyncWrite + Unpin> Counted<T> {
+    /// 之后的写入暂存在内存中，直到调用 `flush_buffered`
+    pub fn start_buffering(&mut self) {
+        self.buffered.get_or_insert_with(Vec::new);
+    }
+
+    /// 写出暂存的数据并恢复直接写入；没有暂存数据时什么也不做
+    pub async fn flush_buffered(&mut self) -> io::Result<()> {
+        if let Some(buf) = self.buffered.take() {
+            if !buf.is_empty() {
+                self.inner.write_all(&buf).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Counted<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        // 暂存时字节数在写入缓冲区时计入，flush_buffered 不再重复统计
+        if let Some(pending) = self.buffered.as_mut() {
+            pending.extend_from_slice(buf);
+            self.bytes.fetch_add(buf.len() as u64, Ordering::Relaxed);
+            self.transferred += buf.len() as u64;
+            return Poll::Ready(Ok(buf.len()));
+        }
+        let poll = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = poll {
+            self.bytes.fetch_add(n as u64, Ordering::Relaxed);
+            self.transferred += n as u64;
+        }
+        poll
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+pub type ClientReader = Counted<OwnedReadHalf>;
+pub type ClientWriter = Counted<OwnedWriteHalf>;
+
+/// 读取一条命令：multibulk 数组（`*<n>\r\n` 后跟 n 个 `$<len>\r\n<data>\r\n`），
+/// 或不以 `*` 开头的 inline 行（整行作为单个元素返回）。
+///
+/// 任何格式错误都以 `InvalidData` 返回，调用方回复协议错误后关闭连接：
+/// 长度必须是纯十进制数字（不接受 `+`、空格等），头部行必须以 CRLF 结尾且不超过
+/// `MAX_INLINE_LEN`，bulk 数据后必须紧跟 CRLF。`*0` / `*-1` 与 Redis 一样视为空请求直接跳过。
+/// bulk 数据随读取逐步分配，只声明巨大长度而不发送数据不会让服务端一次性分配大块内存。
+///
+/// 参数个数使用默认上限 `MAX_ARRAY_SIZE`；需要按实例配置时使用 [`read_resp_array_with_limit`]。
+pub async fn read_resp_array<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+) -> io::Result<Option<Vec<Vec<u8>>>> {
+    read_resp_array_with_limit(reader, MAX_ARRAY_SIZE).await
+}
 
-    if array_len > MAX_ARRAY_SIZE {
+/// 与 [`read_resp_array`] 相同，但单条命令最多允许 `max_len` 个参数（含命令名）
+pub async fn read_resp_array_with_limit<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    max_len: usize,
+) -> io::Result<Option<Vec<Vec<u8>>>> {
+    let array_len = loop {
+        let Some(header) = read_header_line(reader).await? else {
+            return Ok(None);
+        };
+
+        let Some(count) = header.strip_prefix(b"*") else {
+            return Ok(Some(vec![header.trim_ascii_end().to_vec()]));
+        };
+        let count = strip_crlf(count, "multibulk count")?;
+        if count == b"0" || count == b"-1" {
+            continue;
+        }
+        break parse_length(count).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Invalid array length: {:?}",
+                    String::from_utf8_lossy(count)
+                ),
+            )
+        })?;
+    };
+
+    if array_len > max_len {
         return Err(io::Error::new(
             io::ErrorKind::InvalidData,
-            format!(
-                "array length exceeds limit: {} > {}",
-                array_len, MAX_ARRAY_SIZE
-            ),
+            format!("array length exceeds limit: {} > {}", array_len, max_len),
         ));
     }
 
-    let mut parts = Vec::with_capacity(array_len);
+    let mut parts = Vec::with_capacity(array_len.min(MAX_ARRAY_PREALLOC));
 
     for _ in 0..array_len {
-        let mut bulk_header = String::new();
-        if reader.read_line(&mut bulk_header).await? == 0 {
+        let Some(bulk_header) = read_header_line(reader).await? else {
             return Ok(None);
-        }
+        };
 
-        let bulk_header = bulk_header.trim_end();
-        let Some(stripped) = bulk_header.strip_prefix('$') else {
+        let Some(stripped) = bulk_header.strip_prefix(b"$") else {
             // This case handles non-bulk string elements in the array, which is not valid RESP
             // for the commands we are parsing. We'll treat it as an error.
             return Err(io::Error::new(
@@ -53,11 +175,16 @@ pub async fn read_resp_array(
                 "expected bulk string in array",
             ));
         };
+        let stripped = strip_crlf(stripped, "bulk length")?;
 
-        let bulk_len: usize = stripped.parse().map_err(|e| {
+        // 请求中的参数不能为 null，负数长度（包括 -1）一律视为格式错误
+        let bulk_len = parse_length(stripped).ok_or_else(|| {
             io::Error::new(
                 io::ErrorKind::InvalidData,
-                format!("Invalid bulk string length: {}", e),
+                format!(
+                    "Invalid bulk string length: {:?}",
+                    String::from_utf8_lossy(stripped)
+                ),
             )
         })?;
 
@@ -71,8 +198,14 @@ pub async fn read_resp_array(
             ));
         }
 
-        let mut buf = vec![0u8; bulk_len];
-        reader.read_exact(&mut buf).await?;
+        let mut buf = Vec::with_capacity(bulk_len.min(MAX_BULK_PREALLOC));
+        (&mut *reader)
+            .take(bulk_len as u64)
+            .read_to_end(&mut buf)
+            .await?;
+        if buf.len() < bulk_len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
 
         // Consume trailing CRLF after the bulk string
         let mut crlf = [0u8; 2];
@@ -90,15 +223,62 @@ pub async fn read_resp_array(
     Ok(Some(parts))
 }
 
+/// 读取一行（含行尾 `\n`），超过 `MAX_INLINE_LEN` 仍未遇到换行时报错；
+/// 连接关闭且没有读到任何字节时返回 None
+async fn read_header_line<R: AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut line = Vec::new();
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() {
+            return Ok(if line.is_empty() { None } else { Some(line) });
+        }
+        let (used, done) = match available.iter().position(|&b| b == b'\n') {
+            Some(i) => (i + 1, true),
+            None => (available.len(), false),
+        };
+        line.extend_from_slice(&available[..used]);
+        reader.consume(used);
+        if line.len() > MAX_INLINE_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("request line exceeds limit of {} bytes", MAX_INLINE_LEN),
+            ));
+        }
+        if done {
+            return Ok(Some(line));
+        }
+    }
+}
+
+/// 去掉 multibulk / bulk 头部行末尾的 CRLF；只有 `\n` 或缺少行尾都视为格式错误
+fn strip_crlf<'a>(line: &'a [u8], what: &str) -> io::Result<&'a [u8]> {
+    line.strip_suffix(b"\r\n").ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("expected CRLF after {}", what),
+        )
+    })
+}
+
+/// 严格解析非负十进制长度：非空、只含数字、不溢出
+fn parse_length(digits: &[u8]) -> Option<usize> {
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    digits.iter().try_fold(0usize, |acc, &d| {
+        acc.checked_mul(10)?.checked_add((d - b'0') as usize)
+    })
+}
+
 pub async fn respond_bulk_string(
-    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    writer: &mut ClientWriter,
     value: &str,
 ) -> io::Result<()> {
     respond_bulk_bytes(writer, value.as_bytes()).await
 }
 
 pub async fn respond_bulk_bytes(
-    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    writer: &mut ClientWriter,
     value: &[u8],
 ) -> io::Result<()> {
     let header = format!("${}\r\n", value.len());
@@ -107,8 +287,55 @@ pub async fn respond_bulk_bytes(
     writer.write_all(b"\r\n").await
 }
 
+/// 向响应缓冲区追加一个二进制安全的 bulk string
+pub fn push_bulk_bytes(buf: &mut Vec<u8>, value: &[u8]) {
+    buf.extend_from_slice(format!("${}\r\n", value.len()).as_bytes());
+    buf.extend_from_slice(value);
+    buf.extend_from_slice(b"\r\n");
+}
+
+/// 数组回复构造器：先在内存中拼出完整的 RESP 回复，再一次 `write_all` 发出。
+/// 元素按字节追加，二进制安全；大数组回复也只产生一次写调用。
+pub struct ReplyBuilder {
+    buf: Vec<u8>,
+}
+
+impl ReplyBuilder {
+    /// 以数组头 `*<len>` 开始一个回复，并按元素个数预留缓冲区
+    pub fn array(len: usize) -> Self {
+        let mut buf = Vec::with_capacity(16 + len * 16);
+        buf.extend_from_slice(format!("*{}\r\n", len).as_bytes());
+        ReplyBuilder { buf }
+    }
+
+    pub fn bulk(&mut self, value: &[u8]) {
+        push_bulk_bytes(&mut self.buf, value);
+    }
+
+    pub fn integer(&mut self, value: i64) {
+        self.buf.extend_from_slice(format!(":{}\r\n", value).as_bytes());
+    }
+
+    /// 追加嵌套数组的头部，之后的 `len` 个元素属于该数组
+    pub fn nested_array(&mut self, len: usize) {
+        self.buf.extend_from_slice(format!("*{}\r\n", len).as_bytes());
+    }
+
+    pub fn null_bulk(&mut self) {
+        self.buf.extend_from_slice(b"$-1\r\n");
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    pub async fn flush(self, writer: &mut ClientWriter) -> io::Result<()> {
+        writer.write_all(&self.buf).await
+    }
+}
+
 pub async fn respond_simple_string(
-    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    writer: &mut ClientWriter,
     value: &str,
 ) -> io::Result<()> {
     let response = format!("+{}\r\n", value);
@@ -116,7 +343,7 @@ pub async fn respond_simple_string(
 }
 
 pub async fn respond_error(
-    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    writer: &mut ClientWriter,
     message: &str,
 ) -> io::Result<()> {
     let response = format!("-{}\r\n", message);
@@ -124,14 +351,14 @@ pub async fn respond_error(
 }
 
 pub async fn respond_integer(
-    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    writer: &mut ClientWriter,
     value: i64,
 ) -> io::Result<()> {
     let response = format!(":{}\r\n", value);
     writer.write_all(response.as_bytes()).await
 }
 
-pub async fn respond_null_bulk(writer: &mut tokio::net::tcp::OwnedWriteHalf) -> io::Result<()> {
+pub async fn respond_null_bulk(writer: &mut ClientWriter) -> io::Result<()> {
     writer.write_all(b"$-1\r\n").await
 }
 
@@ -144,7 +371,7 @@ mod tests {
 
     async fn setup_test_client(
         data: Vec<u8>,
-    ) -> (BufReader<tokio::net::tcp::OwnedReadHalf>, TcpListener) {
+    ) -> (BufReader<ClientReader>, TcpListener) {
         let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
         let addr = listener.local_addr().unwrap();
 
@@ -156,7 +383,7 @@ mod tests {
 
         let (stream, _) = listener.accept().await.unwrap();
         let (read_half, _write_half) = stream.into_split();
-        let reader = BufReader::new(read_half);
+        let reader = BufReader::new(Counted::new(read_half, Arc::default()));
         client.await.unwrap();
         (reader, listener)
     }
@@ -228,4 +455,112 @@ mod tests {
         assert_eq!(err.kind(), io::ErrorKind::InvalidData);
         assert!(err.to_string().contains("expected CRLF after bulk string"));
     }
+
+    async fn parse_bytes(data: &[u8]) -> io::Result<Option<Vec<Vec<u8>>>> {
+        let mut reader = data;
+        read_resp_array(&mut reader).await
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_lengths_and_line_endings() {
+        let invalid: &[&[u8]] = &[
+            // 负数长度（含 -1）、带符号或空白的长度
+            b"*1\r\n$-1\r\n",
+            b"*1\r\n$-2\r\nab\r\n",
+            b"*1\r\n$+4\r\nPING\r\n",
+            b"*1\r\n$ 4\r\nPING\r\n",
+            b"*1\r\n$\r\n",
+            b"*-2\r\n",
+            b"*+1\r\n$4\r\nPING\r\n",
+            b"*99999999999999999999999\r\n",
+            // 头部行只有 LF
+            b"*1\n$4\r\nPING\r\n",
+            b"*1\r\n$4\nPING\r\n",
+            // 声明的长度比实际数据短：数据后面不是 CRLF
+            b"*2\r\n$3\r\nabcd\r\n$1\r\nx\r\n",
+        ];
+        for data in invalid {
+            let err = parse_bytes(data).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{:?}", String::from_utf8_lossy(data));
+        }
+
+        // 声明的长度比实际数据长：读到连接关闭
+        let err = parse_bytes(b"*1\r\n$5\r\nabc\r\n").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        // 没有换行的超长行
+        let long_line = vec![b'a'; MAX_INLINE_LEN + 1];
+        let err = parse_bytes(&long_line).await.unwrap_err();
+        assert!(err.to_string().contains("request line exceeds limit"));
+        let mut long_header = b"*1\r\n$".to_vec();
+        long_header.extend(vec![b'1'; MAX_INLINE_LEN + 1]);
+        let err = parse_bytes(&long_header).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // *0 / *-1 是空请求，跳过后继续解析下一条命令
+        let parts = parse_bytes(b"*0\r\n*-1\r\n*1\r\n$4\r\nPING\r\n")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(parts, vec![b"PING".to_vec()]);
+    }
+
+    /// 随机字节与对合法帧的随机变异：解析要么成功、要么返回错误，不能 panic 或卡住
+    #[tokio::test]
+    async fn random_input_never_panics() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        let alphabet = b"*$+-:\r\n0123456789abcPING \x00\xff";
+        let valid = b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$5\r\nvalue\r\n".to_vec();
+
+        for round in 0..20_000 {
+            let data: Vec<u8> = if round % 2 == 0 {
+                let len = rng.gen_range(0..48);
+                (0..len)
+                    .map(|_| alphabet[rng.gen_range(0..alphabet.len())])
+                    .collect()
+            } else {
+                let mut data = valid.clone();
+                for _ in 0..rng.gen_range(1..4) {
+                    let idx = rng.gen_range(0..data.len());
+                    match rng.gen_range(0..3) {
+                        0 => data[idx] = alphabet[rng.gen_range(0..alphabet.len())],
+                        1 => {
+                            data.remove(idx);
+                        }
+                        _ => data.truncate(idx),
+                    }
+                    if data.is_empty() {
+                        break;
+                    }
+                }
+                data
+            };
+
+            // 每次成功的调用至少消费一个字节，因此调用次数不会超过输入长度
+            let mut reader = &data[..];
+            let mut calls = 0;
+            loop {
+                calls += 1;
+                assert!(calls <= data.len() + 1, "parser did not make progress on {:?}", data);
+                match read_resp_array(&mut reader).await {
+                    Ok(Some(_)) => continue,
+                    Ok(None) | Err(_) => break,
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reply_builder_encodes_binary_array() {
+        let mut reply = ReplyBuilder::array(3);
+        reply.bulk(b"a\r\nb");
+        reply.null_bulk();
+        reply.bulk(&[0xff, 0x00]);
+        assert_eq!(
+            reply.as_bytes(),
+            b"*3\r\n$4\r\na\r\nb\r\n$-1\r\n$2\r\n\xff\x00\r\n"
+        );
+    }
 }
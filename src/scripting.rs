@@ -1,32 +1,189 @@
 //! Lua scripting support for Redis-compatible EVAL/EVALSHA commands.
 
 use dashmap::DashMap;
-use mlua::{Lua, Value};
+use mlua::{HookTriggers, Lua, Value};
 use sha1::{Digest, Sha1};
 use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
 use std::rc::Rc;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
-use crate::storage::Storage;
+use crate::storage::{IncrError, Storage};
 
-/// Script cache: SHA1 -> script source
+/// Default `busy-reply-threshold` (alias `lua-time-limit`) in milliseconds, same as Redis
+pub const DEFAULT_SCRIPT_TIME_LIMIT_MS: u64 = 5000;
+
+/// Once a script has run this long other commands get BUSY; 0 disables the BUSY state
+static SCRIPT_TIME_LIMIT_MS: AtomicU64 = AtomicU64::new(DEFAULT_SCRIPT_TIME_LIMIT_MS);
+
+/// How many VM instructions run between two kill / deadline checks
+const SCRIPT_HOOK_INTERVAL: u32 = 10_000;
+
+/// Library bodies run by FUNCTION LOAD must finish within this time (same as Redis)
+const FUNCTION_LOAD_TIMEOUT: Duration = Duration::from_millis(500);
+
+pub fn script_time_limit_ms() -> u64 {
+    SCRIPT_TIME_LIMIT_MS.load(Ordering::Relaxed)
+}
+
+pub fn set_script_time_limit_ms(ms: u64) {
+    SCRIPT_TIME_LIMIT_MS.store(ms, Ordering::Relaxed);
+}
+
+/// The script currently holding the exclusive lock, shared with SCRIPT KILL and
+/// the BUSY check at command dispatch
+#[derive(Debug, Default)]
+pub struct ScriptRunState {
+    started: Mutex<Option<Instant>>,
+    kill_requested: AtomicBool,
+    wrote: AtomicBool,
+}
+
+/// Clears the running marker when the script returns, however it returns
+struct RunningScript<'a>(&'a ScriptRunState);
+
+impl Drop for RunningScript<'_> {
+    fn drop(&mut self) {
+        *self.0.started.lock().unwrap() = None;
+    }
+}
+
+impl ScriptRunState {
+    fn begin(&self) -> RunningScript<'_> {
+        self.kill_requested.store(false, Ordering::Relaxed);
+        self.wrote.store(false, Ordering::Relaxed);
+        *self.started.lock().unwrap() = Some(Instant::now());
+        RunningScript(self)
+    }
+
+    /// A script has been running past `busy-reply-threshold`
+    pub fn is_busy(&self) -> bool {
+        let limit = script_time_limit_ms();
+        limit != 0
+            && self
+                .started
+                .lock()
+                .unwrap()
+                .is_some_and(|t| t.elapsed() >= Duration::from_millis(limit))
+    }
+
+    /// SCRIPT KILL: ask the running script to stop at its next hook check.
+    /// Scripts that already wrote cannot be killed, like Redis.
+    pub fn kill(&self) -> Result<(), &'static str> {
+        if self.started.lock().unwrap().is_none() {
+            return Err("NOTBUSY No scripts in execution right now.");
+        }
+        if self.wrote.load(Ordering::Relaxed) {
+            return Err("UNKILLABLE Sorry the script already executed write commands against the dataset. You can either wait the script termination or kill the server in a hard way using the SHUTDOWN NOSAVE command.");
+        }
+        self.kill_requested.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Raise an error inside the VM once SCRIPT KILL was requested, so even
+/// `while true do end` is interrupted
+fn install_kill_hook(lua: &Lua, state: Arc<ScriptRunState>) {
+    lua.set_hook(
+        HookTriggers::new().every_nth_instruction(SCRIPT_HOOK_INTERVAL),
+        move |_, _| {
+            if state.kill_requested.load(Ordering::Relaxed) {
+                Err(mlua::Error::RuntimeError(
+                    "ERR Script killed by user with SCRIPT KILL...".to_string(),
+                ))
+            } else {
+                Ok(())
+            }
+        },
+    );
+}
+
+/// Abort a FUNCTION LOAD whose library body does not return in time
+fn install_load_deadline(lua: &Lua) {
+    let deadline = Instant::now() + FUNCTION_LOAD_TIMEOUT;
+    lua.set_hook(
+        HookTriggers::new().every_nth_instruction(SCRIPT_HOOK_INTERVAL),
+        move |_, _| {
+            if Instant::now() >= deadline {
+                Err(mlua::Error::RuntimeError("ERR FUNCTION LOAD timeout".to_string()))
+            } else {
+                Ok(())
+            }
+        },
+    );
+}
+
+/// A library registered by FUNCTION LOAD
+#[derive(Debug, Clone)]
+pub struct FunctionLibrary {
+    pub name: String,
+    pub code: String,
+    /// (function name, flags) in registration order
+    pub functions: Vec<(String, Vec<String>)>,
+}
+
+/// Loaded function libraries plus the function name -> library index
+#[derive(Default)]
+struct FunctionStore {
+    libraries: BTreeMap<String, FunctionLibrary>,
+    owners: HashMap<String, String>,
+}
+
+/// Script cache: SHA1 -> script source, plus the FUNCTION libraries
 pub struct ScriptCache {
     scripts: DashMap<String, String>,
+    functions: Mutex<FunctionStore>,
+    run_state: Arc<ScriptRunState>,
+    /// Script atomicity: ordinary commands hold the shared side while they run,
+    /// EVAL/EVALSHA hold the exclusive side, so no command interleaves with a script
+    exec_lock: RwLock<()>,
 }
 
 impl ScriptCache {
     pub fn new() -> Self {
         Self {
             scripts: DashMap::new(),
+            functions: Mutex::new(FunctionStore::default()),
+            run_state: Arc::new(ScriptRunState::default()),
+            exec_lock: RwLock::new(()),
+        }
+    }
+
+    /// Held by every non-script command for the duration of its execution
+    pub async fn shared_guard(&self) -> RwLockReadGuard<'_, ()> {
+        self.exec_lock.read().await
+    }
+
+    /// Held by a script for its whole run; waits for in-flight commands to finish
+    pub async fn exclusive_guard(&self) -> RwLockWriteGuard<'_, ()> {
+        self.exec_lock.write().await
+    }
+
+    /// Shared with the scripts this cache runs, see `ScriptContext::run_state`
+    pub fn run_state(&self) -> Arc<ScriptRunState> {
+        self.run_state.clone()
+    }
+
+    /// Wait for the shared side of the execution lock, giving up (None) as soon
+    /// as the running script passes `busy-reply-threshold`
+    pub async fn shared_guard_unless_busy(&self) -> Option<RwLockReadGuard<'_, ()>> {
+        loop {
+            if self.run_state.is_busy() {
+                return None;
+            }
+            tokio::select! {
+                guard = self.exec_lock.read() => return Some(guard),
+                _ = tokio::time::sleep(Duration::from_millis(10)) => {}
+            }
         }
     }
 
     /// Compute SHA1 hash of a script
     pub fn compute_sha1(script: &str) -> String {
-        let mut hasher = Sha1::new();
-        hasher.update(script.as_bytes());
-        let result = hasher.finalize();
-        hex::encode(result)
+        sha1_hex(script.as_bytes())
     }
 
     /// Load a script into the cache, returns its SHA1
@@ -36,20 +193,76 @@ impl ScriptCache {
         sha1
     }
 
-    /// Get a script by SHA1
+    /// Get a script by SHA1 (case-insensitive, like Redis)
     pub fn get(&self, sha1: &str) -> Option<String> {
-        self.scripts.get(sha1).map(|v| v.clone())
+        let sha1 = normalize_sha1(sha1)?;
+        self.scripts.get(&sha1).map(|v| v.clone())
     }
 
-    /// Check if scripts exist
+    /// Check if scripts exist (case-insensitive, like Redis);
+    /// malformed digests simply do not exist
     pub fn exists(&self, sha1s: &[String]) -> Vec<bool> {
-        sha1s.iter().map(|s| self.scripts.contains_key(s)).collect()
+        sha1s
+            .iter()
+            .map(|s| normalize_sha1(s).is_some_and(|s| self.scripts.contains_key(&s)))
+            .collect()
     }
 
     /// Flush all scripts
     pub fn flush(&self) {
         self.scripts.clear();
     }
+
+    /// FUNCTION LOAD: register a library, returns its name
+    pub fn load_library(&self, code: &str, replace: bool) -> Result<String, String> {
+        let library = parse_library(code)?;
+        let mut store = self.functions.lock().unwrap();
+        if store.libraries.contains_key(&library.name) && !replace {
+            return Err(format!("ERR Library '{}' already exists", library.name));
+        }
+        for (function, _) in &library.functions {
+            if let Some(owner) = store.owners.get(function) {
+                if *owner != library.name {
+                    return Err(format!("ERR Function {} already exists", function));
+                }
+            }
+        }
+        if let Some(old) = store.libraries.remove(&library.name) {
+            for (function, _) in &old.functions {
+                store.owners.remove(function);
+            }
+        }
+        for (function, _) in &library.functions {
+            store.owners.insert(function.clone(), library.name.clone());
+        }
+        let name = library.name.clone();
+        store.libraries.insert(name.clone(), library);
+        Ok(name)
+    }
+
+    /// Look up a function for FCALL: returns its library code and flags
+    pub fn function(&self, name: &str) -> Option<(String, Vec<String>)> {
+        let store = self.functions.lock().unwrap();
+        let library = store.libraries.get(store.owners.get(name)?)?;
+        let flags = library
+            .functions
+            .iter()
+            .find(|(f, _)| f == name)
+            .map(|(_, flags)| flags.clone())?;
+        Some((library.code.clone(), flags))
+    }
+
+    /// FUNCTION LIST: all libraries ordered by name
+    pub fn libraries(&self) -> Vec<FunctionLibrary> {
+        self.functions.lock().unwrap().libraries.values().cloned().collect()
+    }
+
+    /// FUNCTION FLUSH
+    pub fn flush_functions(&self) {
+        let mut store = self.functions.lock().unwrap();
+        store.libraries.clear();
+        store.owners.clear();
+    }
 }
 
 impl Default for ScriptCache {
@@ -58,6 +271,22 @@ impl Default for ScriptCache {
     }
 }
 
+/// Lowercase a 40-char hex digest; anything else can never name a cached script
+fn normalize_sha1(sha1: &str) -> Option<String> {
+    if sha1.len() == 40 && sha1.bytes().all(|b| b.is_ascii_hexdigit()) {
+        Some(sha1.to_ascii_lowercase())
+    } else {
+        None
+    }
+}
+
+/// Lowercase hex SHA1, shared by SCRIPT LOAD and redis.sha1hex
+pub fn sha1_hex(data: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(data);
+    hex::encode(hasher.finalize())
+}
+
 /// Result of script execution
 #[derive(Debug, Clone)]
 pub enum ScriptResult {
@@ -90,8 +319,9 @@ impl ScriptResult {
                 }
                 resp
             }
-            ScriptResult::Status(s) => format!("+{}\r\n", s).into_bytes(),
-            ScriptResult::Error(e) => format!("-{}\r\n", e).into_bytes(),
+            // 状态与错误回复不能包含换行，与 Redis 一样替换为空格
+            ScriptResult::Status(s) => format!("+{}\r\n", s.replace(['\r', '\n'], " ")).into_bytes(),
+            ScriptResult::Error(e) => format!("-{}\r\n", e.replace(['\r', '\n'], " ")).into_bytes(),
         }
     }
 }
@@ -102,6 +332,10 @@ pub struct ScriptContext {
     pub current_db: u32,
     pub keys: Vec<String>,
     pub args: Vec<String>,
+    /// EVAL_RO / EVALSHA_RO：禁止脚本通过 redis.call/pcall 执行写命令
+    pub read_only: bool,
+    /// Running marker and SCRIPT KILL flag, usually `ScriptCache::run_state()`
+    pub run_state: Arc<ScriptRunState>,
 }
 
 /// Execute a Lua script with redis.call/pcall support
@@ -128,21 +362,237 @@ pub fn execute_script(
     }
     globals.set("ARGV", argv_table).map_err(|e| format!("ERR {}", e))?;
 
+    setup_environment(&lua, &ctx)?;
+    let _running = ctx.run_state.begin();
+
+    // Execute the script
+    let result: Value = lua.load(script).eval().map_err(script_error_message)?;
+
+    // Convert Lua value to ScriptResult
+    lua_value_to_result(result)
+}
+
+/// Invoke a function from a FUNCTION LOAD library with FCALL semantics:
+/// the library body is run to register its functions, then the named callback
+/// is called with the KEYS and ARGV tables as its two arguments
+pub fn execute_function(
+    code: &str,
+    function: &str,
+    ctx: ScriptContext,
+) -> Result<ScriptResult, String> {
+    let (_, body) = parse_library_metadata(code)?;
+    let lua = Lua::new();
+    setup_environment(&lua, &ctx)?;
+    install_register_function(&lua).map_err(|e| format!("ERR {}", e))?;
+    let _running = ctx.run_state.begin();
+    lua.load(body.as_str()).exec().map_err(script_error_message)?;
+
+    let mut callback = None;
+    for entry in registered_functions(&lua).map_err(|e| format!("ERR {}", e))? {
+        let entry: mlua::Table = entry.map_err(|e| format!("ERR {}", e))?;
+        let name: String = entry.get("name").map_err(|e| format!("ERR {}", e))?;
+        if name == function {
+            callback = Some(
+                entry
+                    .get::<_, mlua::Function>("callback")
+                    .map_err(|e| format!("ERR {}", e))?,
+            );
+            break;
+        }
+    }
+    let Some(callback) = callback else {
+        return Err("ERR Function not found".to_string());
+    };
+
+    let keys_table = lua
+        .create_sequence_from(ctx.keys.iter().map(|k| k.as_str()))
+        .map_err(|e| format!("ERR {}", e))?;
+    let argv_table = lua
+        .create_sequence_from(ctx.args.iter().map(|a| a.as_str()))
+        .map_err(|e| format!("ERR {}", e))?;
+    let result: Value = callback
+        .call((keys_table, argv_table))
+        .map_err(script_error_message)?;
+    lua_value_to_result(result)
+}
+
+/// Registry slot collecting redis.register_function calls, in registration order
+const FUNCTIONS_REGISTRY_KEY: &str = "redust_registered_functions";
+
+/// Flags accepted by redis.register_function
+const FUNCTION_FLAGS: &[&str] = &[
+    "no-writes",
+    "allow-oom",
+    "allow-stale",
+    "no-cluster",
+    "allow-cross-slot-keys",
+];
+
+/// Split `#!lua name=<lib>` off a library, returning the library name and a body
+/// in which the shebang line is blanked so Lua line numbers stay correct
+fn parse_library_metadata(code: &str) -> Result<(String, String), String> {
+    let Some(rest) = code.strip_prefix("#!") else {
+        return Err("ERR Missing library metadata".to_string());
+    };
+    let (shebang, body) = match rest.find('\n') {
+        Some(pos) => (&rest[..pos], &rest[pos..]),
+        None => (rest, ""),
+    };
+    let mut parts = shebang.split_whitespace();
+    let engine = parts.next().unwrap_or("");
+    if !engine.eq_ignore_ascii_case("lua") {
+        return Err(format!("ERR Engine '{}' not found", engine));
+    }
+    let mut name = None;
+    for part in parts {
+        match part.strip_prefix("name=") {
+            Some(n) => name = Some(n.to_string()),
+            None => return Err(format!("ERR Invalid metadata value given: {}", part)),
+        }
+    }
+    let Some(name) = name else {
+        return Err("ERR Library name was not given".to_string());
+    };
+    if !is_valid_function_name(&name) {
+        return Err("ERR Library names can only contain letters, numbers, or underscores(_) and must be at least one character long".to_string());
+    }
+    Ok((name, body.to_string()))
+}
+
+fn is_valid_function_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Run a library body in a sandbox that only exposes redis.register_function,
+/// collecting the functions it registers
+fn parse_library(code: &str) -> Result<FunctionLibrary, String> {
+    let (name, body) = parse_library_metadata(code)?;
+    let lua = Lua::new();
+    install_load_deadline(&lua);
+    let redis_table = lua.create_table().map_err(|e| format!("ERR {}", e))?;
+    lua.globals()
+        .set("redis", redis_table)
+        .map_err(|e| format!("ERR {}", e))?;
+    install_register_function(&lua).map_err(|e| format!("ERR {}", e))?;
+    lua.load(body.as_str()).exec().map_err(script_error_message)?;
+
+    let mut functions = Vec::new();
+    for entry in registered_functions(&lua).map_err(|e| format!("ERR {}", e))? {
+        let entry: mlua::Table = entry.map_err(|e| format!("ERR {}", e))?;
+        let function: String = entry.get("name").map_err(|e| format!("ERR {}", e))?;
+        let flags: Vec<String> = entry.get("flags").map_err(|e| format!("ERR {}", e))?;
+        functions.push((function, flags));
+    }
+    if functions.is_empty() {
+        return Err("ERR No functions registered".to_string());
+    }
+    Ok(FunctionLibrary {
+        name,
+        code: code.to_string(),
+        functions,
+    })
+}
+
+fn registered_functions(
+    lua: &Lua,
+) -> mlua::Result<mlua::TableSequence<'_, mlua::Table<'_>>> {
+    let registry: mlua::Table = lua.named_registry_value(FUNCTIONS_REGISTRY_KEY)?;
+    Ok(registry.sequence_values())
+}
+
+/// Add redis.register_function(name, callback) / redis.register_function{function_name=, callback=, flags=}
+fn install_register_function(lua: &Lua) -> mlua::Result<()> {
+    lua.set_named_registry_value(FUNCTIONS_REGISTRY_KEY, lua.create_table()?)?;
+    let register_fn = lua.create_function(|lua, args: mlua::MultiValue| {
+        let mut args = args.into_iter();
+        let (name, callback, flags) = match (args.next(), args.next(), args.next()) {
+            (Some(Value::String(name)), Some(Value::Function(callback)), None) => {
+                (name.to_str()?.to_string(), callback, Vec::new())
+            }
+            (Some(Value::Table(spec)), None, None) => {
+                let name = match spec.get::<_, Value>("function_name")? {
+                    Value::String(s) => s.to_str()?.to_string(),
+                    _ => {
+                        return Err(mlua::Error::RuntimeError(
+                            "function_name argument given to redis.register_function must be a string".to_string(),
+                        ))
+                    }
+                };
+                let callback = match spec.get::<_, Value>("callback")? {
+                    Value::Function(f) => f,
+                    _ => {
+                        return Err(mlua::Error::RuntimeError(
+                            "callback argument given to redis.register_function must be a function".to_string(),
+                        ))
+                    }
+                };
+                let flags = match spec.get::<_, Value>("flags")? {
+                    Value::Nil => Vec::new(),
+                    Value::Table(t) => t.sequence_values::<String>().collect::<mlua::Result<Vec<_>>>()?,
+                    _ => {
+                        return Err(mlua::Error::RuntimeError(
+                            "flags argument to redis.register_function must be a table representing function flags".to_string(),
+                        ))
+                    }
+                };
+                (name, callback, flags)
+            }
+            _ => {
+                return Err(mlua::Error::RuntimeError(
+                    "wrong number of arguments to redis.register_function".to_string(),
+                ))
+            }
+        };
+        if !is_valid_function_name(&name) {
+            return Err(mlua::Error::RuntimeError(
+                "Function names can only contain letters, numbers, or underscores(_) and must be at least one character long".to_string(),
+            ));
+        }
+        if let Some(flag) = flags.iter().find(|f| !FUNCTION_FLAGS.contains(&f.as_str())) {
+            return Err(mlua::Error::RuntimeError(format!("unknown flag given: {}", flag)));
+        }
+        let registry: mlua::Table = lua.named_registry_value(FUNCTIONS_REGISTRY_KEY)?;
+        for existing in registry.clone().sequence_values::<mlua::Table>() {
+            if existing?.get::<_, String>("name")? == name {
+                return Err(mlua::Error::RuntimeError(
+                    "Function already exists in the library".to_string(),
+                ));
+            }
+        }
+        let entry = lua.create_table()?;
+        entry.set("name", name)?;
+        entry.set("callback", callback)?;
+        entry.set("flags", flags)?;
+        registry.push(entry)?;
+        Ok(())
+    })?;
+    let redis_table: mlua::Table = lua.globals().get("redis")?;
+    redis_table.set("register_function", register_fn)
+}
+
+/// Install the redis and cjson tables shared by EVAL scripts and FCALL functions
+fn setup_environment(lua: &Lua, ctx: &ScriptContext) -> Result<(), String> {
+    install_kill_hook(lua, ctx.run_state.clone());
+    let globals = lua.globals();
+
     // Create redis table with call/pcall
     let redis_table = lua.create_table().map_err(|e| format!("ERR {}", e))?;
     
     // Shared context for redis.call/pcall
     let storage = ctx.storage.clone();
     let current_db = ctx.current_db;
+    let read_only = ctx.read_only;
+    let run_state = ctx.run_state.clone();
     
     // Wrap storage in Rc<RefCell> for sharing between closures
     let storage_rc = Rc::new(RefCell::new(storage));
     
     // redis.call - executes command and raises error on failure
     let storage_call = storage_rc.clone();
+    let state_call = run_state.clone();
     let call_fn = lua.create_function(move |lua, args: mlua::MultiValue| -> mlua::Result<Value> {
         let storage = storage_call.borrow();
-        execute_redis_command(lua, &storage, current_db, args, false)
+        execute_redis_command(lua, &storage, current_db, read_only, &state_call, args, false)
     }).map_err(|e| format!("ERR {}", e))?;
     redis_table.set("call", call_fn).map_err(|e| format!("ERR {}", e))?;
     
@@ -150,17 +600,69 @@ pub fn execute_script(
     let storage_pcall = storage_rc.clone();
     let pcall_fn = lua.create_function(move |lua, args: mlua::MultiValue| -> mlua::Result<Value> {
         let storage = storage_pcall.borrow();
-        execute_redis_command(lua, &storage, current_db, args, true)
+        execute_redis_command(lua, &storage, current_db, read_only, &run_state, args, true)
     }).map_err(|e| format!("ERR {}", e))?;
     redis_table.set("pcall", pcall_fn).map_err(|e| format!("ERR {}", e))?;
+
+    // redis.error_reply / redis.status_reply - build {err=msg} / {ok=msg} tables,
+    // which lua_value_to_result turns into error / status replies
+    let error_reply_fn = lua.create_function(|lua, msg: String| {
+        let table = lua.create_table()?;
+        table.set("err", msg)?;
+        Ok(table)
+    }).map_err(|e| format!("ERR {}", e))?;
+    redis_table.set("error_reply", error_reply_fn).map_err(|e| format!("ERR {}", e))?;
+
+    let status_reply_fn = lua.create_function(|lua, msg: String| {
+        let table = lua.create_table()?;
+        table.set("ok", msg)?;
+        Ok(table)
+    }).map_err(|e| format!("ERR {}", e))?;
+    redis_table.set("status_reply", status_reply_fn).map_err(|e| format!("ERR {}", e))?;
     
+    // redis.sha1hex - same digest as SCRIPT LOAD, binary-safe
+    let sha1hex_fn = lua.create_function(|_, args: mlua::MultiValue| {
+        let mut args = args.into_iter();
+        match (args.next(), args.next()) {
+            (Some(Value::String(s)), None) => Ok(sha1_hex(s.as_bytes())),
+            (Some(Value::Integer(n)), None) => Ok(sha1_hex(n.to_string().as_bytes())),
+            (Some(Value::Number(n)), None) => Ok(sha1_hex(n.to_string().as_bytes())),
+            _ => Err(mlua::Error::RuntimeError(
+                "wrong number of arguments".to_string(),
+            )),
+        }
+    }).map_err(|e| format!("ERR {}", e))?;
+    redis_table.set("sha1hex", sha1hex_fn).map_err(|e| format!("ERR {}", e))?;
+
     globals.set("redis", redis_table).map_err(|e| format!("ERR {}", e))?;
 
-    // Execute the script
-    let result: Value = lua.load(script).eval().map_err(|e| format!("ERR {}", e))?;
+    let cjson_table = create_cjson_table(lua).map_err(|e| format!("ERR {}", e))?;
+    globals.set("cjson", cjson_table).map_err(|e| format!("ERR {}", e))?;
+    Ok(())
+}
 
-    // Convert Lua value to ScriptResult
-    lua_value_to_result(result)
+/// Turn a Lua error into a single-line error reply.
+/// Errors raised by redis.call keep their original message (e.g. `ERR ...`, `WRONGTYPE ...`)
+/// instead of the Lua callback wrapper and stack traceback.
+fn script_error_message(err: mlua::Error) -> String {
+    let mut current = &err;
+    while let mlua::Error::CallbackError { cause, .. } = current {
+        current = cause.as_ref();
+    }
+    let message = match current {
+        mlua::Error::RuntimeError(msg) if !msg.contains('\n') && has_error_code(msg) => msg.clone(),
+        mlua::Error::RuntimeError(msg) if !msg.contains('\n') => format!("ERR {}", msg),
+        other => format!("ERR {}", other),
+    };
+    // RESP 错误回复不能包含换行
+    message.replace(['\r', '\n'], " ")
+}
+
+/// Whether a message already starts with an error code such as `ERR` or `WRONGTYPE`
+fn has_error_code(msg: &str) -> bool {
+    msg.split(' ')
+        .next()
+        .is_some_and(|code| !code.is_empty() && code.chars().all(|c| c.is_ascii_uppercase()))
 }
 
 /// Convert Lua value to ScriptResult
@@ -223,6 +725,8 @@ fn execute_redis_command<'lua>(
     lua: &'lua Lua,
     storage: &Arc<Storage>,
     current_db: u32,
+    read_only: bool,
+    run_state: &ScriptRunState,
     args: mlua::MultiValue<'lua>,
     is_pcall: bool,
 ) -> mlua::Result<Value<'lua>> {
@@ -271,11 +775,30 @@ fn execute_redis_command<'lua>(
     };
     let cmd_args = &args_vec[1..];
 
+    // 只读脚本按命令表的 write 标志拦截写命令；执行过写命令的脚本不能再被 SCRIPT KILL
+    if crate::command_table::lookup(&cmd).is_some_and(|spec| spec.flags.contains(&"write")) {
+        if read_only {
+            return make_error(
+                lua,
+                "ERR Write commands are not allowed from read-only scripts",
+                is_pcall,
+            );
+        }
+        if storage.is_read_only() {
+            return make_error(
+                lua,
+                "READONLY You can't write against a read only replica.",
+                is_pcall,
+            );
+        }
+        run_state.wrote.store(true, Ordering::Relaxed);
+    }
+
     // Execute the command
     let result = match cmd.as_str() {
         "GET" => cmd_get(storage, current_db, cmd_args),
         "SET" => cmd_set(storage, current_db, cmd_args),
-        "DEL" => cmd_del(storage, current_db, cmd_args),
+        "DEL" | "UNLINK" => cmd_del(storage, current_db, cmd_args),
         "EXISTS" => cmd_exists(storage, current_db, cmd_args),
         "INCR" => cmd_incr(storage, current_db, cmd_args),
         "DECR" => cmd_decr(storage, current_db, cmd_args),
@@ -340,6 +863,110 @@ fn make_error<'lua>(lua: &'lua Lua, msg: &str, is_pcall: bool) -> mlua::Result<V
     }
 }
 
+/// cjson 编码的最大嵌套深度，与 lua-cjson 默认值一致；同时防止自引用表无限递归
+const CJSON_MAX_DEPTH: usize = 1000;
+
+/// Build the `cjson` table (encode / decode / null) backed by serde_json
+fn create_cjson_table(lua: &Lua) -> mlua::Result<mlua::Table<'_>> {
+    let cjson = lua.create_table()?;
+    // cjson.null: JSON null 的占位值，可放进表中（Lua 表无法保存 nil）
+    cjson.set("null", Value::LightUserData(mlua::LightUserData(std::ptr::null_mut())))?;
+    cjson.set(
+        "encode",
+        lua.create_function(|_, value: Value| {
+            let json = lua_to_json(&value, 0).map_err(mlua::Error::RuntimeError)?;
+            Ok(json.to_string())
+        })?,
+    )?;
+    cjson.set(
+        "decode",
+        lua.create_function(|lua, text: mlua::String| {
+            let json: serde_json::Value = serde_json::from_slice(text.as_bytes())
+                .map_err(|e| mlua::Error::RuntimeError(format!("cjson.decode: {}", e)))?;
+            json_to_lua(lua, &json)
+        })?,
+    )?;
+    Ok(cjson)
+}
+
+/// Lua 值转 JSON：连续整数键 1..n 的表编码为数组，其余表编码为对象（空表为 `{}`）
+fn lua_to_json(value: &Value, depth: usize) -> Result<serde_json::Value, String> {
+    if depth > CJSON_MAX_DEPTH {
+        return Err(format!("Cannot serialise, excessive nesting ({})", depth));
+    }
+    Ok(match value {
+        Value::Nil => serde_json::Value::Null,
+        Value::LightUserData(ud) if ud.0.is_null() => serde_json::Value::Null,
+        Value::Boolean(b) => serde_json::Value::Bool(*b),
+        Value::Integer(n) => serde_json::Value::from(*n),
+        Value::Number(n) => serde_json::Number::from_f64(*n)
+            .map(serde_json::Value::Number)
+            .ok_or_else(|| "Cannot serialise number: must not be NaN or Inf".to_string())?,
+        Value::String(s) => serde_json::Value::String(String::from_utf8_lossy(s.as_bytes()).into_owned()),
+        Value::Table(t) => {
+            let len = t.raw_len();
+            let pairs: Vec<(Value, Value)> = t
+                .clone()
+                .pairs::<Value, Value>()
+                .collect::<mlua::Result<_>>()
+                .map_err(|e| e.to_string())?;
+            if len > 0 && pairs.len() == len {
+                let mut items = Vec::with_capacity(len);
+                for i in 1..=len {
+                    let item: Value = t.raw_get(i).map_err(|e| e.to_string())?;
+                    items.push(lua_to_json(&item, depth + 1)?);
+                }
+                serde_json::Value::Array(items)
+            } else {
+                let mut map = serde_json::Map::new();
+                for (k, v) in pairs {
+                    let key = match k {
+                        Value::String(s) => String::from_utf8_lossy(s.as_bytes()).into_owned(),
+                        Value::Integer(n) => n.to_string(),
+                        Value::Number(n) => n.to_string(),
+                        _ => return Err("Cannot serialise table: table key must be a number or string".to_string()),
+                    };
+                    map.insert(key, lua_to_json(&v, depth + 1)?);
+                }
+                serde_json::Value::Object(map)
+            }
+        }
+        other => {
+            return Err(format!(
+                "Cannot serialise {}: type not supported",
+                other.type_name()
+            ))
+        }
+    })
+}
+
+/// JSON 转 Lua 值：null 转为 cjson.null，整数保持为 Lua 整数
+fn json_to_lua<'lua>(lua: &'lua Lua, json: &serde_json::Value) -> mlua::Result<Value<'lua>> {
+    Ok(match json {
+        serde_json::Value::Null => Value::LightUserData(mlua::LightUserData(std::ptr::null_mut())),
+        serde_json::Value::Bool(b) => Value::Boolean(*b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Number(n.as_f64().unwrap_or(0.0)),
+        },
+        serde_json::Value::String(s) => Value::String(lua.create_string(s)?),
+        serde_json::Value::Array(items) => {
+            let table = lua.create_table_with_capacity(items.len(), 0)?;
+            for (i, item) in items.iter().enumerate() {
+                table.raw_set(i + 1, json_to_lua(lua, item)?)?;
+            }
+            Value::Table(table)
+        }
+        serde_json::Value::Object(map) => {
+            let table = lua.create_table_with_capacity(0, map.len())?;
+            for (k, v) in map {
+                table.raw_set(k.as_str(), json_to_lua(lua, v)?)?;
+            }
+            Value::Table(table)
+        }
+    })
+}
+
 /// Convert ScriptResult to Lua Value
 /// FIX P2: Redis scripting semantics - nil bulk replies are converted to false (not nil)
 /// because Lua tables cannot contain nil values without losing their slots.
@@ -430,6 +1057,16 @@ fn cmd_exists(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<Scrip
     Ok(ScriptResult::Integer(count as i64))
 }
 
+/// Maps an INCR-family storage error to the Redis error reply
+fn incr_error(err: IncrError) -> String {
+    match err {
+        IncrError::WrongType => "WRONGTYPE Operation against a key holding the wrong kind of value",
+        IncrError::NotInteger => "ERR value is not an integer or out of range",
+        IncrError::Overflow => "ERR increment or decrement would overflow",
+    }
+    .to_string()
+}
+
 fn cmd_incr(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<ScriptResult, String> {
     if args.len() != 1 {
         return Err("ERR wrong number of arguments for 'incr' command".to_string());
@@ -438,7 +1075,7 @@ fn cmd_incr(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<ScriptR
     let key = prefix_key(db, &key_str);
     match storage.incr(&key) {
         Ok(v) => Ok(ScriptResult::Integer(v)),
-        Err(()) => Err("ERR value is not an integer or out of range".to_string()),
+        Err(e) => Err(incr_error(e)),
     }
 }
 
@@ -450,7 +1087,7 @@ fn cmd_decr(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<ScriptR
     let key = prefix_key(db, &key_str);
     match storage.decr(&key) {
         Ok(v) => Ok(ScriptResult::Integer(v)),
-        Err(()) => Err("ERR value is not an integer or out of range".to_string()),
+        Err(e) => Err(incr_error(e)),
     }
 }
 
@@ -464,7 +1101,7 @@ fn cmd_incrby(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<Scrip
     let delta: i64 = delta_str.parse().map_err(|_| "ERR value is not an integer or out of range".to_string())?;
     match storage.incr_by(&key, delta) {
         Ok(v) => Ok(ScriptResult::Integer(v)),
-        Err(()) => Err("ERR value is not an integer or out of range".to_string()),
+        Err(e) => Err(incr_error(e)),
     }
 }
 
@@ -478,7 +1115,7 @@ fn cmd_decrby(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<Scrip
     let delta: i64 = delta_str.parse().map_err(|_| "ERR value is not an integer or out of range".to_string())?;
     match storage.incr_by(&key, -delta) {
         Ok(v) => Ok(ScriptResult::Integer(v)),
-        Err(()) => Err("ERR value is not an integer or out of range".to_string()),
+        Err(e) => Err(incr_error(e)),
     }
 }
 
@@ -545,7 +1182,7 @@ fn cmd_hget(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<ScriptR
     let key = prefix_key(db, &key_str);
     let field = bytes_to_string(&args[1])?;
     match storage.hget(&key, &field) {
-        Ok(Some(v)) => Ok(ScriptResult::String(v)),
+        Ok(Some(v)) => Ok(ScriptResult::BulkString(v)),
         Ok(None) => Ok(ScriptResult::Nil),
         Err(()) => Err("ERR WRONGTYPE Operation against a key holding the wrong kind of value".to_string()),
     }
@@ -560,8 +1197,7 @@ fn cmd_hset(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<ScriptR
     let mut total_added = 0;
     for i in (1..args.len()).step_by(2) {
         let field = bytes_to_string(&args[i])?;
-        let value_bytes = &args[i + 1];
-        let value = String::from_utf8_lossy(value_bytes).to_string(); // Convert to String for storage
+        let value = args[i + 1].clone();
         match storage.hset(&key, &field, value) {
             Ok(added) => total_added += added as i64,
             Err(()) => return Err("ERR WRONGTYPE Operation against a key holding the wrong kind of value".to_string()),
@@ -607,7 +1243,7 @@ fn cmd_hgetall(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<Scri
             let mut results = Vec::new();
             for (field, value) in pairs {
                 results.push(ScriptResult::String(field));
-                results.push(ScriptResult::String(value));
+                results.push(ScriptResult::BulkString(value));
             }
             Ok(ScriptResult::Array(results))
         }
@@ -643,7 +1279,7 @@ fn cmd_hvals(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<Script
         Ok(vals) => {
             let results: Vec<ScriptResult> = vals
                 .into_iter()
-                .map(|v| ScriptResult::String(v))
+                .map(ScriptResult::BulkString)
                 .collect();
             Ok(ScriptResult::Array(results))
         }
@@ -675,7 +1311,7 @@ fn cmd_hmget(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<Script
             let results: Vec<ScriptResult> = values
                 .into_iter()
                 .map(|v| match v {
-                    Some(val) => ScriptResult::String(val),
+                    Some(val) => ScriptResult::BulkString(val),
                     None => ScriptResult::Nil,
                 })
                 .collect();
@@ -693,8 +1329,7 @@ fn cmd_hmset(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<Script
     let key = prefix_key(db, &key_str);
     for i in (1..args.len()).step_by(2) {
         let field = bytes_to_string(&args[i])?;
-        let value_bytes = &args[i + 1];
-        let value = String::from_utf8_lossy(value_bytes).to_string();
+        let value = args[i + 1].clone();
         let _ = storage.hset(&key, &field, value);
     }
     Ok(ScriptResult::Status("OK".to_string()))
@@ -722,7 +1357,7 @@ fn cmd_lpush(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<Script
     }
     let key_str = bytes_to_string(&args[0])?;
     let key = prefix_key(db, &key_str);
-    let values: Vec<String> = args[1..].iter().map(|v| String::from_utf8_lossy(v).to_string()).collect();
+    let values: Vec<Vec<u8>> = args[1..].to_vec();
     match storage.lpush(&key, &values) {
         Ok(len) => Ok(ScriptResult::Integer(len as i64)),
         Err(()) => Err("ERR WRONGTYPE Operation against a key holding the wrong kind of value".to_string()),
@@ -735,7 +1370,7 @@ fn cmd_rpush(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<Script
     }
     let key_str = bytes_to_string(&args[0])?;
     let key = prefix_key(db, &key_str);
-    let values: Vec<String> = args[1..].iter().map(|v| String::from_utf8_lossy(v).to_string()).collect();
+    let values: Vec<Vec<u8>> = args[1..].to_vec();
     match storage.rpush(&key, &values) {
         Ok(len) => Ok(ScriptResult::Integer(len as i64)),
         Err(()) => Err("ERR WRONGTYPE Operation against a key holding the wrong kind of value".to_string()),
@@ -749,7 +1384,7 @@ fn cmd_lpop(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<ScriptR
     let key_str = bytes_to_string(&args[0])?;
     let key = prefix_key(db, &key_str);
     match storage.lpop(&key) {
-        Ok(Some(v)) => Ok(ScriptResult::String(v)),
+        Ok(Some(v)) => Ok(ScriptResult::BulkString(v)),
         Ok(None) => Ok(ScriptResult::Nil),
         Err(()) => Err("ERR WRONGTYPE Operation against a key holding the wrong kind of value".to_string()),
     }
@@ -762,7 +1397,7 @@ fn cmd_rpop(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<ScriptR
     let key_str = bytes_to_string(&args[0])?;
     let key = prefix_key(db, &key_str);
     match storage.rpop(&key) {
-        Ok(Some(v)) => Ok(ScriptResult::String(v)),
+        Ok(Some(v)) => Ok(ScriptResult::BulkString(v)),
         Ok(None) => Ok(ScriptResult::Nil),
         Err(()) => Err("ERR WRONGTYPE Operation against a key holding the wrong kind of value".to_string()),
     }
@@ -794,7 +1429,7 @@ fn cmd_lrange(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<Scrip
         Ok(values) => {
             let results: Vec<ScriptResult> = values
                 .into_iter()
-                .map(|v| ScriptResult::String(v))
+                .map(ScriptResult::BulkString)
                 .collect();
             Ok(ScriptResult::Array(results))
         }
@@ -811,7 +1446,7 @@ fn cmd_lindex(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<Scrip
     let index_str = bytes_to_string(&args[1])?;
     let index: isize = index_str.parse().map_err(|_| "ERR value is not an integer or out of range".to_string())?;
     match storage.lindex(&key, index) {
-        Ok(Some(v)) => Ok(ScriptResult::String(v)),
+        Ok(Some(v)) => Ok(ScriptResult::BulkString(v)),
         Ok(None) => Ok(ScriptResult::Nil),
         Err(()) => Err("ERR WRONGTYPE Operation against a key holding the wrong kind of value".to_string()),
     }
@@ -824,7 +1459,7 @@ fn cmd_sadd(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<ScriptR
     }
     let key_str = bytes_to_string(&args[0])?;
     let key = prefix_key(db, &key_str);
-    let members: Vec<String> = args[1..].iter().map(|v| String::from_utf8_lossy(v).to_string()).collect();
+    let members: Vec<Vec<u8>> = args[1..].to_vec();
     match storage.sadd(&key, &members) {
         Ok(count) => Ok(ScriptResult::Integer(count as i64)),
         Err(()) => Err("ERR WRONGTYPE Operation against a key holding the wrong kind of value".to_string()),
@@ -837,7 +1472,7 @@ fn cmd_srem(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<ScriptR
     }
     let key_str = bytes_to_string(&args[0])?;
     let key = prefix_key(db, &key_str);
-    let members: Vec<String> = args[1..].iter().map(|v| String::from_utf8_lossy(v).to_string()).collect();
+    let members: Vec<Vec<u8>> = args[1..].to_vec();
     match storage.srem(&key, &members) {
         Ok(count) => Ok(ScriptResult::Integer(count as i64)),
         Err(()) => Err("ERR WRONGTYPE Operation against a key holding the wrong kind of value".to_string()),
@@ -854,7 +1489,7 @@ fn cmd_smembers(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<Scr
         Ok(members) => {
             let results: Vec<ScriptResult> = members
                 .into_iter()
-                .map(|m| ScriptResult::String(m))
+                .map(ScriptResult::BulkString)
                 .collect();
             Ok(ScriptResult::Array(results))
         }
@@ -868,8 +1503,7 @@ fn cmd_sismember(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<Sc
     }
     let key_str = bytes_to_string(&args[0])?;
     let key = prefix_key(db, &key_str);
-    let member = String::from_utf8_lossy(&args[1]).to_string();
-    match storage.sismember(&key, &member) {
+    match storage.sismember(&key, &args[1]) {
         Ok(is_member) => Ok(ScriptResult::Integer(if is_member { 1 } else { 0 })),
         Err(()) => Err("ERR WRONGTYPE Operation against a key holding the wrong kind of value".to_string()),
     }
@@ -898,10 +1532,9 @@ fn cmd_zadd(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<ScriptR
     for i in (1..args.len()).step_by(2) {
         let score_str = bytes_to_string(&args[i])?;
         let score: f64 = score_str.parse().map_err(|_| "ERR value is not a valid float".to_string())?;
-        let member = String::from_utf8_lossy(&args[i + 1]).to_string();
-        members.push((score, member));
+        members.push((score, args[i + 1].clone()));
     }
-    match storage.zadd(&key, &members) {
+    match storage.zadd(&key, &members, Default::default()) {
         Ok(added) => Ok(ScriptResult::Integer(added as i64)),
         Err(_) => Err("ERR WRONGTYPE Operation against a key holding the wrong kind of value".to_string()),
     }
@@ -913,7 +1546,7 @@ fn cmd_zrem(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<ScriptR
     }
     let key_str = bytes_to_string(&args[0])?;
     let key = prefix_key(db, &key_str);
-    let members: Vec<String> = args[1..].iter().map(|v| String::from_utf8_lossy(v).to_string()).collect();
+    let members: Vec<Vec<u8>> = args[1..].to_vec();
     match storage.zrem(&key, &members) {
         Ok(count) => Ok(ScriptResult::Integer(count as i64)),
         Err(()) => Err("ERR WRONGTYPE Operation against a key holding the wrong kind of value".to_string()),
@@ -926,8 +1559,7 @@ fn cmd_zscore(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<Scrip
     }
     let key_str = bytes_to_string(&args[0])?;
     let key = prefix_key(db, &key_str);
-    let member = String::from_utf8_lossy(&args[1]).to_string();
-    match storage.zscore(&key, &member) {
+    match storage.zscore(&key, &args[1]) {
         Ok(Some(score)) => Ok(ScriptResult::String(score.to_string())),
         Ok(None) => Ok(ScriptResult::Nil),
         Err(()) => Err("ERR WRONGTYPE Operation against a key holding the wrong kind of value".to_string()),
@@ -962,7 +1594,7 @@ fn cmd_zrange(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<Scrip
         Ok(results) => {
             let mut script_results = Vec::new();
             for (member, score) in results {
-                script_results.push(ScriptResult::String(member));
+                script_results.push(ScriptResult::BulkString(member));
                 if withscores {
                     script_results.push(ScriptResult::String(score.to_string()));
                 }
@@ -990,7 +1622,7 @@ fn cmd_zrevrange(storage: &Arc<Storage>, db: u32, args: &[Vec<u8>]) -> Result<Sc
         Ok(results) => {
             let mut script_results = Vec::new();
             for (member, score) in results {
-                script_results.push(ScriptResult::String(member));
+                script_results.push(ScriptResult::BulkString(member));
                 if withscores {
                     script_results.push(ScriptResult::String(score.to_string()));
                 }
@@ -1088,11 +1720,35 @@ mod tests {
         assert!(cache.get(&sha1).is_some());
         assert_eq!(cache.get(&sha1).unwrap(), script);
         assert_eq!(cache.exists(&[sha1.clone()]), vec![true]);
+
+        // 十六进制摘要不区分大小写，格式不对的摘要视为不存在
+        let upper = sha1.to_uppercase();
+        assert_eq!(cache.get(&upper).unwrap(), script);
+        assert_eq!(
+            cache.exists(&[upper, sha1[..39].to_string(), format!("{}z", &sha1[..39])]),
+            vec![true, false, false]
+        );
         
         cache.flush();
         assert!(cache.get(&sha1).is_none());
     }
 
+    #[test]
+    fn test_script_kill_states() {
+        let state = ScriptRunState::default();
+        assert!(state.kill().unwrap_err().starts_with("NOTBUSY"));
+
+        let running = state.begin();
+        assert!(state.kill().is_ok());
+        drop(running);
+        assert!(state.kill().unwrap_err().starts_with("NOTBUSY"));
+
+        // 已执行写命令的脚本不能被终止
+        let _running = state.begin();
+        state.wrote.store(true, Ordering::Relaxed);
+        assert!(state.kill().unwrap_err().starts_with("UNKILLABLE"));
+    }
+
     #[test]
     fn test_simple_script_execution() {
         let storage = Arc::new(Storage::new(None));
@@ -1101,6 +1757,8 @@ mod tests {
             current_db: 0,
             keys: vec!["key1".to_string()],
             args: vec!["arg1".to_string()],
+            read_only: false,
+            run_state: Arc::default(),
         };
         
         // Test returning integer
@@ -1119,6 +1777,8 @@ mod tests {
             current_db: 0,
             keys: vec!["mykey".to_string()],
             args: vec!["myarg".to_string()],
+            read_only: false,
+            run_state: Arc::default(),
         };
         
         // Test accessing KEYS
@@ -1137,6 +1797,8 @@ mod tests {
             current_db: 0,
             keys: vec![],
             args: vec![],
+            read_only: false,
+            run_state: Arc::default(),
         };
         
         let result = execute_script("return {1, 2, 3}", ctx).unwrap();
@@ -1,41 +1,174 @@
 use std::collections::HashMap;
 use std::env;
 use std::future::Future;
+use std::net::SocketAddr;
 use std::sync::{
     atomic::{AtomicBool, AtomicI64, AtomicU64, AtomicUsize, Ordering},
-    Arc,
+    Arc, Mutex, OnceLock,
 };
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 use dashmap::DashMap;
 use tokio::io::{self, AsyncWriteExt, BufReader};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::{broadcast, mpsc};
-use tokio::time::{sleep, Duration};
+use tokio::sync::{broadcast, mpsc, Notify, OwnedRwLockWriteGuard};
+use tokio::time::Duration;
 
-use log::{error, info};
+use log::{error, info, warn};
+use sha2::{Digest, Sha256};
 
-use crate::command::{read_command, Command, CommandError}; // Import CommandError
+use crate::command::{read_command_with_args, Binary, Command, CommandError}; // Import CommandError
+use crate::command_table::{self, CommandSpec, COMMAND_TABLE};
 use crate::resp::{
-    respond_bulk_bytes, respond_bulk_string, respond_error, respond_integer, respond_null_bulk,
-    respond_simple_string,
+    push_bulk_bytes, respond_bulk_bytes, respond_bulk_string, respond_error, respond_integer,
+    respond_null_bulk, respond_simple_string, ClientWriter, Counted, ReplyBuilder,
+    MAX_BULK_STRING_SIZE,
 };
-use crate::scripting::{execute_script, ScriptCache, ScriptContext};
-use crate::storage::Storage;
+use crate::scripting::{self, execute_function, execute_script, ScriptCache, ScriptContext, ScriptResult};
+use crate::hyperloglog;
+use crate::notify::{self, KeyspaceEvent, NotifyConfig};
+use crate::slowlog::SlowLog;
+use crate::storage::{MaxmemoryPolicy, RdbCompression, Storage};
 
 // 全局客户端 ID 计数器
 static CLIENT_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
 
+/// INFO server 中的 run_id：进程启动后首次使用时生成的 40 位随机十六进制串，之后保持不变
+static RUN_ID: OnceLock<String> = OnceLock::new();
+
+fn run_id() -> &'static str {
+    RUN_ID.get_or_init(|| {
+        let bytes: [u8; 20] = rand::random();
+        hex::encode(bytes)
+    })
+}
+
+/// 脚本运行超过 busy-reply-threshold 时其他命令收到的回复
+const READONLY_ERROR: &str = "READONLY You can't write against a read only replica.";
+const OOM_ERROR: &str = "OOM command not allowed when used memory > 'maxmemory'.";
+const BUSY_SCRIPT_ERROR: &str =
+    "BUSY Redis is busy running a script. You can only call SCRIPT KILL or SHUTDOWN NOSAVE.";
+
+/// 在阻塞线程池中执行脚本，长时间运行的脚本不会占住异步工作线程，
+/// 其他连接仍能收到 BUSY 回复并发送 SCRIPT KILL
+async fn run_script_blocking<F>(f: F) -> Result<ScriptResult, String>
+where
+    F: FnOnce() -> Result<ScriptResult, String> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .unwrap_or_else(|e| Err(format!("ERR script execution failed: {}", e)))
+}
+
 struct Metrics {
     start_time: Instant,
     connected_clients: AtomicUsize,
     total_commands: AtomicU64,
     tcp_port: u16,
+    /// 主监听地址，用于 metrics 中区分 IPv4/IPv6 监听
+    listen_addr: SocketAddr,
     pubsub_channel_subs: AtomicU64,
     pubsub_pattern_subs: AtomicU64,
     pubsub_shard_subs: AtomicU64,
     pubsub_messages_delivered: AtomicU64,
     pubsub_messages_dropped: AtomicU64,
+    /// 读命令按 key 查找时命中/未命中的次数
+    keyspace_hits: AtomicU64,
+    keyspace_misses: AtomicU64,
+    /// 所有客户端连接累计读入/写出的字节数
+    total_net_input_bytes: Arc<AtomicU64>,
+    total_net_output_bytes: Arc<AtomicU64>,
+    /// 命令统计：命令名 -> (调用次数, 累计耗时微秒)
+    command_stats: DashMap<&'static str, (u64, u64)>,
+    slowlog: SlowLog,
+}
+
+/// 正在执行的命令：用于在命令结束后统一记录命令统计与慢查询日志
+struct PendingCommand {
+    name: &'static str,
+    started: Instant,
+    timestamp: i64,
+    args: Vec<Binary>,
+    /// 命令开始时连接已写出的字节数，结束时的差值即回复大小
+    written_before: u64,
+}
+
+impl PendingCommand {
+    fn start(name: &'static str, cmd: &Command, mut args: Vec<Binary>, written_before: u64) -> Self {
+        // AUTH 的参数包含密码，不写入慢查询日志
+        if let Command::Auth { .. } = cmd {
+            args.truncate(1);
+            args.push(b"(redacted)".to_vec());
+        }
+        // HELLO ... AUTH <user> <pass> 同样隐去密码
+        if let Command::Hello { auth: Some(_), .. } = cmd {
+            if let Some(pos) = args.iter().position(|a| a.eq_ignore_ascii_case(b"auth")) {
+                if let Some(pass) = args.get_mut(pos + 2) {
+                    *pass = b"(redacted)".to_vec();
+                }
+            }
+        }
+        // CONFIG SET requirepass <pw> 的取值同样是密码
+        if let Command::ConfigSet { parameter, .. } = cmd {
+            if parameter.eq_ignore_ascii_case("requirepass") {
+                if let Some(value) = args.get_mut(3) {
+                    *value = b"(redacted)".to_vec();
+                }
+            }
+        }
+        PendingCommand {
+            name,
+            started: Instant::now(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0),
+            args,
+            written_before,
+        }
+    }
+
+    fn finish(self, metrics: &Metrics, client_addr: &str, client_name: &str, written_now: u64) {
+        let elapsed = self.started.elapsed();
+        metrics.record_command(self.name, elapsed);
+        metrics.slowlog.log_if_slow(
+            self.timestamp,
+            elapsed.as_micros() as u64,
+            self.args,
+            client_addr,
+            client_name,
+            Some(written_now.saturating_sub(self.written_before)),
+        );
+    }
+}
+
+impl Metrics {
+    fn record_command(&self, name: &'static str, elapsed: Duration) {
+        let usec = elapsed.as_micros() as u64;
+        let mut entry = self.command_stats.entry(name).or_insert((0, 0));
+        entry.0 += 1;
+        entry.1 += usec;
+    }
+
+    /// 在读命令执行前统计其查找的 key 是否存在（过期 key 计为未命中）。
+    ///
+    /// 这是执行前的预检查，并不是命令处理路径里的那次查找：每个读命令会多做一次 map 查找，
+    /// 并发写入时统计结果也可能与命令实际看到的不一致（例如预检查之后 key 恰好被删除或过期）。
+    /// 存储层的读接口没有统一的查找入口，暂不在各读路径内逐一计数
+    fn record_keyspace_lookups(&self, cmd: &Command, storage: &Storage, current_db: u8) {
+        let keys: Vec<String> = cmd
+            .lookup_keys()
+            .into_iter()
+            .map(|k| prefix_key(current_db, k))
+            .collect();
+        if keys.is_empty() {
+            return;
+        }
+        let hits = storage.exists(&keys) as u64;
+        self.keyspace_hits.fetch_add(hits, Ordering::Relaxed);
+        self.keyspace_misses
+            .fetch_add(keys.len() as u64 - hits, Ordering::Relaxed);
+    }
 }
 
 struct PersistenceState {
@@ -43,7 +176,17 @@ struct PersistenceState {
     aof_path: Option<String>,
     last_save: AtomicI64,
     bgsave_running: AtomicBool,
+    /// 同一时刻只允许一个保存在进行：SAVE、BGSAVE、保存点、AOF everysec、DEBUG RELOAD 与
+    /// SHUTDOWN 的保存都要先拿到这把锁，避免并发写同一个文件、重复扣减 dirty
+    save_lock: tokio::sync::Mutex<()>,
     enabled: bool,
+    rdb_compression: RdbCompression,
+    /// Redis 风格的保存点：(seconds, changes)，任一规则满足即触发后台保存
+    save_points: Vec<(u64, u64)>,
+    /// SHUTDOWN 命令发出的关闭请求，accept 循环与外部 shutdown future 一起等待
+    shutdown_requested: Notify,
+    /// SHUTDOWN 已经处理过最终保存（保存成功或 NOSAVE），退出时不再重复保存
+    final_save_handled: AtomicBool,
 }
 
 #[derive(Clone)]
@@ -51,16 +194,56 @@ struct PubSubHub {
     channels: Arc<DashMap<String, broadcast::Sender<PubMessage>>>,
     patterns: Arc<DashMap<String, broadcast::Sender<PubMessage>>>,
     shard_channels: Arc<DashMap<String, broadcast::Sender<PubMessage>>>,
+    /// notify-keyspace-events 配置
+    notify: Arc<NotifyConfig>,
+    /// 新建频道/模式时 broadcast 环形缓冲区的容量（每个频道一份）
+    buffer: Arc<AtomicUsize>,
 }
 
-const PUBSUB_BUFFER: usize = 128;
+const DEFAULT_PUBSUB_BUFFER: usize = 128;
+const MAX_PUBSUB_BUFFER: usize = 1 << 20;
+
+/// 解析缓冲区容量：必须是 1..=MAX_PUBSUB_BUFFER 的整数
+fn parse_pubsub_buffer(value: &str) -> Option<usize> {
+    value
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .filter(|v| (1..=MAX_PUBSUB_BUFFER).contains(v))
+}
 
 impl PubSubHub {
     fn new() -> Self {
+        // REDUST_PUBSUB_BUFFER：每个频道最多缓存的未消费消息数，非法值回退为默认值
+        let buffer = env::var("REDUST_PUBSUB_BUFFER")
+            .ok()
+            .and_then(|v| parse_pubsub_buffer(&v))
+            .unwrap_or(DEFAULT_PUBSUB_BUFFER);
         PubSubHub {
             channels: Arc::new(DashMap::new()),
             patterns: Arc::new(DashMap::new()),
             shard_channels: Arc::new(DashMap::new()),
+            notify: Arc::new(NotifyConfig::from_env()),
+            buffer: Arc::new(AtomicUsize::new(buffer)),
+        }
+    }
+
+    fn buffer_size(&self) -> usize {
+        self.buffer.load(Ordering::Relaxed)
+    }
+
+    /// 修改缓冲区容量；只影响之后新建的频道，已有频道保持创建时的容量
+    fn set_buffer_size(&self, size: usize) {
+        self.buffer.store(size, Ordering::Relaxed);
+    }
+
+    /// 按 notify-keyspace-events 配置发布键空间事件；`physical_key` 为带 db 前缀的内部 key
+    fn notify_keyspace_event(&self, class: u32, event: &str, physical_key: &str) {
+        let Some((db, key)) = physical_key.split_once(':') else {
+            return;
+        };
+        for (channel, payload) in self.notify.channels_for(class, event, db, key) {
+            self.publish(&channel, &payload);
         }
     }
 
@@ -68,7 +251,7 @@ impl PubSubHub {
         let tx = self
             .channels
             .entry(channel.to_string())
-            .or_insert_with(|| broadcast::channel(PUBSUB_BUFFER).0)
+            .or_insert_with(|| broadcast::channel(self.buffer_size()).0)
             .clone();
         tx.subscribe()
     }
@@ -77,7 +260,7 @@ impl PubSubHub {
         let tx = self
             .patterns
             .entry(pattern.to_string())
-            .or_insert_with(|| broadcast::channel(PUBSUB_BUFFER).0)
+            .or_insert_with(|| broadcast::channel(self.buffer_size()).0)
             .clone();
         tx.subscribe()
     }
@@ -86,7 +269,7 @@ impl PubSubHub {
         let tx = self
             .shard_channels
             .entry(channel.to_string())
-            .or_insert_with(|| broadcast::channel(PUBSUB_BUFFER).0)
+            .or_insert_with(|| broadcast::channel(self.buffer_size()).0)
             .clone();
         tx.subscribe()
     }
@@ -226,13 +409,14 @@ impl PubSubHub {
         channels
     }
 
-    fn pattern_subscription_count(&self) -> usize {
+    /// 至少有一个订阅者的不同模式个数（PUBSUB NUMPAT），同一模式被多个客户端订阅只算一次
+    fn active_pattern_count(&self) -> usize {
         self.cleanup_stale();
 
         self.patterns
             .iter()
-            .map(|entry| entry.value().receiver_count())
-            .sum()
+            .filter(|entry| entry.value().receiver_count() > 0)
+            .count()
     }
 }
 
@@ -262,6 +446,34 @@ fn parse_maxmemory_bytes(input: &str) -> Option<u64> {
     base.checked_mul(multiplier)
 }
 
+/// 解析 Redis 风格的保存点配置，例如 `"900 1 300 10"` -> [(900, 1), (300, 10)]。
+/// 空字符串表示不启用保存点。
+fn parse_save_points(input: &str) -> Option<Vec<(u64, u64)>> {
+    let parts: Vec<&str> = input.split_whitespace().collect();
+    let pairs = parts.chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return None;
+    }
+    let mut points = Vec::with_capacity(parts.len() / 2);
+    for pair in pairs {
+        let seconds = pair[0].parse::<u64>().ok()?;
+        let changes = pair[1].parse::<u64>().ok()?;
+        if seconds == 0 {
+            return None;
+        }
+        points.push((seconds, changes));
+    }
+    Some(points)
+}
+
+fn format_save_points(points: &[(u64, u64)]) -> String {
+    points
+        .iter()
+        .map(|(secs, changes)| format!("{} {}", secs, changes))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 fn format_bytes(bytes: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = 1024 * KB;
@@ -283,6 +495,11 @@ fn format_bytes(bytes: u64) -> String {
 }
 
 fn pattern_match(pattern: &str, value: &str) -> bool {
+    pattern_match_bytes(pattern.as_bytes(), value.as_bytes())
+}
+
+/// 按字节进行 glob 匹配，供二进制集合成员等非 UTF-8 数据使用
+fn pattern_match_bytes(pattern: &[u8], value: &[u8]) -> bool {
     fn match_set(p: &[u8], ch: u8) -> Option<(bool, usize)> {
         let mut ranges: Vec<(u8, u8)> = Vec::new();
         let mut i = 1; // skip '['
@@ -357,16 +574,63 @@ fn pattern_match(pattern: &str, value: &str) -> bool {
         }
     }
 
-    helper(pattern.as_bytes(), 0, value.as_bytes(), 0)
+    helper(pattern, 0, value, 0)
 }
 
+/// 保存一次快照；已有保存在进行时等待它结束后再开始
 async fn perform_save(
     storage: Storage,
     path: String,
     persistence: Arc<PersistenceState>,
 ) -> io::Result<()> {
-    let res = tokio::task::spawn_blocking(move || storage.save_rdb(&path)).await;
+    let _save = persistence.save_lock.lock().await;
+    perform_save_locked(storage, path, &persistence, true).await
+}
+
+/// AOF everysec 快照：与其他保存互斥，但不算一次 RDB 保存，
+/// 既不扣减 dirty 也不更新 LASTSAVE，保存点照常按 RDB 的变更数触发
+async fn perform_aof_snapshot(
+    storage: Storage,
+    path: String,
+    persistence: Arc<PersistenceState>,
+) -> io::Result<()> {
+    let _save = persistence.save_lock.lock().await;
+    perform_save_locked(storage, path, &persistence, false).await
+}
+
+/// 调用方必须已持有 `save_lock`
+async fn perform_save_locked(
+    storage: Storage,
+    path: String,
+    persistence: &PersistenceState,
+    counts_as_save: bool,
+) -> io::Result<()> {
+    // 独占写命令闸门只覆盖内存拷贝；序列化与写盘在释放闸门后进行，不阻塞写命令
+    let gate = storage.snapshot_guard().await;
+    save_snapshot(storage, path, persistence, counts_as_save, Some(gate)).await
+}
+
+/// 拷贝快照并写盘。`gate` 为 Some 时拷贝完成即释放；为 None 时调用方已持有写命令闸门，
+/// 由调用方决定何时释放（DEBUG RELOAD 需要在保存与重新加载之间一直持有）
+async fn save_snapshot(
+    storage: Storage,
+    path: String,
+    persistence: &PersistenceState,
+    counts_as_save: bool,
+    gate: Option<OwnedRwLockWriteGuard<()>>,
+) -> io::Result<()> {
+    let compression = persistence.rdb_compression;
+    let res = tokio::task::spawn_blocking(move || {
+        let mut snapshot = storage.snapshot();
+        drop(gate);
+        if !counts_as_save {
+            snapshot = snapshot.without_dirty();
+        }
+        storage.save_rdb_snapshot(snapshot, &path, compression)
+    })
+    .await;
     match res {
+        Ok(Ok(())) if !counts_as_save => Ok(()),
         Ok(Ok(())) => {
             if let Ok(dur) = SystemTime::now().duration_since(UNIX_EPOCH) {
                 persistence
@@ -390,6 +654,13 @@ fn current_max_value_bytes() -> Option<u64> {
         .filter(|v| *v > 0)
 }
 
+/// 是否对客户端连接关闭 Nagle 算法（`REDUST_TCP_NODELAY`，默认开启，与 Redis 一致）
+fn tcp_nodelay_enabled() -> bool {
+    env::var("REDUST_TCP_NODELAY")
+        .map(|v| !matches!(v.to_ascii_lowercase().as_str(), "0" | "false" | "no"))
+        .unwrap_or(true)
+}
+
 fn file_mtime_seconds(path: &str) -> Option<i64> {
     let meta = std::fs::metadata(path).ok()?;
     let modified = meta.modified().ok()?;
@@ -407,9 +678,24 @@ fn build_prometheus_metrics(storage: &Storage, metrics: &Metrics) -> String {
     let pubsub_shards = metrics.pubsub_shard_subs.load(Ordering::Relaxed);
     let pubsub_delivered = metrics.pubsub_messages_delivered.load(Ordering::Relaxed);
     let pubsub_dropped = metrics.pubsub_messages_dropped.load(Ordering::Relaxed);
+    let keyspace_hits = metrics.keyspace_hits.load(Ordering::Relaxed);
+    let keyspace_misses = metrics.keyspace_misses.load(Ordering::Relaxed);
+    let net_input = metrics.total_net_input_bytes.load(Ordering::Relaxed);
+    let net_output = metrics.total_net_output_bytes.load(Ordering::Relaxed);
 
     let mut buf = String::new();
 
+    let family = if metrics.listen_addr.is_ipv6() {
+        "ipv6"
+    } else {
+        "ipv4"
+    };
+    buf.push_str("# TYPE redust_listener_info gauge\n");
+    buf.push_str(&format!(
+        "redust_listener_info{{addr=\"{}\",family=\"{}\"}} 1\n",
+        metrics.listen_addr, family
+    ));
+
     buf.push_str("# TYPE redust_uptime_seconds counter\n");
     buf.push_str(&format!("redust_uptime_seconds {}\n", uptime));
 
@@ -422,6 +708,21 @@ fn build_prometheus_metrics(storage: &Storage, metrics: &Metrics) -> String {
     buf.push_str("# TYPE redust_keyspace_keys gauge\n");
     buf.push_str(&format!("redust_keyspace_keys{{db=\"0\"}} {}\n", keys));
 
+    buf.push_str("# TYPE redust_keyspace_hits_total counter\n");
+    buf.push_str(&format!("redust_keyspace_hits_total {}\n", keyspace_hits));
+
+    buf.push_str("# TYPE redust_keyspace_misses_total counter\n");
+    buf.push_str(&format!("redust_keyspace_misses_total {}\n", keyspace_misses));
+
+    buf.push_str("# TYPE redust_expired_keys_total counter\n");
+    buf.push_str(&format!("redust_expired_keys_total {}\n", storage.expired_keys()));
+
+    buf.push_str("# TYPE redust_net_input_bytes_total counter\n");
+    buf.push_str(&format!("redust_net_input_bytes_total {}\n", net_input));
+
+    buf.push_str("# TYPE redust_net_output_bytes_total counter\n");
+    buf.push_str(&format!("redust_net_output_bytes_total {}\n", net_output));
+
     buf.push_str("# TYPE redust_pubsub_channel_subscriptions gauge\n");
     buf.push_str(&format!(
         "redust_pubsub_channel_subscriptions {}\n",
@@ -480,7 +781,7 @@ enum PubSubOverflowStrategy {
 }
 
 async fn write_subscribe_event(
-    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    writer: &mut ClientWriter,
     kind: &str,
     channel: &str,
     count: usize,
@@ -500,8 +801,9 @@ async fn write_subscribe_event(
         .await
 }
 
+/// payload 以字节长度前缀原样写出，不经过字符串转换，CRLF、NUL 等任意字节都能完整送达
 async fn write_message_event(
-    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    writer: &mut ClientWriter,
     channel: &str,
     payload: &[u8],
 ) -> io::Result<()> {
@@ -523,7 +825,7 @@ async fn write_message_event(
 }
 
 async fn write_pmessage_event(
-    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    writer: &mut ClientWriter,
     pattern: &str,
     channel: &str,
     payload: &[u8],
@@ -548,7 +850,7 @@ async fn write_pmessage_event(
 }
 
 async fn write_pub_message_event(
-    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    writer: &mut ClientWriter,
     message: &PubMessage,
 ) -> io::Result<()> {
     match message {
@@ -565,7 +867,7 @@ async fn write_pub_message_event(
 }
 
 async fn write_pong_event(
-    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    writer: &mut ClientWriter,
     payload: &[u8],
 ) -> io::Result<()> {
     writer
@@ -575,10 +877,49 @@ async fn write_pong_event(
     writer.write_all(b"\r\n").await
 }
 
+/// PFDEBUG GETREG / ENCODING：返回寄存器数组或编码名，用于校验 HLL 实现
+async fn respond_pfdebug(
+    storage: &Storage,
+    writer: &mut ClientWriter,
+    current_db: u8,
+    key: &str,
+    encoding_only: bool,
+) -> io::Result<()> {
+    let physical = prefix_key(current_db, key);
+    match storage.pfdebug(&physical) {
+        Ok(Some((_, encoding))) if encoding_only => respond_simple_string(writer, encoding).await,
+        Ok(Some((registers, _))) => {
+            let mut reply = ReplyBuilder::array(registers.len());
+            for register in registers {
+                reply.integer(register as i64);
+            }
+            reply.flush(writer).await
+        }
+        Ok(None) => respond_error(writer, "ERR The specified key does not exist").await,
+        Err(()) => {
+            respond_error(
+                writer,
+                "WRONGTYPE Key is not a valid HyperLogLog string value.",
+            )
+            .await
+        }
+    }
+}
+
+fn incr_error_message(err: crate::storage::IncrError) -> &'static str {
+    match err {
+        crate::storage::IncrError::WrongType => {
+            "WRONGTYPE Operation against a key holding the wrong kind of value"
+        }
+        crate::storage::IncrError::NotInteger => "ERR value is not an integer or out of range",
+        crate::storage::IncrError::Overflow => "ERR increment or decrement would overflow",
+    }
+}
+
 async fn handle_string_command(
     cmd: Command,
     storage: &Storage,
-    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    writer: &mut ClientWriter,
     current_db: u8,
 ) -> io::Result<()> {
     match cmd {
@@ -591,6 +932,62 @@ async fn handle_string_command(
         Command::Echo(value) => {
             respond_bulk_bytes(writer, &value).await?;
         }
+        Command::Lolwut => {
+            respond_bulk_string(writer, &format!("Redust ver. {}\n", env!("CARGO_PKG_VERSION")))
+                .await?;
+        }
+        Command::Role => {
+            // 没有复制功能：始终是没有副本的 master，复制偏移量为 0
+            let mut reply = ReplyBuilder::array(3);
+            reply.bulk(b"master");
+            reply.integer(0);
+            reply.nested_array(0);
+            reply.flush(writer).await?;
+        }
+        Command::Replicaof { host, port } => {
+            if host.eq_ignore_ascii_case("no") && port.eq_ignore_ascii_case("one") {
+                respond_simple_string(writer, "OK").await?;
+            } else {
+                respond_error(writer, "ERR replication not supported").await?;
+            }
+        }
+        Command::Wait { .. } => {
+            // 没有副本：已确认写入的副本数总是 0，不等待超时
+            respond_integer(writer, 0).await?;
+        }
+        Command::Failover => {
+            respond_error(writer, "ERR FAILOVER requires connected replicas.").await?;
+        }
+        Command::Cluster { subcommand, args } => {
+            // 只读探测返回“未启用集群”的结果，其余子命令统一报错
+            match (subcommand.as_str(), args.len()) {
+                ("info", 0) => respond_bulk_string(writer, "cluster_enabled:0\r\n").await?,
+                // 没有集群节点 ID，复用同样是 40 位十六进制、进程内稳定的 run_id
+                ("myid", 0) => respond_bulk_string(writer, run_id()).await?,
+                ("slots", 0) | ("shards", 0) => ReplyBuilder::array(0).flush(writer).await?,
+                ("info" | "myid" | "slots" | "shards", _) => {
+                    respond_error(
+                        writer,
+                        &format!(
+                            "ERR Unknown subcommand or wrong number of arguments for 'cluster|{}'",
+                            subcommand
+                        ),
+                    )
+                    .await?
+                }
+                _ => respond_error(writer, "ERR This instance has cluster support disabled").await?,
+            }
+        }
+        Command::Time => {
+            // [Unix 秒, 当前秒内的微秒]，均为 bulk string
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            let mut reply = ReplyBuilder::array(2);
+            reply.bulk(now.as_secs().to_string().as_bytes());
+            reply.bulk(now.subsec_micros().to_string().as_bytes());
+            reply.flush(writer).await?;
+        }
         Command::Quit => {
             respond_simple_string(writer, "OK").await?;
         }
@@ -614,6 +1011,16 @@ async fn handle_string_command(
                 }
             }
 
+            // 带 GET 时旧值必须是字符串，否则与 Redis 一致返回 WRONGTYPE 且不做任何修改
+            if get && !matches!(storage.type_of(&physical).as_str(), "none" | "string") {
+                respond_error(
+                    writer,
+                    "WRONGTYPE Operation against a key holding the wrong kind of value",
+                )
+                .await?;
+                return Ok(());
+            }
+
             // 获取旧值（字符串类型才有意义）
             let old_value = storage.get(&physical);
 
@@ -647,6 +1054,8 @@ async fn handle_string_command(
                 storage.set(physical.clone(), value);
 
                 // 先根据 EX/PX/EXAT/PXAT 计算“显式指定”的 TTL；存在显式 TTL 时优先级高于 KEEPTTL。
+                // EXAT/PXAT 给出的是绝对到期时间，换算成剩余毫秒后不叠加抖动。
+                let absolute_deadline = expire_at_millis.is_some();
                 let explicit_ttl_ms: Option<i64> = if let Some(at_ms) = expire_at_millis {
                     use std::time::{SystemTime, UNIX_EPOCH};
                     let now_ms = SystemTime::now()
@@ -664,9 +1073,13 @@ async fn handle_string_command(
                 // 3. 否则：清除已有 TTL
                 if let Some(ms) = explicit_ttl_ms {
                     // Redis 语义：到期时间在“现在及以前”视为立即过期
-                    storage.expire_millis(&physical, ms);
+                    if absolute_deadline {
+                        storage.restore_ttl_millis(&physical, ms);
+                    } else {
+                        storage.expire_millis(&physical, ms);
+                    }
                 } else if let Some(ms) = keep_ttl_ms {
-                    storage.expire_millis(&physical, ms);
+                    storage.restore_ttl_millis(&physical, ms);
                 } else {
                     let _ = storage.persist(&physical);
                 }
@@ -688,24 +1101,22 @@ async fn handle_string_command(
         Command::Incrbyfloat { key, delta } => {
             let physical = prefix_key(current_db, &key);
             match storage.incr_by_float(&physical, delta) {
-                Ok(value) => {
-                    let s = {
-                        let mut s = value.to_string();
-                        if s.contains('.') {
-                            while s.ends_with('0') {
-                                s.pop();
-                            }
-                            if s.ends_with('.') {
-                                s.push('0');
-                            }
-                        }
-                        s
-                    };
+                Ok(s) => {
                     respond_bulk_string(writer, &s).await?;
                 }
-                Err(_) => {
+                Err(crate::storage::IncrFloatError::WrongType) => {
+                    respond_error(
+                        writer,
+                        "WRONGTYPE Operation against a key holding the wrong kind of value",
+                    )
+                    .await?;
+                }
+                Err(crate::storage::IncrFloatError::NotFloat) => {
                     respond_error(writer, "ERR value is not a valid float").await?;
                 }
+                Err(crate::storage::IncrFloatError::NanOrInfinity) => {
+                    respond_error(writer, "ERR increment would produce NaN or Infinity").await?;
+                }
             }
         }
         Command::Get { key } => {
@@ -720,6 +1131,7 @@ async fn handle_string_command(
             let physical = prefix_key(current_db, &key);
             match storage.getdel(&physical) {
                 Ok(Some(value)) => {
+                    storage.notify_keyspace_event(notify::NOTIFY_GENERIC, "del", &physical);
                     respond_bulk_bytes(writer, &value).await?;
                 }
                 Ok(None) => {
@@ -748,11 +1160,14 @@ async fn handle_string_command(
                     // 先返回当前值
                     respond_bulk_bytes(writer, v).await?;
 
-                    // 然后根据选项更新 TTL
+                    // 然后根据选项更新 TTL；TTL 真正改变时（版本号已递增）发出对应事件
+                    // 非正的过期时间在解析阶段已被拒绝
                     if let Some(ms) = expire_millis {
-                        let _ = storage.expire_millis(&physical, ms);
-                    } else if persist {
-                        let _ = storage.persist(&physical);
+                        if storage.expire_millis(&physical, ms) {
+                            storage.notify_keyspace_event(notify::NOTIFY_GENERIC, "expire", &physical);
+                        }
+                    } else if persist && storage.persist(&physical) {
+                        storage.notify_keyspace_event(notify::NOTIFY_GENERIC, "persist", &physical);
                     }
                 }
                 None => {
@@ -778,11 +1193,33 @@ async fn handle_string_command(
         }
         Command::Setrange { key, offset, value } => {
             let physical = prefix_key(current_db, &key);
-            if let Some(limit) = current_max_value_bytes() {
-                if (value.len() as u64) > limit {
+            // 空 value 不修改 key，不做长度检查
+            if !value.is_empty() {
+                // 与 Redis 一致，补齐后的长度不能超过 proto-max-bulk-len；checked_add 防止溢出
+                let Some(target_len) = offset
+                    .checked_add(value.len())
+                    .filter(|len| *len <= MAX_BULK_STRING_SIZE)
+                else {
+                    respond_error(
+                        writer,
+                        "ERR string exceeds maximum allowed size (proto-max-bulk-len)",
+                    )
+                    .await?;
+                    return Ok(());
+                };
+                // 补齐后的字符串长度同样受 REDUST_MAXVALUE_BYTES 限制
+                if current_max_value_bytes().is_some_and(|limit| target_len as u64 > limit) {
                     respond_error(writer, "ERR value exceeds REDUST_MAXVALUE_BYTES").await?;
                     return Ok(());
                 }
+                // 在分配补齐的字节之前判断能否留在 maxmemory 以内
+                if let Ok(cur_len) = storage.strlen(&physical) {
+                    let growth = target_len.saturating_sub(cur_len) as u64;
+                    if !storage.can_grow_within_maxmemory(target_len as u64, growth) {
+                        respond_error(writer, OOM_ERROR).await?;
+                        return Ok(());
+                    }
+                }
             }
 
             match storage.setrange(&physical, offset, &value) {
@@ -820,6 +1257,44 @@ async fn handle_string_command(
                 }
             }
         }
+        Command::Setbit { key, offset, bit } => {
+            let physical = prefix_key(current_db, &key);
+            // 补齐后的字符串长度同样受 REDUST_MAXVALUE_BYTES 限制
+            if let Some(limit) = current_max_value_bytes() {
+                if offset / 8 + 1 > limit {
+                    respond_error(writer, "ERR value exceeds REDUST_MAXVALUE_BYTES").await?;
+                    return Ok(());
+                }
+            }
+
+            match storage.setbit(&physical, offset, bit) {
+                Ok(old) => {
+                    respond_integer(writer, old as i64).await?;
+                }
+                Err(()) => {
+                    respond_error(
+                        writer,
+                        "WRONGTYPE Operation against a key holding the wrong kind of value",
+                    )
+                    .await?;
+                }
+            }
+        }
+        Command::Getbit { key, offset } => {
+            let physical = prefix_key(current_db, &key);
+            match storage.getbit(&physical, offset) {
+                Ok(bit) => {
+                    respond_integer(writer, bit as i64).await?;
+                }
+                Err(()) => {
+                    respond_error(
+                        writer,
+                        "WRONGTYPE Operation against a key holding the wrong kind of value",
+                    )
+                    .await?;
+                }
+            }
+        }
         Command::Strlen { key } => {
             let physical = prefix_key(current_db, &key);
             match storage.strlen(&physical) {
@@ -866,8 +1341,8 @@ async fn handle_string_command(
                 Ok(value) => {
                     respond_integer(writer, value).await?;
                 }
-                Err(_) => {
-                    respond_error(writer, "ERR value is not an integer or out of range").await?;
+                Err(e) => {
+                    respond_error(writer, incr_error_message(e)).await?;
                 }
             }
         }
@@ -877,8 +1352,8 @@ async fn handle_string_command(
                 Ok(value) => {
                     respond_integer(writer, value).await?;
                 }
-                Err(_) => {
-                    respond_error(writer, "ERR value is not an integer or out of range").await?;
+                Err(e) => {
+                    respond_error(writer, incr_error_message(e)).await?;
                 }
             }
         }
@@ -888,8 +1363,8 @@ async fn handle_string_command(
                 Ok(value) => {
                     respond_integer(writer, value).await?;
                 }
-                Err(_) => {
-                    respond_error(writer, "ERR value is not an integer or out of range").await?;
+                Err(e) => {
+                    respond_error(writer, incr_error_message(e)).await?;
                 }
             }
         }
@@ -899,12 +1374,12 @@ async fn handle_string_command(
                 Ok(value) => {
                     respond_integer(writer, value).await?;
                 }
-                Err(_) => {
-                    respond_error(writer, "ERR value is not an integer or out of range").await?;
+                Err(e) => {
+                    respond_error(writer, incr_error_message(e)).await?;
                 }
             }
         }
-        Command::Del { keys } => {
+        Command::Del { keys } | Command::Unlink { keys } => {
             let physical: Vec<String> = keys
                 .into_iter()
                 .map(|k| prefix_key(current_db, &k))
@@ -926,23 +1401,14 @@ async fn handle_string_command(
                 .map(|k| prefix_key(current_db, &k))
                 .collect();
             let values = storage.mget(&physical);
-            writer
-                .write_all(format!("*{}\r\n", values.len()).as_bytes())
-                .await?;
+            let mut reply = ReplyBuilder::array(values.len());
             for v in values {
                 match v {
-                    Some(s) => {
-                        writer
-                            .write_all(format!("${}\r\n", s.len()).as_bytes())
-                            .await?;
-                        writer.write_all(&s).await?;
-                        writer.write_all(b"\r\n").await?;
-                    }
-                    None => {
-                        writer.write_all(b"$-1\r\n").await?;
-                    }
+                    Some(s) => reply.bulk(&s),
+                    None => reply.null_bulk(),
                 }
             }
+            reply.flush(writer).await?;
         }
         Command::Mset { pairs } => {
             if let Some(limit) = current_max_value_bytes() {
@@ -1074,6 +1540,12 @@ async fn handle_string_command(
                 }
             }
         }
+        Command::PfdebugGetreg { key } => {
+            respond_pfdebug(storage, writer, current_db, &key, false).await?;
+        }
+        Command::PfdebugEncoding { key } => {
+            respond_pfdebug(storage, writer, current_db, &key, true).await?;
+        }
         _ => {}
     }
 
@@ -1083,7 +1555,7 @@ async fn handle_string_command(
 async fn handle_list_command(
     cmd: Command,
     storage: &Storage,
-    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    writer: &mut ClientWriter,
     current_db: u8,
 ) -> io::Result<()> {
     match cmd {
@@ -1091,7 +1563,7 @@ async fn handle_list_command(
             let physical = prefix_key(current_db, &key);
             if let Some(limit) = current_max_value_bytes() {
                 for v in &values {
-                    if (v.as_bytes().len() as u64) > limit {
+                    if (v.len() as u64) > limit {
                         respond_error(writer, "ERR value exceeds REDUST_MAXVALUE_BYTES").await?;
                         return Ok(());
                     }
@@ -1133,11 +1605,11 @@ async fn handle_list_command(
             let physical = prefix_key(current_db, &key);
             match storage.hvals(&physical) {
                 Ok(vals) => {
-                    let mut response = format!("*{}\r\n", vals.len());
+                    let mut response = format!("*{}\r\n", vals.len()).into_bytes();
                     for v in vals {
-                        response.push_str(&format!("${}\r\n{}\r\n", v.len(), v));
+                        push_bulk_bytes(&mut response, &v);
                     }
-                    writer.write_all(response.as_bytes()).await?;
+                    writer.write_all(&response).await?;
                 }
                 Err(()) => {
                     respond_error(
@@ -1152,18 +1624,18 @@ async fn handle_list_command(
             let physical = prefix_key(current_db, &key);
             match storage.hmget(&physical, &fields) {
                 Ok(values) => {
-                    let mut response = format!("*{}\r\n", values.len());
+                    let mut response = format!("*{}\r\n", values.len()).into_bytes();
                     for v in values {
                         match v {
                             Some(s) => {
-                                response.push_str(&format!("${}\r\n{}\r\n", s.len(), s));
+                                push_bulk_bytes(&mut response, &s);
                             }
                             None => {
-                                response.push_str("$-1\r\n");
+                                response.extend_from_slice(b"$-1\r\n");
                             }
                         }
                     }
-                    writer.write_all(response.as_bytes()).await?;
+                    writer.write_all(&response).await?;
                 }
                 Err(()) => {
                     respond_error(
@@ -1178,7 +1650,7 @@ async fn handle_list_command(
             let physical = prefix_key(current_db, &key);
             if let Some(limit) = current_max_value_bytes() {
                 for v in &values {
-                    if (v.as_bytes().len() as u64) > limit {
+                    if (v.len() as u64) > limit {
                         respond_error(writer, "ERR value exceeds REDUST_MAXVALUE_BYTES").await?;
                         return Ok(());
                     }
@@ -1201,11 +1673,11 @@ async fn handle_list_command(
             let physical = prefix_key(current_db, &key);
             match storage.lrange(&physical, start, stop) {
                 Ok(items) => {
-                    let mut response = format!("*{}\r\n", items.len());
+                    let mut reply = ReplyBuilder::array(items.len());
                     for item in items {
-                        response.push_str(&format!("${}\r\n{}\r\n", item.len(), item));
+                        reply.bulk(&item);
                     }
-                    writer.write_all(response.as_bytes()).await?;
+                    reply.flush(writer).await?;
                 }
                 Err(()) => {
                     respond_error(
@@ -1220,7 +1692,7 @@ async fn handle_list_command(
             let physical = prefix_key(current_db, &key);
             match storage.lpop(&physical) {
                 Ok(Some(value)) => {
-                    respond_bulk_string(writer, &value).await?;
+                    respond_bulk_bytes(writer, &value).await?;
                 }
                 Ok(None) => {
                     respond_null_bulk(writer).await?;
@@ -1238,7 +1710,7 @@ async fn handle_list_command(
             let physical = prefix_key(current_db, &key);
             match storage.rpop(&physical) {
                 Ok(Some(value)) => {
-                    respond_bulk_string(writer, &value).await?;
+                    respond_bulk_bytes(writer, &value).await?;
                 }
                 Ok(None) => {
                     respond_null_bulk(writer).await?;
@@ -1271,7 +1743,7 @@ async fn handle_list_command(
             let physical = prefix_key(current_db, &key);
             match storage.lindex(&physical, index) {
                 Ok(Some(value)) => {
-                    respond_bulk_string(writer, &value).await?;
+                    respond_bulk_bytes(writer, &value).await?;
                 }
                 Ok(None) => {
                     respond_null_bulk(writer).await?;
@@ -1300,12 +1772,30 @@ async fn handle_list_command(
                 }
             }
         }
-        Command::Ltrim { key, start, stop } => {
+        Command::Lpos {
+            key,
+            element,
+            rank,
+            count,
+            maxlen,
+        } => {
             let physical = prefix_key(current_db, &key);
-            match storage.ltrim(&physical, start, stop) {
-                Ok(()) => {
-                    respond_simple_string(writer, "OK").await?;
-                }
+            // 未指定 COUNT 时只找一个匹配并回复整数（或 nil）
+            let wanted = count.unwrap_or(1) as usize;
+            match storage.lpos(&physical, &element, rank, wanted, maxlen as usize) {
+                Ok(positions) => match count {
+                    None => match positions.first() {
+                        Some(&idx) => respond_integer(writer, idx as i64).await?,
+                        None => respond_null_bulk(writer).await?,
+                    },
+                    Some(_) => {
+                        let mut reply = ReplyBuilder::array(positions.len());
+                        for idx in positions {
+                            reply.integer(idx as i64);
+                        }
+                        reply.flush(writer).await?;
+                    }
+                },
                 Err(()) => {
                     respond_error(
                         writer,
@@ -1315,38 +1805,37 @@ async fn handle_list_command(
                 }
             }
         }
-        Command::Hscan {
+        Command::Linsert {
             key,
-            cursor: _,
-            pattern,
-            count: _,
+            before,
+            pivot,
+            value,
         } => {
             let physical = prefix_key(current_db, &key);
-            match storage.hgetall(&physical) {
-                Ok(entries) => {
-                    // entries: Vec<(String, String)>
-                    let pat = pattern.unwrap_or_else(|| "*".to_string());
-                    let mut flat: Vec<(String, String)> = Vec::new();
-                    for (field, value) in &entries {
-                        if pattern_match(&pat, field) {
-                            flat.push((field.to_string(), value.clone()));
-                        }
-                    }
-
-                    let next_cursor = 0u64;
-                    let cursor_str = next_cursor.to_string();
-
-                    let mut response = format!(
-                        "*2\r\n${}\r\n{}\r\n*{}\r\n",
-                        cursor_str.len(),
-                        cursor_str,
-                        flat.len() * 2
-                    );
-                    for (f, v) in flat {
-                        response.push_str(&format!("${}\r\n{}\r\n", f.len(), f));
-                        response.push_str(&format!("${}\r\n{}\r\n", v.len(), v));
-                    }
-                    writer.write_all(response.as_bytes()).await?;
+            if let Some(limit) = current_max_value_bytes() {
+                if (value.len() as u64) > limit {
+                    respond_error(writer, "ERR value exceeds REDUST_MAXVALUE_BYTES").await?;
+                    return Ok(());
+                }
+            }
+            match storage.linsert(&physical, before, &pivot, &value) {
+                Ok(len) => {
+                    respond_integer(writer, len).await?;
+                }
+                Err(()) => {
+                    respond_error(
+                        writer,
+                        "WRONGTYPE Operation against a key holding the wrong kind of value",
+                    )
+                    .await?;
+                }
+            }
+        }
+        Command::Ltrim { key, start, stop } => {
+            let physical = prefix_key(current_db, &key);
+            match storage.ltrim(&physical, start, stop) {
+                Ok(()) => {
+                    respond_simple_string(writer, "OK").await?;
                 }
                 Err(()) => {
                     respond_error(
@@ -1366,7 +1855,7 @@ async fn handle_list_command(
 async fn handle_set_command(
     cmd: Command,
     storage: &Storage,
-    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    writer: &mut ClientWriter,
     current_db: u8,
 ) -> io::Result<()> {
     match cmd {
@@ -1374,7 +1863,7 @@ async fn handle_set_command(
             let physical = prefix_key(current_db, &key);
             if let Some(limit) = current_max_value_bytes() {
                 for m in &members {
-                    if (m.as_bytes().len() as u64) > limit {
+                    if (m.len() as u64) > limit {
                         respond_error(writer, "ERR value exceeds REDUST_MAXVALUE_BYTES").await?;
                         return Ok(());
                     }
@@ -1412,11 +1901,11 @@ async fn handle_set_command(
             let physical = prefix_key(current_db, &key);
             match storage.smembers(&physical) {
                 Ok(members) => {
-                    let mut response = format!("*{}\r\n", members.len());
+                    let mut reply = ReplyBuilder::array(members.len());
                     for m in members {
-                        response.push_str(&format!("${}\r\n{}\r\n", m.len(), m));
+                        reply.bulk(&m);
                     }
-                    writer.write_all(response.as_bytes()).await?;
+                    reply.flush(writer).await?;
                 }
                 Err(()) => {
                     respond_error(
@@ -1465,11 +1954,11 @@ async fn handle_set_command(
                 .collect();
             match storage.sunion(&physical) {
                 Ok(members) => {
-                    let mut response = format!("*{}\r\n", members.len());
+                    let mut response = format!("*{}\r\n", members.len()).into_bytes();
                     for m in members {
-                        response.push_str(&format!("${}\r\n{}\r\n", m.len(), m));
+                        push_bulk_bytes(&mut response, &m);
                     }
-                    writer.write_all(response.as_bytes()).await?;
+                    writer.write_all(&response).await?;
                 }
                 Err(()) => {
                     respond_error(
@@ -1487,11 +1976,11 @@ async fn handle_set_command(
                 .collect();
             match storage.sinter(&physical) {
                 Ok(members) => {
-                    let mut response = format!("*{}\r\n", members.len());
+                    let mut response = format!("*{}\r\n", members.len()).into_bytes();
                     for m in members {
-                        response.push_str(&format!("${}\r\n{}\r\n", m.len(), m));
+                        push_bulk_bytes(&mut response, &m);
                     }
-                    writer.write_all(response.as_bytes()).await?;
+                    writer.write_all(&response).await?;
                 }
                 Err(()) => {
                     respond_error(
@@ -1509,11 +1998,11 @@ async fn handle_set_command(
                 .collect();
             match storage.sdiff(&physical) {
                 Ok(members) => {
-                    let mut response = format!("*{}\r\n", members.len());
+                    let mut response = format!("*{}\r\n", members.len()).into_bytes();
                     for m in members {
-                        response.push_str(&format!("${}\r\n{}\r\n", m.len(), m));
+                        push_bulk_bytes(&mut response, &m);
                     }
-                    writer.write_all(response.as_bytes()).await?;
+                    writer.write_all(&response).await?;
                 }
                 Err(()) => {
                     respond_error(
@@ -1540,10 +2029,10 @@ async fn handle_set_command(
                     let end = std::cmp::min(start + batch_size, members.len());
 
                     let pat = pattern.unwrap_or_else(|| "*".to_string());
-                    let mut matched: Vec<String> = Vec::new();
+                    let mut matched: Vec<&[u8]> = Vec::new();
                     for m in &members[start..end] {
-                        if pattern_match(&pat, m) {
-                            matched.push(m.to_string());
+                        if pattern_match_bytes(pat.as_bytes(), m) {
+                            matched.push(m);
                         }
                     }
 
@@ -1555,11 +2044,12 @@ async fn handle_set_command(
                         cursor_str.len(),
                         cursor_str,
                         matched.len()
-                    );
+                    )
+                    .into_bytes();
                     for m in matched {
-                        response.push_str(&format!("${}\r\n{}\r\n", m.len(), m));
+                        push_bulk_bytes(&mut response, m);
                     }
-                    writer.write_all(response.as_bytes()).await?;
+                    writer.write_all(&response).await?;
                 }
                 Err(()) => {
                     respond_error(
@@ -1638,14 +2128,14 @@ async fn handle_set_command(
                         }
                         1 => {
                             let m = members.pop().unwrap();
-                            respond_bulk_string(writer, &m).await?;
+                            respond_bulk_bytes(writer, &m).await?;
                         }
                         n => {
-                            let mut response = format!("*{}\r\n", n);
+                            let mut response = format!("*{}\r\n", n).into_bytes();
                             for m in members {
-                                response.push_str(&format!("${}\r\n{}\r\n", m.len(), m));
+                                push_bulk_bytes(&mut response, &m);
                             }
-                            writer.write_all(response.as_bytes()).await?;
+                            writer.write_all(&response).await?;
                         }
                     }
                 }
@@ -1664,17 +2154,17 @@ async fn handle_set_command(
                 Ok(members) => match count {
                     None => {
                         if let Some(m) = members.into_iter().next() {
-                            respond_bulk_string(writer, &m).await?;
+                            respond_bulk_bytes(writer, &m).await?;
                         } else {
                             respond_null_bulk(writer).await?;
                         }
                     }
                     Some(_) => {
-                        let mut response = format!("*{}\r\n", members.len());
+                        let mut response = format!("*{}\r\n", members.len()).into_bytes();
                         for m in members {
-                            response.push_str(&format!("${}\r\n{}\r\n", m.len(), m));
+                            push_bulk_bytes(&mut response, &m);
                         }
-                        writer.write_all(response.as_bytes()).await?;
+                        writer.write_all(&response).await?;
                     }
                 },
                 Err(()) => {
@@ -1695,14 +2185,15 @@ async fn handle_set_command(
 async fn handle_hash_command(
     cmd: Command,
     storage: &Storage,
-    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    writer: &mut ClientWriter,
     current_db: u8,
 ) -> io::Result<()> {
     match cmd {
         Command::Hset { key, field, value } => {
             let physical = prefix_key(current_db, &key);
             if let Some(limit) = current_max_value_bytes() {
-                if (value.as_bytes().len() as u64) > limit {
+                // field 名与 value 都受限制
+                if (field.len() as u64) > limit || (value.len() as u64) > limit {
                     respond_error(writer, "ERR value exceeds REDUST_MAXVALUE_BYTES").await?;
                     return Ok(());
                 }
@@ -1724,7 +2215,7 @@ async fn handle_hash_command(
             let physical = prefix_key(current_db, &key);
             match storage.hget(&physical, &field) {
                 Ok(Some(value)) => {
-                    respond_bulk_string(writer, &value).await?;
+                    respond_bulk_bytes(writer, &value).await?;
                 }
                 Ok(None) => {
                     respond_null_bulk(writer).await?;
@@ -1773,12 +2264,12 @@ async fn handle_hash_command(
             let physical = prefix_key(current_db, &key);
             match storage.hgetall(&physical) {
                 Ok(entries) => {
-                    let mut response = format!("*{}\r\n", entries.len() * 2);
+                    let mut reply = ReplyBuilder::array(entries.len() * 2);
                     for (field, value) in entries {
-                        response.push_str(&format!("${}\r\n{}\r\n", field.len(), field));
-                        response.push_str(&format!("${}\r\n{}\r\n", value.len(), value));
+                        reply.bulk(field.as_bytes());
+                        reply.bulk(&value);
                     }
-                    writer.write_all(response.as_bytes()).await?;
+                    reply.flush(writer).await?;
                 }
                 Err(()) => {
                     respond_error(
@@ -1812,11 +2303,11 @@ async fn handle_hash_command(
             let physical = prefix_key(current_db, &key);
             match storage.hvals(&physical) {
                 Ok(values) => {
-                    let mut response = format!("*{}\r\n", values.len());
+                    let mut response = format!("*{}\r\n", values.len()).into_bytes();
                     for value in values {
-                        response.push_str(&format!("${}\r\n{}\r\n", value.len(), value));
+                        push_bulk_bytes(&mut response, &value);
                     }
-                    writer.write_all(response.as_bytes()).await?;
+                    writer.write_all(&response).await?;
                 }
                 Err(()) => {
                     respond_error(
@@ -1832,18 +2323,18 @@ async fn handle_hash_command(
             match storage.hmget(&physical, &fields) {
                 Ok(values) => {
                     // HMGET 返回数组，每个元素是 bulk 或 null bulk
-                    let mut response = format!("*{}\r\n", values.len());
+                    let mut response = format!("*{}\r\n", values.len()).into_bytes();
                     for v in values {
                         match v {
                             Some(s) => {
-                                response.push_str(&format!("${}\r\n{}\r\n", s.len(), s));
+                                push_bulk_bytes(&mut response, &s);
                             }
                             None => {
-                                response.push_str("$-1\r\n");
+                                response.extend_from_slice(b"$-1\r\n");
                             }
                         }
                     }
-                    writer.write_all(response.as_bytes()).await?;
+                    writer.write_all(&response).await?;
                 }
                 Err(()) => {
                     respond_error(
@@ -1857,6 +2348,10 @@ async fn handle_hash_command(
         Command::Hincrby { key, field, delta } => {
             let physical = prefix_key(current_db, &key);
             let max = current_max_value_bytes();
+            if max.is_some_and(|limit| field.len() as u64 > limit) {
+                respond_error(writer, "ERR value exceeds REDUST_MAXVALUE_BYTES").await?;
+                return Ok(());
+            }
             match storage.hincr_by(&physical, &field, delta, max) {
                 Ok(value) => {
                     respond_integer(writer, value).await?;
@@ -1882,17 +2377,12 @@ async fn handle_hash_command(
         Command::Hincrbyfloat { key, field, delta } => {
             let physical = prefix_key(current_db, &key);
             let max = current_max_value_bytes();
+            if max.is_some_and(|limit| field.len() as u64 > limit) {
+                respond_error(writer, "ERR value exceeds REDUST_MAXVALUE_BYTES").await?;
+                return Ok(());
+            }
             match storage.hincr_by_float(&physical, &field, delta, max) {
-                Ok(value) => {
-                    let mut s = value.to_string();
-                    if s.contains('.') {
-                        while s.ends_with('0') {
-                            s.pop();
-                        }
-                        if s.ends_with('.') {
-                            s.push('0');
-                        }
-                    }
+                Ok(s) => {
                     respond_bulk_string(writer, &s).await?;
                 }
                 Err(crate::storage::HincrFloatError::WrongType) => {
@@ -1905,6 +2395,9 @@ async fn handle_hash_command(
                 Err(crate::storage::HincrFloatError::NotFloat) => {
                     respond_error(writer, "ERR hash value is not a valid float").await?;
                 }
+                Err(crate::storage::HincrFloatError::NanOrInfinity) => {
+                    respond_error(writer, "ERR increment would produce NaN or Infinity").await?;
+                }
                 Err(crate::storage::HincrFloatError::MaxValueExceeded) => {
                     respond_error(writer, "ERR value exceeds REDUST_MAXVALUE_BYTES").await?;
                 }
@@ -1930,6 +2423,7 @@ async fn handle_hash_command(
             cursor,
             pattern,
             count,
+            novalues,
         } => {
             let physical = prefix_key(current_db, &key);
             match storage.hgetall(&physical) {
@@ -1940,7 +2434,7 @@ async fn handle_hash_command(
                     let end = std::cmp::min(start + batch_size, entries.len());
 
                     let pat = pattern.unwrap_or_else(|| "*".to_string());
-                    let mut flat: Vec<(String, String)> = Vec::new();
+                    let mut flat: Vec<(String, Vec<u8>)> = Vec::new();
                     for (field, value) in &entries[start..end] {
                         if pattern_match(&pat, field) {
                             flat.push((field.clone(), value.clone()));
@@ -1951,17 +2445,22 @@ async fn handle_hash_command(
 
                     let cursor_str = next_cursor.to_string();
 
+                    // NOVALUES 只返回 field，游标与 COUNT 分批规则不变
+                    let per_entry = if novalues { 1 } else { 2 };
                     let mut response = format!(
                         "*2\r\n${}\r\n{}\r\n*{}\r\n",
                         cursor_str.len(),
                         cursor_str,
-                        flat.len() * 2
-                    );
+                        flat.len() * per_entry
+                    )
+                    .into_bytes();
                     for (f, v) in flat {
-                        response.push_str(&format!("${}\r\n{}\r\n", f.len(), f));
-                        response.push_str(&format!("${}\r\n{}\r\n", v.len(), v));
+                        push_bulk_bytes(&mut response, f.as_bytes());
+                        if !novalues {
+                            push_bulk_bytes(&mut response, &v);
+                        }
                     }
-                    writer.write_all(response.as_bytes()).await?;
+                    writer.write_all(&response).await?;
                 }
                 Err(()) => {
                     respond_error(
@@ -1981,7 +2480,7 @@ async fn handle_hash_command(
 async fn handle_zset_command(
     cmd: Command,
     storage: &Storage,
-    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    writer: &mut ClientWriter,
     current_db: u8,
 ) -> io::Result<()> {
     // helper to format floating scores similar to Redis (trim trailing zeros)
@@ -1999,11 +2498,44 @@ async fn handle_zset_command(
     };
 
     match cmd {
-        Command::Zadd { key, entries } => {
+        Command::Zadd {
+            key,
+            entries,
+            nx,
+            xx,
+            gt,
+            lt,
+            ch,
+            incr,
+        } => {
             let physical = prefix_key(current_db, &key);
-            match storage.zadd(&physical, &entries) {
-                Ok(added) => {
-                    respond_integer(writer, added as i64).await?;
+            if let Some(limit) = current_max_value_bytes() {
+                if entries.iter().any(|(_, member)| member.len() as u64 > limit) {
+                    respond_error(writer, "ERR value exceeds REDUST_MAXVALUE_BYTES").await?;
+                    return Ok(());
+                }
+            }
+            let flags = crate::storage::ZaddFlags { nx, xx, gt, lt, ch };
+            // INCR 只允许一个 score/member（解析阶段已校验），回复新分数，被选项跳过时回复 nil
+            let result = if incr {
+                let (increment, member) = &entries[0];
+                storage
+                    .zadd_incr(&physical, *increment, member, flags)
+                    .map(|score| match score {
+                        Some(score) => {
+                            let s = format_score(score);
+                            format!("${}\r\n{}\r\n", s.len(), s)
+                        }
+                        None => "$-1\r\n".to_string(),
+                    })
+            } else {
+                storage
+                    .zadd(&physical, &entries, flags)
+                    .map(|count| format!(":{}\r\n", count))
+            };
+            match result {
+                Ok(reply) => {
+                    writer.write_all(reply.as_bytes()).await?;
                 }
                 Err(crate::storage::ZsetError::WrongType) => {
                     respond_error(
@@ -2040,19 +2572,18 @@ async fn handle_zset_command(
             let physical = prefix_key(current_db, &key);
             match storage.zrange(&physical, start, stop, rev) {
                 Ok(items) => {
-                    let mut response = if withscores {
-                        format!("*{}\r\n", items.len() * 2)
+                    let mut reply = ReplyBuilder::array(if withscores {
+                        items.len() * 2
                     } else {
-                        format!("*{}\r\n", items.len())
-                    };
+                        items.len()
+                    });
                     for (member, score) in items {
-                        response.push_str(&format!("${}\r\n{}\r\n", member.len(), member));
+                        reply.bulk(&member);
                         if withscores {
-                            let score_s = format_score(score);
-                            response.push_str(&format!("${}\r\n{}\r\n", score_s.len(), score_s));
+                            reply.bulk(format_score(score).as_bytes());
                         }
                     }
-                    writer.write_all(response.as_bytes()).await?;
+                    reply.flush(writer).await?;
                 }
                 Err(()) => {
                     respond_error(
@@ -2103,6 +2634,10 @@ async fn handle_zset_command(
             member,
         } => {
             let physical = prefix_key(current_db, &key);
+            if current_max_value_bytes().is_some_and(|limit| member.len() as u64 > limit) {
+                respond_error(writer, "ERR value exceeds REDUST_MAXVALUE_BYTES").await?;
+                return Ok(());
+            }
             match storage.zincrby(&physical, increment, &member) {
                 Ok(score) => {
                     let s = format_score(score);
@@ -2135,10 +2670,10 @@ async fn handle_zset_command(
                     let end = std::cmp::min(start + batch_size, entries.len());
 
                     let pat = pattern.unwrap_or_else(|| "*".to_string());
-                    let mut flat: Vec<(String, f64)> = Vec::new();
+                    let mut flat: Vec<(&[u8], f64)> = Vec::new();
                     for (member, score) in &entries[start..end] {
-                        if pattern_match(&pat, member) {
-                            flat.push((member.clone(), *score));
+                        if pattern_match_bytes(pat.as_bytes(), member) {
+                            flat.push((member, *score));
                         }
                     }
 
@@ -2150,13 +2685,14 @@ async fn handle_zset_command(
                         cursor_str.len(),
                         cursor_str,
                         flat.len() * 2
-                    );
+                    )
+                    .into_bytes();
                     for (member, score) in flat {
                         let score_s = format_score(score);
-                        response.push_str(&format!("${}\r\n{}\r\n", member.len(), member));
-                        response.push_str(&format!("${}\r\n{}\r\n", score_s.len(), score_s));
+                        push_bulk_bytes(&mut response, member);
+                        push_bulk_bytes(&mut response, score_s.as_bytes());
                     }
-                    writer.write_all(response.as_bytes()).await?;
+                    writer.write_all(&response).await?;
                 }
                 Err(()) => {
                     respond_error(
@@ -2176,7 +2712,7 @@ async fn handle_zset_command(
 async fn handle_persistence_command(
     cmd: Command,
     storage: &Storage,
-    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    writer: &mut ClientWriter,
     persistence: Arc<PersistenceState>,
 ) -> io::Result<()> {
     if !persistence.enabled {
@@ -2185,8 +2721,16 @@ async fn handle_persistence_command(
     }
     match cmd {
         Command::Save => {
+            // 与 Redis 一致：已有保存在进行时 SAVE 直接报错，而不是排队等待
+            let save_guard = persistence.save_lock.try_lock();
+            if persistence.bgsave_running.load(Ordering::SeqCst) || save_guard.is_err() {
+                respond_error(writer, "ERR Background save already in progress").await?;
+                return Ok(());
+            }
             let path = persistence.rdb_path.clone();
-            let save_res = perform_save(storage.clone(), path.clone(), persistence.clone()).await;
+            let save_res =
+                perform_save_locked(storage.clone(), path.clone(), &persistence, true).await;
+            drop(save_guard);
             match save_res {
                 Ok(()) => respond_simple_string(writer, "OK").await?,
                 Err(e) => {
@@ -2230,7 +2774,7 @@ async fn handle_persistence_command(
 async fn handle_key_meta_command(
     cmd: Command,
     storage: &Storage,
-    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    writer: &mut ClientWriter,
     current_db: u8,
 ) -> io::Result<()> {
     match cmd {
@@ -2267,6 +2811,41 @@ async fn handle_key_meta_command(
             let t = storage.type_of(&physical);
             respond_simple_string(writer, &t).await?;
         }
+        Command::ObjectIdletime { key } => {
+            let physical = prefix_key(current_db, &key);
+            match storage.idle_time(&physical) {
+                Some(idle) => respond_integer(writer, idle.as_secs() as i64).await?,
+                None => respond_null_bulk(writer).await?,
+            }
+        }
+        Command::ObjectEncoding { key } => {
+            let physical = prefix_key(current_db, &key);
+            match storage.object_encoding(&physical) {
+                Some(encoding) => respond_bulk_string(writer, encoding).await?,
+                None => respond_null_bulk(writer).await?,
+            }
+        }
+        Command::MemoryUsage { key } => {
+            let physical = prefix_key(current_db, &key);
+            match storage.serialized_length(&physical) {
+                Some(len) => respond_integer(writer, (key.len() as u64 + len) as i64).await?,
+                None => respond_null_bulk(writer).await?,
+            }
+        }
+        Command::DebugObject { key } => {
+            let physical = prefix_key(current_db, &key);
+            match (storage.serialized_length(&physical), storage.idle_time(&physical)) {
+                (Some(len), Some(idle)) => {
+                    let info = format!(
+                        "Value at:0x0 refcount:1 serializedlength:{} lru_seconds_idle:{}",
+                        len,
+                        idle.as_secs()
+                    );
+                    respond_simple_string(writer, &info).await?;
+                }
+                _ => respond_error(writer, "ERR no such key").await?,
+            }
+        }
         Command::Keys { pattern } => {
             let all = storage.keys("*");
             let prefix = format!("{}:", current_db);
@@ -2294,8 +2873,12 @@ async fn handle_key_meta_command(
             cursor,
             pattern,
             count,
+            type_filter,
         } => {
-            // 简化实现：基于当前 DB 的逻辑 key 列表做一次切片扫描。
+            // 简化实现：基于当前 DB 的逻辑 key 列表（已排序）做一次切片扫描。
+            // cursor 即下一次开始的下标；COUNT 限制的是本次「检查」的 key 数而不是返回数，
+            // 因此 MATCH 很挑剔时可能返回空批次和非 0 cursor，但每次至少前进一个 key，
+            // 持续迭代必然以 cursor 0 结束，且期间一直存在的匹配 key 都会被返回。
             let all = storage.keys("*");
             let prefix = format!("{}:", current_db);
             let mut logical: Vec<String> = Vec::new();
@@ -2307,15 +2890,25 @@ async fn handle_key_meta_command(
 
             let total = logical.len() as u64;
             let start = if cursor > total { total } else { cursor } as usize;
-            let batch_size = count.unwrap_or(10) as usize;
+            let batch_size = count.unwrap_or(10).max(1) as usize;
             let end = std::cmp::min(start + batch_size, logical.len());
 
             let pat = pattern.unwrap_or_else(|| "*".to_string());
+            // 与 Redis 一致：未知的类型名不报错，只是匹配不到任何 key
+            let type_known = type_filter
+                .as_deref()
+                .is_none_or(|t| crate::storage::TYPE_NAMES.contains(&t));
             let mut matched: Vec<String> = Vec::new();
             for k in &logical[start..end] {
-                if pattern_match(&pat, k) {
-                    matched.push(k.clone());
+                if !type_known || !pattern_match(&pat, k) {
+                    continue;
+                }
+                if let Some(t) = &type_filter {
+                    if storage.type_of(&prefix_key(current_db, k)) != *t {
+                        continue;
+                    }
                 }
+                matched.push(k.clone());
             }
 
             let next_cursor = if end >= logical.len() { 0 } else { end as u64 };
@@ -2340,6 +2933,10 @@ async fn handle_key_meta_command(
             let to = prefix_key(current_db, &newkey);
             match storage.rename(&from, &to) {
                 Ok(()) => {
+                    if from != to {
+                        storage.notify_keyspace_event(notify::NOTIFY_GENERIC, "rename_from", &from);
+                        storage.notify_keyspace_event(notify::NOTIFY_GENERIC, "rename_to", &to);
+                    }
                     respond_simple_string(writer, "OK").await?;
                 }
                 Err(()) => {
@@ -2352,6 +2949,8 @@ async fn handle_key_meta_command(
             let to = prefix_key(current_db, &newkey);
             match storage.renamenx(&from, &to) {
                 Ok(true) => {
+                    storage.notify_keyspace_event(notify::NOTIFY_GENERIC, "rename_from", &from);
+                    storage.notify_keyspace_event(notify::NOTIFY_GENERIC, "rename_to", &to);
                     respond_integer(writer, 1).await?;
                 }
                 Ok(false) => {
@@ -2362,12 +2961,32 @@ async fn handle_key_meta_command(
                 }
             }
         }
+        Command::Copy {
+            source,
+            destination,
+            db,
+            replace,
+        } => {
+            let from = prefix_key(current_db, &source);
+            let to = prefix_key(db.unwrap_or(current_db), &destination);
+            if from == to {
+                respond_error(writer, "ERR source and destination objects are the same").await?;
+            } else {
+                let copied = storage.copy(&from, &to, replace);
+                respond_integer(writer, copied as i64).await?;
+            }
+        }
+        // 清空的每个 key 都发出 del 事件，订阅方可按 __keyevent@<db>__:del 感知清库
         Command::Flushdb => {
-            storage.flushdb(current_db);
+            for physical in storage.flushdb(current_db) {
+                storage.notify_keyspace_event(notify::NOTIFY_GENERIC, "del", &physical);
+            }
             respond_simple_string(writer, "OK").await?;
         }
         Command::Flushall => {
-            storage.flushall();
+            for physical in storage.flushall() {
+                storage.notify_keyspace_event(notify::NOTIFY_GENERIC, "del", &physical);
+            }
             respond_simple_string(writer, "OK").await?;
         }
         _ => {}
@@ -2376,11 +2995,137 @@ async fn handle_key_meta_command(
     Ok(())
 }
 
+/// 生成 default 用户的 ACL 规则描述；配置了密码时与 Redis 一样以 SHA-256 摘要（64 个十六进制字符）表示，不回显明文
+fn default_user_rules(auth_password: Option<&str>) -> (Vec<&'static str>, Vec<String>) {
+    match auth_password {
+        Some(pwd) => {
+            let mut hasher = Sha256::new();
+            hasher.update(pwd.as_bytes());
+            (vec!["on"], vec![hex::encode(hasher.finalize())])
+        }
+        None => (vec!["on", "nopass"], Vec::new()),
+    }
+}
+
+async fn handle_acl_command(
+    subcommand: &str,
+    args: &[String],
+    auth_password: Option<&str>,
+    writer: &mut ClientWriter,
+) -> io::Result<()> {
+    let wrong_args = || {
+        format!(
+            "ERR Unknown subcommand or wrong number of arguments for 'acl|{}'",
+            subcommand.to_lowercase()
+        )
+    };
+    match subcommand {
+        "WHOAMI" => {
+            if !args.is_empty() {
+                return respond_error(writer, &wrong_args()).await;
+            }
+            respond_bulk_string(writer, "default").await
+        }
+        "LIST" => {
+            if !args.is_empty() {
+                return respond_error(writer, &wrong_args()).await;
+            }
+            let (flags, passwords) = default_user_rules(auth_password);
+            let mut line = format!("user default {}", flags.join(" "));
+            for hash in &passwords {
+                line.push_str(&format!(" #{}", hash));
+            }
+            line.push_str(" ~* &* +@all");
+            writer.write_all(b"*1\r\n").await?;
+            respond_bulk_string(writer, &line).await
+        }
+        "GETUSER" => {
+            if args.len() != 1 {
+                return respond_error(writer, &wrong_args()).await;
+            }
+            if args[0] != "default" {
+                return respond_null_bulk(writer).await;
+            }
+            let (flags, passwords) = default_user_rules(auth_password);
+            let mut resp = String::from("*12\r\n");
+            let push_bulk = |resp: &mut String, s: &str| {
+                resp.push_str(&format!("${}\r\n{}\r\n", s.len(), s));
+            };
+            push_bulk(&mut resp, "flags");
+            resp.push_str(&format!("*{}\r\n", flags.len()));
+            for flag in &flags {
+                push_bulk(&mut resp, flag);
+            }
+            push_bulk(&mut resp, "passwords");
+            resp.push_str(&format!("*{}\r\n", passwords.len()));
+            for hash in &passwords {
+                push_bulk(&mut resp, hash);
+            }
+            push_bulk(&mut resp, "commands");
+            push_bulk(&mut resp, "+@all");
+            push_bulk(&mut resp, "keys");
+            push_bulk(&mut resp, "~*");
+            push_bulk(&mut resp, "channels");
+            push_bulk(&mut resp, "&*");
+            push_bulk(&mut resp, "selectors");
+            resp.push_str("*0\r\n");
+            writer.write_all(resp.as_bytes()).await
+        }
+        _ => respond_error(writer, "ERR unknown ACL subcommand").await,
+    }
+}
+
+/// 按 COMMAND INFO 格式编码单条命令：[name, arity, [flags...], first_key, last_key, step]
+fn encode_command_spec(spec: &CommandSpec, out: &mut String) {
+    out.push_str("*6\r\n");
+    out.push_str(&format!("${}\r\n{}\r\n", spec.name.len(), spec.name));
+    out.push_str(&format!(":{}\r\n", spec.arity));
+    out.push_str(&format!("*{}\r\n", spec.flags.len()));
+    for flag in spec.flags {
+        out.push_str(&format!("+{}\r\n", flag));
+    }
+    out.push_str(&format!(":{}\r\n", spec.first_key));
+    out.push_str(&format!(":{}\r\n", spec.last_key));
+    out.push_str(&format!(":{}\r\n", spec.step));
+}
+
+fn format_commandstats(metrics: &Metrics) -> String {
+    let mut stats: Vec<(&'static str, u64, u64)> = metrics
+        .command_stats
+        .iter()
+        .map(|entry| (*entry.key(), entry.value().0, entry.value().1))
+        .collect();
+    stats.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut section = String::from("# Commandstats\r\n");
+    for (name, calls, usec) in stats {
+        let per_call = if calls == 0 {
+            0.0
+        } else {
+            usec as f64 / calls as f64
+        };
+        section.push_str(&format!(
+            "cmdstat_{}:calls={},usec={},usec_per_call={:.2}\r\n",
+            name, calls, usec, per_call
+        ));
+    }
+    section
+}
+
 async fn handle_info_command(
+    section: Option<&str>,
     storage: &Storage,
     metrics: &Metrics,
-    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    persistence: &PersistenceState,
+    writer: &mut ClientWriter,
 ) -> io::Result<()> {
+    // INFO commandstats 只返回命令统计；默认输出不包含该段，与 Redis 一致
+    if section == Some("commandstats") {
+        let mut info = format_commandstats(metrics);
+        info.push_str("\r\n");
+        return respond_bulk_string(writer, &info).await;
+    }
+
     let uptime = Instant::now().duration_since(metrics.start_time).as_secs();
     let connected = metrics.connected_clients.load(Ordering::Relaxed);
     let total_cmds = metrics.total_commands.load(Ordering::Relaxed);
@@ -2392,7 +3137,12 @@ async fn handle_info_command(
     let mut info = String::new();
     info.push_str("# Server\r\n");
     info.push_str(&format!("redust_version:0.1.0\r\n"));
+    info.push_str(&format!("run_id:{}\r\n", run_id()));
     info.push_str(&format!("tcp_port:{}\r\n", metrics.tcp_port));
+    info.push_str(&format!(
+        "supported_commands:{}\r\n",
+        command_table::command_count()
+    ));
     info.push_str(&format!("uptime_in_seconds:{}\r\n", uptime));
     info.push_str(&format!("maxmemory:{}\r\n", maxmemory));
     info.push_str(&format!("maxmemory_human:{}\r\n", maxmemory_human));
@@ -2400,8 +3150,46 @@ async fn handle_info_command(
     info.push_str(&format!("used_memory_human:{}\r\n", used_memory_human));
     info.push_str("\r\n# Clients\r\n");
     info.push_str(&format!("connected_clients:{}\r\n", connected));
+    info.push_str("\r\n# Replication\r\n");
+    info.push_str("role:master\r\n");
+    info.push_str("connected_slaves:0\r\n");
+    info.push_str("master_repl_offset:0\r\n");
+    info.push_str("\r\n# Persistence\r\n");
+    info.push_str(&format!(
+        "rdb_changes_since_last_save:{}\r\n",
+        storage.dirty()
+    ));
+    info.push_str(&format!(
+        "rdb_bgsave_in_progress:{}\r\n",
+        if persistence.bgsave_running.load(Ordering::Relaxed) {
+            1
+        } else {
+            0
+        }
+    ));
+    info.push_str(&format!(
+        "rdb_last_save_time:{}\r\n",
+        persistence.last_save.load(Ordering::Relaxed)
+    ));
     info.push_str("\r\n# Stats\r\n");
     info.push_str(&format!("total_commands_processed:{}\r\n", total_cmds));
+    info.push_str(&format!(
+        "keyspace_hits:{}\r\n",
+        metrics.keyspace_hits.load(Ordering::Relaxed)
+    ));
+    info.push_str(&format!(
+        "keyspace_misses:{}\r\n",
+        metrics.keyspace_misses.load(Ordering::Relaxed)
+    ));
+    info.push_str(&format!("expired_keys:{}\r\n", storage.expired_keys()));
+    info.push_str(&format!(
+        "total_net_input_bytes:{}\r\n",
+        metrics.total_net_input_bytes.load(Ordering::Relaxed)
+    ));
+    info.push_str(&format!(
+        "total_net_output_bytes:{}\r\n",
+        metrics.total_net_output_bytes.load(Ordering::Relaxed)
+    ));
     info.push_str(&format!(
         "pubsub_channel_subscriptions:{}\r\n",
         metrics.pubsub_channel_subs.load(Ordering::Relaxed)
@@ -2446,6 +3234,10 @@ async fn handle_info_command(
             info.push_str(&format!("db{}:keys={}\r\n", idx, count));
         }
     }
+    if matches!(section, Some("all") | Some("everything")) {
+        info.push_str("\r\n");
+        info.push_str(&format_commandstats(metrics));
+    }
     info.push_str("\r\n");
 
     respond_bulk_string(writer, &info).await
@@ -2505,11 +3297,30 @@ async fn run_metrics_exporter(
     }
 }
 
+/// 非只读的脚本命令：脚本内的写入不逐条检查 maxmemory，执行前统一按策略淘汰
+fn is_write_script(cmd: &Command) -> bool {
+    match cmd {
+        Command::Eval { read_only, .. }
+        | Command::Evalsha { read_only, .. }
+        | Command::Fcall { read_only, .. } => !read_only,
+        _ => false,
+    }
+}
+
+/// 执行前需要按 maxmemory 淘汰的命令：带 denyoom 标志的命令与可能写入的脚本
+fn may_grow_memory(cmd: &Command) -> bool {
+    is_write_script(cmd)
+        || cmd
+            .name()
+            .and_then(command_table::lookup)
+            .is_some_and(|spec| spec.flags.contains(&"denyoom"))
+}
+
 /// 在事务中执行单个命令，返回结果写入 writer
 async fn execute_command_in_transaction(
     cmd: Command,
     storage: &Storage,
-    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    writer: &mut ClientWriter,
     current_db: u8,
 ) -> io::Result<()> {
     match cmd {
@@ -2517,6 +3328,13 @@ async fn execute_command_in_transaction(
         Command::Ping
         | Command::PingWithPayload(_)
         | Command::Echo(_)
+        | Command::Time
+        | Command::Lolwut
+        | Command::Role
+        | Command::Replicaof { .. }
+        | Command::Wait { .. }
+        | Command::Failover
+        | Command::Cluster { .. }
         | Command::Set { .. }
         | Command::Get { .. }
         | Command::Getdel { .. }
@@ -2524,6 +3342,8 @@ async fn execute_command_in_transaction(
         | Command::Getrange { .. }
         | Command::Setrange { .. }
         | Command::Append { .. }
+        | Command::Setbit { .. }
+        | Command::Getbit { .. }
         | Command::Strlen { .. }
         | Command::Getset { .. }
         | Command::Incr { .. }
@@ -2532,6 +3352,7 @@ async fn execute_command_in_transaction(
         | Command::Decrby { .. }
         | Command::Incrbyfloat { .. }
         | Command::Del { .. }
+        | Command::Unlink { .. }
         | Command::Exists { .. }
         | Command::Mget { .. }
         | Command::Mset { .. }
@@ -2552,6 +3373,8 @@ async fn execute_command_in_transaction(
         | Command::Llen { .. }
         | Command::Lindex { .. }
         | Command::Lrem { .. }
+        | Command::Lpos { .. }
+        | Command::Linsert { .. }
         | Command::Ltrim { .. } => {
             handle_list_command(cmd, storage, writer, current_db).await?;
         }
@@ -2601,12 +3424,20 @@ async fn execute_command_in_transaction(
         }
 
         // HyperLogLog 命令
-        Command::Pfadd { .. } | Command::Pfcount { .. } | Command::Pfmerge { .. } => {
+        Command::Pfadd { .. }
+        | Command::Pfcount { .. }
+        | Command::Pfmerge { .. }
+        | Command::PfdebugGetreg { .. }
+        | Command::PfdebugEncoding { .. } => {
             handle_string_command(cmd, storage, writer, current_db).await?;
         }
 
         // key meta 命令
         Command::Type { .. }
+        | Command::ObjectIdletime { .. }
+        | Command::ObjectEncoding { .. }
+        | Command::MemoryUsage { .. }
+        | Command::DebugObject { .. }
         | Command::Keys { .. }
         | Command::Dbsize
         | Command::Expire { .. }
@@ -2616,6 +3447,7 @@ async fn execute_command_in_transaction(
         | Command::Persist { .. }
         | Command::Rename { .. }
         | Command::Renamenx { .. }
+        | Command::Copy { .. }
         | Command::Scan { .. }
         | Command::Zscan { .. } => {
             // 简化处理：这些命令在事务中可能需要特殊处理
@@ -2637,8 +3469,18 @@ async fn execute_command_in_transaction(
         | Command::Evalsha { .. }
         | Command::ScriptLoad { .. }
         | Command::ScriptExists { .. }
-        | Command::ScriptFlush => {
-            respond_error(writer, "ERR EVAL/SCRIPT commands not supported in transaction").await?;
+        | Command::ScriptFlush
+        | Command::ScriptKill
+        | Command::FunctionLoad { .. }
+        | Command::FunctionList { .. }
+        | Command::FunctionFlush
+        | Command::Fcall { .. } => {
+            respond_error(writer, "ERR EVAL/SCRIPT/FUNCTION commands not supported in transaction").await?;
+        }
+
+        Command::DebugSleep { seconds } => {
+            tokio::time::sleep(Duration::from_secs_f64(seconds)).await;
+            respond_simple_string(writer, "OK").await?;
         }
 
         // 其他命令返回错误
@@ -2649,6 +3491,7 @@ async fn execute_command_in_transaction(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_connection(
     stream: TcpStream,
     storage: Storage,
@@ -2657,24 +3500,38 @@ async fn handle_connection(
     overflow_strategy: PubSubOverflowStrategy,
     persistence: Arc<PersistenceState>,
     script_cache: Arc<ScriptCache>,
+    requirepass: Arc<Mutex<Option<String>>>,
+    max_multibulk_len: usize,
 ) -> io::Result<()> {
     let peer_addr = stream.peer_addr().ok();
     info!("[conn] new connection from {:?}", peer_addr);
 
     metrics.connected_clients.fetch_add(1, Ordering::Relaxed);
 
-    let (read_half, mut write_half) = stream.into_split();
-    let mut reader = BufReader::new(read_half);
+    // 回复常由多次小写入组成，开启 Nagle 时可能被客户端延迟 ACK 拖慢约 40ms
+    if tcp_nodelay_enabled() {
+        if let Err(e) = stream.set_nodelay(true) {
+            error!("[conn] failed to set TCP_NODELAY: {}", e);
+        }
+    }
+
+    let (read_half, write_half) = stream.into_split();
+    let mut reader = BufReader::new(Counted::new(
+        read_half,
+        metrics.total_net_input_bytes.clone(),
+    ));
+    let mut write_half = Counted::new(write_half, metrics.total_net_output_bytes.clone());
     let mut current_db: u8 = 0;
     
     // 客户端标识
     let client_id = CLIENT_ID_COUNTER.fetch_add(1, Ordering::Relaxed);
     let mut client_name = String::new();
 
-    let auth_password = env::var("REDUST_AUTH_PASSWORD")
-        .ok()
-        .filter(|s| !s.is_empty());
-    let mut authenticated = auth_password.is_none();
+    // 建连时未启用密码的连接视为已认证；之后通过 CONFIG SET requirepass 开启认证不影响已有连接
+    let mut authenticated = requirepass.lock().unwrap().is_none();
+    // 每个订阅（频道/模式/分片频道）由独立的转发任务写入同一个 msg_rx：
+    // 同一订阅内的消息保持发布顺序，不同订阅之间按进入 msg_rx 的先后推送，
+    // 不保证与全局发布顺序一致（见 tests/pubsub.rs 的顺序特征测试）
     let (msg_tx, mut msg_rx) = mpsc::unbounded_channel::<PubMessage>();
     let mut channel_subscriptions: HashMap<String, tokio::task::JoinHandle<()>> = HashMap::new();
     let mut pattern_subscriptions: HashMap<String, tokio::task::JoinHandle<()>> = HashMap::new();
@@ -2689,26 +3546,45 @@ async fn handle_connection(
     // 事务是否因解析错误而中止
     let mut transaction_aborted = false;
 
+    // 最近一次执行的命令名（CLIENT INFO 的 cmd 字段）
+    let mut last_command: &'static str = "NULL";
+    // 正在执行的命令；命令处理路径上有大量 continue，
+    // 因此在读取下一条命令前统一结算耗时（命令统计 + 慢查询日志）
+    let mut pending_stat: Option<PendingCommand> = None;
+    // 客户端地址（ip:port，IPv6 带方括号），CLIENT LIST 与 SLOWLOG 共用；
+    // 目前只监听 TCP，只有连接建立后对端已断开时才会取不到地址
+    let client_addr = peer_addr
+        .map(|a| a.to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
     loop {
+        // 上一条命令持锁期间的回复暂存在内存中，锁已随上一轮循环释放，这里才写出
+        write_half.flush_buffered().await?;
+        if let Some(pending) = pending_stat.take() {
+            pending.finish(&metrics, &client_addr, &client_name, write_half.transferred());
+        }
         let cmd_result = if subscribed_mode {
             tokio::select! {
                 maybe_msg = msg_rx.recv() => {
                     if let Some(msg) = maybe_msg {
                         if let PubMessage::Lagged = msg {
+                            // disconnect 策略：断开前告知客户端原因，便于客户端记录日志
+                            info!("[pubsub] subscriber {} lagged behind, disconnecting", client_addr);
+                            respond_error(&mut write_half, "ERR Pub/Sub message buffer overflow, disconnecting").await?;
                             break;
                         }
                         write_pub_message_event(&mut write_half, &msg).await?;
                         continue;
                     }
-                    read_command(&mut reader).await
+                    read_command_with_args(&mut reader, max_multibulk_len).await
                 }
-                cmd = read_command(&mut reader) => cmd,
+                cmd = read_command_with_args(&mut reader, max_multibulk_len) => cmd,
             }
         } else {
-            read_command(&mut reader).await
+            read_command_with_args(&mut reader, max_multibulk_len).await
         };
-        let cmd = match cmd_result {
-            Ok(Some(command)) => command,
+        let (cmd, raw_args) = match cmd_result {
+            Ok(Some(parsed)) => parsed,
             Ok(None) => break, // Connection closed or no more data
             Err(CommandError::Io(e)) => {
                 // Log the IO error and break
@@ -2721,24 +3597,107 @@ async fn handle_connection(
                 write_half.write_all(response.as_bytes()).await?;
                 continue; // Continue to read next command
             }
+            Err(CommandError::Protocol(msg)) => {
+                // 帧已失去同步，无法继续解析后续命令：回复错误后关闭连接
+                error!("[conn] protocol error: {}", msg);
+                let response = format!("-{}\r\n", msg);
+                write_half.write_all(response.as_bytes()).await?;
+                break;
+            }
         };
 
         metrics.total_commands.fetch_add(1, Ordering::Relaxed);
+        if let Some(name) = cmd.name() {
+            last_command = name;
+            pending_stat = Some(PendingCommand::start(name, &cmd, raw_args, write_half.transferred()));
+        }
 
         match &cmd {
             Command::Auth { .. } => {
                 info!("[conn] received command: AUTH ****");
             }
+            Command::Hello { auth: Some(_), .. } => {
+                info!("[conn] received command: HELLO ... AUTH ****");
+            }
+            Command::ConfigSet { parameter, .. } if parameter.eq_ignore_ascii_case("requirepass") => {
+                info!("[conn] received command: CONFIG SET requirepass ****");
+            }
             _ => {
                 info!("[conn] received command: {:?}", cmd);
             }
         }
 
-        // AUTH 处理与权限检查
+        // AUTH 处理与权限检查；密码可能被 CONFIG SET requirepass 在运行时修改
+        let auth_password = requirepass.lock().unwrap().clone();
+        // HELLO 是新式握手，可以顺带完成认证；目前只实现 RESP2，请求 RESP3 等其他版本一律返回 NOPROTO，
+        // 避免客户端以 RESP3 解析 RESP2 回复
+        if let Command::Hello {
+            protover,
+            ref auth,
+            ref setname,
+        } = cmd
+        {
+            if matches!(protover, Some(v) if v != 2) {
+                respond_error(&mut write_half, "NOPROTO unsupported protocol version").await?;
+                continue;
+            }
+            match auth {
+                Some((username, password)) => {
+                    let ok = match &auth_password {
+                        Some(pwd) => username == "default" && password == pwd,
+                        // 未启用密码时 default 用户等价于 nopass
+                        None => username == "default",
+                    };
+                    if !ok {
+                        respond_error(
+                            &mut write_half,
+                            "WRONGPASS invalid username-password pair or user is disabled",
+                        )
+                        .await?;
+                        continue;
+                    }
+                    authenticated = true;
+                }
+                None if auth_password.is_some() && !authenticated => {
+                    respond_error(
+                        &mut write_half,
+                        "NOAUTH HELLO must be called with the client already authenticated, otherwise the HELLO <proto> AUTH <user> <pass> option can be used to authenticate the client and select the RESP protocol version at the same time",
+                    )
+                    .await?;
+                    continue;
+                }
+                None => {}
+            }
+            if let Some(name) = setname {
+                client_name = name.clone();
+            }
+            let mut reply = ReplyBuilder::array(14);
+            reply.bulk(b"server");
+            reply.bulk(b"redis");
+            reply.bulk(b"version");
+            reply.bulk(env!("CARGO_PKG_VERSION").as_bytes());
+            reply.bulk(b"proto");
+            reply.integer(2);
+            reply.bulk(b"id");
+            reply.integer(client_id as i64);
+            reply.bulk(b"mode");
+            reply.bulk(b"standalone");
+            reply.bulk(b"role");
+            reply.bulk(b"master");
+            reply.bulk(b"modules");
+            reply.nested_array(0);
+            reply.flush(&mut write_half).await?;
+            continue;
+        }
         if let Some(ref pwd) = auth_password {
             match cmd {
-                Command::Auth { ref password } => {
-                    if password == pwd {
+                Command::Auth {
+                    ref username,
+                    ref password,
+                } => {
+                    // 只有单一的 default 用户，其他用户名一律视为认证失败
+                    let user_ok = matches!(username.as_deref(), None | Some("default"));
+                    if user_ok && password == pwd {
                         authenticated = true;
                         respond_simple_string(&mut write_half, "OK").await?;
                     } else {
@@ -2760,9 +3719,20 @@ async fn handle_connection(
                     }
                 }
             }
-        } else if let Command::Auth { .. } = cmd {
-            // 未启用 AUTH，但客户端仍然发送 AUTH
-            respond_error(&mut write_half, "ERR AUTH not enabled").await?;
+        } else if let Command::Auth { ref username, .. } = cmd {
+            match username.as_deref() {
+                // 未启用密码时 default 用户等价于 nopass，任意密码均可通过
+                Some("default") => respond_simple_string(&mut write_half, "OK").await?,
+                Some(_) => {
+                    respond_error(
+                        &mut write_half,
+                        "WRONGPASS invalid username-password pair or user is disabled",
+                    )
+                    .await?
+                }
+                // 未启用 AUTH，但客户端仍然发送 AUTH
+                None => respond_error(&mut write_half, "ERR AUTH not enabled").await?,
+            }
             continue;
         }
 
@@ -2795,6 +3765,34 @@ async fn handle_connection(
             }
         }
 
+        // 脚本原子性：除脚本外的命令在执行期间持有共享锁，脚本持有独占锁；
+        // DEBUG SLEEP 不持锁，避免睡眠期间阻塞脚本以及排在脚本之后的所有命令。
+        // 脚本运行超过 busy-reply-threshold 后，除 SCRIPT KILL、SHUTDOWN NOSAVE 外的命令直接返回 BUSY。
+        // 与 EVAL 一样，锁只覆盖命令执行本身：持锁期间的回复先写入内存，释放锁之后再写出，
+        // 读取缓慢的客户端不会让等待独占锁的脚本（以及排在它后面的所有连接）一直阻塞
+        let script_guard = match &cmd {
+            Command::ScriptKill
+            | Command::Shutdown { save: Some(false) }
+            | Command::DebugSleep { .. } => None,
+            Command::Eval { .. } | Command::Evalsha { .. } | Command::Fcall { .. } => {
+                if script_cache.run_state().is_busy() {
+                    respond_error(&mut write_half, BUSY_SCRIPT_ERROR).await?;
+                    continue;
+                }
+                None
+            }
+            _ => match script_cache.shared_guard_unless_busy().await {
+                Some(guard) => Some(guard),
+                None => {
+                    respond_error(&mut write_half, BUSY_SCRIPT_ERROR).await?;
+                    continue;
+                }
+            },
+        };
+        if script_guard.is_some() {
+            write_half.start_buffering();
+        }
+
         // 事务处理
         match &cmd {
             Command::Multi => {
@@ -2842,13 +3840,23 @@ async fn handle_connection(
                     continue;
                 }
 
-                // 执行队列中的命令
+                // 执行队列中的命令；整个事务持有写命令闸门，快照要么包含全部写入，要么一条都不包含。
+                // 回复先写入内存，闸门随本轮循环释放后才写给客户端
+                let _write = storage.write_guard().await;
+                write_half.start_buffering();
                 let commands = std::mem::take(&mut queued_commands);
+                // 淘汰只在执行前做一次（估算内存需要遍历整个键空间），写入后不再检查
+                if commands.iter().any(may_grow_memory) {
+                    storage.free_memory_if_needed();
+                }
                 let count = commands.len();
                 write_half
                     .write_all(format!("*{}\r\n", count).as_bytes())
                     .await?;
                 for queued_cmd in commands {
+                    let name = queued_cmd.name();
+                    let started = Instant::now();
+                    metrics.record_keyspace_lookups(&queued_cmd, &storage, current_db);
                     execute_command_in_transaction(
                         queued_cmd,
                         &storage,
@@ -2856,6 +3864,9 @@ async fn handle_connection(
                         current_db,
                     )
                     .await?;
+                    if let Some(name) = name {
+                        metrics.record_command(name, started.elapsed());
+                    }
                 }
                 continue;
             }
@@ -2893,6 +3904,30 @@ async fn handle_connection(
             _ => {}
         }
 
+        // 只读模式拒绝写命令；超过 maxmemory 且无法淘汰时拒绝带 denyoom 标志的命令。
+        // 事务中入队时即拒绝并中止事务。淘汰只在执行前做一次，写入后不再检查
+        let flags = cmd
+            .name()
+            .and_then(command_table::lookup)
+            .map_or(&[][..], |spec| spec.flags);
+        let rejection = if flags.contains(&"write") && storage.is_read_only() {
+            Some(READONLY_ERROR)
+        } else if flags.contains(&"denyoom") && !storage.free_memory_if_needed() {
+            Some(OOM_ERROR)
+        } else {
+            None
+        };
+        if let Some(msg) = rejection {
+            if in_transaction {
+                transaction_aborted = true;
+            }
+            respond_error(&mut write_half, msg).await?;
+            continue;
+        }
+        if !in_transaction && is_write_script(&cmd) {
+            storage.free_memory_if_needed();
+        }
+
         // 如果在事务中，将命令加入队列
         if in_transaction {
             // 检查是否是解析错误
@@ -2902,16 +3937,51 @@ async fn handle_connection(
                 respond_error(&mut write_half, msg).await?;
                 continue;
             }
+            // 订阅类命令会切换连接模式，不能入队：立即报错，但不中止整个事务
+            let subscribe_name = match cmd {
+                Command::Subscribe { .. } => Some("SUBSCRIBE"),
+                Command::Unsubscribe { .. } => Some("UNSUBSCRIBE"),
+                Command::Psubscribe { .. } => Some("PSUBSCRIBE"),
+                Command::Punsubscribe { .. } => Some("PUNSUBSCRIBE"),
+                Command::Ssubscribe { .. } => Some("SSUBSCRIBE"),
+                Command::Sunsubscribe { .. } => Some("SUNSUBSCRIBE"),
+                _ => None,
+            };
+            if let Some(name) = subscribe_name {
+                let msg = format!("ERR {} is not allowed in transactions", name);
+                respond_error(&mut write_half, &msg).await?;
+                continue;
+            }
+            // 入队的命令在 EXEC 时才真正执行并计入统计
+            pending_stat = None;
             queued_commands.push(cmd);
             respond_simple_string(&mut write_half, "QUEUED").await?;
             continue;
         }
 
+        metrics.record_keyspace_lookups(&cmd, &storage, current_db);
+
+        // 写命令执行期间持有写命令闸门的共享侧，BGSAVE 拷贝快照时不会看到执行到一半的写命令；
+        // 闸门只覆盖修改本身，回复暂存在内存中，释放闸门后再写出，慢客户端不会拖住快照
+        let _write_gate = if flags.contains(&"write") {
+            write_half.start_buffering();
+            Some(storage.write_guard().await)
+        } else {
+            None
+        };
+
         match cmd {
             // string / generic key-value 命令
             Command::Ping
             | Command::PingWithPayload(_)
             | Command::Echo(_)
+            | Command::Time
+            | Command::Lolwut
+            | Command::Role
+            | Command::Replicaof { .. }
+            | Command::Wait { .. }
+            | Command::Failover
+            | Command::Cluster { .. }
             | Command::Set { .. }
             | Command::Get { .. }
             | Command::Getdel { .. }
@@ -2919,6 +3989,8 @@ async fn handle_connection(
             | Command::Getrange { .. }
             | Command::Setrange { .. }
             | Command::Append { .. }
+            | Command::Setbit { .. }
+            | Command::Getbit { .. }
             | Command::Strlen { .. }
             | Command::Getset { .. }
             | Command::Incr { .. }
@@ -2927,6 +3999,7 @@ async fn handle_connection(
             | Command::Decrby { .. }
             | Command::Incrbyfloat { .. }
             | Command::Del { .. }
+            | Command::Unlink { .. }
             | Command::Exists { .. }
             | Command::Mget { .. }
             | Command::Mset { .. }
@@ -2941,6 +4014,30 @@ async fn handle_connection(
                 handle_string_command(cmd, &storage, &mut write_half, current_db).await?;
                 break;
             }
+            Command::Shutdown { save } => {
+                // 未指定时按是否启用持久化决定；SAVE 强制保存，NOSAVE 跳过。
+                // 保存在本连接内完成，失败时报错且不关闭服务
+                if save == Some(true) && !persistence.enabled {
+                    respond_error(&mut write_half, "ERR persistence disabled").await?;
+                    continue;
+                }
+                if save.unwrap_or(persistence.enabled) {
+                    let path = persistence
+                        .aof_path
+                        .clone()
+                        .unwrap_or_else(|| persistence.rdb_path.clone());
+                    if let Err(e) = perform_save(storage.clone(), path, persistence.clone()).await {
+                        error!("[shutdown] final save failed: {}", e);
+                        respond_error(&mut write_half, "ERR Errors trying to SHUTDOWN. Check logs.")
+                            .await?;
+                        continue;
+                    }
+                }
+                persistence.final_save_handled.store(true, Ordering::SeqCst);
+                persistence.shutdown_requested.notify_one();
+                // 成功时不回复，直接关闭连接
+                break;
+            }
 
             // list 命令
             Command::Lpush { .. }
@@ -2951,6 +4048,8 @@ async fn handle_connection(
             | Command::Llen { .. }
             | Command::Lindex { .. }
             | Command::Lrem { .. }
+            | Command::Lpos { .. }
+            | Command::Linsert { .. }
             | Command::Ltrim { .. } => {
                 handle_list_command(cmd, &storage, &mut write_half, current_db).await?;
             }
@@ -3001,7 +4100,11 @@ async fn handle_connection(
             }
 
             // HyperLogLog 命令
-            Command::Pfadd { .. } | Command::Pfcount { .. } | Command::Pfmerge { .. } => {
+            Command::Pfadd { .. }
+            | Command::Pfcount { .. }
+            | Command::Pfmerge { .. }
+            | Command::PfdebugGetreg { .. }
+            | Command::PfdebugEncoding { .. } => {
                 handle_string_command(cmd, &storage, &mut write_half, current_db).await?;
             }
 
@@ -3018,19 +4121,31 @@ async fn handle_connection(
             | Command::Pttl { .. }
             | Command::Persist { .. }
             | Command::Type { .. }
+            | Command::ObjectIdletime { .. }
+            | Command::ObjectEncoding { .. }
+            | Command::MemoryUsage { .. }
+            | Command::DebugObject { .. }
             | Command::Keys { .. }
             | Command::Scan { .. }
             | Command::Dbsize
             | Command::Rename { .. }
             | Command::Renamenx { .. }
+            | Command::Copy { .. }
             | Command::Flushdb
             | Command::Flushall => {
                 handle_key_meta_command(cmd, &storage, &mut write_half, current_db).await?;
             }
 
             // info
-            Command::Info => {
-                handle_info_command(&storage, &metrics, &mut write_half).await?;
+            Command::Info { section } => {
+                handle_info_command(
+                    section.as_deref(),
+                    &storage,
+                    &metrics,
+                    &persistence,
+                    &mut write_half,
+                )
+                .await?;
             }
 
             // 多 DB：SELECT
@@ -3136,6 +4251,9 @@ async fn handle_connection(
                     for ch in targets {
                         if let Some(handle) = channel_subscriptions.remove(&ch) {
                             handle.abort();
+                            // 等待转发任务真正结束，确保 receiver 已释放，
+                            // 回复退订之后 PUBSUB CHANNELS/NUMSUB 不会再看到该订阅
+                            let _ = handle.await;
                             metrics.pubsub_channel_subs.fetch_sub(1, Ordering::Relaxed);
                         }
                         let count = channel_subscriptions.len()
@@ -3218,6 +4336,7 @@ async fn handle_connection(
                     for ch in targets {
                         if let Some(handle) = shard_subscriptions.remove(&ch) {
                             handle.abort();
+                            let _ = handle.await;
                             metrics.pubsub_shard_subs.fetch_sub(1, Ordering::Relaxed);
                         }
                         let count = channel_subscriptions.len()
@@ -3273,10 +4392,15 @@ async fn handle_connection(
                         pattern_subscriptions.insert(pattern.clone(), handle);
                         metrics.pubsub_pattern_subs.fetch_add(1, Ordering::Relaxed);
                     }
-                    let count = channel_subscriptions.len() + pattern_subscriptions.len();
+                    let count = channel_subscriptions.len()
+                        + pattern_subscriptions.len()
+                        + shard_subscriptions.len();
                     write_subscribe_event(&mut write_half, "psubscribe", &pattern, count).await?;
                 }
-                subscribed_mode = (channel_subscriptions.len() + pattern_subscriptions.len()) > 0;
+                subscribed_mode = (channel_subscriptions.len()
+                    + pattern_subscriptions.len()
+                    + shard_subscriptions.len())
+                    > 0;
             }
             Command::Punsubscribe { patterns } => {
                 let targets: Vec<String> = if patterns.is_empty() {
@@ -3288,19 +4412,27 @@ async fn handle_connection(
                 };
 
                 if targets.is_empty() {
-                    let count = channel_subscriptions.len() + pattern_subscriptions.len();
+                    let count = channel_subscriptions.len()
+                        + pattern_subscriptions.len()
+                        + shard_subscriptions.len();
                     write_subscribe_event(&mut write_half, "punsubscribe", "", count).await?;
                 } else {
                     for pat in targets {
                         if let Some(handle) = pattern_subscriptions.remove(&pat) {
                             handle.abort();
+                            let _ = handle.await;
                             metrics.pubsub_pattern_subs.fetch_sub(1, Ordering::Relaxed);
                         }
-                        let count = channel_subscriptions.len() + pattern_subscriptions.len();
+                        let count = channel_subscriptions.len()
+                            + pattern_subscriptions.len()
+                            + shard_subscriptions.len();
                         write_subscribe_event(&mut write_half, "punsubscribe", &pat, count).await?;
                     }
                 }
-                subscribed_mode = (channel_subscriptions.len() + pattern_subscriptions.len()) > 0;
+                subscribed_mode = (channel_subscriptions.len()
+                    + pattern_subscriptions.len()
+                    + shard_subscriptions.len())
+                    > 0;
                 pubsub.cleanup_stale();
             }
             Command::PubsubChannels { pattern } => {
@@ -3321,7 +4453,7 @@ async fn handle_connection(
                 }
             }
             Command::PubsubNumpat => {
-                let total = pubsub.pattern_subscription_count();
+                let total = pubsub.active_pattern_count();
                 respond_integer(&mut write_half, total as i64).await?;
             }
             Command::PubsubShardchannels { pattern } => {
@@ -3357,7 +4489,7 @@ async fn handle_connection(
                 }
             }
 
-            Command::Auth { .. } => {
+            Command::Auth { .. } | Command::Hello { .. } => {
                 unreachable!();
             }
 
@@ -3367,7 +4499,12 @@ async fn handle_connection(
             }
 
             // Lua 脚本命令
-            Command::Eval { script, keys, args } => {
+            Command::Eval {
+                script,
+                keys,
+                args,
+                read_only,
+            } => {
                 // 缓存脚本
                 script_cache.load(&script);
                 
@@ -3376,8 +4513,16 @@ async fn handle_connection(
                     current_db: current_db as u32,
                     keys,
                     args,
+                    read_only,
+                    run_state: script_cache.run_state(),
+                };
+                // 独占锁只覆盖脚本执行本身，回复在释放锁之后写出
+                let outcome = {
+                    let _exclusive = script_cache.exclusive_guard().await;
+                    let _write = storage.write_guard().await;
+                    run_script_blocking(move || execute_script(&script, ctx)).await
                 };
-                match execute_script(&script, ctx) {
+                match outcome {
                     Ok(result) => {
                         let resp = result.to_resp_bytes();
                         write_half.write_all(&resp).await?;
@@ -3387,7 +4532,12 @@ async fn handle_connection(
                     }
                 }
             }
-            Command::Evalsha { sha1, keys, args } => {
+            Command::Evalsha {
+                sha1,
+                keys,
+                args,
+                read_only,
+            } => {
                 match script_cache.get(&sha1) {
                     Some(script) => {
                         let ctx = ScriptContext {
@@ -3395,8 +4545,15 @@ async fn handle_connection(
                             current_db: current_db as u32,
                             keys,
                             args,
+                            read_only,
+                            run_state: script_cache.run_state(),
                         };
-                        match execute_script(&script, ctx) {
+                        let outcome = {
+                            let _exclusive = script_cache.exclusive_guard().await;
+                            let _write = storage.write_guard().await;
+                            run_script_blocking(move || execute_script(&script, ctx)).await
+                        };
+                        match outcome {
                             Ok(result) => {
                                 let resp = result.to_resp_bytes();
                                 write_half.write_all(&resp).await?;
@@ -3427,11 +4584,120 @@ async fn handle_connection(
                 script_cache.flush();
                 respond_simple_string(&mut write_half, "OK").await?;
             }
+            Command::ScriptKill => match script_cache.run_state().kill() {
+                Ok(()) => respond_simple_string(&mut write_half, "OK").await?,
+                Err(e) => respond_error(&mut write_half, e).await?,
+            },
+            Command::FunctionLoad { code, replace } => {
+                match script_cache.load_library(&code, replace) {
+                    Ok(name) => respond_bulk_string(&mut write_half, &name).await?,
+                    Err(e) => respond_error(&mut write_half, &e).await?,
+                }
+            }
+            Command::FunctionList {
+                library_pattern,
+                with_code,
+            } => {
+                let libraries: Vec<_> = script_cache
+                    .libraries()
+                    .into_iter()
+                    .filter(|lib| {
+                        library_pattern
+                            .as_deref()
+                            .is_none_or(|p| pattern_match(p, &lib.name))
+                    })
+                    .collect();
+                let bulk = |s: &str| format!("${}\r\n{}\r\n", s.len(), s);
+                let mut resp = format!("*{}\r\n", libraries.len());
+                for lib in libraries {
+                    resp.push_str(if with_code { "*8\r\n" } else { "*6\r\n" });
+                    resp.push_str(&bulk("library_name"));
+                    resp.push_str(&bulk(&lib.name));
+                    resp.push_str(&bulk("engine"));
+                    resp.push_str(&bulk("LUA"));
+                    resp.push_str(&bulk("functions"));
+                    resp.push_str(&format!("*{}\r\n", lib.functions.len()));
+                    for (name, flags) in &lib.functions {
+                        resp.push_str("*6\r\n");
+                        resp.push_str(&bulk("name"));
+                        resp.push_str(&bulk(name));
+                        resp.push_str(&bulk("description"));
+                        resp.push_str("$-1\r\n");
+                        resp.push_str(&bulk("flags"));
+                        resp.push_str(&format!("*{}\r\n", flags.len()));
+                        for flag in flags {
+                            resp.push_str(&bulk(flag));
+                        }
+                    }
+                    if with_code {
+                        resp.push_str(&bulk("library_code"));
+                        resp.push_str(&bulk(&lib.code));
+                    }
+                }
+                write_half.write_all(resp.as_bytes()).await?;
+            }
+            Command::FunctionFlush => {
+                script_cache.flush_functions();
+                respond_simple_string(&mut write_half, "OK").await?;
+            }
+            Command::Fcall {
+                function,
+                keys,
+                args,
+                read_only,
+            } => {
+                match script_cache.function(&function) {
+                    Some((code, flags)) => {
+                        let no_writes = flags.iter().any(|f| f == "no-writes");
+                        if read_only && !no_writes {
+                            respond_error(
+                                &mut write_half,
+                                "ERR Can not execute a script with write flag using *_ro command.",
+                            )
+                            .await?;
+                        } else {
+                            // no-writes 函数即使通过 FCALL 调用也不能执行写命令
+                            let ctx = ScriptContext {
+                                storage: Arc::new(storage.clone()),
+                                current_db: current_db as u32,
+                                keys,
+                                args,
+                                read_only: no_writes,
+                                run_state: script_cache.run_state(),
+                            };
+                            let outcome = {
+                                let _exclusive = script_cache.exclusive_guard().await;
+                                let _write = storage.write_guard().await;
+                                run_script_blocking(move || execute_function(&code, &function, ctx)).await
+                            };
+                            match outcome {
+                                Ok(result) => {
+                                    let resp = result.to_resp_bytes();
+                                    write_half.write_all(&resp).await?;
+                                }
+                                Err(e) => {
+                                    respond_error(&mut write_half, &e).await?;
+                                }
+                            }
+                        }
+                    }
+                    None => {
+                        respond_error(&mut write_half, "ERR Function not found").await?;
+                    }
+                }
+            }
 
             // 运维命令
             Command::ConfigGet { pattern } => {
                 // 返回匹配的配置参数
-                let configs = get_config_values(&pattern);
+                let configs = get_config_values(
+                    &pattern,
+                    &storage,
+                    &persistence,
+                    &requirepass,
+                    &metrics.slowlog,
+                    &pubsub,
+                );
                 let mut resp = format!("*{}\r\n", configs.len() * 2);
                 for (key, value) in configs {
                     resp.push_str(&format!("${}\r\n{}\r\n", key.len(), key));
@@ -3441,19 +4707,20 @@ async fn handle_connection(
             }
             Command::ConfigSet { parameter, value } => {
                 // 尝试设置配置参数
-                match set_config_value(&parameter, &value) {
+                match set_config_value(&parameter, &value, &storage, &requirepass, &pubsub) {
                     Ok(()) => respond_simple_string(&mut write_half, "OK").await?,
                     Err(e) => respond_error(&mut write_half, &e).await?,
                 }
             }
-            Command::ClientList => {
+            Command::ClientList | Command::ClientInfo => {
                 // 返回当前连接信息（简化版）
                 let info = format!(
-                    "id={} addr={} name={} db={}\n",
+                    "id={} addr={} name={} db={} cmd={}\n",
                     client_id,
-                    peer_addr.map(|a| a.to_string()).unwrap_or_else(|| "unknown".to_string()),
+                    client_addr,
                     client_name,
-                    current_db
+                    current_db,
+                    last_command
                 );
                 let resp = format!("${}\r\n{}\r\n", info.len(), info);
                 write_half.write_all(resp.as_bytes()).await?;
@@ -3473,16 +4740,123 @@ async fn handle_connection(
                     write_half.write_all(resp.as_bytes()).await?;
                 }
             }
-            Command::SlowlogGet { count } => {
-                // 简化实现：返回空数组
-                let _ = count;
-                write_half.write_all(b"*0\r\n").await?;
+            Command::SlowlogGet { count, with_reply_bytes } => {
+                // 与 Redis 一致，默认返回最新 10 条；WITHREPLYBYTES 时每条多一个回复字节数字段
+                let entries = metrics.slowlog.get(count.unwrap_or(10));
+                let mut resp = format!("*{}\r\n", entries.len()).into_bytes();
+                for entry in entries {
+                    resp.extend_from_slice(
+                        format!(
+                            "*{}\r\n:{}\r\n:{}\r\n:{}\r\n*{}\r\n",
+                            if with_reply_bytes { 7 } else { 6 },
+                            entry.id,
+                            entry.timestamp,
+                            entry.duration_us,
+                            entry.args.len()
+                        )
+                        .as_bytes(),
+                    );
+                    for arg in &entry.args {
+                        resp.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+                        resp.extend_from_slice(arg);
+                        resp.extend_from_slice(b"\r\n");
+                    }
+                    for field in [&entry.client_addr, &entry.client_name] {
+                        resp.extend_from_slice(
+                            format!("${}\r\n{}\r\n", field.len(), field).as_bytes(),
+                        );
+                    }
+                    if with_reply_bytes {
+                        match entry.reply_bytes {
+                            Some(n) => resp.extend_from_slice(format!(":{}\r\n", n).as_bytes()),
+                            None => resp.extend_from_slice(b"$-1\r\n"),
+                        }
+                    }
+                }
+                write_half.write_all(&resp).await?;
             }
             Command::SlowlogReset => {
+                metrics.slowlog.reset();
                 respond_simple_string(&mut write_half, "OK").await?;
             }
             Command::SlowlogLen => {
-                respond_integer(&mut write_half, 0).await?;
+                respond_integer(&mut write_half, metrics.slowlog.len() as i64).await?;
+            }
+            Command::DebugSleep { seconds } => {
+                tokio::time::sleep(Duration::from_secs_f64(seconds)).await;
+                respond_simple_string(&mut write_half, "OK").await?;
+            }
+            Command::DebugSetUsedMemory { bytes } => {
+                // 淘汰在之后的写命令中按新的用量触发
+                storage.set_used_memory_override(bytes);
+                respond_simple_string(&mut write_half, "OK").await?;
+            }
+            Command::DebugListpackEntries { entries } => {
+                storage.set_list_max_listpack_entries(entries);
+                respond_simple_string(&mut write_half, "OK").await?;
+            }
+            Command::DebugReload => {
+                // 先同步落盘；保存失败时保持内存数据不变。
+                // 保存到重新加载完成之间一直独占写命令闸门，其他连接的写命令不会落在两者之间而丢失
+                let path = persistence.rdb_path.clone();
+                let _save = persistence.save_lock.lock().await;
+                let _gate = storage.snapshot_guard().await;
+                match save_snapshot(storage.clone(), path.clone(), &persistence, true, None).await {
+                    Ok(()) => {
+                        // load_rdb 全部解析成功后才替换内存数据，并递增旧 key 的版本号使 WATCH 失效；
+                        // 加载失败时保留原有数据，避免下一次保存用空数据集覆盖完好的 RDB
+                        let storage_clone = storage.clone();
+                        let res =
+                            tokio::task::spawn_blocking(move || storage_clone.load_rdb(&path)).await;
+                        match res {
+                            Ok(Ok(())) => respond_simple_string(&mut write_half, "OK").await?,
+                            Ok(Err(e)) => {
+                                error!("[rdb] DEBUG RELOAD load failed: {}", e);
+                                respond_error(&mut write_half, "ERR Error trying to load the RDB dump")
+                                    .await?;
+                            }
+                            Err(e) => {
+                                error!("[rdb] DEBUG RELOAD task failed: {}", e);
+                                respond_error(&mut write_half, "ERR Error trying to load the RDB dump")
+                                    .await?;
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        error!("[rdb] DEBUG RELOAD save failed: {}", e);
+                        respond_error(&mut write_half, "ERR Error trying to save the DB").await?;
+                    }
+                }
+            }
+            Command::CommandAll => {
+                let mut resp = format!("*{}\r\n", COMMAND_TABLE.len());
+                for spec in COMMAND_TABLE {
+                    encode_command_spec(spec, &mut resp);
+                }
+                write_half.write_all(resp.as_bytes()).await?;
+            }
+            Command::CommandCount => {
+                respond_integer(&mut write_half, command_table::command_count() as i64).await?;
+            }
+            Command::CommandInfo { names } => {
+                // 不带名称时与 Redis 7 一致，返回全部命令
+                let specs: Vec<Option<&CommandSpec>> = if names.is_empty() {
+                    COMMAND_TABLE.iter().map(Some).collect()
+                } else {
+                    names.iter().map(|n| command_table::lookup(n)).collect()
+                };
+                let mut resp = format!("*{}\r\n", specs.len());
+                for spec in specs {
+                    match spec {
+                        Some(spec) => encode_command_spec(spec, &mut resp),
+                        None => resp.push_str("*-1\r\n"),
+                    }
+                }
+                write_half.write_all(resp.as_bytes()).await?;
+            }
+            Command::Acl { subcommand, args } => {
+                handle_acl_command(&subcommand, &args, auth_password.as_deref(), &mut write_half)
+                    .await?;
             }
 
             // 解析阶段构造的错误命令
@@ -3526,6 +4900,12 @@ async fn handle_connection(
         .pubsub_shard_subs
         .fetch_sub(shard_len as u64, Ordering::Relaxed);
 
+    // QUIT 等命令回复后直接跳出循环，写出暂存的回复；对端已断开时忽略错误
+    let _ = write_half.flush_buffered().await;
+    if let Some(pending) = pending_stat.take() {
+        pending.finish(&metrics, &client_addr, &client_name, write_half.transferred());
+    }
+
     pubsub.cleanup_stale();
 
     metrics.connected_clients.fetch_sub(1, Ordering::Relaxed);
@@ -3545,6 +4925,40 @@ pub async fn serve(
         .filter(|v| *v > 0);
 
     let storage = Storage::new(maxmemory_bytes);
+    if let Some(policy) = env::var("REDUST_MAXMEMORY_POLICY")
+        .ok()
+        .and_then(|v| MaxmemoryPolicy::parse(&v))
+    {
+        storage.set_maxmemory_policy(policy);
+    }
+    let read_only = env::var("REDUST_READONLY")
+        .map(|v| matches!(v.to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false);
+    storage.set_read_only(read_only);
+    if let Some(jitter_ms) = env::var("REDUST_EXPIRE_JITTER_MS")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+    {
+        storage.set_expire_jitter_ms(jitter_ms);
+    }
+    // 启动时即确定 run_id
+    run_id();
+
+    // 单条命令的最大参数个数按实例保存并传给每个连接，不影响同一进程中的其他服务实例
+    let max_multibulk_len = match env::var("REDUST_MAX_MULTIBULK_LEN") {
+        Ok(v) => match v.trim().parse::<usize>() {
+            Ok(limit) if limit > 0 => limit,
+            _ => {
+                warn!(
+                    "[conn] invalid REDUST_MAX_MULTIBULK_LEN '{}', using default {}",
+                    v,
+                    crate::resp::MAX_ARRAY_SIZE
+                );
+                crate::resp::MAX_ARRAY_SIZE
+            }
+        },
+        Err(_) => crate::resp::MAX_ARRAY_SIZE,
+    };
 
     let rdb_path = env::var("REDUST_RDB_PATH").unwrap_or_else(|_| "redust.rdb".to_string());
     let persistence_disabled = env::var("REDUST_DISABLE_PERSISTENCE")
@@ -3554,6 +4968,39 @@ pub async fn serve(
         .map(|v| matches!(v.to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
         .unwrap_or(false);
     let aof_path = env::var("REDUST_AOF_PATH").unwrap_or_else(|_| "redust.aof".to_string());
+    let rdb_compression = match env::var("REDUST_RDB_COMPRESSION") {
+        Ok(v) => RdbCompression::parse(&v).unwrap_or_else(|| {
+            error!(
+                "[rdb] invalid REDUST_RDB_COMPRESSION '{}', falling back to none",
+                v
+            );
+            RdbCompression::None
+        }),
+        Err(_) => RdbCompression::None,
+    };
+
+    // 保存点：优先读取 REDUST_RDB_SAVE（如 "900 1 300 10"），
+    // 否则兼容旧的 REDUST_RDB_AUTO_SAVE_SECS / REDUST_RDB_AUTO_SAVE_CHANGES 单条规则
+    let save_points = if let Ok(spec) = env::var("REDUST_RDB_SAVE") {
+        parse_save_points(&spec).unwrap_or_else(|| {
+            error!("[rdb] invalid REDUST_RDB_SAVE '{}', save points disabled", spec);
+            Vec::new()
+        })
+    } else {
+        match env::var("REDUST_RDB_AUTO_SAVE_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+        {
+            Some(secs) if secs > 0 => {
+                let changes = env::var("REDUST_RDB_AUTO_SAVE_CHANGES")
+                    .ok()
+                    .and_then(|s| s.parse::<u64>().ok())
+                    .unwrap_or(1);
+                vec![(secs, changes)]
+            }
+            _ => Vec::new(),
+        }
+    };
 
     let persistence = Arc::new(PersistenceState {
         rdb_path: rdb_path.clone(),
@@ -3564,7 +5011,12 @@ pub async fn serve(
         },
         last_save: AtomicI64::new(-1),
         bgsave_running: AtomicBool::new(false),
+        save_lock: tokio::sync::Mutex::new(()),
         enabled: !persistence_disabled,
+        rdb_compression,
+        save_points,
+        shutdown_requested: Notify::new(),
+        final_save_handled: AtomicBool::new(false),
     });
 
     if !persistence_disabled {
@@ -3599,39 +5051,86 @@ pub async fn serve(
         }
     }
 
+    let pubsub = PubSubHub::new();
+
+    // 存储层产生的键空间事件（主动过期、GETDEL 等）通过通道转交给 pub/sub 发布
+    let (event_tx, mut event_rx) = mpsc::unbounded_channel::<KeyspaceEvent>();
+    storage.set_event_sender(event_tx);
     storage.spawn_expiration_task();
+    {
+        let pubsub = pubsub.clone();
+        tokio::spawn(async move {
+            while let Some(ev) = event_rx.recv().await {
+                pubsub.notify_keyspace_event(ev.class, ev.event, &ev.key);
+            }
+        });
+    }
 
-    if !persistence_disabled && persistence.aof_path.is_none() {
-        if let Ok(interval_str) = env::var("REDUST_RDB_AUTO_SAVE_SECS") {
-            if let Ok(secs) = interval_str.parse::<u64>() {
-                if secs > 0 {
-                    let storage_clone = storage.clone();
-                    let path_clone = rdb_path.clone();
-                    let persistence_clone = persistence.clone();
-                    info!(
-                        "[rdb] auto-save enabled: every {} seconds to {}",
-                        secs, path_clone
-                    );
-                    tokio::spawn(async move {
-                        let interval = std::time::Duration::from_secs(secs);
-                        loop {
-                            sleep(interval).await;
-                            let storage_for_blocking = storage_clone.clone();
-                            let path_for_blocking = path_clone.clone();
-                            if let Err(e) = perform_save(
-                                storage_for_blocking.clone(),
-                                path_for_blocking.clone(),
-                                persistence_clone.clone(),
-                            )
-                            .await
-                            {
-                                error!("[rdb] auto-save failed: {}", e);
-                            }
-                        }
-                    });
+    if !persistence_disabled
+        && persistence.aof_path.is_none()
+        && !persistence.save_points.is_empty()
+    {
+        let storage_clone = storage.clone();
+        let path_clone = rdb_path.clone();
+        let persistence_clone = persistence.clone();
+        info!(
+            "[rdb] save points enabled: \"{}\" to {}",
+            format_save_points(&persistence.save_points),
+            path_clone
+        );
+        tokio::spawn(async move {
+            let started_at = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0);
+            let mut interval = tokio::time::interval(Duration::from_secs(1));
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let dirty = storage_clone.dirty();
+                if dirty == 0 {
+                    continue;
+                }
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs() as i64)
+                    .unwrap_or(0);
+                // 尚未保存过时，以服务启动时间作为起点
+                let last_save = persistence_clone.last_save.load(Ordering::Relaxed);
+                let since = if last_save >= 0 { last_save } else { started_at };
+                let elapsed = now.saturating_sub(since).max(0) as u64;
+                let triggered = persistence_clone
+                    .save_points
+                    .iter()
+                    .find(|(secs, changes)| dirty >= *changes && elapsed >= *secs);
+                let Some((secs, changes)) = triggered else {
+                    continue;
+                };
+                if persistence_clone
+                    .bgsave_running
+                    .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+                    .is_err()
+                {
+                    continue;
+                }
+                info!(
+                    "[rdb] {} changes in {} seconds (save point {} {}), saving",
+                    dirty, elapsed, secs, changes
+                );
+                let res = perform_save(
+                    storage_clone.clone(),
+                    path_clone.clone(),
+                    persistence_clone.clone(),
+                )
+                .await;
+                persistence_clone
+                    .bgsave_running
+                    .store(false, Ordering::SeqCst);
+                if let Err(e) = res {
+                    error!("[rdb] background save failed: {}", e);
                 }
             }
-        }
+        });
     }
 
     if !persistence_disabled {
@@ -3645,9 +5144,12 @@ pub async fn serve(
                 interval.tick().await;
                 loop {
                     interval.tick().await;
-                    if let Err(e) =
-                        perform_save(storage_clone.clone(), path.clone(), persistence_clone.clone())
-                            .await
+                    if let Err(e) = perform_aof_snapshot(
+                        storage_clone.clone(),
+                        path.clone(),
+                        persistence_clone.clone(),
+                    )
+                    .await
                     {
                         error!("[aof] everysec save failed: {}", e);
                     }
@@ -3662,14 +5164,26 @@ pub async fn serve(
         connected_clients: AtomicUsize::new(0),
         total_commands: AtomicU64::new(0),
         tcp_port: port,
+        listen_addr: local_addr,
         pubsub_channel_subs: AtomicU64::new(0),
         pubsub_pattern_subs: AtomicU64::new(0),
         pubsub_shard_subs: AtomicU64::new(0),
         pubsub_messages_delivered: AtomicU64::new(0),
         pubsub_messages_dropped: AtomicU64::new(0),
+        keyspace_hits: AtomicU64::new(0),
+        keyspace_misses: AtomicU64::new(0),
+        total_net_input_bytes: Arc::new(AtomicU64::new(0)),
+        total_net_output_bytes: Arc::new(AtomicU64::new(0)),
+        command_stats: DashMap::new(),
+        slowlog: SlowLog::from_env(),
     });
-    let pubsub = PubSubHub::new();
     let script_cache = Arc::new(ScriptCache::new());
+    // 认证密码：启动时取自 REDUST_AUTH_PASSWORD，可通过 CONFIG SET requirepass 修改
+    let requirepass = Arc::new(Mutex::new(
+        env::var("REDUST_AUTH_PASSWORD")
+            .ok()
+            .filter(|s| !s.is_empty()),
+    ));
 
     if let Ok(metrics_addr) = env::var("REDUST_METRICS_ADDR") {
         if !metrics_addr.is_empty() {
@@ -3703,8 +5217,9 @@ pub async fn serve(
                 info!("Accepted connection from {}", addr);
                 let persistence_clone = persistence.clone();
                 let script_cache_clone = script_cache.clone();
+                let requirepass = requirepass.clone();
                 tokio::spawn(async move {
-                    if let Err(err) = handle_connection(stream, storage, metrics, pubsub, overflow_strategy, persistence_clone.clone(), script_cache_clone).await {
+                    if let Err(err) = handle_connection(stream, storage, metrics, pubsub, overflow_strategy, persistence_clone.clone(), script_cache_clone, requirepass, max_multibulk_len).await {
                         error!("Connection error: {}", err);
                     }
                 });
@@ -3712,11 +5227,15 @@ pub async fn serve(
             _ = &mut shutdown => {
                 break;
             }
+            _ = persistence.shutdown_requested.notified() => {
+                info!("Shutdown requested by SHUTDOWN command");
+                break;
+            }
         }
     }
 
     // 尝试在关闭前做一次快照（优先 aof 路径）
-    if persistence.enabled {
+    if persistence.enabled && !persistence.final_save_handled.load(Ordering::SeqCst) {
         if let Some(path) = aof_path_for_shutdown {
             if let Err(e) = perform_save(storage.clone(), path.clone(), persistence.clone()).await {
                 error!("[aof] final save failed: {}", e);
@@ -3750,26 +5269,46 @@ pub async fn run_server(
 // ============================================================================
 
 /// 获取匹配模式的配置值
-fn get_config_values(pattern: &str) -> Vec<(String, String)> {
+fn get_config_values(
+    pattern: &str,
+    storage: &Storage,
+    persistence: &PersistenceState,
+    requirepass: &Mutex<Option<String>>,
+    slowlog: &SlowLog,
+    pubsub: &PubSubHub,
+) -> Vec<(String, String)> {
     let mut results = Vec::new();
     
     // 支持的配置参数
     let configs = [
-        ("maxmemory", env::var("REDUST_MAXMEMORY_BYTES").unwrap_or_else(|_| "0".to_string())),
-        ("maxmemory-policy", "noeviction".to_string()),
+        ("maxmemory", storage.maxmemory_bytes().unwrap_or(0).to_string()),
+        ("maxmemory-policy", storage.maxmemory_policy().as_str().to_string()),
+        ("replica-read-only", if storage.is_read_only() { "yes" } else { "no" }.to_string()),
         ("timeout", "0".to_string()),
         ("tcp-keepalive", "300".to_string()),
         ("databases", "16".to_string()),
-        ("save", "".to_string()),
+        ("save", format_save_points(&persistence.save_points)),
         ("appendonly", env::var("REDUST_AOF_ENABLED").unwrap_or_else(|_| "no".to_string())),
         ("appendfsync", "everysec".to_string()),
-        ("dir", ".".to_string()),
+        (
+            "dir",
+            env::current_dir()
+                .map(|d| d.display().to_string())
+                .unwrap_or_else(|_| ".".to_string()),
+        ),
         ("dbfilename", env::var("REDUST_RDB_PATH").unwrap_or_else(|_| "redust.rdb".to_string())),
         ("appendfilename", env::var("REDUST_AOF_PATH").unwrap_or_else(|_| "redust.aof".to_string())),
-        ("requirepass", if env::var("REDUST_AUTH_PASSWORD").is_ok() { "yes".to_string() } else { "".to_string() }),
+        ("requirepass", requirepass.lock().unwrap().clone().unwrap_or_default()),
         ("loglevel", "notice".to_string()),
-        ("slowlog-log-slower-than", "10000".to_string()),
-        ("slowlog-max-len", "128".to_string()),
+        ("slowlog-log-slower-than", slowlog.log_slower_than_us().to_string()),
+        ("slowlog-max-len", slowlog.max_len().to_string()),
+        ("notify-keyspace-events", notify::format_flags(pubsub.notify.flags())),
+        ("pubsub-buffer", pubsub.buffer_size().to_string()),
+        ("hll-sparse-max-bytes", hyperloglog::sparse_max_bytes().to_string()),
+        ("set-max-intset-entries", storage.set_max_intset_entries().to_string()),
+        ("set-max-listpack-entries", storage.set_max_listpack_entries().to_string()),
+        ("busy-reply-threshold", scripting::script_time_limit_ms().to_string()),
+        ("lua-time-limit", scripting::script_time_limit_ms().to_string()),
     ];
     
     for (key, value) in configs {
@@ -3782,10 +5321,122 @@ fn get_config_values(pattern: &str) -> Vec<(String, String)> {
 }
 
 /// 设置配置值（大多数配置在运行时不可修改）
-fn set_config_value(parameter: &str, _value: &str) -> Result<(), String> {
+fn set_config_value(
+    parameter: &str,
+    value: &str,
+    storage: &Storage,
+    requirepass: &Mutex<Option<String>>,
+    pubsub: &PubSubHub,
+) -> Result<(), String> {
     // 大多数配置在运行时不可修改，返回错误
     match parameter.to_lowercase().as_str() {
-        "maxmemory" | "timeout" | "tcp-keepalive" | "slowlog-log-slower-than" | "slowlog-max-len" => {
+        "requirepass" => {
+            // 空字符串表示关闭认证
+            let password = if value.is_empty() {
+                None
+            } else {
+                Some(value.to_string())
+            };
+            *requirepass.lock().unwrap() = password;
+            Ok(())
+        }
+        "notify-keyspace-events" => match notify::parse_flags(value) {
+            Some(flags) => {
+                pubsub.notify.set_flags(flags);
+                Ok(())
+            }
+            None => Err(format!(
+                "ERR Invalid argument '{}' for CONFIG SET 'notify-keyspace-events'",
+                value
+            )),
+        },
+        "pubsub-buffer" => match parse_pubsub_buffer(value) {
+            Some(size) => {
+                pubsub.set_buffer_size(size);
+                Ok(())
+            }
+            None => Err(format!(
+                "ERR Invalid argument '{}' for CONFIG SET 'pubsub-buffer'",
+                value
+            )),
+        },
+        "hll-sparse-max-bytes" => match value.parse::<usize>() {
+            Ok(bytes) => {
+                hyperloglog::set_sparse_max_bytes(bytes);
+                Ok(())
+            }
+            Err(_) => Err(format!(
+                "ERR Invalid argument '{}' for CONFIG SET 'hll-sparse-max-bytes'",
+                value
+            )),
+        },
+        "set-max-intset-entries" => match value.parse::<usize>() {
+            Ok(entries) => {
+                storage.set_set_max_intset_entries(entries);
+                Ok(())
+            }
+            Err(_) => Err(format!(
+                "ERR Invalid argument '{}' for CONFIG SET 'set-max-intset-entries'",
+                value
+            )),
+        },
+        "set-max-listpack-entries" => match value.parse::<usize>() {
+            Ok(entries) => {
+                storage.set_set_max_listpack_entries(entries);
+                Ok(())
+            }
+            Err(_) => Err(format!(
+                "ERR Invalid argument '{}' for CONFIG SET 'set-max-listpack-entries'",
+                value
+            )),
+        },
+        "replica-read-only" | "slave-read-only" => match value.to_ascii_lowercase().as_str() {
+            "yes" => {
+                storage.set_read_only(true);
+                Ok(())
+            }
+            "no" => {
+                storage.set_read_only(false);
+                Ok(())
+            }
+            _ => Err(format!(
+                "ERR Invalid argument '{}' for CONFIG SET '{}'",
+                value, parameter
+            )),
+        },
+        "maxmemory" => match parse_maxmemory_bytes(value) {
+            Some(bytes) => {
+                storage.set_maxmemory_bytes(bytes);
+                // 与 Redis 一致：调低上限后立即按策略淘汰，淘汰不够时仍返回 OK
+                storage.free_memory_if_needed();
+                Ok(())
+            }
+            None => Err(format!(
+                "ERR Invalid argument '{}' for CONFIG SET 'maxmemory'",
+                value
+            )),
+        },
+        "maxmemory-policy" => match MaxmemoryPolicy::parse(value) {
+            Some(policy) => {
+                storage.set_maxmemory_policy(policy);
+                Ok(())
+            }
+            None => Err(format!(
+                "ERR Invalid argument '{}' for CONFIG SET 'maxmemory-policy'",
+                value
+            )),
+        },
+        "busy-reply-threshold" | "lua-time-limit" => match value.parse::<u64>() {
+            Ok(ms) => {
+                scripting::set_script_time_limit_ms(ms);
+                Ok(())
+            }
+            Err(_) => Err(format!(
+                "ERR Invalid argument '{}' for CONFIG SET '{}'",
+                value, parameter
+            )),
+        },
+        "timeout" | "tcp-keepalive" | "slowlog-log-slower-than" | "slowlog-max-len" => {
             // 这些配置理论上可以动态修改，但我们简化实现，暂不支持
             Err(format!("ERR Unsupported CONFIG parameter: {}", parameter))
         }
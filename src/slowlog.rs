@@ -0,0 +1,190 @@
+//! 慢查询日志（SLOWLOG）
+//!
+//! 与 Redis 一致：执行耗时超过 `slowlog-log-slower-than`（微秒）的命令会被记录，
+//! 最多保留 `slowlog-max-len` 条，新条目在前。阈值为 0 时记录所有命令，负数时关闭记录。
+
+use std::collections::VecDeque;
+use std::env;
+use std::sync::atomic::{AtomicI64, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// 单条记录最多保留的参数个数（含命令名）
+pub const SLOWLOG_ENTRY_MAX_ARGC: usize = 32;
+/// 单个参数最多保留的字节数
+pub const SLOWLOG_ENTRY_MAX_STRING: usize = 128;
+
+const DEFAULT_LOG_SLOWER_THAN_US: i64 = 10_000;
+const DEFAULT_MAX_LEN: usize = 128;
+
+#[derive(Debug, Clone)]
+pub struct SlowlogEntry {
+    pub id: u64,
+    /// 命令开始执行时的 Unix 时间戳（秒）
+    pub timestamp: i64,
+    pub duration_us: u64,
+    pub args: Vec<Vec<u8>>,
+    pub client_addr: String,
+    pub client_name: String,
+    /// 命令回复写出的字节数；未统计时为 None。SLOWLOG GET 带 WITHREPLYBYTES 时作为第 7 个字段返回
+    pub reply_bytes: Option<u64>,
+}
+
+pub struct SlowLog {
+    entries: Mutex<VecDeque<SlowlogEntry>>,
+    /// 下一条记录的 id。不变式：id 在进程生命周期内单调递增、永不复用，
+    /// `reset` 只清空条目而不回退 id（与 Redis 一致），客户端可据此判断是否有新慢查询。
+    next_id: AtomicU64,
+    log_slower_than_us: AtomicI64,
+    max_len: AtomicUsize,
+}
+
+impl SlowLog {
+    pub fn new(log_slower_than_us: i64, max_len: usize) -> Self {
+        SlowLog {
+            entries: Mutex::new(VecDeque::new()),
+            next_id: AtomicU64::new(0),
+            log_slower_than_us: AtomicI64::new(log_slower_than_us),
+            max_len: AtomicUsize::new(max_len),
+        }
+    }
+
+    /// 从 `REDUST_SLOWLOG_LOG_SLOWER_THAN` / `REDUST_SLOWLOG_MAX_LEN` 读取配置
+    pub fn from_env() -> Self {
+        let slower_than = env::var("REDUST_SLOWLOG_LOG_SLOWER_THAN")
+            .ok()
+            .and_then(|v| v.trim().parse::<i64>().ok())
+            .unwrap_or(DEFAULT_LOG_SLOWER_THAN_US);
+        let max_len = env::var("REDUST_SLOWLOG_MAX_LEN")
+            .ok()
+            .and_then(|v| v.trim().parse::<usize>().ok())
+            .unwrap_or(DEFAULT_MAX_LEN);
+        SlowLog::new(slower_than, max_len)
+    }
+
+    pub fn log_slower_than_us(&self) -> i64 {
+        self.log_slower_than_us.load(Ordering::Relaxed)
+    }
+
+    pub fn max_len(&self) -> usize {
+        self.max_len.load(Ordering::Relaxed)
+    }
+
+    /// 耗时达到阈值时记录一条慢查询
+    pub fn log_if_slow(
+        &self,
+        timestamp: i64,
+        duration_us: u64,
+        args: Vec<Vec<u8>>,
+        client_addr: &str,
+        client_name: &str,
+        reply_bytes: Option<u64>,
+    ) {
+        let threshold = self.log_slower_than_us();
+        if threshold < 0 || (duration_us as i64) < threshold {
+            return;
+        }
+        let max_len = self.max_len();
+        let mut entries = self.entries.lock().unwrap();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        entries.push_front(SlowlogEntry {
+            id,
+            timestamp,
+            duration_us,
+            args,
+            client_addr: client_addr.to_string(),
+            client_name: client_name.to_string(),
+            reply_bytes,
+        });
+        entries.truncate(max_len);
+    }
+
+    /// 返回最新的 `count` 条记录（新条目在前）
+    pub fn get(&self, count: usize) -> Vec<SlowlogEntry> {
+        let entries = self.entries.lock().unwrap();
+        entries.iter().take(count).cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 清空所有记录；`next_id` 保持不变，保证之后的 id 大于此前出现过的任何 id
+    pub fn reset(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+/// 按 Redis 规则截断命令参数：最多保留 32 个参数，单个参数最多 128 字节，
+/// 超出部分以 `... (N more arguments)` / `... (N more bytes)` 标注
+pub fn summarize_args(parts: &[Vec<u8>]) -> Vec<Vec<u8>> {
+    let keep = if parts.len() > SLOWLOG_ENTRY_MAX_ARGC {
+        SLOWLOG_ENTRY_MAX_ARGC - 1
+    } else {
+        parts.len()
+    };
+    let mut out: Vec<Vec<u8>> = parts[..keep]
+        .iter()
+        .map(|arg| {
+            if arg.len() > SLOWLOG_ENTRY_MAX_STRING {
+                let mut truncated = arg[..SLOWLOG_ENTRY_MAX_STRING].to_vec();
+                truncated.extend_from_slice(
+                    format!("... ({} more bytes)", arg.len() - SLOWLOG_ENTRY_MAX_STRING)
+                        .as_bytes(),
+                );
+                truncated
+            } else {
+                arg.clone()
+            }
+        })
+        .collect();
+    if keep < parts.len() {
+        out.push(format!("... ({} more arguments)", parts.len() - keep).into_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarize_truncates_long_args_and_argc() {
+        let mut parts = vec![b"RPUSH".to_vec(), b"k".to_vec(), vec![b'x'; 200]];
+        parts.extend((0..40).map(|i| i.to_string().into_bytes()));
+        let out = summarize_args(&parts);
+        assert_eq!(out.len(), SLOWLOG_ENTRY_MAX_ARGC);
+        assert_eq!(out[0], b"RPUSH");
+        assert!(out[2].ends_with(b"... (72 more bytes)"));
+        assert_eq!(out[31], b"... (12 more arguments)");
+    }
+
+    #[test]
+    fn ids_stay_monotonic_across_reset() {
+        let log = SlowLog::new(0, 10);
+        log.log_if_slow(0, 1, vec![b"GET".to_vec()], "a", "", None);
+        log.log_if_slow(0, 1, vec![b"GET".to_vec()], "a", "", None);
+        let max_before = log.get(10).iter().map(|e| e.id).max().unwrap();
+        log.reset();
+        assert!(log.is_empty());
+        log.log_if_slow(0, 1, vec![b"GET".to_vec()], "a", "", None);
+        assert!(log.get(1)[0].id > max_before);
+    }
+
+    #[test]
+    fn log_respects_threshold_and_max_len() {
+        let log = SlowLog::new(100, 2);
+        log.log_if_slow(0, 50, vec![b"GET".to_vec()], "a", "", None);
+        assert!(log.is_empty());
+        for _ in 0..3 {
+            log.log_if_slow(0, 150, vec![b"GET".to_vec()], "a", "", None);
+        }
+        let entries = log.get(10);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].id, 2);
+        assert_eq!(entries[1].id, 1);
+    }
+}
@@ -1,24 +1,132 @@
+use crate::crc64;
 use crate::hyperloglog::HyperLogLog;
+use crate::notify::{self, KeyspaceEvent};
 use dashmap::DashMap;
 use ordered_float::OrderedFloat;
 use rand::prelude::SliceRandom;
-use rand::{seq::IteratorRandom, thread_rng};
-use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use rand::{seq::IteratorRandom, thread_rng, Rng};
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet, VecDeque};
 use std::fs::File;
 use std::io::{self, Read, Write};
 use std::path::Path;
 use std::sync::{
-    atomic::{AtomicU64, Ordering},
-    Arc,
+    atomic::{AtomicBool, AtomicI64, AtomicU64, AtomicUsize, Ordering},
+    Arc, Mutex, OnceLock,
 };
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{mpsc, OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock};
+
+/// RDB 文件魔数：文件头 + 尾部 CRC64 校验的格式。
+const RDB_MAGIC: &[u8; 8] = b"REDUST01";
+/// zstd 压缩的 RDB 文件魔数：记录流整体压缩，CRC64 覆盖压缩后的内容。
+const RDB_ZSTD_MAGIC: &[u8; 8] = b"REDUSTZ1";
+/// 旧版（无校验和）RDB 文件魔数，仅用于兼容加载。
+const RDB_LEGACY_MAGIC: &[u8; 8] = b"REDUSTDB";
+/// 当前写出的 RDB 版本号：v2 起 TTL 以绝对 Unix 毫秒时间戳保存。
+const RDB_VERSION: u32 = 2;
 
 type ByteString = Vec<u8>;
 
-#[derive(Debug, Clone)]
+/// RDB 快照的压缩方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RdbCompression {
+    #[default]
+    None,
+    Zstd,
+}
+
+impl RdbCompression {
+    /// 解析 `none` / `zstd`（大小写不敏感）
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "" | "none" => Some(RdbCompression::None),
+            "zstd" => Some(RdbCompression::Zstd),
+            _ => None,
+        }
+    }
+}
+
+/// 超过 maxmemory 时的处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MaxmemoryPolicy {
+    /// 采样淘汰最近最少使用的 key（默认）
+    #[default]
+    AllkeysLru,
+    /// 不淘汰，带 denyoom 标志的写命令直接返回 OOM
+    Noeviction,
+}
+
+impl MaxmemoryPolicy {
+    /// 解析 `allkeys-lru` / `noeviction`（大小写不敏感）
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "allkeys-lru" => Some(MaxmemoryPolicy::AllkeysLru),
+            "noeviction" => Some(MaxmemoryPolicy::Noeviction),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MaxmemoryPolicy::AllkeysLru => "allkeys-lru",
+            MaxmemoryPolicy::Noeviction => "noeviction",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 struct ZSetInner {
-    by_member: HashMap<String, f64>,
-    by_score: BTreeSet<(OrderedFloat<f64>, String)>,
+    by_member: HashMap<ByteString, f64>,
+    by_score: BTreeSet<(OrderedFloat<f64>, ByteString)>,
+}
+
+/// 单个成员按 ZADD 选项写入后的结果
+enum ZaddOutcome {
+    /// 被 NX/XX/GT/LT 跳过
+    Skipped,
+    Added(f64),
+    Updated(f64),
+    /// 成员已存在且分数不变
+    Unchanged(f64),
+}
+
+impl ZSetInner {
+    /// 按 ZADD 选项写入一个成员；`incr` 为 true 时 `score` 是增量（ZADD INCR / ZINCRBY）
+    fn upsert(
+        &mut self,
+        member: &[u8],
+        score: f64,
+        incr: bool,
+        flags: ZaddFlags,
+    ) -> Result<ZaddOutcome, ZsetError> {
+        let Some(&old) = self.by_member.get(member) else {
+            if flags.xx {
+                return Ok(ZaddOutcome::Skipped);
+            }
+            self.by_member.insert(member.to_vec(), score);
+            self.by_score.insert((OrderedFloat(score), member.to_vec()));
+            return Ok(ZaddOutcome::Added(score));
+        };
+        if flags.nx {
+            return Ok(ZaddOutcome::Skipped);
+        }
+        let new_score = if incr { old + score } else { score };
+        if !new_score.is_finite() {
+            return Err(ZsetError::NotFloat);
+        }
+        if (flags.gt && new_score <= old) || (flags.lt && new_score >= old) {
+            return Ok(ZaddOutcome::Skipped);
+        }
+        if new_score == old {
+            return Ok(ZaddOutcome::Unchanged(old));
+        }
+        self.by_score.remove(&(OrderedFloat(old), member.to_vec()));
+        self.by_score.insert((OrderedFloat(new_score), member.to_vec()));
+        self.by_member.insert(member.to_vec(), new_score);
+        Ok(ZaddOutcome::Updated(new_score))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -28,15 +136,15 @@ enum StorageValue {
         expires_at: Option<Instant>,
     },
     List {
-        value: VecDeque<String>,
+        value: VecDeque<ByteString>,
         expires_at: Option<Instant>,
     },
     Set {
-        value: HashSet<String>,
+        value: HashSet<ByteString>,
         expires_at: Option<Instant>,
     },
     Hash {
-        value: HashMap<String, String>,
+        value: HashMap<String, ByteString>,
         expires_at: Option<Instant>,
     },
     Zset {
@@ -49,16 +157,148 @@ enum StorageValue {
     },
 }
 
+/// list 从 listpack 切换为 quicklist 的默认元素数阈值（对应 Redis 的 list-max-listpack-size 128）
+pub const DEFAULT_LIST_MAX_LISTPACK_ENTRIES: usize = 128;
+/// set-max-intset-entries / set-max-listpack-entries 的默认值，与 Redis 一致
+pub const DEFAULT_SET_MAX_INTSET_ENTRIES: usize = 512;
+pub const DEFAULT_SET_MAX_LISTPACK_ENTRIES: usize = 128;
+
+/// 是否为规范形式的 64 位整数（无前导零、无 `+` 号），与 Redis 判断能否按整数编码的规则一致
+fn is_canonical_i64(bytes: &[u8]) -> bool {
+    std::str::from_utf8(bytes)
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .is_some_and(|n| n.to_string().as_bytes() == bytes)
+}
+
+/// Redis 已知的全部类型名（含 Redust 尚未实现的 stream），用于校验 SCAN TYPE 的参数
+pub const TYPE_NAMES: &[&str] = &["string", "list", "set", "zset", "hash", "stream"];
+
+impl StorageValue {
+    /// 与 Redis TYPE 一致的小写类型名；TYPE、SCAN TYPE 过滤、OBJECT 等命令统一走这里。
+    /// 后续新增 stream 等类型时需在此补充（stream 对应 "stream"）。
+    fn type_name(&self) -> &'static str {
+        match self {
+            StorageValue::String { .. } => "string",
+            StorageValue::List { .. } => "list",
+            StorageValue::Set { .. } => "set",
+            StorageValue::Hash { .. } => "hash",
+            StorageValue::Zset { .. } => "zset",
+            // Redis 中 HLL 以字符串存储，类型显示为 string
+            StorageValue::HyperLogLog { .. } => "string",
+        }
+    }
+
+    fn expires_at(&self) -> Option<Instant> {
+        match self {
+            StorageValue::String { expires_at, .. }
+            | StorageValue::List { expires_at, .. }
+            | StorageValue::Set { expires_at, .. }
+            | StorageValue::Hash { expires_at, .. }
+            | StorageValue::Zset { expires_at, .. }
+            | StorageValue::HyperLogLog { expires_at, .. } => *expires_at,
+        }
+    }
+}
+
+/// 主动过期用的最小堆，按过期时间弹出 (deadline, key)。
+///
+/// 堆中的记录允许过时（key 已删除、TTL 被修改或 PERSIST），弹出时以 key 当前的 TTL 为准：
+/// 已过期则删除，TTL 被延后则按新时间重新入堆，没有 TTL 则丢弃。
+/// `scheduled` 记录每个 key 在堆中最早的登记时间，只有新的过期时间更早时才需要再次入堆，
+/// 避免反复刷新 TTL 的 key 让堆无限增长。
+#[derive(Default)]
+struct ExpiryQueue {
+    heap: BinaryHeap<Reverse<(Instant, String)>>,
+    scheduled: HashMap<String, Instant>,
+}
+
+impl ExpiryQueue {
+    fn schedule(&mut self, key: &str, deadline: Instant) {
+        match self.scheduled.get_mut(key) {
+            Some(at) if *at <= deadline => {}
+            Some(at) => {
+                *at = deadline;
+                self.heap.push(Reverse((deadline, key.to_string())));
+            }
+            None => {
+                self.scheduled.insert(key.to_string(), deadline);
+                self.heap.push(Reverse((deadline, key.to_string())));
+            }
+        }
+    }
+
+    /// 弹出一条已到期的记录
+    fn pop_due(&mut self, now: Instant) -> Option<String> {
+        let Reverse((deadline, _)) = self.heap.peek()?;
+        if *deadline > now {
+            return None;
+        }
+        let Reverse((deadline, key)) = self.heap.pop()?;
+        // 只有与登记时间一致的记录才代表该 key 当前的登记，其余是被更早时间取代的旧记录
+        if self.scheduled.get(&key) == Some(&deadline) {
+            self.scheduled.remove(&key);
+        }
+        Some(key)
+    }
+}
+
+/// 某一时刻数据集的完整拷贝，由 [`Storage::snapshot`] 生成，再交给 [`Storage::save_rdb_snapshot`] 序列化
+pub struct RdbSnapshot {
+    entries: Vec<(String, StorageValue)>,
+    /// 生成快照时的 dirty 计数，保存成功后只扣除这部分
+    dirty: u64,
+    taken_at: Instant,
+    taken_at_unix_ms: i64,
+}
+
+impl RdbSnapshot {
+    /// 保存后不扣减 dirty 的快照（AOF 快照不算一次 RDB 保存）
+    pub fn without_dirty(mut self) -> Self {
+        self.dirty = 0;
+        self
+    }
+}
+
 #[derive(Clone)]
 pub struct Storage {
     data: Arc<DashMap<String, StorageValue>>,
-    maxmemory_bytes: Option<u64>,
-    last_access: Arc<DashMap<String, u64>>,
-    access_counter: Arc<AtomicU64>,
+    /// 内存上限（字节），0 表示不限制；可由 CONFIG SET maxmemory 修改
+    maxmemory_bytes: Arc<AtomicU64>,
+    /// 超过 maxmemory 时的处理策略，可由 CONFIG SET maxmemory-policy 修改
+    maxmemory_policy: Arc<Mutex<MaxmemoryPolicy>>,
+    /// 只读模式（replica-read-only）：开启后拒绝所有带 write 标志的命令
+    read_only: Arc<AtomicBool>,
+    /// 每个 key 最近一次被读写的时间，用于 OBJECT IDLETIME 与 LRU 淘汰
+    last_access: Arc<DashMap<String, Instant>>,
+    /// 没有访问记录的 key（如从 RDB 载入后未被访问）按此时间计算空闲时长
+    created_at: Instant,
     /// 全局版本计数器，每次写操作递增
     global_version: Arc<AtomicU64>,
     /// 每个 key 的版本号，用于 WATCH 机制
     key_versions: Arc<DashMap<String, u64>>,
+    /// 自上次成功保存 RDB 以来的写操作次数（对应 Redis 的 dirty 计数）
+    dirty: Arc<AtomicU64>,
+    /// 因过期被删除的 key 数（惰性删除与后台主动过期都计入）
+    expired_keys: Arc<AtomicU64>,
+    /// 带 TTL 的 key 的过期时间堆，后台主动过期只处理真正到期的 key
+    expiry_queue: Arc<Mutex<ExpiryQueue>>,
+    /// 写命令闸门：写命令执行期间持有共享侧，生成 RDB 快照时短暂持有独占侧，
+    /// 保证快照中不会出现执行到一半的多 key 写命令（MSET、RENAME、EXEC 等）
+    write_gate: Arc<RwLock<()>>,
+    /// DEBUG SET-USED-MEMORY 设置的修正量，叠加在实际估算的内存用量上
+    used_memory_adjust: Arc<AtomicI64>,
+    /// list 元素数不超过该值时 OBJECT ENCODING 报告 listpack，否则报告 quicklist；
+    /// 可由 DEBUG LISTPACK-ENTRIES 调整
+    list_max_listpack_entries: Arc<AtomicUsize>,
+    /// 全部成员为整数且元素数不超过该值的 set 报告 intset（CONFIG set-max-intset-entries）
+    set_max_intset_entries: Arc<AtomicUsize>,
+    /// 非 intset 的 set 元素数不超过该值时报告 listpack，否则 hashtable（CONFIG set-max-listpack-entries）
+    set_max_listpack_entries: Arc<AtomicUsize>,
+    /// 设置 TTL 时额外叠加的随机偏移上限（毫秒），0 表示关闭；由 REDUST_EXPIRE_JITTER_MS 配置
+    expire_jitter_ms: Arc<AtomicU64>,
+    /// 键空间事件的接收端，由 server 设置；未设置时事件直接丢弃
+    events: Arc<OnceLock<mpsc::UnboundedSender<KeyspaceEvent>>>,
 }
 
 // HINCRBY 专用错误类型，用于区分 WRONGTYPE / 非整数 / 溢出 / 超过 maxvalue 限制
@@ -72,14 +312,75 @@ pub enum HincrError {
 pub enum HincrFloatError {
     WrongType,
     NotFloat,
+    NanOrInfinity,
     MaxValueExceeded,
 }
 
+/// INCR/DECR/INCRBY/DECRBY 专用错误类型；类型错误优先于数值解析错误
+pub enum IncrError {
+    WrongType,
+    NotInteger,
+    Overflow,
+}
+
+/// INCRBYFLOAT 专用错误类型
+pub enum IncrFloatError {
+    WrongType,
+    NotFloat,
+    NanOrInfinity,
+}
+
+/// 按 Redis string2ll 的规则解析整数：不允许首尾空白、`+` 号和前导 0（`"0"` 本身除外）
+fn parse_redis_i64(bytes: &[u8]) -> Option<i64> {
+    let digits = bytes.strip_prefix(b"-").unwrap_or(bytes);
+    match digits {
+        [b'0'] => (bytes.len() == 1).then_some(0),
+        [b'1'..=b'9', rest @ ..] if rest.iter().all(u8::is_ascii_digit) => {
+            std::str::from_utf8(bytes).ok()?.parse().ok()
+        }
+        _ => None,
+    }
+}
+
+/// INCRBYFLOAT / HINCRBYFLOAT 结果的字符串表示，对齐 Redis 的 `%.17Lf` 去尾零格式：
+/// 不使用科学计数法（3.0e3 -> "3000"），一般情况下取 f64 的最短往返表示（10.5+0.1 -> "10.6"），
+/// 小数位超过 17 位时按 17 位四舍五入后去掉尾随的 0 和小数点
+pub fn format_incr_float(value: f64) -> String {
+    let shortest = value.to_string();
+    let frac_digits = shortest.split_once('.').map_or(0, |(_, frac)| frac.len());
+    if frac_digits <= 17 {
+        return shortest;
+    }
+    let mut s = format!("{:.17}", value);
+    while s.ends_with('0') {
+        s.pop();
+    }
+    if s.ends_with('.') {
+        s.pop();
+    }
+    s
+}
+
 pub enum ZsetError {
     WrongType,
     NotFloat,
 }
 
+/// ZADD 的 NX/XX/GT/LT/CH 选项；互斥组合由命令解析阶段拒绝
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ZaddFlags {
+    /// 只新增成员，不更新已有成员
+    pub nx: bool,
+    /// 只更新已有成员，不新增
+    pub xx: bool,
+    /// 只在新分数更大时更新
+    pub gt: bool,
+    /// 只在新分数更小时更新
+    pub lt: bool,
+    /// 返回新增与分数变化的成员总数，而不只是新增数
+    pub ch: bool,
+}
+
 impl Default for Storage {
     fn default() -> Self {
         Storage::new(None)
@@ -90,11 +391,39 @@ impl Storage {
     pub fn new(maxmemory_bytes: Option<u64>) -> Self {
         Storage {
             data: Arc::new(DashMap::new()),
-            maxmemory_bytes,
+            maxmemory_bytes: Arc::new(AtomicU64::new(maxmemory_bytes.unwrap_or(0))),
+            maxmemory_policy: Arc::new(Mutex::new(MaxmemoryPolicy::default())),
+            read_only: Arc::new(AtomicBool::new(false)),
             last_access: Arc::new(DashMap::new()),
-            access_counter: Arc::new(AtomicU64::new(0)),
+            created_at: Instant::now(),
             global_version: Arc::new(AtomicU64::new(0)),
             key_versions: Arc::new(DashMap::new()),
+            dirty: Arc::new(AtomicU64::new(0)),
+            expired_keys: Arc::new(AtomicU64::new(0)),
+            expiry_queue: Arc::new(Mutex::new(ExpiryQueue::default())),
+            write_gate: Arc::new(RwLock::new(())),
+            used_memory_adjust: Arc::new(AtomicI64::new(0)),
+            list_max_listpack_entries: Arc::new(AtomicUsize::new(DEFAULT_LIST_MAX_LISTPACK_ENTRIES)),
+            set_max_intset_entries: Arc::new(AtomicUsize::new(DEFAULT_SET_MAX_INTSET_ENTRIES)),
+            set_max_listpack_entries: Arc::new(AtomicUsize::new(DEFAULT_SET_MAX_LISTPACK_ENTRIES)),
+            expire_jitter_ms: Arc::new(AtomicU64::new(0)),
+            events: Arc::new(OnceLock::new()),
+        }
+    }
+
+    /// 设置键空间事件的接收端；只能设置一次，重复设置会被忽略
+    pub fn set_event_sender(&self, tx: mpsc::UnboundedSender<KeyspaceEvent>) {
+        let _ = self.events.set(tx);
+    }
+
+    /// 发出一个键空间事件（`key` 带 db 前缀），是否真正发布由 notify-keyspace-events 决定
+    pub fn notify_keyspace_event(&self, class: u32, event: &'static str, key: &str) {
+        if let Some(tx) = self.events.get() {
+            let _ = tx.send(KeyspaceEvent {
+                class,
+                event,
+                key: key.to_string(),
+            });
         }
     }
 
@@ -107,9 +436,20 @@ impl Storage {
     pub fn bump_key_version(&self, key: &str) {
         let new_version = self.global_version.fetch_add(1, Ordering::SeqCst) + 1;
         self.key_versions.insert(key.to_string(), new_version);
+        self.dirty.fetch_add(1, Ordering::Relaxed);
     }
 
-    pub fn flushdb(&self, db: u8) {
+    /// 自上次成功保存 RDB 以来的写操作次数
+    pub fn expired_keys(&self) -> u64 {
+        self.expired_keys.load(Ordering::Relaxed)
+    }
+
+    pub fn dirty(&self) -> u64 {
+        self.dirty.load(Ordering::Relaxed)
+    }
+
+    /// 清空指定 DB，返回被删除的物理 key。每个 key 都会递增版本号，使 WATCH 它们的事务失效
+    pub fn flushdb(&self, db: u8) -> Vec<String> {
         let prefix = format!("{}:", db);
         let keys_to_remove: Vec<String> = self
             .data
@@ -123,9 +463,11 @@ impl Storage {
             self.last_access.remove(k);
             self.bump_key_version(k);
         }
+        keys_to_remove
     }
 
-    pub fn flushall(&self) {
+    /// 清空所有 DB，返回被删除的物理 key；与 flushdb 一样递增每个 key 的版本号
+    pub fn flushall(&self) -> Vec<String> {
         // 先记录所有 key 并更新版本
         let all_keys: Vec<String> = self.data.iter().map(|e| e.key().clone()).collect();
         for k in &all_keys {
@@ -133,10 +475,11 @@ impl Storage {
         }
         self.data.clear();
         self.last_access.clear();
-        self.access_counter.store(0, Ordering::Relaxed);
+        *self.expiry_queue.lock().unwrap() = ExpiryQueue::default();
+        all_keys
     }
 
-    pub fn hset(&self, key: &str, field: &str, value: String) -> Result<usize, ()> {
+    pub fn hset(&self, key: &str, field: &str, value: ByteString) -> Result<usize, ()> {
         let now = Instant::now();
         self.remove_if_expired(key, now);
 
@@ -168,14 +511,11 @@ impl Storage {
         // hset 总是修改 key（即使 field 已存在），需要更新版本
         self.touch_key(key);
         self.bump_key_version(key);
-        if added > 0 {
-            self.maybe_evict_for_write();
-        }
 
         Ok(added)
     }
 
-    pub fn hget(&self, key: &str, field: &str) -> Result<Option<String>, ()> {
+    pub fn hget(&self, key: &str, field: &str) -> Result<Option<ByteString>, ()> {
         let now = Instant::now();
         if self.remove_if_expired(key, now) {
             return Ok(None);
@@ -226,7 +566,7 @@ impl Storage {
         Ok(keys)
     }
 
-    pub fn hvals(&self, key: &str) -> Result<Vec<String>, ()> {
+    pub fn hvals(&self, key: &str) -> Result<Vec<ByteString>, ()> {
         let now = Instant::now();
         if self.remove_if_expired(key, now) {
             return Ok(Vec::new());
@@ -240,7 +580,7 @@ impl Storage {
                 _ => return Err(()),
             };
 
-            let vals: Vec<String> = map.values().cloned().collect();
+            let vals: Vec<ByteString> = map.values().cloned().collect();
             let touch = !vals.is_empty();
             (vals, touch)
         };
@@ -254,7 +594,7 @@ impl Storage {
         Ok(vals)
     }
 
-    pub fn hmget(&self, key: &str, fields: &[String]) -> Result<Vec<Option<String>>, ()> {
+    pub fn hmget(&self, key: &str, fields: &[String]) -> Result<Vec<Option<ByteString>>, ()> {
         let now = Instant::now();
         if self.remove_if_expired(key, now) {
             return Ok(vec![None; fields.len()]);
@@ -306,10 +646,13 @@ impl Storage {
         };
 
         // Default missing field to 0, then add delta
-        let current_s = map.get(field).cloned().unwrap_or_else(|| "0".to_string());
-        let current = current_s
-            .parse::<i64>()
-            .map_err(|_| HincrError::NotInteger)?;
+        let current = match map.get(field) {
+            Some(bytes) => std::str::from_utf8(bytes)
+                .ok()
+                .and_then(|s| s.parse::<i64>().ok())
+                .ok_or(HincrError::NotInteger)?,
+            None => 0,
+        };
         let new_val = current.checked_add(delta).ok_or(HincrError::Overflow)?;
 
         let new_str = new_val.to_string();
@@ -319,11 +662,10 @@ impl Storage {
             }
         }
 
-        map.insert(field.to_string(), new_str);
+        map.insert(field.to_string(), new_str.into_bytes());
 
         self.touch_key(key);
         self.bump_key_version(key);
-        self.maybe_evict_for_write();
 
         Ok(new_val)
     }
@@ -334,7 +676,7 @@ impl Storage {
         field: &str,
         delta: f64,
         max_value_bytes: Option<u64>,
-    ) -> Result<f64, HincrFloatError> {
+    ) -> Result<String, HincrFloatError> {
         let now = Instant::now();
         if self.remove_if_expired(key, now) {
             // treat as non-existent hash
@@ -353,25 +695,19 @@ impl Storage {
             _ => return Err(HincrFloatError::WrongType),
         };
 
-        let current_s = map.get(field).cloned().unwrap_or_else(|| "0".to_string());
-        let current = current_s
-            .parse::<f64>()
-            .map_err(|_| HincrFloatError::NotFloat)?;
+        let current = match map.get(field) {
+            Some(bytes) => std::str::from_utf8(bytes)
+                .ok()
+                .and_then(|s| s.parse::<f64>().ok())
+                .ok_or(HincrFloatError::NotFloat)?,
+            None => 0.0,
+        };
         let new_val = current + delta;
         if !new_val.is_finite() {
-            return Err(HincrFloatError::NotFloat);
+            return Err(HincrFloatError::NanOrInfinity);
         }
 
-        // 生成紧凑十进制表示，逻辑与 incr_by_float 保持一致
-        let mut s = new_val.to_string();
-        if s.contains('.') {
-            while s.ends_with('0') {
-                s.pop();
-            }
-            if s.ends_with('.') {
-                s.push('0');
-            }
-        }
+        let s = format_incr_float(new_val);
 
         if let Some(limit) = max_value_bytes {
             if (s.as_bytes().len() as u64) > limit {
@@ -379,13 +715,12 @@ impl Storage {
             }
         }
 
-        map.insert(field.to_string(), s);
+        map.insert(field.to_string(), s.clone().into_bytes());
 
         self.touch_key(key);
         self.bump_key_version(key);
-        self.maybe_evict_for_write();
 
-        Ok(new_val)
+        Ok(s)
     }
 
     pub fn hlen(&self, key: &str) -> Result<usize, ()> {
@@ -438,7 +773,7 @@ impl Storage {
         Ok(removed)
     }
 
-    pub fn spop(&self, key: &str, count: Option<i64>) -> Result<Vec<String>, ()> {
+    pub fn spop(&self, key: &str, count: Option<i64>) -> Result<Vec<ByteString>, ()> {
         let now = Instant::now();
         if self.remove_if_expired(key, now) {
             return Ok(Vec::new());
@@ -468,18 +803,15 @@ impl Storage {
             return Ok(Vec::new());
         }
 
+        // 部分 Fisher–Yates：只需打乱前 n 个位置即可得到均匀的无放回样本
         let mut rng = thread_rng();
-        for _ in 0..n {
-            if let Some(chosen) = set.iter().choose(&mut rng).cloned() {
-                set.remove(&chosen);
-                result.push(chosen);
-                if set.is_empty() {
-                    break;
-                }
-            } else {
-                break;
-            }
+        let mut members: Vec<ByteString> = set.iter().cloned().collect();
+        let n = n.min(members.len());
+        let (chosen, _) = members.partial_shuffle(&mut rng, n);
+        for m in chosen.iter() {
+            set.remove(m);
         }
+        result.extend_from_slice(chosen);
 
         if !result.is_empty() {
             self.touch_key(key);
@@ -489,7 +821,7 @@ impl Storage {
         Ok(result)
     }
 
-    pub fn srandmember(&self, key: &str, count: Option<i64>) -> Result<Vec<String>, ()> {
+    pub fn srandmember(&self, key: &str, count: Option<i64>) -> Result<Vec<ByteString>, ()> {
         let now = Instant::now();
         if self.remove_if_expired(key, now) {
             return Ok(Vec::new());
@@ -517,22 +849,22 @@ impl Storage {
             }
             Some(c) if c == 0 => {}
             Some(c) if c > 0 => {
+                // 正数：不重复的成员，部分 Fisher–Yates 保证均匀
                 let n = c as usize;
-                let mut candidates: Vec<String> = set.iter().cloned().collect();
+                let mut candidates: Vec<ByteString> = set.iter().cloned().collect();
                 if n >= candidates.len() {
                     result = candidates;
                 } else {
-                    candidates.shuffle(&mut rng);
-                    result.extend(candidates.into_iter().take(n));
+                    let (chosen, _) = candidates.partial_shuffle(&mut rng, n);
+                    result.extend_from_slice(chosen);
                 }
             }
             Some(c) => {
-                let n = (-c) as usize;
-                let elems: Vec<&String> = set.iter().collect();
+                // 负数：有放回抽样，每次独立均匀地选一个下标，允许重复
+                let n = c.unsigned_abs() as usize;
+                let elems: Vec<&ByteString> = set.iter().collect();
                 for _ in 0..n {
-                    if let Some(chosen) = elems.iter().choose(&mut rng) {
-                        result.push((*chosen).to_string());
-                    }
+                    result.push(elems[rng.gen_range(0..elems.len())].clone());
                 }
             }
         }
@@ -559,13 +891,14 @@ impl Storage {
         Ok(exists)
     }
 
-    pub fn hgetall(&self, key: &str) -> Result<Vec<(String, String)>, ()> {
+    /// 返回按 field 字节序排序的全部 field/value，保证多次调用顺序一致（HSCAN 游标依赖此顺序）
+    pub fn hgetall(&self, key: &str) -> Result<Vec<(String, ByteString)>, ()> {
         let now = Instant::now();
         if self.remove_if_expired(key, now) {
             return Ok(Vec::new());
         }
 
-        let (result, should_touch) = {
+        let (mut result, should_touch) = {
             let entry = self.data.get(key);
             let map = match entry.as_ref().map(|e| e.value()) {
                 Some(StorageValue::Hash { value: m, .. }) => m,
@@ -573,12 +906,14 @@ impl Storage {
                 _ => return Err(()),
             };
 
-            let vec: Vec<(String, String)> =
+            let vec: Vec<(String, ByteString)> =
                 map.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
             let touch = !vec.is_empty();
             (vec, touch)
         };
 
+        result.sort_by(|a, b| a.0.cmp(&b.0));
+
         if should_touch {
             self.touch_key(key);
         }
@@ -609,7 +944,6 @@ impl Storage {
             });
         self.touch_key(&key);
         self.bump_key_version(&key);
-        self.maybe_evict_for_write();
     }
 
     pub fn get(&self, key: &str) -> Option<ByteString> {
@@ -641,23 +975,24 @@ impl Storage {
             return Ok(None);
         }
 
-        // 直接从 DashMap 中移除 key，避免在持有引用的同时再调用 remove
-        let removed = self.data.remove(key);
+        // 只移除 string 类型的 key；类型不符时保留原值并返回 WRONGTYPE
+        let removed = self
+            .data
+            .remove_if(key, |_, v| matches!(v, StorageValue::String { .. }));
 
         let Some((_k, value)) = removed else {
-            return Ok(None);
+            return if self.data.contains_key(key) { Err(()) } else { Ok(None) };
         };
 
-        let result = match value {
-            StorageValue::String { value, .. } => Ok(Some(value)),
-            _ => Err(()),
-        }?;
+        let StorageValue::String { value: result, .. } = value else {
+            unreachable!("remove_if only removes string values");
+        };
 
         // 删除访问记录
         self.last_access.remove(key);
         self.bump_key_version(key);
 
-        Ok(result)
+        Ok(Some(result))
     }
 
     pub fn getrange(&self, key: &str, start: isize, end: isize) -> Result<ByteString, ()> {
@@ -733,13 +1068,75 @@ impl Storage {
         };
         self.touch_key(key);
         self.bump_key_version(key);
-        if len > 0 {
-            self.maybe_evict_for_write();
-        }
 
         Ok(len)
     }
 
+    /// SETBIT：位图就是普通字符串，偏移超出当前长度时用 0 字节补齐；返回该位原来的值
+    pub(crate) fn setbit(&self, key: &str, offset: u64, bit: bool) -> Result<u8, ()> {
+        let now = Instant::now();
+        self.remove_if_expired(key, now);
+
+        let byte = (offset / 8) as usize;
+        let mask = 0x80u8 >> (offset % 8);
+        let old = if let Some(mut entry) = self.data.get_mut(key) {
+            match entry.value_mut() {
+                StorageValue::String { value, .. } => {
+                    if value.len() <= byte {
+                        value.resize(byte + 1, 0);
+                    }
+                    let old = value[byte] & mask != 0;
+                    if bit {
+                        value[byte] |= mask;
+                    } else {
+                        value[byte] &= !mask;
+                    }
+                    old
+                }
+                _ => return Err(()),
+            }
+        } else {
+            let mut value = vec![0u8; byte + 1];
+            if bit {
+                value[byte] |= mask;
+            }
+            self.data.insert(
+                key.to_string(),
+                StorageValue::String {
+                    value,
+                    expires_at: None,
+                },
+            );
+            false
+        };
+
+        self.touch_key(key);
+        self.bump_key_version(key);
+
+        Ok(old as u8)
+    }
+
+    /// GETBIT：key 不存在或偏移超出字符串长度时返回 0
+    pub(crate) fn getbit(&self, key: &str, offset: u64) -> Result<u8, ()> {
+        let now = Instant::now();
+        if self.remove_if_expired(key, now) {
+            return Ok(0);
+        }
+        self.touch_key(key);
+
+        let Some(entry) = self.data.get(key) else {
+            return Ok(0);
+        };
+        match entry.value() {
+            StorageValue::String { value, .. } => {
+                let byte = (offset / 8) as usize;
+                let mask = 0x80u8 >> (offset % 8);
+                Ok(value.get(byte).map_or(0, |b| (b & mask != 0) as u8))
+            }
+            _ => Err(()),
+        }
+    }
+
     pub fn strlen(&self, key: &str) -> Result<usize, ()> {
         let now = Instant::now();
         if self.remove_if_expired(key, now) {
@@ -784,7 +1181,6 @@ impl Storage {
 
         self.touch_key(key);
         self.bump_key_version(key);
-        self.maybe_evict_for_write();
 
         Ok(old)
     }
@@ -793,6 +1189,11 @@ impl Storage {
         let now = Instant::now();
         self.remove_if_expired(key, now);
 
+        // 与 Redis 一致：空 value 不补齐也不创建 key，只返回当前长度
+        if value.is_empty() {
+            return self.strlen(key);
+        }
+
         let new_len = if let Some(mut entry) = self.data.get_mut(key) {
             match entry.value_mut() {
                 StorageValue::String { value: s, .. } => {
@@ -826,13 +1227,11 @@ impl Storage {
 
         self.touch_key(key);
         self.bump_key_version(key);
-        if new_len > 0 {
-            self.maybe_evict_for_write();
-        }
 
         Ok(new_len)
     }
 
+    /// MGET：不存在、已过期或不是 string 类型的 key 都对应 None，不会返回 WRONGTYPE
     pub fn mget(&self, keys: &[String]) -> Vec<Option<ByteString>> {
         keys.iter().map(|k| self.get(k)).collect()
     }
@@ -890,7 +1289,6 @@ impl Storage {
         if inserted {
             self.touch_key(key);
             self.bump_key_version(key);
-            self.maybe_evict_for_write();
         }
         inserted
     }
@@ -906,8 +1304,13 @@ impl Storage {
     }
 
     pub fn del(&self, keys: &[String]) -> usize {
+        let now = Instant::now();
         let mut removed = 0;
         for key in keys {
+            // 逻辑上已过期的 key 对 GET 不可见：清理掉但不计入删除数
+            if self.remove_if_expired(key, now) {
+                continue;
+            }
             if self.data.remove(key).is_some() {
                 self.last_access.remove(key);
                 self.bump_key_version(key);
@@ -924,7 +1327,13 @@ impl Storage {
             .count()
     }
 
+    /// RENAME：源 key 的值连同 TTL 移到目标 key，目标原有的值（无论类型）被覆盖；
+    /// 两个 key 的版本号都会递增，WATCH 任一名字的事务都会失败
     pub fn rename(&self, from: &str, to: &str) -> Result<(), ()> {
+        let now = Instant::now();
+        if self.remove_if_expired(from, now) {
+            return Err(());
+        }
         if from == to {
             // Redis: RENAME key key 也是 OK，不做任何修改
             if !self.data.contains_key(from) {
@@ -933,11 +1342,6 @@ impl Storage {
             return Ok(());
         }
 
-        let now = Instant::now();
-        if self.remove_if_expired(from, now) {
-            return Err(());
-        }
-
         let removed = self.data.remove(from);
         let Some((_k, value)) = removed else {
             return Err(());
@@ -948,13 +1352,36 @@ impl Storage {
         self.bump_key_version(from);
 
         // 插入新 key，保留 TTL 信息
+        let expires_at = value.expires_at();
         self.data.insert(to.to_string(), value);
+        self.schedule_expiry(to, expires_at);
         self.touch_key(to);
         self.bump_key_version(to);
 
         Ok(())
     }
 
+    /// COPY：把 `from` 的值（连同 TTL）深拷贝到 `to`。所有类型的 value 都是独立拥有的集合，
+    /// clone 后与源 key 不共享任何数据。源 key 不存在、或目标已存在且未指定 REPLACE 时返回 false
+    pub fn copy(&self, from: &str, to: &str, replace: bool) -> bool {
+        let now = Instant::now();
+        if self.remove_if_expired(from, now) {
+            return false;
+        }
+        let Some(value) = self.data.get(from).map(|entry| entry.value().clone()) else {
+            return false;
+        };
+        if !self.remove_if_expired(to, now) && !replace && self.data.contains_key(to) {
+            return false;
+        }
+        let expires_at = value.expires_at();
+        self.data.insert(to.to_string(), value);
+        self.schedule_expiry(to, expires_at);
+        self.touch_key(to);
+        self.bump_key_version(to);
+        true
+    }
+
     pub fn renamenx(&self, from: &str, to: &str) -> Result<bool, ()> {
         let now = Instant::now();
         if from == to {
@@ -987,22 +1414,24 @@ impl Storage {
 
         self.last_access.remove(from);
         self.bump_key_version(from);
+        let expires_at = value.expires_at();
         self.data.insert(to.to_string(), value);
+        self.schedule_expiry(to, expires_at);
         self.touch_key(to);
         self.bump_key_version(to);
 
         Ok(true)
     }
 
-    pub fn incr(&self, key: &str) -> Result<i64, ()> {
+    pub fn incr(&self, key: &str) -> Result<i64, IncrError> {
         self.incr_by(key, 1)
     }
 
-    pub fn decr(&self, key: &str) -> Result<i64, ()> {
+    pub fn decr(&self, key: &str) -> Result<i64, IncrError> {
         self.incr_by(key, -1)
     }
 
-    pub fn incr_by(&self, key: &str, delta: i64) -> Result<i64, ()> {
+    pub fn incr_by(&self, key: &str, delta: i64) -> Result<i64, IncrError> {
         let now = Instant::now();
         if self.remove_if_expired(key, now) {
             // Treat as non-existent and start from 0
@@ -1025,67 +1454,60 @@ impl Storage {
 
         let current_val = match entry.value_mut() {
             StorageValue::String { value, .. } => value,
-            _ => return Err(()), // Key exists but is not a string
+            _ => return Err(IncrError::WrongType), // Key exists but is not a string
         };
 
-        let current_str = std::str::from_utf8(current_val).map_err(|_| ())?;
-        let value: i64 = current_str.parse().map_err(|_| ())?;
-        let new_val = value.checked_add(delta).ok_or(())?;
+        let value = parse_redis_i64(current_val).ok_or(IncrError::NotInteger)?;
+        let new_val = value.checked_add(delta).ok_or(IncrError::Overflow)?;
         *current_val = new_val.to_string().into_bytes();
         self.touch_key(key);
         self.bump_key_version(key);
-        self.maybe_evict_for_write();
         Ok(new_val)
     }
 
-    pub fn incr_by_float(&self, key: &str, delta: f64) -> Result<f64, ()> {
+    /// INCRBYFLOAT：不存在的 key 视为 0；结果为 NaN/Infinity 时报错且不修改（也不创建）key。
+    /// 返回写入的字符串表示
+    pub fn incr_by_float(&self, key: &str, delta: f64) -> Result<String, IncrFloatError> {
         let now = Instant::now();
-        if self.remove_if_expired(key, now) {
-            self.data.insert(
-                key.to_string(),
-                StorageValue::String {
-                    value: b"0".to_vec(),
-                    expires_at: None,
-                },
-            );
-        }
-
-        let mut entry = self
-            .data
-            .entry(key.to_string())
-            .or_insert(StorageValue::String {
-                value: b"0".to_vec(),
-                expires_at: None,
-            });
+        self.remove_if_expired(key, now);
 
-        let current_val = match entry.value_mut() {
-            StorageValue::String { value, .. } => value,
-            _ => return Err(()),
+        let current = match self.data.get(key) {
+            Some(entry) => match entry.value() {
+                StorageValue::String { value, .. } => std::str::from_utf8(value)
+                    .ok()
+                    .and_then(|s| s.parse::<f64>().ok())
+                    .filter(|v| v.is_finite())
+                    .ok_or(IncrFloatError::NotFloat)?,
+                _ => return Err(IncrFloatError::WrongType),
+            },
+            None => 0.0,
         };
-
-        let current_str = std::str::from_utf8(current_val).map_err(|_| ())?;
-        let current: f64 = current_str.parse().map_err(|_| ())?;
         let new_val = current + delta;
         if !new_val.is_finite() {
-            return Err(());
+            return Err(IncrFloatError::NanOrInfinity);
         }
 
-        // 尽量生成紧凑的人类可读十进制表示，接近 Redis 行为
-        let mut s = new_val.to_string();
-        if s.contains('.') {
-            while s.ends_with('0') {
-                s.pop();
+        // 保留原有 TTL，只替换值
+        let s = format_incr_float(new_val);
+        match self.data.get_mut(key) {
+            Some(mut entry) => {
+                if let StorageValue::String { value, .. } = entry.value_mut() {
+                    *value = s.clone().into_bytes();
+                }
             }
-            if s.ends_with('.') {
-                s.push('0');
+            None => {
+                self.data.insert(
+                    key.to_string(),
+                    StorageValue::String {
+                        value: s.clone().into_bytes(),
+                        expires_at: None,
+                    },
+                );
             }
         }
-
-        *current_val = s.into_bytes();
         self.touch_key(key);
         self.bump_key_version(key);
-        self.maybe_evict_for_write();
-        Ok(new_val)
+        Ok(s)
     }
 
     pub fn type_of(&self, key: &str) -> String {
@@ -1094,17 +1516,10 @@ impl Storage {
             return "none".to_string();
         }
 
-        self.data.get(key).map_or_else(
-            || "none".to_string(),
-            |entry| match entry.value() {
-                StorageValue::String { .. } => "string".to_string(),
-                StorageValue::List { .. } => "list".to_string(),
-                StorageValue::Set { .. } => "set".to_string(),
-                StorageValue::Hash { .. } => "hash".to_string(),
-                StorageValue::Zset { .. } => "zset".to_string(),
-                StorageValue::HyperLogLog { .. } => "string".to_string(), // Redis 中 HLL 的类型显示为 string
-            },
-        )
+        self.data
+            .get(key)
+            .map_or("none", |entry| entry.value().type_name())
+            .to_string()
     }
 
     pub fn keys(&self, _pattern: &str) -> Vec<String> {
@@ -1121,15 +1536,15 @@ impl Storage {
         all
     }
 
-    pub fn lpush(&self, key: &str, values: &[String]) -> Result<usize, ()> {
+    pub fn lpush(&self, key: &str, values: &[ByteString]) -> Result<usize, ()> {
         self.push_internal(key, values, true)
     }
 
-    pub fn rpush(&self, key: &str, values: &[String]) -> Result<usize, ()> {
+    pub fn rpush(&self, key: &str, values: &[ByteString]) -> Result<usize, ()> {
         self.push_internal(key, values, false)
     }
 
-    fn push_internal(&self, key: &str, values: &[String], left: bool) -> Result<usize, ()> {
+    fn push_internal(&self, key: &str, values: &[ByteString], left: bool) -> Result<usize, ()> {
         let now = Instant::now();
         self.remove_if_expired(key, now);
 
@@ -1170,14 +1585,11 @@ impl Storage {
 
         self.touch_key(key);
         self.bump_key_version(key);
-        if len > 0 {
-            self.maybe_evict_for_write();
-        }
 
         Ok(len)
     }
 
-    pub fn lrange(&self, key: &str, start: isize, stop: isize) -> Result<Vec<String>, ()> {
+    pub fn lrange(&self, key: &str, start: isize, stop: isize) -> Result<Vec<ByteString>, ()> {
         let now = Instant::now();
         if self.remove_if_expired(key, now) {
             return Ok(Vec::new());
@@ -1219,7 +1631,7 @@ impl Storage {
         let start_idx = s as usize;
         let end_idx = e as usize;
 
-        let result: Vec<String> = list
+        let result: Vec<ByteString> = list
             .iter()
             .skip(start_idx)
             .take(end_idx - start_idx + 1)
@@ -1233,7 +1645,7 @@ impl Storage {
         Ok(result)
     }
 
-    pub fn lpop(&self, key: &str) -> Result<Option<String>, ()> {
+    pub fn lpop(&self, key: &str) -> Result<Option<ByteString>, ()> {
         let now = Instant::now();
         if self.remove_if_expired(key, now) {
             return Ok(None);
@@ -1255,7 +1667,7 @@ impl Storage {
         Ok(result)
     }
 
-    pub fn rpop(&self, key: &str) -> Result<Option<String>, ()> {
+    pub fn rpop(&self, key: &str) -> Result<Option<ByteString>, ()> {
         let now = Instant::now();
         if self.remove_if_expired(key, now) {
             return Ok(None);
@@ -1296,7 +1708,7 @@ impl Storage {
         Ok(len)
     }
 
-    pub fn lindex(&self, key: &str, index: isize) -> Result<Option<String>, ()> {
+    pub fn lindex(&self, key: &str, index: isize) -> Result<Option<ByteString>, ()> {
         let now = Instant::now();
         if self.remove_if_expired(key, now) {
             return Ok(None);
@@ -1327,7 +1739,7 @@ impl Storage {
         Ok(list.get(idx as usize).cloned())
     }
 
-    pub fn lrem(&self, key: &str, count: isize, value: &str) -> Result<usize, ()> {
+    pub fn lrem(&self, key: &str, count: isize, value: &[u8]) -> Result<usize, ()> {
         let now = Instant::now();
         if self.remove_if_expired(key, now) {
             return Ok(0);
@@ -1390,45 +1802,127 @@ impl Storage {
         Ok(removed)
     }
 
-    pub fn ltrim(&self, key: &str, start: isize, stop: isize) -> Result<(), ()> {
+    /// LPOS：返回等于 `element` 的元素下标（总是从表头开始计数）。
+    /// `rank` 为正时从表头跳过前 rank-1 个匹配，为负时从表尾反向查找；
+    /// `count` 为最多返回的匹配数（0 表示全部），`maxlen` 为最多比较的元素数（0 表示不限）。
+    /// key 不存在时返回空列表
+    pub fn lpos(
+        &self,
+        key: &str,
+        element: &[u8],
+        rank: i64,
+        count: usize,
+        maxlen: usize,
+    ) -> Result<Vec<usize>, ()> {
         let now = Instant::now();
         if self.remove_if_expired(key, now) {
-            return Ok(());
+            return Ok(Vec::new());
         }
 
-        let Some(mut entry) = self.data.get_mut(key) else {
-            // key does not exist: Redis treats as no-op
-            return Ok(());
+        let Some(entry) = self.data.get(key) else {
+            return Ok(Vec::new());
         };
 
-        let list = match entry.value_mut() {
+        let list = match entry.value() {
             StorageValue::List { value: list, .. } => list,
-            _ => return Err(()), // WRONGTYPE
+            _ => return Err(()),
         };
 
-        if list.is_empty() {
-            return Ok(());
-        }
-
-        let len = list.len() as isize;
-        let mut s = start;
-        let mut e = stop;
-
-        if s < 0 {
-            s += len;
-        }
-        if e < 0 {
-            e += len;
+        let limit = if maxlen == 0 { list.len() } else { maxlen };
+        let wanted = if count == 0 { usize::MAX } else { count };
+        let mut skip = rank.unsigned_abs() - 1;
+        let mut matches = Vec::new();
+        let indexed: Box<dyn Iterator<Item = (usize, &ByteString)>> = if rank > 0 {
+            Box::new(list.iter().enumerate())
+        } else {
+            Box::new(list.iter().enumerate().rev())
+        };
+        for (idx, item) in indexed.take(limit) {
+            if item.as_slice() != element {
+                continue;
+            }
+            if skip > 0 {
+                skip -= 1;
+                continue;
+            }
+            matches.push(idx);
+            if matches.len() >= wanted {
+                break;
+            }
         }
+        Ok(matches)
+    }
 
-        if s < 0 {
-            s = 0;
-        }
-        if e >= len {
-            e = len - 1;
+    /// LINSERT：在第一个等于 `pivot` 的元素之前（`before`）或之后插入 `value`。
+    /// 返回插入后的列表长度；key 不存在时返回 0，找不到 pivot 时返回 -1，两种情况都不修改列表
+    pub fn linsert(&self, key: &str, before: bool, pivot: &[u8], value: &[u8]) -> Result<i64, ()> {
+        let now = Instant::now();
+        if self.remove_if_expired(key, now) {
+            return Ok(0);
         }
 
-        if s > e || s >= len {
+        let Some(mut entry) = self.data.get_mut(key) else {
+            return Ok(0);
+        };
+
+        let list = match entry.value_mut() {
+            StorageValue::List { value: list, .. } => list,
+            _ => return Err(()),
+        };
+
+        let Some(pos) = list.iter().position(|v| v == pivot) else {
+            return Ok(-1);
+        };
+        let at = if before { pos } else { pos + 1 };
+        list.insert(at, value.to_vec());
+        let len = list.len() as i64;
+        drop(entry);
+
+        self.touch_key(key);
+        self.bump_key_version(key);
+
+        Ok(len)
+    }
+
+    pub fn ltrim(&self, key: &str, start: isize, stop: isize) -> Result<(), ()> {
+        let now = Instant::now();
+        if self.remove_if_expired(key, now) {
+            return Ok(());
+        }
+
+        let Some(mut entry) = self.data.get_mut(key) else {
+            // key does not exist: Redis treats as no-op
+            return Ok(());
+        };
+
+        let list = match entry.value_mut() {
+            StorageValue::List { value: list, .. } => list,
+            _ => return Err(()), // WRONGTYPE
+        };
+
+        if list.is_empty() {
+            return Ok(());
+        }
+
+        let len = list.len() as isize;
+        let mut s = start;
+        let mut e = stop;
+
+        if s < 0 {
+            s += len;
+        }
+        if e < 0 {
+            e += len;
+        }
+
+        if s < 0 {
+            s = 0;
+        }
+        if e >= len {
+            e = len - 1;
+        }
+
+        if s > e || s >= len {
             // Result is empty list
             list.clear();
             self.touch_key(key);
@@ -1456,7 +1950,7 @@ impl Storage {
         Ok(())
     }
 
-    pub fn sadd(&self, key: &str, members: &[String]) -> Result<usize, ()> {
+    pub fn sadd(&self, key: &str, members: &[ByteString]) -> Result<usize, ()> {
         let now = Instant::now();
         self.remove_if_expired(key, now);
 
@@ -1492,13 +1986,12 @@ impl Storage {
         if added > 0 {
             self.touch_key(key);
             self.bump_key_version(key);
-            self.maybe_evict_for_write();
         }
 
         Ok(added)
     }
 
-    pub fn srem(&self, key: &str, members: &[String]) -> Result<usize, ()> {
+    pub fn srem(&self, key: &str, members: &[ByteString]) -> Result<usize, ()> {
         let now = Instant::now();
         if self.remove_if_expired(key, now) {
             return Ok(0);
@@ -1529,7 +2022,8 @@ impl Storage {
         Ok(removed)
     }
 
-    pub fn smembers(&self, key: &str) -> Result<Vec<String>, ()> {
+    /// 返回按字节序排序的全部成员，保证多次调用顺序一致（SSCAN 游标依赖此顺序）
+    pub fn smembers(&self, key: &str) -> Result<Vec<ByteString>, ()> {
         let now = Instant::now();
         if self.remove_if_expired(key, now) {
             return Ok(Vec::new());
@@ -1543,7 +2037,7 @@ impl Storage {
                 _ => return Err(()),
             };
 
-            let members: Vec<String> = set.iter().cloned().collect();
+            let members: Vec<ByteString> = set.iter().cloned().collect();
             let touch = !members.is_empty();
             (members, touch)
         };
@@ -1585,7 +2079,7 @@ impl Storage {
         Ok(len)
     }
 
-    pub fn sismember(&self, key: &str, member: &str) -> Result<bool, ()> {
+    pub fn sismember(&self, key: &str, member: &[u8]) -> Result<bool, ()> {
         let now = Instant::now();
         if self.remove_if_expired(key, now) {
             return Ok(false);
@@ -1604,8 +2098,8 @@ impl Storage {
         Ok(exists)
     }
 
-    pub fn sunion(&self, keys: &[String]) -> Result<Vec<String>, ()> {
-        let mut result: HashSet<String> = HashSet::new();
+    pub fn sunion(&self, keys: &[String]) -> Result<Vec<ByteString>, ()> {
+        let mut result: HashSet<ByteString> = HashSet::new();
         for key in keys {
             let now = Instant::now();
             if self.remove_if_expired(key, now) {
@@ -1624,12 +2118,20 @@ impl Storage {
             }
         }
 
-        let mut members: Vec<String> = result.into_iter().collect();
+        let mut members: Vec<ByteString> = result.into_iter().collect();
         members.sort();
         Ok(members)
     }
 
-    fn set_store_result(&self, dest: &str, members: HashSet<String>) -> usize {
+    /// 写入 *STORE 的结果；结果为空时与 Redis 一致删除目标 key（原本存在时才算一次修改）
+    fn set_store_result(&self, dest: &str, members: HashSet<ByteString>) -> usize {
+        if members.is_empty() {
+            if self.data.remove(dest).is_some() {
+                self.last_access.remove(dest);
+                self.bump_key_version(dest);
+            }
+            return 0;
+        }
         let len = members.len();
         self.data.insert(
             dest.to_string(),
@@ -1640,15 +2142,12 @@ impl Storage {
         );
         self.touch_key(dest);
         self.bump_key_version(dest);
-        if len > 0 {
-            self.maybe_evict_for_write();
-        }
         len
     }
 
     pub fn sunionstore(&self, dest: &str, keys: &[String]) -> Result<usize, ()> {
         let now = Instant::now();
-        let mut result: HashSet<String> = HashSet::new();
+        let mut result: HashSet<ByteString> = HashSet::new();
         for key in keys {
             if self.remove_if_expired(key, now) {
                 continue;
@@ -1680,7 +2179,7 @@ impl Storage {
             return Ok(self.set_store_result(dest, HashSet::new()));
         }
 
-        let mut result: HashSet<String> = match self.data.get(first_key) {
+        let mut result: HashSet<ByteString> = match self.data.get(first_key) {
             Some(entry) => match entry.value() {
                 StorageValue::Set { value: set, .. } => set.iter().cloned().collect(),
                 _ => return Err(()),
@@ -1721,7 +2220,7 @@ impl Storage {
             return Ok(self.set_store_result(dest, HashSet::new()));
         }
 
-        let mut result: HashSet<String> = match self.data.get(first_key) {
+        let mut result: HashSet<ByteString> = match self.data.get(first_key) {
             Some(entry) => match entry.value() {
                 StorageValue::Set { value: set, .. } => set.iter().cloned().collect(),
                 _ => return Err(()),
@@ -1747,7 +2246,12 @@ impl Storage {
         Ok(self.set_store_result(dest, result))
     }
 
-    pub fn zadd(&self, key: &str, entries: &[(f64, String)]) -> Result<usize, ZsetError> {
+    pub fn zadd(
+        &self,
+        key: &str,
+        entries: &[(f64, ByteString)],
+        flags: ZaddFlags,
+    ) -> Result<usize, ZsetError> {
         let now = Instant::now();
         self.remove_if_expired(key, now);
 
@@ -1758,51 +2262,92 @@ impl Storage {
         }
 
         let mut added = 0usize;
+        let mut updated = 0usize;
 
-        if let Some(mut entry) = self.data.get_mut(key) {
-            match entry.value_mut() {
+        match self.data.get_mut(key) {
+            Some(mut entry) => match entry.value_mut() {
                 StorageValue::Zset { value, .. } => {
                     for (score, member) in entries {
-                        let ord_score = OrderedFloat(*score);
-                        if let Some(old_score) = value.by_member.insert(member.clone(), *score) {
-                            let _ = value
-                                .by_score
-                                .remove(&(OrderedFloat(old_score), member.clone()));
-                        } else {
-                            added += 1;
+                        match value.upsert(member, *score, false, flags)? {
+                            ZaddOutcome::Added(_) => added += 1,
+                            ZaddOutcome::Updated(_) => updated += 1,
+                            ZaddOutcome::Skipped | ZaddOutcome::Unchanged(_) => {}
                         }
-                        value.by_score.insert((ord_score, member.clone()));
                     }
                 }
                 _ => return Err(ZsetError::WrongType),
+            },
+            // XX 不会新增成员，也就不会创建 key
+            None if flags.xx => {}
+            None => {
+                let mut inner = ZSetInner::default();
+                for (score, member) in entries {
+                    if let ZaddOutcome::Added(_) = inner.upsert(member, *score, false, flags)? {
+                        added += 1;
+                    }
+                }
+                self.data.insert(
+                    key.to_string(),
+                    StorageValue::Zset {
+                        value: inner,
+                        expires_at: None,
+                    },
+                );
             }
-        } else {
-            let mut inner = ZSetInner {
-                by_member: HashMap::new(),
-                by_score: BTreeSet::new(),
-            };
-            for (score, member) in entries {
-                let ord_score = OrderedFloat(*score);
-                inner.by_member.insert(member.clone(), *score);
-                inner.by_score.insert((ord_score, member.clone()));
-                added += 1;
-            }
-            self.data.insert(
-                key.to_string(),
-                StorageValue::Zset {
-                    value: inner,
-                    expires_at: None,
-                },
-            );
         }
 
-        if !entries.is_empty() {
+        if added + updated > 0 {
             self.touch_key(key);
             self.bump_key_version(key);
-            self.maybe_evict_for_write();
         }
 
-        Ok(added)
+        Ok(if flags.ch { added + updated } else { added })
+    }
+
+    /// ZADD ... INCR：与 ZINCRBY 相同，但受 NX/XX/GT/LT 约束；被选项跳过时返回 None
+    pub fn zadd_incr(
+        &self,
+        key: &str,
+        increment: f64,
+        member: &[u8],
+        flags: ZaddFlags,
+    ) -> Result<Option<f64>, ZsetError> {
+        if !increment.is_finite() {
+            return Err(ZsetError::NotFloat);
+        }
+
+        let now = Instant::now();
+        self.remove_if_expired(key, now);
+
+        let outcome = match self.data.get_mut(key) {
+            Some(mut entry) => match entry.value_mut() {
+                StorageValue::Zset { value, .. } => value.upsert(member, increment, true, flags)?,
+                _ => return Err(ZsetError::WrongType),
+            },
+            None if flags.xx => ZaddOutcome::Skipped,
+            None => {
+                let mut inner = ZSetInner::default();
+                let outcome = inner.upsert(member, increment, true, flags)?;
+                self.data.insert(
+                    key.to_string(),
+                    StorageValue::Zset {
+                        value: inner,
+                        expires_at: None,
+                    },
+                );
+                outcome
+            }
+        };
+
+        match outcome {
+            ZaddOutcome::Skipped => Ok(None),
+            ZaddOutcome::Unchanged(score) => Ok(Some(score)),
+            ZaddOutcome::Added(score) | ZaddOutcome::Updated(score) => {
+                self.touch_key(key);
+                self.bump_key_version(key);
+                Ok(Some(score))
+            }
+        }
     }
 
     pub fn zcard(&self, key: &str) -> Result<usize, ()> {
@@ -1821,7 +2366,7 @@ impl Storage {
         }
     }
 
-    pub fn zrem(&self, key: &str, members: &[String]) -> Result<usize, ()> {
+    pub fn zrem(&self, key: &str, members: &[ByteString]) -> Result<usize, ()> {
         let now = Instant::now();
         if self.remove_if_expired(key, now) {
             return Ok(0);
@@ -1855,7 +2400,7 @@ impl Storage {
         Ok(removed)
     }
 
-    pub fn zscore(&self, key: &str, member: &str) -> Result<Option<f64>, ()> {
+    pub fn zscore(&self, key: &str, member: &[u8]) -> Result<Option<f64>, ()> {
         let now = Instant::now();
         if self.remove_if_expired(key, now) {
             return Ok(None);
@@ -1871,62 +2416,10 @@ impl Storage {
         }
     }
 
-    pub fn zincrby(&self, key: &str, increment: f64, member: &str) -> Result<f64, ZsetError> {
-        if !increment.is_finite() {
-            return Err(ZsetError::NotFloat);
-        }
-
-        let now = Instant::now();
-        self.remove_if_expired(key, now);
-
-        let new_score: f64;
-
-        if let Some(mut entry) = self.data.get_mut(key) {
-            match entry.value_mut() {
-                StorageValue::Zset { value, .. } => {
-                    let old = value.by_member.get(member).cloned().unwrap_or(0.0);
-                    new_score = old + increment;
-                    if !new_score.is_finite() {
-                        return Err(ZsetError::NotFloat);
-                    }
-                    let _ = value
-                        .by_score
-                        .remove(&(OrderedFloat(old), member.to_string()));
-                    value
-                        .by_score
-                        .insert((OrderedFloat(new_score), member.to_string()));
-                    value
-                        .by_member
-                        .insert(member.to_string(), new_score);
-                }
-                _ => return Err(ZsetError::WrongType),
-            }
-        } else {
-            new_score = increment;
-            let mut inner = ZSetInner {
-                by_member: HashMap::new(),
-                by_score: BTreeSet::new(),
-            };
-            inner
-                .by_member
-                .insert(member.to_string(), new_score);
-            inner
-                .by_score
-                .insert((OrderedFloat(new_score), member.to_string()));
-            self.data.insert(
-                key.to_string(),
-                StorageValue::Zset {
-                    value: inner,
-                    expires_at: None,
-                },
-            );
-        }
-
-        self.touch_key(key);
-        self.bump_key_version(key);
-        self.maybe_evict_for_write();
-
-        Ok(new_score)
+    pub fn zincrby(&self, key: &str, increment: f64, member: &[u8]) -> Result<f64, ZsetError> {
+        // 不带选项时不会被跳过，成员不存在时按 0 分新增
+        self.zadd_incr(key, increment, member, ZaddFlags::default())
+            .map(|score| score.unwrap_or(increment))
     }
 
     pub fn zrange(
@@ -1935,7 +2428,7 @@ impl Storage {
         start: isize,
         stop: isize,
         rev: bool,
-    ) -> Result<Vec<(String, f64)>, ()> {
+    ) -> Result<Vec<(ByteString, f64)>, ()> {
         let now = Instant::now();
         if self.remove_if_expired(key, now) {
             return Ok(Vec::new());
@@ -1951,7 +2444,7 @@ impl Storage {
             _ => return Err(()),
         };
 
-        let mut items: Vec<(String, f64)> = zset
+        let mut items: Vec<(ByteString, f64)> = zset
             .by_score
             .iter()
             .map(|(score, member)| (member.clone(), score.0))
@@ -2003,7 +2496,7 @@ impl Storage {
         Ok(result)
     }
 
-    pub fn zscan_entries(&self, key: &str) -> Result<Vec<(String, f64)>, ()> {
+    pub fn zscan_entries(&self, key: &str) -> Result<Vec<(ByteString, f64)>, ()> {
         let now = Instant::now();
         if self.remove_if_expired(key, now) {
             return Ok(Vec::new());
@@ -2019,7 +2512,7 @@ impl Storage {
             _ => return Err(()),
         };
 
-        let items: Vec<(String, f64)> = zset
+        let items: Vec<(ByteString, f64)> = zset
             .by_score
             .iter()
             .map(|(score, member)| (member.clone(), score.0))
@@ -2029,147 +2522,243 @@ impl Storage {
     }
 
     pub fn save_rdb<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
-        let mut file = File::create(path)?;
+        self.save_rdb_with_compression(path, RdbCompression::None)
+    }
 
-        file.write_all(b"REDUSTDB")?;
-        file.write_all(&1u32.to_le_bytes())?;
+    /// 按指定压缩方式保存 RDB；压缩格式使用独立的 magic，加载时自动识别。
+    ///
+    /// 本身不经过写命令闸门，并发写入时需要时点一致的快照应先持有 [`Storage::snapshot_guard`]
+    /// 再调用 [`Storage::snapshot`]。
+    pub fn save_rdb_with_compression<P: AsRef<Path>>(
+        &self,
+        path: P,
+        compression: RdbCompression,
+    ) -> io::Result<()> {
+        self.save_rdb_snapshot(self.snapshot(), path, compression)
+    }
 
-        let now = Instant::now();
+    /// 写命令执行期间持有，等待进行中的快照拷贝完成
+    pub async fn write_guard(&self) -> OwnedRwLockReadGuard<()> {
+        self.write_gate.clone().read_owned().await
+    }
 
-        for entry in self.data.iter() {
-            let key = entry.key();
-            let value = entry.value();
+    /// 拷贝快照期间持有，等待进行中的写命令执行完，并阻止新的写命令开始
+    pub async fn snapshot_guard(&self) -> OwnedRwLockWriteGuard<()> {
+        self.write_gate.clone().write_owned().await
+    }
 
-            if Storage::value_is_expired(value, now) {
-                continue;
-            }
+    /// 深拷贝当前所有未过期的 key，之后的写入不影响返回的快照
+    pub fn snapshot(&self) -> RdbSnapshot {
+        let dirty = self.dirty();
+        let taken_at = Instant::now();
+        let entries = self
+            .data
+            .iter()
+            .filter(|entry| !Storage::value_is_expired(entry.value(), taken_at))
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
+        RdbSnapshot {
+            entries,
+            dirty,
+            taken_at,
+            taken_at_unix_ms: Self::unix_millis_now(),
+        }
+    }
 
-            let (type_byte, expires_millis) = match value {
-                StorageValue::String { expires_at, .. } => {
-                    (0u8, Self::remaining_millis(*expires_at, now))
-                }
-                StorageValue::List { expires_at, .. } => {
-                    (1u8, Self::remaining_millis(*expires_at, now))
-                }
-                StorageValue::Set { expires_at, .. } => {
-                    (2u8, Self::remaining_millis(*expires_at, now))
-                }
-                StorageValue::Hash { expires_at, .. } => {
-                    (3u8, Self::remaining_millis(*expires_at, now))
-                }
-                StorageValue::Zset { expires_at, .. } => {
-                    (4u8, Self::remaining_millis(*expires_at, now))
-                }
-                StorageValue::HyperLogLog { expires_at, .. } => {
-                    (5u8, Self::remaining_millis(*expires_at, now))
+    /// 把快照序列化为 RDB 文件；TTL 按快照生成的时刻换算
+    pub fn save_rdb_snapshot<P: AsRef<Path>>(
+        &self,
+        snapshot: RdbSnapshot,
+        path: P,
+        compression: RdbCompression,
+    ) -> io::Result<()> {
+        // 保存成功后只扣除快照开始前的写入次数，保存期间的新写入仍计入 dirty
+        let dirty_before = snapshot.dirty;
+
+        // 记录流先写入内存缓冲，便于压缩和计算尾部 CRC64
+        let mut body: Vec<u8> = Vec::new();
+
+        let now = snapshot.taken_at;
+        let now_unix_ms = snapshot.taken_at_unix_ms;
+
+        for (key, value) in &snapshot.entries {
+            // v2 起记录绝对过期时间（Unix 毫秒），重启后按真实流逝的时间计算剩余 TTL
+            let (type_byte, expires_at) = match value {
+                StorageValue::String { expires_at, .. } => (0u8, *expires_at),
+                StorageValue::List { expires_at, .. } => (1u8, *expires_at),
+                StorageValue::Set { expires_at, .. } => (2u8, *expires_at),
+                StorageValue::Hash { expires_at, .. } => (3u8, *expires_at),
+                StorageValue::Zset { expires_at, .. } => (4u8, *expires_at),
+                // 稀疏 HyperLogLog 使用单独的类型 6，按原样保存非零寄存器
+                StorageValue::HyperLogLog { value, expires_at } => {
+                    let type_byte = if value.sparse_entries().is_some() { 6u8 } else { 5u8 };
+                    (type_byte, *expires_at)
                 }
             };
+            let expires_millis = match expires_at {
+                None => -1,
+                Some(_) => now_unix_ms + Self::remaining_millis(expires_at, now),
+            };
 
-            file.write_all(&[type_byte])?;
-            file.write_all(&expires_millis.to_le_bytes())?;
+            body.write_all(&[type_byte])?;
+            body.write_all(&expires_millis.to_le_bytes())?;
 
             let key_bytes = key.as_bytes();
             let key_len = key_bytes.len() as u32;
-            file.write_all(&key_len.to_le_bytes())?;
-            file.write_all(key_bytes)?;
+            body.write_all(&key_len.to_le_bytes())?;
+            body.write_all(key_bytes)?;
 
             match value {
                 StorageValue::String { value, .. } => {
                     let v_len = value.len() as u32;
-                    file.write_all(&v_len.to_le_bytes())?;
-                    file.write_all(value)?;
+                    body.write_all(&v_len.to_le_bytes())?;
+                    body.write_all(value)?;
                 }
                 StorageValue::List { value: list, .. } => {
                     let len = list.len() as u32;
-                    file.write_all(&len.to_le_bytes())?;
+                    body.write_all(&len.to_le_bytes())?;
                     for item in list.iter() {
-                        let b = item.as_bytes();
-                        let l = b.len() as u32;
-                        file.write_all(&l.to_le_bytes())?;
-                        file.write_all(b)?;
+                        let l = item.len() as u32;
+                        body.write_all(&l.to_le_bytes())?;
+                        body.write_all(item)?;
                     }
                 }
                 StorageValue::Set { value: set, .. } => {
                     let len = set.len() as u32;
-                    file.write_all(&len.to_le_bytes())?;
+                    body.write_all(&len.to_le_bytes())?;
                     for member in set.iter() {
-                        let b = member.as_bytes();
-                        let l = b.len() as u32;
-                        file.write_all(&l.to_le_bytes())?;
-                        file.write_all(b)?;
+                        let l = member.len() as u32;
+                        body.write_all(&l.to_le_bytes())?;
+                        body.write_all(member)?;
                     }
                 }
                 StorageValue::Hash { value: map, .. } => {
                     let len = map.len() as u32;
-                    file.write_all(&len.to_le_bytes())?;
+                    body.write_all(&len.to_le_bytes())?;
                     for (field, val) in map.iter() {
                         let f_bytes = field.as_bytes();
                         let f_len = f_bytes.len() as u32;
-                        file.write_all(&f_len.to_le_bytes())?;
-                        file.write_all(f_bytes)?;
+                        body.write_all(&f_len.to_le_bytes())?;
+                        body.write_all(f_bytes)?;
 
-                        let v_bytes = val.as_bytes();
-                        let v_len = v_bytes.len() as u32;
-                        file.write_all(&v_len.to_le_bytes())?;
-                        file.write_all(v_bytes)?;
+                        let v_len = val.len() as u32;
+                        body.write_all(&v_len.to_le_bytes())?;
+                        body.write_all(val)?;
                     }
                 }
                 StorageValue::Zset { value: zset, .. } => {
                     // 序列化 ZSET: 元素数量 + (score, member) 对
                     let len = zset.by_member.len() as u32;
-                    file.write_all(&len.to_le_bytes())?;
+                    body.write_all(&len.to_le_bytes())?;
                     for (member, score) in zset.by_member.iter() {
                         // 写入 score (f64, 8 bytes)
-                        file.write_all(&score.to_le_bytes())?;
+                        body.write_all(&score.to_le_bytes())?;
                         // 写入 member
-                        let m_bytes = member.as_bytes();
-                        let m_len = m_bytes.len() as u32;
-                        file.write_all(&m_len.to_le_bytes())?;
-                        file.write_all(m_bytes)?;
+                        let m_len = member.len() as u32;
+                        body.write_all(&m_len.to_le_bytes())?;
+                        body.write_all(member)?;
                     }
                 }
-                StorageValue::HyperLogLog { value: hll, .. } => {
-                    // 序列化 HyperLogLog: 直接写入 16384 个寄存器
-                    let registers = hll.registers();
-                    file.write_all(registers)?;
-                }
+                StorageValue::HyperLogLog { value: hll, .. } => match hll.sparse_entries() {
+                    // 稀疏表示: 条目数 + (u16 索引, u8 值) 对
+                    Some(entries) => {
+                        let len = entries.len() as u32;
+                        body.write_all(&len.to_le_bytes())?;
+                        for &(index, value) in entries {
+                            body.write_all(&index.to_le_bytes())?;
+                            body.write_all(&[value])?;
+                        }
+                    }
+                    // 密集表示: 16384 个寄存器
+                    None => {
+                        let registers = hll.registers();
+                        body.write_all(&registers)?;
+                    }
+                },
             }
         }
 
+        let (magic, body) = match compression {
+            RdbCompression::None => (RDB_MAGIC, body),
+            RdbCompression::Zstd => (RDB_ZSTD_MAGIC, zstd::encode_all(&body[..], 0)?),
+        };
+        let checksum = crc64::crc64(&body);
+
+        // 先写临时文件并 fsync，再原子 rename 覆盖正式路径：
+        // 任意时刻磁盘上的快照要么是完整的旧版本，要么是完整的新版本。
+        let path_ref = path.as_ref();
+        let tmp_path = Self::rdb_temp_path(path_ref);
+        let write_res = (|| -> io::Result<()> {
+            let mut file = File::create(&tmp_path)?;
+            file.write_all(magic)?;
+            file.write_all(&RDB_VERSION.to_le_bytes())?;
+            file.write_all(&body)?;
+            file.write_all(&checksum.to_le_bytes())?;
+            file.sync_all()
+        })();
+        if let Err(e) = write_res {
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+
+        std::fs::rename(&tmp_path, path_ref)?;
+
+        // rename 之后 fsync 所在目录，确保目录项的更新也已落盘
+        let dir = match path_ref.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _ => Path::new("."),
+        };
+        if let Ok(dir_file) = File::open(dir) {
+            let _ = dir_file.sync_all();
+        }
+
+        // 饱和扣减：即使 dirty 已被其他途径清零也不会回绕成巨大的值
+        let _ = self
+            .dirty
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |d| {
+                Some(d.saturating_sub(dirty_before))
+            });
+
         Ok(())
     }
 
+    /// 保存 RDB 时使用的临时文件路径：`<path>.tmp`
+    pub fn rdb_temp_path(path: &Path) -> std::path::PathBuf {
+        let mut name = path.as_os_str().to_os_string();
+        name.push(".tmp");
+        std::path::PathBuf::from(name)
+    }
+
     pub fn load_rdb<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
         let path_ref = path.as_ref();
         if !path_ref.exists() {
             return Ok(());
         }
 
-        let mut file = File::open(path_ref)?;
-
-        let mut magic = [0u8; 8];
-        if file.read_exact(&mut magic).is_err() {
-            return Ok(());
-        }
-        if &magic != b"REDUSTDB" {
-            return Ok(());
-        }
-
-        let mut version_bytes = [0u8; 4];
-        if file.read_exact(&mut version_bytes).is_err() {
-            return Ok(());
-        }
-        let version = u32::from_le_bytes(version_bytes);
-        if version != 1 {
-            return Ok(());
-        }
-
-        self.data.clear();
-        self.last_access.clear();
+        let bytes = std::fs::read(path_ref)?;
+        let (version, body) = match Self::verify_rdb(&bytes) {
+            Ok(v) => v,
+            Err(e) => {
+                // 校验失败：把损坏文件挪到一边，避免下次保存时被覆盖，便于事后排查
+                let quarantine = Self::rdb_quarantine_path(path_ref);
+                match std::fs::rename(path_ref, &quarantine) {
+                    Ok(()) => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("{} (moved to {})", e, quarantine.display()),
+                        ))
+                    }
+                    Err(_) => return Err(e),
+                }
+            }
+        };
+        let mut reader = io::Cursor::new(body);
+        // 先解析到独立的列表中，全部读完后才替换内存数据：中途出错时原有数据保持不变
+        let mut loaded: Vec<(String, StorageValue, Option<Instant>)> = Vec::new();
 
-        loop {
+        'records: loop {
             let mut type_buf = [0u8; 1];
-            match file.read_exact(&mut type_buf) {
+            match reader.read_exact(&mut type_buf) {
                 Ok(()) => {}
                 Err(e) => {
                     if e.kind() == io::ErrorKind::UnexpectedEof {
@@ -2181,46 +2770,50 @@ impl Storage {
             }
 
             let mut expires_buf = [0u8; 8];
-            if file.read_exact(&mut expires_buf).is_err() {
+            if reader.read_exact(&mut expires_buf).is_err() {
                 break;
             }
             let expires_millis = i64::from_le_bytes(expires_buf);
 
             let mut key_len_buf = [0u8; 4];
-            if file.read_exact(&mut key_len_buf).is_err() {
+            if reader.read_exact(&mut key_len_buf).is_err() {
                 break;
             }
             let key_len = u32::from_le_bytes(key_len_buf) as usize;
             let mut key_bytes = vec![0u8; key_len];
-            if file.read_exact(&mut key_bytes).is_err() {
+            if reader.read_exact(&mut key_bytes).is_err() {
                 break;
             }
             let key = match String::from_utf8(key_bytes) {
                 Ok(s) => s,
                 Err(_) => {
-                    return Ok(());
+                    break 'records;
                 }
             };
 
             let now = Instant::now();
-            let expires_at = if expires_millis < 0 {
+            // v1 记录的是保存时刻的剩余毫秒数，v2 记录的是绝对过期时间（Unix 毫秒）
+            let remaining_millis = if version == 1 || expires_millis < 0 {
+                expires_millis
+            } else {
+                (expires_millis - Self::unix_millis_now()).max(0)
+            };
+            let expires_at = if remaining_millis < 0 {
                 None
-            } else if expires_millis == 0 {
-                continue;
             } else {
-                Some(now + Duration::from_millis(expires_millis as u64))
+                Some(now + Duration::from_millis(remaining_millis as u64))
             };
 
             let t = type_buf[0];
             let value = match t {
                 0 => {
                     let mut len_buf = [0u8; 4];
-                    if file.read_exact(&mut len_buf).is_err() {
+                    if reader.read_exact(&mut len_buf).is_err() {
                         break;
                     }
                     let len = u32::from_le_bytes(len_buf) as usize;
                     let mut v = vec![0u8; len];
-                    if file.read_exact(&mut v).is_err() {
+                    if reader.read_exact(&mut v).is_err() {
                         break;
                     }
                     StorageValue::String {
@@ -2230,28 +2823,22 @@ impl Storage {
                 }
                 1 => {
                     let mut len_buf = [0u8; 4];
-                    if file.read_exact(&mut len_buf).is_err() {
+                    if reader.read_exact(&mut len_buf).is_err() {
                         break;
                     }
                     let len = u32::from_le_bytes(len_buf) as usize;
                     let mut list = VecDeque::with_capacity(len);
                     for _ in 0..len {
                         let mut ilen_buf = [0u8; 4];
-                        if file.read_exact(&mut ilen_buf).is_err() {
+                        if reader.read_exact(&mut ilen_buf).is_err() {
                             break;
                         }
                         let ilen = u32::from_le_bytes(ilen_buf) as usize;
                         let mut item = vec![0u8; ilen];
-                        if file.read_exact(&mut item).is_err() {
+                        if reader.read_exact(&mut item).is_err() {
                             break;
                         }
-                        let s = match String::from_utf8(item) {
-                            Ok(s) => s,
-                            Err(_) => {
-                                return Ok(());
-                            }
-                        };
-                        list.push_back(s);
+                        list.push_back(item);
                     }
                     StorageValue::List {
                         value: list,
@@ -2260,28 +2847,22 @@ impl Storage {
                 }
                 2 => {
                     let mut len_buf = [0u8; 4];
-                    if file.read_exact(&mut len_buf).is_err() {
+                    if reader.read_exact(&mut len_buf).is_err() {
                         break;
                     }
                     let len = u32::from_le_bytes(len_buf) as usize;
                     let mut set = HashSet::with_capacity(len);
                     for _ in 0..len {
                         let mut mlen_buf = [0u8; 4];
-                        if file.read_exact(&mut mlen_buf).is_err() {
+                        if reader.read_exact(&mut mlen_buf).is_err() {
                             break;
                         }
                         let mlen = u32::from_le_bytes(mlen_buf) as usize;
                         let mut member = vec![0u8; mlen];
-                        if file.read_exact(&mut member).is_err() {
+                        if reader.read_exact(&mut member).is_err() {
                             break;
                         }
-                        let s = match String::from_utf8(member) {
-                            Ok(s) => s,
-                            Err(_) => {
-                                return Ok(());
-                            }
-                        };
-                        set.insert(s);
+                        set.insert(member);
                     }
                     StorageValue::Set {
                         value: set,
@@ -2290,45 +2871,38 @@ impl Storage {
                 }
                 3 => {
                     let mut len_buf = [0u8; 4];
-                    if file.read_exact(&mut len_buf).is_err() {
+                    if reader.read_exact(&mut len_buf).is_err() {
                         break;
                     }
                     let len = u32::from_le_bytes(len_buf) as usize;
                     let mut map = HashMap::with_capacity(len);
                     for _ in 0..len {
                         let mut flen_buf = [0u8; 4];
-                        if file.read_exact(&mut flen_buf).is_err() {
+                        if reader.read_exact(&mut flen_buf).is_err() {
                             break;
                         }
                         let flen = u32::from_le_bytes(flen_buf) as usize;
                         let mut field = vec![0u8; flen];
-                        if file.read_exact(&mut field).is_err() {
+                        if reader.read_exact(&mut field).is_err() {
                             break;
                         }
                         let field_str = match String::from_utf8(field) {
                             Ok(s) => s,
                             Err(_) => {
-                                return Ok(());
+                                break 'records;
                             }
                         };
 
                         let mut vlen_buf = [0u8; 4];
-                        if file.read_exact(&mut vlen_buf).is_err() {
+                        if reader.read_exact(&mut vlen_buf).is_err() {
                             break;
                         }
                         let vlen = u32::from_le_bytes(vlen_buf) as usize;
                         let mut val = vec![0u8; vlen];
-                        if file.read_exact(&mut val).is_err() {
+                        if reader.read_exact(&mut val).is_err() {
                             break;
                         }
-                        let val_str = match String::from_utf8(val) {
-                            Ok(s) => s,
-                            Err(_) => {
-                                return Ok(());
-                            }
-                        };
-
-                        map.insert(field_str, val_str);
+                        map.insert(field_str, val);
                     }
                     StorageValue::Hash {
                         value: map,
@@ -2338,7 +2912,7 @@ impl Storage {
                 4 => {
                     // ZSET 类型
                     let mut len_buf = [0u8; 4];
-                    if file.read_exact(&mut len_buf).is_err() {
+                    if reader.read_exact(&mut len_buf).is_err() {
                         break;
                     }
                     let len = u32::from_le_bytes(len_buf) as usize;
@@ -2347,30 +2921,23 @@ impl Storage {
                     for _ in 0..len {
                         // 读取 score (f64, 8 bytes)
                         let mut score_buf = [0u8; 8];
-                        if file.read_exact(&mut score_buf).is_err() {
+                        if reader.read_exact(&mut score_buf).is_err() {
                             break;
                         }
                         let score = f64::from_le_bytes(score_buf);
 
                         // 读取 member
                         let mut mlen_buf = [0u8; 4];
-                        if file.read_exact(&mut mlen_buf).is_err() {
+                        if reader.read_exact(&mut mlen_buf).is_err() {
                             break;
                         }
                         let mlen = u32::from_le_bytes(mlen_buf) as usize;
                         let mut member = vec![0u8; mlen];
-                        if file.read_exact(&mut member).is_err() {
+                        if reader.read_exact(&mut member).is_err() {
                             break;
                         }
-                        let member_str = match String::from_utf8(member) {
-                            Ok(s) => s,
-                            Err(_) => {
-                                return Ok(());
-                            }
-                        };
-
-                        by_member.insert(member_str.clone(), score);
-                        by_score.insert((OrderedFloat(score), member_str));
+                        by_member.insert(member.clone(), score);
+                        by_score.insert((OrderedFloat(score), member));
                     }
                     StorageValue::Zset {
                         value: ZSetInner { by_member, by_score },
@@ -2380,13 +2947,40 @@ impl Storage {
                 5 => {
                     // 反序列化 HyperLogLog: 读取 16384 个寄存器
                     let mut registers = vec![0u8; 16384];
-                    if file.read_exact(&mut registers).is_err() {
+                    if reader.read_exact(&mut registers).is_err() {
                         break;
                     }
                     let hll = match HyperLogLog::from_registers(registers) {
                         Some(h) => h,
                         None => {
-                            return Ok(());
+                            break 'records;
+                        }
+                    };
+                    StorageValue::HyperLogLog {
+                        value: hll,
+                        expires_at,
+                    }
+                }
+                6 => {
+                    // 稀疏 HyperLogLog: 条目数 + (u16 索引, u8 值) 对
+                    let mut len_buf = [0u8; 4];
+                    if reader.read_exact(&mut len_buf).is_err() {
+                        break;
+                    }
+                    let len = u32::from_le_bytes(len_buf) as usize;
+                    let mut entries = Vec::with_capacity(len.min(16384));
+                    for _ in 0..len {
+                        let mut entry_buf = [0u8; 3];
+                        if reader.read_exact(&mut entry_buf).is_err() {
+                            break;
+                        }
+                        let index = u16::from_le_bytes([entry_buf[0], entry_buf[1]]);
+                        entries.push((index, entry_buf[2]));
+                    }
+                    let hll = match HyperLogLog::from_sparse_entries(entries) {
+                        Some(h) => h,
+                        None => {
+                            break 'records;
                         }
                     };
                     StorageValue::HyperLogLog {
@@ -2395,17 +2989,103 @@ impl Storage {
                     }
                 }
                 _ => {
-                    return Ok(());
+                    break 'records;
                 }
             };
 
+            // 已过期的记录仍需完整读出 value，保证后续记录对齐，但不再写入内存
+            if remaining_millis == 0 {
+                continue;
+            }
+
+            loaded.push((key, value, expires_at));
+        }
+
+        // flushall 会递增所有旧 key 的版本号，使 WATCH 失效
+        self.flushall();
+        for (key, value, expires_at) in loaded {
+            self.schedule_expiry(&key, expires_at);
             self.data.insert(key, value);
         }
 
         Ok(())
     }
 
+    /// 校验 RDB 文件头与尾部 CRC64，返回版本号与记录流部分。
+    ///
+    /// 旧格式（magic 为 `REDUSTDB`）没有校验和，仅检查版本号；
+    /// zstd 压缩格式（magic 为 `REDUSTZ1`）在校验通过后解压出记录流。
+    fn verify_rdb(bytes: &[u8]) -> io::Result<(u32, Cow<'_, [u8]>)> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+        if bytes.len() < 12 {
+            return Err(invalid("RDB file too short"));
+        }
+        let magic = &bytes[..8];
+        let version = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+
+        if magic == RDB_LEGACY_MAGIC {
+            if version != 1 && version != 2 {
+                return Err(invalid("unsupported RDB version"));
+            }
+            return Ok((version, Cow::Borrowed(&bytes[12..])));
+        }
+        let compressed = magic == RDB_ZSTD_MAGIC;
+        if magic != RDB_MAGIC && !compressed {
+            return Err(invalid("bad RDB magic header"));
+        }
+        if version != RDB_VERSION {
+            return Err(invalid("unsupported RDB version"));
+        }
+        if bytes.len() < 12 + 8 {
+            return Err(invalid("RDB file truncated: missing checksum"));
+        }
+
+        let (body, trailer) = bytes[12..].split_at(bytes.len() - 12 - 8);
+        let mut crc_buf = [0u8; 8];
+        crc_buf.copy_from_slice(trailer);
+        let expected = u64::from_le_bytes(crc_buf);
+        if crc64::crc64(body) != expected {
+            return Err(invalid("RDB checksum mismatch"));
+        }
+
+        if compressed {
+            let decoded = zstd::decode_all(body)
+                .map_err(|e| invalid(&format!("RDB decompression failed: {}", e)))?;
+            return Ok((version, Cow::Owned(decoded)));
+        }
+        Ok((version, Cow::Borrowed(body)))
+    }
+
+    /// 损坏 RDB 文件的隔离路径：`<path>.corrupt`
+    pub fn rdb_quarantine_path(path: &Path) -> std::path::PathBuf {
+        let mut name = path.as_os_str().to_os_string();
+        name.push(".corrupt");
+        std::path::PathBuf::from(name)
+    }
+
+    /// 设置过期抖动上限：之后每次设置 TTL 都会在 [0, jitter_ms] 内随机后移到期时间，
+    /// 让同一时刻写入、TTL 相同的大批 key 分散过期；0 表示关闭
+    pub fn set_expire_jitter_ms(&self, jitter_ms: u64) {
+        self.expire_jitter_ms.store(jitter_ms, Ordering::Relaxed);
+    }
+
+    /// 由 TTL 计算到期时间，开启抖动时叠加随机偏移
+    fn jittered_deadline(&self, now: Instant, ttl: Duration) -> Instant {
+        let jitter_ms = self.expire_jitter_ms.load(Ordering::Relaxed);
+        if jitter_ms == 0 {
+            return now + ttl;
+        }
+        now + ttl + Duration::from_millis(thread_rng().gen_range(0..=jitter_ms))
+    }
+
     pub fn expire_seconds(&self, key: &str, seconds: i64) -> bool {
+        // 已逻辑过期的 key 视为不存在，返回 0
+        let now = Instant::now();
+        if self.remove_if_expired(key, now) {
+            return false;
+        }
+
         // Redis 语义：seconds <= 0 视为立刻过期并删除，若 key 存在返回 1
         if seconds <= 0 {
             let existed = self.data.remove(key).is_some();
@@ -2416,16 +3096,11 @@ impl Storage {
             return existed;
         }
 
-        let now = Instant::now();
-        if self.remove_if_expired(key, now) {
-            return false;
-        }
-
         let Some(mut entry) = self.data.get_mut(key) else {
             return false;
         };
 
-        let deadline = now + Duration::from_secs(seconds as u64);
+        let deadline = self.jittered_deadline(now, Duration::from_secs(seconds as u64));
 
         match entry.value_mut() {
             StorageValue::String { expires_at, .. }
@@ -2435,6 +3110,8 @@ impl Storage {
             | StorageValue::Zset { expires_at, .. }
             | StorageValue::HyperLogLog { expires_at, .. } => {
                 *expires_at = Some(deadline);
+                drop(entry);
+                self.schedule_expiry(key, Some(deadline));
                 self.bump_key_version(key);
                 true
             }
@@ -2442,6 +3119,21 @@ impl Storage {
     }
 
     pub fn expire_millis(&self, key: &str, millis: i64) -> bool {
+        self.expire_millis_inner(key, millis, true)
+    }
+
+    /// 按给定剩余毫秒设置 TTL 但不叠加过期抖动：用于 SET KEEPTTL 恢复覆盖前的剩余 TTL
+    /// （避免反复 KEEPTTL 让到期时间不断后移），以及 SET EXAT/PXAT 这类绝对到期时间
+    pub fn restore_ttl_millis(&self, key: &str, millis: i64) -> bool {
+        self.expire_millis_inner(key, millis, false)
+    }
+
+    fn expire_millis_inner(&self, key: &str, millis: i64, jitter: bool) -> bool {
+        let now = Instant::now();
+        if self.remove_if_expired(key, now) {
+            return false;
+        }
+
         if millis <= 0 {
             let existed = self.data.remove(key).is_some();
             if existed {
@@ -2451,16 +3143,16 @@ impl Storage {
             return existed;
         }
 
-        let now = Instant::now();
-        if self.remove_if_expired(key, now) {
-            return false;
-        }
-
         let Some(mut entry) = self.data.get_mut(key) else {
             return false;
         };
 
-        let deadline = now + Duration::from_millis(millis as u64);
+        let ttl = Duration::from_millis(millis as u64);
+        let deadline = if jitter {
+            self.jittered_deadline(now, ttl)
+        } else {
+            now + ttl
+        };
 
         match entry.value_mut() {
             StorageValue::String { expires_at, .. }
@@ -2470,6 +3162,8 @@ impl Storage {
             | StorageValue::Zset { expires_at, .. }
             | StorageValue::HyperLogLog { expires_at, .. } => {
                 *expires_at = Some(deadline);
+                drop(entry);
+                self.schedule_expiry(key, Some(deadline));
                 self.bump_key_version(key);
                 true
             }
@@ -2580,7 +3274,7 @@ impl Storage {
         }
     }
 
-    pub fn sinter(&self, keys: &[String]) -> Result<Vec<String>, ()> {
+    pub fn sinter(&self, keys: &[String]) -> Result<Vec<ByteString>, ()> {
         if keys.is_empty() {
             return Ok(Vec::new());
         }
@@ -2632,7 +3326,7 @@ impl Storage {
             return Err(());
         };
 
-        let mut result: HashSet<String> = HashSet::new();
+        let mut result: HashSet<ByteString> = HashSet::new();
 
         'outer: for member in smallest_set.iter() {
             for (j, key) in keys.iter().enumerate() {
@@ -2663,12 +3357,12 @@ impl Storage {
             result.insert(member.clone());
         }
 
-        let mut members: Vec<String> = result.into_iter().collect();
+        let mut members: Vec<ByteString> = result.into_iter().collect();
         members.sort();
         Ok(members)
     }
 
-    pub fn sdiff(&self, keys: &[String]) -> Result<Vec<String>, ()> {
+    pub fn sdiff(&self, keys: &[String]) -> Result<Vec<ByteString>, ()> {
         if keys.is_empty() {
             return Ok(Vec::new());
         }
@@ -2687,7 +3381,7 @@ impl Storage {
             _ => return Err(()),           // WRONGTYPE
         };
 
-        let mut result: HashSet<String> = first_set.iter().cloned().collect();
+        let mut result: HashSet<ByteString> = first_set.iter().cloned().collect();
 
         for key in &keys[1..] {
             let now = Instant::now();
@@ -2707,7 +3401,7 @@ impl Storage {
             }
         }
 
-        let mut members: Vec<String> = result.into_iter().collect();
+        let mut members: Vec<ByteString> = result.into_iter().collect();
         members.sort();
         Ok(members)
     }
@@ -2728,6 +3422,13 @@ impl Storage {
         }
     }
 
+    fn unix_millis_now() -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as i64
+    }
+
     fn remaining_millis(expires_at: Option<Instant>, now: Instant) -> i64 {
         match expires_at {
             None => -1,
@@ -2755,6 +3456,7 @@ impl Storage {
             if self.data.remove(key).is_some() {
                 self.last_access.remove(key);
                 self.bump_key_version(key);
+                self.expired_keys.fetch_add(1, Ordering::Relaxed);
             }
             true
         } else {
@@ -2762,37 +3464,188 @@ impl Storage {
         }
     }
 
+    /// 把 key 登记到过期堆；`deadline` 为 None（没有 TTL）时不做任何事
+    fn schedule_expiry(&self, key: &str, deadline: Option<Instant>) {
+        if let Some(deadline) = deadline {
+            self.expiry_queue.lock().unwrap().schedule(key, deadline);
+        }
+    }
+
+    /// 从过期堆中弹出最多 `limit` 条到期记录并删除已过期的 key，返回 (被删除的 key, 弹出的记录数)。
+    /// 堆中的旧记录在这里校正：TTL 被延后的 key 按新时间重新登记，已无 TTL 或已删除的 key 直接丢弃。
+    /// 旧记录同样占用批次额度，调用方应以弹出数（而不是删除数）判断堆中是否还有到期记录
+    pub fn expire_due_keys(&self, now: Instant, limit: usize) -> (Vec<String>, usize) {
+        let mut expired = Vec::new();
+        let mut popped = 0;
+        while popped < limit {
+            let Some(key) = self.expiry_queue.lock().unwrap().pop_due(now) else {
+                break;
+            };
+            popped += 1;
+            if self.remove_if_expired(&key, now) {
+                expired.push(key);
+                continue;
+            }
+            let deadline = self.data.get(&key).and_then(|entry| entry.value().expires_at());
+            self.schedule_expiry(&key, deadline);
+        }
+        (expired, popped)
+    }
+
+    /// 启动后台主动过期任务；每个被该任务删除的 key 都会发出 `expired` 键空间事件。
+    ///
+    /// 每轮只处理过期堆中已到期的 key，开销与到期 key 数成正比，与带 TTL 的 key 总数无关；
+    /// 单批弹出满 `batch` 条记录时让出执行权后继续，直到堆中没有到期的记录。
     pub fn spawn_expiration_task(&self) {
         let storage = self.clone();
         tokio::spawn(async move {
-            let sample_size: usize = 20;
+            let batch: usize = 1000;
             let interval = Duration::from_millis(100);
 
             loop {
                 tokio::time::sleep(interval).await;
-                let now = Instant::now();
 
-                let keys: Vec<String> = storage
-                    .data
-                    .iter()
-                    .map(|entry| entry.key().clone())
-                    .take(sample_size)
-                    .collect();
-
-                if keys.is_empty() {
-                    continue;
-                }
-
-                for key in keys {
-                    storage.remove_if_expired(&key, now);
+                loop {
+                    let (keys, popped) = storage.expire_due_keys(Instant::now(), batch);
+                    let done = popped < batch;
+                    for key in keys {
+                        storage.notify_keyspace_event(notify::NOTIFY_EXPIRED, "expired", &key);
+                    }
+                    if done {
+                        break;
+                    }
+                    tokio::task::yield_now().await;
                 }
             }
         });
     }
 
     fn touch_key(&self, key: &str) {
-        let ts = self.access_counter.fetch_add(1, Ordering::Relaxed);
-        self.last_access.insert(key.to_string(), ts);
+        self.last_access.insert(key.to_string(), Instant::now());
+    }
+
+    fn last_access_time(&self, key: &str) -> Instant {
+        self.last_access
+            .get(key)
+            .map(|v| *v.value())
+            .unwrap_or(self.created_at)
+    }
+
+    /// OBJECT IDLETIME：key 自上次被读写以来的时长；本身不算一次访问。key 不存在时返回 None
+    pub fn idle_time(&self, key: &str) -> Option<Duration> {
+        if self.remove_if_expired(key, Instant::now()) || !self.data.contains_key(key) {
+            return None;
+        }
+        Some(self.last_access_time(key).elapsed())
+    }
+
+    /// OBJECT ENCODING：按 Redis 的命名返回 value 的编码；本身不算一次访问，key 不存在时返回 None。
+    /// list 按当前元素数与阈值比较，在 listpack 与 quicklist 之间切换；
+    /// set 全为整数且不超过 set-max-intset-entries 时为 intset，否则按 set-max-listpack-entries
+    /// 在 listpack 与 hashtable 之间切换；string 区分 int/embstr/raw；其余类型固定报告 Redis 大对象时的编码
+    pub fn object_encoding(&self, key: &str) -> Option<&'static str> {
+        if self.remove_if_expired(key, Instant::now()) {
+            return None;
+        }
+        let entry = self.data.get(key)?;
+        let encoding = match entry.value() {
+            StorageValue::String { value, .. } => {
+                if is_canonical_i64(value) {
+                    "int"
+                } else if value.len() <= 44 {
+                    "embstr"
+                } else {
+                    "raw"
+                }
+            }
+            StorageValue::List { value: list, .. } => {
+                if list.len() <= self.list_max_listpack_entries.load(Ordering::Relaxed) {
+                    "listpack"
+                } else {
+                    "quicklist"
+                }
+            }
+            StorageValue::Set { value: set, .. } => {
+                if set.len() <= self.set_max_intset_entries()
+                    && set.iter().all(|m| is_canonical_i64(m))
+                {
+                    "intset"
+                } else if set.len() <= self.set_max_listpack_entries() {
+                    "listpack"
+                } else {
+                    "hashtable"
+                }
+            }
+            StorageValue::Hash { .. } => "hashtable",
+            StorageValue::Zset { .. } => "skiplist",
+            StorageValue::HyperLogLog { .. } => "raw",
+        };
+        Some(encoding)
+    }
+
+    /// DEBUG LISTPACK-ENTRIES：调整 list 报告为 listpack 的最大元素数，立即对已有 key 生效
+    pub fn set_list_max_listpack_entries(&self, entries: usize) {
+        self.list_max_listpack_entries.store(entries, Ordering::Relaxed);
+    }
+
+    pub fn set_max_intset_entries(&self) -> usize {
+        self.set_max_intset_entries.load(Ordering::Relaxed)
+    }
+
+    /// CONFIG SET set-max-intset-entries
+    pub fn set_set_max_intset_entries(&self, entries: usize) {
+        self.set_max_intset_entries.store(entries, Ordering::Relaxed);
+    }
+
+    pub fn set_max_listpack_entries(&self) -> usize {
+        self.set_max_listpack_entries.load(Ordering::Relaxed)
+    }
+
+    /// CONFIG SET set-max-listpack-entries
+    pub fn set_set_max_listpack_entries(&self, entries: usize) {
+        self.set_max_listpack_entries.store(entries, Ordering::Relaxed);
+    }
+
+    /// value 按 RDB 记录格式序列化后的字节数（不含类型、过期时间与 key 头部），
+    /// 供 DEBUG OBJECT 的 serializedlength 与 MEMORY USAGE 共用；本身不算一次访问。
+    /// 长度前缀均为 4 字节：string 为 4+len；list/set 为 4+Σ(4+len)；
+    /// hash 为 4+Σ(4+field+4+value)；zset 为 4+Σ(8+4+member)；
+    /// HyperLogLog 稀疏表示为 4+3×非零寄存器数，密集表示为 16384 个寄存器。
+    pub fn serialized_length(&self, key: &str) -> Option<u64> {
+        if self.remove_if_expired(key, Instant::now()) {
+            return None;
+        }
+        let entry = self.data.get(key)?;
+        const PREFIX: u64 = 4;
+        let len = match entry.value() {
+            StorageValue::String { value, .. } => PREFIX + value.len() as u64,
+            StorageValue::List { value: list, .. } => {
+                PREFIX + list.iter().map(|v| PREFIX + v.len() as u64).sum::<u64>()
+            }
+            StorageValue::Set { value: set, .. } => {
+                PREFIX + set.iter().map(|v| PREFIX + v.len() as u64).sum::<u64>()
+            }
+            StorageValue::Hash { value: map, .. } => {
+                PREFIX
+                    + map
+                        .iter()
+                        .map(|(f, v)| PREFIX + f.len() as u64 + PREFIX + v.len() as u64)
+                        .sum::<u64>()
+            }
+            StorageValue::Zset { value, .. } => {
+                PREFIX
+                    + value
+                        .by_member
+                        .keys()
+                        .map(|m| std::mem::size_of::<f64>() as u64 + PREFIX + m.len() as u64)
+                        .sum::<u64>()
+            }
+            StorageValue::HyperLogLog { value, .. } => match value.sparse_entries() {
+                Some(entries) => PREFIX + 3 * entries.len() as u64,
+                None => value.registers().len() as u64,
+            },
+        };
+        Some(len)
     }
 
     fn evict_one_sampled_key(&self) -> bool {
@@ -2811,19 +3664,11 @@ impl Storage {
             return false;
         }
 
-        let mut oldest_key: Option<String> = None;
-        let mut oldest_ts: u64 = u64::MAX;
-
-        for k in candidates.into_iter() {
-            let ts = self.last_access.get(&k).map(|v| *v.value()).unwrap_or(0);
-
-            if ts < oldest_ts {
-                oldest_ts = ts;
-                oldest_key = Some(k);
-            }
-        }
-
-        let Some(key) = oldest_key else {
+        // 候选中最久未被访问的 key
+        let Some(key) = candidates
+            .into_iter()
+            .min_by_key(|k| self.last_access_time(k))
+        else {
             return false;
         };
 
@@ -2833,25 +3678,78 @@ impl Storage {
         true
     }
 
-    pub fn maybe_evict_for_write(&self) {
-        let Some(limit) = self.maxmemory_bytes else {
-            return;
+    /// 写入会让某个 value 增长到 `new_len` 字节（增长 `growth` 字节）时，在分配前判断能否留在
+    /// maxmemory 以内：value 本身超过上限时淘汰其他 key 也放不下；noeviction 下已用内存加上增长不能超限
+    pub fn can_grow_within_maxmemory(&self, new_len: u64, growth: u64) -> bool {
+        let Some(limit) = self.maxmemory_bytes() else {
+            return true;
+        };
+        if new_len > limit {
+            return false;
+        }
+        match self.maxmemory_policy() {
+            MaxmemoryPolicy::AllkeysLru => true,
+            MaxmemoryPolicy::Noeviction => {
+                self.approximate_used_memory().saturating_add(growth) <= limit
+            }
+        }
+    }
+
+    /// 写命令执行前调用：超过 maxmemory 时按策略淘汰，返回 false 表示仍然超限，
+    /// 带 denyoom 标志的命令应返回 OOM
+    pub fn free_memory_if_needed(&self) -> bool {
+        let Some(limit) = self.maxmemory_bytes() else {
+            return true;
         };
+        let evict = self.maxmemory_policy() == MaxmemoryPolicy::AllkeysLru;
 
         // 简单实现：反复检查 approximate_used_memory，直到不再超限或没有可淘汰的键
         loop {
             let used = self.approximate_used_memory();
             if used <= limit {
-                break;
+                return true;
             }
 
-            if !self.evict_one_sampled_key() {
-                break;
+            if !evict || !self.evict_one_sampled_key() {
+                return false;
             }
         }
     }
 
+    pub fn maxmemory_policy(&self) -> MaxmemoryPolicy {
+        *self.maxmemory_policy.lock().unwrap()
+    }
+
+    pub fn set_maxmemory_policy(&self, policy: MaxmemoryPolicy) {
+        *self.maxmemory_policy.lock().unwrap() = policy;
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::Relaxed)
+    }
+
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::Relaxed);
+    }
+
+    /// 估算的内存用量，包含 DEBUG SET-USED-MEMORY 的修正量
     pub fn approximate_used_memory(&self) -> u64 {
+        let adjust = self.used_memory_adjust.load(Ordering::Relaxed);
+        self.estimate_data_memory().saturating_add_signed(adjust)
+    }
+
+    /// DEBUG SET-USED-MEMORY：让 approximate_used_memory 此刻报告 `bytes`，
+    /// 之后随数据增删同步变化（淘汰 key 会让报告值下降）；`bytes` 为 0 时取消修正
+    pub fn set_used_memory_override(&self, bytes: u64) {
+        let adjust = if bytes == 0 {
+            0
+        } else {
+            (bytes as i64).saturating_sub(self.estimate_data_memory() as i64)
+        };
+        self.used_memory_adjust.store(adjust, Ordering::Relaxed);
+    }
+
+    fn estimate_data_memory(&self) -> u64 {
         let mut total: u64 = 0;
 
         for entry in self.data.iter() {
@@ -2872,9 +3770,9 @@ impl Storage {
                         .map(|(member, _)| member.len() as u64 + std::mem::size_of::<f64>() as u64)
                         .sum()
                 }
-                StorageValue::HyperLogLog { .. } => {
-                    // HyperLogLog 固定占用约 12KB (16384 个 6-bit 寄存器)
-                    12288
+                StorageValue::HyperLogLog { value, .. } => {
+                    // 密集表示约 12KB (16384 个 6-bit 寄存器)，稀疏表示按非零寄存器计
+                    value.memory_usage() as u64
                 }
             };
 
@@ -2885,7 +3783,15 @@ impl Storage {
     }
 
     pub fn maxmemory_bytes(&self) -> Option<u64> {
-        self.maxmemory_bytes
+        match self.maxmemory_bytes.load(Ordering::Relaxed) {
+            0 => None,
+            bytes => Some(bytes),
+        }
+    }
+
+    /// CONFIG SET maxmemory：`bytes` 为 0 表示取消上限
+    pub fn set_maxmemory_bytes(&self, bytes: u64) {
+        self.maxmemory_bytes.store(bytes, Ordering::Relaxed);
     }
 
     // ========== HyperLogLog 操作 ==========
@@ -2937,6 +3843,24 @@ impl Storage {
         Ok(if modified { 1 } else { 0 })
     }
 
+    /// PFDEBUG: 读取 HyperLogLog 的寄存器副本与编码
+    ///
+    /// key 不存在返回 Ok(None)，不是 HyperLogLog 返回 Err
+    pub(crate) fn pfdebug(&self, key: &str) -> Result<Option<(Vec<u8>, &'static str)>, ()> {
+        let now = Instant::now();
+        self.remove_if_expired(key, now);
+
+        match self.data.get(key) {
+            Some(entry) => match entry.value() {
+                StorageValue::HyperLogLog { value: hll, .. } => {
+                    Ok(Some((hll.registers().into_owned(), hll.encoding())))
+                }
+                _ => Err(()),
+            },
+            None => Ok(None),
+        }
+    }
+
     /// PFCOUNT: 估算一个或多个 HyperLogLog 的基数
     ///
     /// 如果提供多个 key，会临时合并它们并返回并集的基数
@@ -2962,7 +3886,7 @@ impl Storage {
                 Ok(0) // key 不存在，返回 0
             }
         } else {
-            // 多个 key 的情况：临时合并
+            // 多个 key 的情况：合并到临时 HLL，只读输入、不修改也不缓存到任何源 key
             let mut merged = HyperLogLog::new();
 
             for key in keys {
@@ -1,4 +1,4 @@
-//! Integration tests for admin/ops commands (CONFIG, CLIENT, SLOWLOG)
+//! Integration tests for admin/ops commands (CONFIG, CLIENT, SLOWLOG, COMMAND)
 
 use std::net::SocketAddr;
 
@@ -31,16 +31,19 @@ async fn spawn_server() -> (
 struct TestClient {
     reader: BufReader<tokio::net::tcp::OwnedReadHalf>,
     writer: tokio::net::tcp::OwnedWriteHalf,
+    local_addr: SocketAddr,
 }
 
 impl TestClient {
     async fn connect(addr: SocketAddr) -> Self {
         let stream = TcpStream::connect(addr).await.unwrap();
+        let local_addr = stream.local_addr().unwrap();
         let (read_half, write_half) = stream.into_split();
         let reader = BufReader::new(read_half);
         TestClient {
             reader,
             writer: write_half,
+            local_addr,
         }
     }
 
@@ -75,6 +78,7 @@ impl TestClient {
 
 #[tokio::test]
 async fn config_get_returns_values() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
     let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
     
     let (addr, shutdown, handle) = spawn_server().await;
@@ -103,13 +107,14 @@ async fn config_get_returns_values() {
 
 #[tokio::test]
 async fn config_set_returns_error() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
     let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
     
     let (addr, shutdown, handle) = spawn_server().await;
     let mut client = TestClient::connect(addr).await;
 
-    // CONFIG SET maxmemory 100mb - should return error (not supported)
-    client.send_array(&["CONFIG", "SET", "maxmemory", "100mb"]).await;
+    // CONFIG SET timeout 100 - should return error (not supported)
+    client.send_array(&["CONFIG", "SET", "timeout", "100"]).await;
     let line = client.read_line().await;
     assert!(line.starts_with("-ERR"), "Expected error response, got: {}", line);
 
@@ -119,6 +124,7 @@ async fn config_set_returns_error() {
 
 #[tokio::test]
 async fn client_id_returns_integer() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
     let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
     
     let (addr, shutdown, handle) = spawn_server().await;
@@ -137,6 +143,7 @@ async fn client_id_returns_integer() {
 
 #[tokio::test]
 async fn client_setname_getname_roundtrip() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
     let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
     
     let (addr, shutdown, handle) = spawn_server().await;
@@ -163,6 +170,7 @@ async fn client_setname_getname_roundtrip() {
 
 #[tokio::test]
 async fn client_list_returns_info() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
     let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
     
     let (addr, shutdown, handle) = spawn_server().await;
@@ -180,6 +188,7 @@ async fn client_list_returns_info() {
 
 #[tokio::test]
 async fn slowlog_commands_work() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
     let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
     
     let (addr, shutdown, handle) = spawn_server().await;
@@ -203,3 +212,765 @@ async fn slowlog_commands_work() {
     shutdown.send(()).unwrap();
     handle.await.unwrap().unwrap();
 }
+
+#[tokio::test]
+async fn client_info_reports_current_command() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["CLIENT", "INFO"]).await;
+    let info = client.read_bulk_string().await;
+    assert!(info.contains("cmd=client|info"), "Expected cmd field, got: {}", info);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn info_commandstats_counts_calls() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["SET", "k", "v"]).await;
+    assert_eq!(client.read_line().await, "+OK\r\n");
+    for _ in 0..3 {
+        client.send_array(&["GET", "k"]).await;
+        assert_eq!(client.read_bulk_string().await, "v");
+    }
+
+    client.send_array(&["INFO", "commandstats"]).await;
+    let info = client.read_bulk_string().await;
+    assert!(info.starts_with("# Commandstats"), "got: {}", info);
+    let get_line = info
+        .lines()
+        .find(|l| l.starts_with("cmdstat_get:"))
+        .unwrap_or_else(|| panic!("missing cmdstat_get in: {}", info));
+    assert!(get_line.starts_with("cmdstat_get:calls=3,usec="), "got: {}", get_line);
+    assert!(get_line.contains("usec_per_call="), "got: {}", get_line);
+    assert!(info.contains("cmdstat_set:calls=1,"), "got: {}", info);
+    // 默认 INFO 不包含命令统计
+    client.send_array(&["INFO"]).await;
+    let info = client.read_bulk_string().await;
+    assert!(!info.contains("cmdstat_"), "got: {}", info);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn command_returns_full_spec_table() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["COMMAND"]).await;
+    let header = client.read_line().await;
+    let count: usize = header.trim_start_matches('*').trim().parse().unwrap();
+    assert!(count > 50, "Expected full command table, got {} entries", count);
+
+    let mut names = Vec::new();
+    for _ in 0..count {
+        assert_eq!(client.read_line().await, "*6\r\n");
+        names.push(client.read_bulk_string().await);
+        let arity = client.read_line().await;
+        assert!(arity.starts_with(':'), "Expected arity, got: {}", arity);
+        let flags = client.read_line().await;
+        let nflags: usize = flags.trim_start_matches('*').trim().parse().unwrap();
+        for _ in 0..nflags {
+            assert!(client.read_line().await.starts_with('+'));
+        }
+        for _ in 0..3 {
+            assert!(client.read_line().await.starts_with(':'));
+        }
+    }
+    assert!(names.iter().any(|n| n == "get"));
+    assert!(names.iter().any(|n| n == "command"));
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn command_info_matches_table_entry() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["COMMAND", "INFO", "MSET", "nosuchcmd"]).await;
+    assert_eq!(client.read_line().await, "*2\r\n");
+    assert_eq!(client.read_line().await, "*6\r\n");
+    assert_eq!(client.read_bulk_string().await, "mset");
+    assert_eq!(client.read_line().await, ":-3\r\n");
+    assert_eq!(client.read_line().await, "*2\r\n");
+    assert_eq!(client.read_line().await, "+write\r\n");
+    assert_eq!(client.read_line().await, "+denyoom\r\n");
+    assert_eq!(client.read_line().await, ":1\r\n");
+    assert_eq!(client.read_line().await, ":-1\r\n");
+    assert_eq!(client.read_line().await, ":2\r\n");
+    assert_eq!(client.read_line().await, "*-1\r\n");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn slowlog_records_full_command_arguments() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+    // 阈值 0：记录所有命令
+    let _slow_guard = set_env("REDUST_SLOWLOG_LOG_SLOWER_THAN", "0");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["CLIENT", "SETNAME", "slowpoke"]).await;
+    assert_eq!(client.read_line().await, "+OK\r\n");
+    client.send_array(&["ZADD", "board", "1.5", "alice", "2", "bob"]).await;
+    assert_eq!(client.read_line().await, ":2\r\n");
+
+    client.send_array(&["SLOWLOG", "GET", "1"]).await;
+    assert_eq!(client.read_line().await, "*1\r\n");
+    assert_eq!(client.read_line().await, "*6\r\n");
+    let id = client.read_line().await;
+    assert!(id.starts_with(':'), "Expected id, got: {}", id);
+    let ts = client.read_line().await;
+    assert!(ts.starts_with(':'), "Expected timestamp, got: {}", ts);
+    let duration = client.read_line().await;
+    assert!(duration.starts_with(':'), "Expected duration, got: {}", duration);
+    assert_eq!(client.read_line().await, "*6\r\n");
+    for expected in ["ZADD", "board", "1.5", "alice", "2", "bob"] {
+        assert_eq!(client.read_bulk_string().await, expected);
+    }
+    let client_addr = client.read_bulk_string().await;
+    assert!(client_addr.starts_with("127.0.0.1:"), "got: {}", client_addr);
+    assert_eq!(client.read_bulk_string().await, "slowpoke");
+
+    client.send_array(&["SLOWLOG", "RESET"]).await;
+    assert_eq!(client.read_line().await, "+OK\r\n");
+    client.send_array(&["SLOWLOG", "LEN"]).await;
+    // RESET 本身在下一条命令读取前入日志
+    assert_eq!(client.read_line().await, ":1\r\n");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn slowlog_redacts_config_set_requirepass() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+    let _slow_guard = set_env("REDUST_SLOWLOG_LOG_SLOWER_THAN", "0");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["CONFIG", "SET", "requirepass", "s3cret"]).await;
+    assert_eq!(client.read_line().await, "+OK\r\n");
+    client.send_array(&["AUTH", "s3cret"]).await;
+    assert_eq!(client.read_line().await, "+OK\r\n");
+
+    client.send_array(&["SLOWLOG", "GET", "2"]).await;
+    assert_eq!(client.read_line().await, "*2\r\n");
+    let mut commands = Vec::new();
+    for _ in 0..2 {
+        assert_eq!(client.read_line().await, "*6\r\n");
+        for _ in 0..3 {
+            let _ = client.read_line().await;
+        }
+        let argc = client.read_line().await;
+        let argc: usize = argc.trim_start_matches('*').trim().parse().unwrap();
+        let mut args = Vec::new();
+        for _ in 0..argc {
+            args.push(client.read_bulk_string().await);
+        }
+        commands.push(args.join(" "));
+        let _addr = client.read_bulk_string().await;
+        let _name = client.read_bulk_string().await;
+    }
+    assert_eq!(
+        commands,
+        vec![
+            "AUTH (redacted)".to_string(),
+            "CONFIG SET requirepass (redacted)".to_string()
+        ]
+    );
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn slowlog_captures_debug_sleep_and_exec() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+    let _slow_guard = set_env("REDUST_SLOWLOG_LOG_SLOWER_THAN", "20000");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["DEBUG", "SLEEP", "0.05"]).await;
+    assert_eq!(client.read_line().await, "+OK\r\n");
+
+    // 事务中的慢命令以 EXEC 的总耗时记录
+    client.send_array(&["MULTI"]).await;
+    assert_eq!(client.read_line().await, "+OK\r\n");
+    client.send_array(&["DEBUG", "SLEEP", "0.05"]).await;
+    assert_eq!(client.read_line().await, "+QUEUED\r\n");
+    client.send_array(&["EXEC"]).await;
+    assert_eq!(client.read_line().await, "*1\r\n");
+    assert_eq!(client.read_line().await, "+OK\r\n");
+
+    client.send_array(&["SLOWLOG", "GET"]).await;
+    assert_eq!(client.read_line().await, "*2\r\n");
+    let mut commands = Vec::new();
+    for _ in 0..2 {
+        assert_eq!(client.read_line().await, "*6\r\n");
+        let _id = client.read_line().await;
+        let _ts = client.read_line().await;
+        let duration = client.read_line().await;
+        let duration: u64 = duration.trim_start_matches(':').trim().parse().unwrap();
+        assert!(duration >= 20_000, "duration too small: {}", duration);
+        let argc = client.read_line().await;
+        let argc: usize = argc.trim_start_matches('*').trim().parse().unwrap();
+        let mut args = Vec::new();
+        for _ in 0..argc {
+            args.push(client.read_bulk_string().await);
+        }
+        commands.push(args.join(" "));
+        let _addr = client.read_bulk_string().await;
+        let _name = client.read_bulk_string().await;
+    }
+    assert_eq!(commands, vec!["EXEC".to_string(), "DEBUG SLEEP 0.05".to_string()]);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn slowlog_entry_addr_is_real_peer_address() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+    let _slow_guard = set_env("REDUST_SLOWLOG_LOG_SLOWER_THAN", "0");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["SET", "k", "v"]).await;
+    assert_eq!(client.read_line().await, "+OK\r\n");
+
+    client.send_array(&["SLOWLOG", "GET", "1"]).await;
+    assert_eq!(client.read_line().await, "*1\r\n");
+    assert_eq!(client.read_line().await, "*6\r\n");
+    for _ in 0..3 {
+        let _ = client.read_line().await;
+    }
+    assert_eq!(client.read_line().await, "*3\r\n");
+    for _ in 0..3 {
+        let _ = client.read_bulk_string().await;
+    }
+    // addr 字段应为客户端的真实地址（ip:port），未设置名称时 name 为空串
+    let entry_addr = client.read_bulk_string().await;
+    assert_eq!(entry_addr, client.local_addr.to_string());
+    assert_eq!(client.read_bulk_string().await, "");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn slowlog_ids_are_not_reused_after_reset() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+    let _slow_guard = set_env("REDUST_SLOWLOG_LOG_SLOWER_THAN", "0");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    // 读取 SLOWLOG GET 1 返回的最新条目 id
+    async fn latest_id(client: &mut TestClient) -> u64 {
+        client.send_array(&["SLOWLOG", "GET", "1"]).await;
+        assert_eq!(client.read_line().await, "*1\r\n");
+        assert_eq!(client.read_line().await, "*6\r\n");
+        let id = client.read_line().await;
+        let _ts = client.read_line().await;
+        let _duration = client.read_line().await;
+        let argc = client.read_line().await;
+        let argc: usize = argc.trim_start_matches('*').trim().parse().unwrap();
+        for _ in 0..argc + 2 {
+            let _ = client.read_bulk_string().await;
+        }
+        id.trim_start_matches(':').trim().parse().unwrap()
+    }
+
+    for _ in 0..3 {
+        client.send_array(&["PING"]).await;
+        assert_eq!(client.read_line().await, "+PONG\r\n");
+    }
+    let before = latest_id(&mut client).await;
+
+    client.send_array(&["SLOWLOG", "RESET"]).await;
+    assert_eq!(client.read_line().await, "+OK\r\n");
+    client.send_array(&["PING"]).await;
+    assert_eq!(client.read_line().await, "+PONG\r\n");
+
+    let after = latest_id(&mut client).await;
+    assert!(after > before, "id reused after reset: {} <= {}", after, before);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn slowlog_withreplybytes_records_reply_size() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+    let _slow_guard = set_env("REDUST_SLOWLOG_LOG_SLOWER_THAN", "0");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    let value = "x".repeat(1000);
+    let mut parts = vec!["RPUSH", "big"];
+    parts.extend(std::iter::repeat(value.as_str()).take(200));
+    client.send_array(&parts).await;
+    assert_eq!(client.read_line().await, ":200\r\n");
+
+    client.send_array(&["LRANGE", "big", "0", "-1"]).await;
+    assert_eq!(client.read_line().await, "*200\r\n");
+    for _ in 0..200 {
+        assert_eq!(client.read_bulk_string().await.len(), 1000);
+    }
+
+    client.send_array(&["SLOWLOG", "GET", "1", "WITHREPLYBYTES"]).await;
+    assert_eq!(client.read_line().await, "*1\r\n");
+    assert_eq!(client.read_line().await, "*7\r\n");
+    for _ in 0..3 {
+        let _ = client.read_line().await;
+    }
+    assert_eq!(client.read_line().await, "*4\r\n");
+    for expected in ["LRANGE", "big", "0", "-1"] {
+        assert_eq!(client.read_bulk_string().await, expected);
+    }
+    let _addr = client.read_bulk_string().await;
+    let _name = client.read_bulk_string().await;
+    // "*200\r\n" 加上 200 个 "$1000\r\n<1000 字节>\r\n"
+    assert_eq!(client.read_line().await, format!(":{}\r\n", 6 + 200 * (7 + 1000 + 2)));
+
+    // 不带 WITHREPLYBYTES 时保持 Redis 的 6 字段格式
+    client.send_array(&["SLOWLOG", "GET", "1"]).await;
+    assert_eq!(client.read_line().await, "*1\r\n");
+    assert_eq!(client.read_line().await, "*6\r\n");
+    for _ in 0..3 {
+        let _ = client.read_line().await;
+    }
+    assert_eq!(client.read_line().await, "*4\r\n");
+    for _ in 0..6 {
+        let _ = client.read_bulk_string().await;
+    }
+
+    client.send_array(&["SLOWLOG", "GET", "WITHREPLYBYTES", "1"]).await;
+    assert!(client.read_line().await.starts_with("-ERR syntax error"));
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn replica_read_only_rejects_writes_but_allows_reads() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["SET", "ro_key", "v1"]).await;
+    assert_eq!(client.read_line().await, "+OK\r\n");
+
+    client.send_array(&["CONFIG", "SET", "replica-read-only", "yes"]).await;
+    assert_eq!(client.read_line().await, "+OK\r\n");
+    client.send_array(&["CONFIG", "GET", "replica-read-only"]).await;
+    assert_eq!(client.read_line().await, "*2\r\n");
+    client.read_bulk_string().await;
+    assert_eq!(client.read_bulk_string().await, "yes");
+
+    // 写命令按命令表的 write 标志拦截，读命令不受影响
+    client.send_array(&["SET", "ro_key", "v2"]).await;
+    assert_eq!(
+        client.read_line().await,
+        "-READONLY You can't write against a read only replica.\r\n"
+    );
+    client.send_array(&["DEL", "ro_key"]).await;
+    assert!(client.read_line().await.starts_with("-READONLY"));
+    client.send_array(&["GET", "ro_key"]).await;
+    assert_eq!(client.read_bulk_string().await, "v1");
+
+    // 脚本内的写命令同样被拒绝
+    client
+        .send_array(&["EVAL", "return redis.call('SET', KEYS[1], 'v3')", "1", "ro_key"])
+        .await;
+    assert!(client.read_line().await.starts_with("-READONLY"));
+
+    client.send_array(&["CONFIG", "SET", "replica-read-only", "no"]).await;
+    assert_eq!(client.read_line().await, "+OK\r\n");
+    client.send_array(&["SET", "ro_key", "v2"]).await;
+    assert_eq!(client.read_line().await, "+OK\r\n");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn info_stats_counts_keyspace_hits_and_misses() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    // 写命令不计入
+    client.send_array(&["SET", "hit_key", "v"]).await;
+    assert_eq!(client.read_line().await, "+OK\r\n");
+
+    client.send_array(&["GET", "hit_key"]).await;
+    assert_eq!(client.read_bulk_string().await, "v");
+    client.send_array(&["GET", "missing_key"]).await;
+    assert_eq!(client.read_line().await, "$-1\r\n");
+    // 多 key 读命令按每个 key 分别统计
+    client.send_array(&["MGET", "hit_key", "missing_key"]).await;
+    assert_eq!(client.read_line().await, "*2\r\n");
+    client.read_bulk_string().await;
+    client.read_bulk_string().await;
+
+    client.send_array(&["INFO", "stats"]).await;
+    let info = client.read_bulk_string().await;
+    assert!(info.contains("keyspace_hits:2\r\n"), "got: {}", info);
+    assert!(info.contains("keyspace_misses:2\r\n"), "got: {}", info);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+fn info_field(info: &str, name: &str) -> u64 {
+    info.lines()
+        .find_map(|l| l.strip_prefix(name)?.strip_prefix(':'))
+        .unwrap_or_else(|| panic!("missing {} in: {}", name, info))
+        .parse()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn active_expiry_scales_with_due_keys_not_volatile_keys() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    // 10 万个带长 TTL 的 key 加 100 个很快到期的 key；到期的 key 不再被访问，只能靠主动过期删除
+    let volatile = 100_000;
+    let short_lived = 100;
+    let batch = 1000;
+    for start in (0..volatile).step_by(batch) {
+        let mut buf = String::new();
+        for i in start..start + batch {
+            let key = format!("vol:{}", i);
+            buf.push_str(&format!(
+                "*5\r\n$3\r\nSET\r\n${}\r\n{}\r\n$1\r\nv\r\n$2\r\nEX\r\n$4\r\n3600\r\n",
+                key.len(),
+                key
+            ));
+        }
+        client.writer.write_all(buf.as_bytes()).await.unwrap();
+        for _ in 0..batch {
+            assert_eq!(client.read_line().await, "+OK\r\n");
+        }
+    }
+    for i in 0..short_lived {
+        client.send_array(&["SET", &format!("short:{}", i), "v", "PX", "50"]).await;
+        assert_eq!(client.read_line().await, "+OK\r\n");
+    }
+
+    let start = std::time::Instant::now();
+    loop {
+        client.send_array(&["INFO", "stats"]).await;
+        let info = client.read_bulk_string().await;
+        if info_field(&info, "expired_keys") == short_lived {
+            break;
+        }
+        assert!(
+            start.elapsed() < std::time::Duration::from_secs(3),
+            "short-lived keys were not actively expired: {}",
+            info
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+
+    client.send_array(&["DBSIZE"]).await;
+    assert_eq!(client.read_line().await, format!(":{}\r\n", volatile));
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[test]
+fn expire_cycle_pops_only_due_entries() {
+    use redust::storage::Storage;
+    use std::time::{Duration, Instant};
+
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let storage = Storage::default();
+    let batch = 1000;
+
+    // 10 万个带长 TTL 的 key：没有到期时一轮主动过期一条堆记录都不弹出
+    for i in 0..100_000 {
+        storage.set_with_expire_millis(format!("0:vol:{}", i), b"v".to_vec(), 3_600_000);
+    }
+    let (expired, popped) = storage.expire_due_keys(Instant::now(), batch);
+    assert!(expired.is_empty());
+    assert_eq!(popped, 0);
+
+    // 100 个到期的 key：一轮只弹出这 100 条记录
+    for i in 0..100 {
+        storage.set_with_expire_millis(format!("0:short:{}", i), b"v".to_vec(), 50);
+    }
+    let later = Instant::now() + Duration::from_millis(100);
+    let (expired, popped) = storage.expire_due_keys(later, batch);
+    assert_eq!(expired.len(), 100);
+    assert_eq!(popped, 100);
+
+    // TTL 被延后的 key 在堆中留下旧记录：旧记录占满一批时没有 key 被删除，
+    // 但弹出数等于批大小，调用方据此继续处理，直到旧记录全部校正
+    for i in 0..2500 {
+        let key = format!("0:stale:{}", i);
+        storage.set_with_expire_millis(key.clone(), b"v".to_vec(), 50);
+        assert!(storage.expire_millis(&key, 3_600_000));
+    }
+    let mut cycles = Vec::new();
+    loop {
+        let (expired, popped) = storage.expire_due_keys(later, batch);
+        assert!(expired.is_empty());
+        cycles.push(popped);
+        if popped < batch {
+            break;
+        }
+    }
+    assert_eq!(cycles, vec![1000, 1000, 500]);
+    assert_eq!(storage.dbsize(), 102_500);
+}
+
+#[tokio::test]
+async fn info_stats_counts_expired_keys_and_net_bytes() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["INFO", "stats"]).await;
+    let before = client.read_bulk_string().await;
+    assert_eq!(info_field(&before, "expired_keys"), 0);
+
+    // 惰性删除：过期后第一次访问时删除
+    client.send_array(&["SET", "short_lived", "v", "PX", "20"]).await;
+    assert_eq!(client.read_line().await, "+OK\r\n");
+    tokio::time::sleep(std::time::Duration::from_millis(60)).await;
+    client.send_array(&["GET", "short_lived"]).await;
+    assert_eq!(client.read_line().await, "$-1\r\n");
+
+    let value = "x".repeat(1000);
+    client.send_array(&["SET", "big", &value]).await;
+    assert_eq!(client.read_line().await, "+OK\r\n");
+
+    client.send_array(&["INFO", "stats"]).await;
+    let after = client.read_bulk_string().await;
+    assert_eq!(info_field(&after, "expired_keys"), 1);
+
+    // 两次 INFO 之间读入的是 SET PX、GET、SET big 与第二个 INFO 的完整 RESP 编码
+    let resp_len = |parts: &[&str]| -> u64 {
+        let mut len = format!("*{}\r\n", parts.len()).len();
+        for p in parts {
+            len += format!("${}\r\n", p.len()).len() + p.len() + 2;
+        }
+        len as u64
+    };
+    let sent = resp_len(&["SET", "short_lived", "v", "PX", "20"])
+        + resp_len(&["GET", "short_lived"])
+        + resp_len(&["SET", "big", &value])
+        + resp_len(&["INFO", "stats"]);
+    assert_eq!(
+        info_field(&after, "total_net_input_bytes") - info_field(&before, "total_net_input_bytes"),
+        sent
+    );
+    // 写出至少包含第一个 INFO 的回复
+    assert!(
+        info_field(&after, "total_net_output_bytes") - info_field(&before, "total_net_output_bytes")
+            >= before.len() as u64
+    );
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn command_count_matches_table_and_info() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["COMMAND", "COUNT"]).await;
+    let line = client.read_line().await;
+    let count: u64 = line.trim_start_matches(':').trim().parse().unwrap();
+    assert_eq!(count as usize, redust::command_table::COMMAND_TABLE.len());
+    assert!(count > 100, "got {}", count);
+
+    // INFO 中的字段同样来自命令表
+    client.send_array(&["INFO", "server"]).await;
+    let info = client.read_bulk_string().await;
+    assert_eq!(info_field(&info, "supported_commands"), count);
+
+    client.send_array(&["COMMAND", "COUNT", "extra"]).await;
+    assert!(client.read_line().await.starts_with("-ERR"));
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn expire_jitter_spreads_keys_with_identical_ttl() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+    let _jitter_guard = set_env("REDUST_EXPIRE_JITTER_MS", "1000");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    for i in 0..100 {
+        let key = format!("jitter:{}", i);
+        client.send_array(&["SET", &key, "v", "PX", "10000"]).await;
+        assert_eq!(client.read_line().await, "+OK\r\n");
+    }
+
+    let mut ttls = Vec::new();
+    for i in 0..100 {
+        let key = format!("jitter:{}", i);
+        client.send_array(&["PTTL", &key]).await;
+        let line = client.read_line().await;
+        ttls.push(line.trim_start_matches(':').trim().parse::<i64>().unwrap());
+    }
+    let min = *ttls.iter().min().unwrap();
+    let max = *ttls.iter().max().unwrap();
+    // 所有到期时间落在 [TTL, TTL + jitter] 内（留出执行耗时的余量），且确实被打散
+    assert!(min > 9000 && max <= 11000, "ttls out of window: {}..{}", min, max);
+    assert!(max - min >= 200, "ttls not spread: {}..{}", min, max);
+
+    // KEEPTTL 保留原到期时间，不会再叠加一次抖动
+    client.send_array(&["PTTL", "jitter:0"]).await;
+    let before: i64 = client.read_line().await.trim_start_matches(':').trim().parse().unwrap();
+    client.send_array(&["SET", "jitter:0", "v2", "KEEPTTL"]).await;
+    assert_eq!(client.read_line().await, "+OK\r\n");
+    client.send_array(&["PTTL", "jitter:0"]).await;
+    let after: i64 = client.read_line().await.trim_start_matches(':').trim().parse().unwrap();
+    assert!(after <= before, "KEEPTTL moved expiry: {} -> {}", before, after);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn expire_jitter_keeps_absolute_deadlines_exact() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+    let _jitter_guard = set_env("REDUST_EXPIRE_JITTER_MS", "1000");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    let now_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64;
+    let at = (now_ms + 10000).to_string();
+    let at_sec = ((now_ms + 10000) / 1000).to_string();
+
+    // PXAT/EXAT 是绝对到期时间：开启抖动后也不能被后移
+    for i in 0..50 {
+        let key = format!("pxat:{}", i);
+        client.send_array(&["SET", &key, "v", "PXAT", &at]).await;
+        assert_eq!(client.read_line().await, "+OK\r\n");
+        let key = format!("exat:{}", i);
+        client.send_array(&["SET", &key, "v", "EXAT", &at_sec]).await;
+        assert_eq!(client.read_line().await, "+OK\r\n");
+    }
+
+    for i in 0..50 {
+        let key = format!("pxat:{}", i);
+        client.send_array(&["PTTL", &key]).await;
+        let ttl: i64 = client.read_line().await.trim_start_matches(':').trim().parse().unwrap();
+        assert!(ttl > 9000 && ttl <= 10000, "PXAT deadline moved: {}", ttl);
+
+        let key = format!("exat:{}", i);
+        client.send_array(&["PTTL", &key]).await;
+        let ttl: i64 = client.read_line().await.trim_start_matches(':').trim().parse().unwrap();
+        assert!(ttl > 8000 && ttl <= 10000, "EXAT deadline moved: {}", ttl);
+    }
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn max_multibulk_len_is_per_server() {
+    let _lock = env_guard::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+
+    // serve 在首次被调度时读取环境变量，收到第一条回复后再恢复变量
+    let limit_guard = set_env("REDUST_MAX_MULTIBULK_LEN", "3");
+    let (limited_addr, limited_shutdown, limited_handle) = spawn_server().await;
+    let mut limited = TestClient::connect(limited_addr).await;
+    limited.send_array(&["SET", "k", "v"]).await;
+    assert_eq!(limited.read_line().await, "+OK\r\n");
+    drop(limit_guard);
+
+    // 上限只属于配置它的实例：之后启动、未设置该变量的服务仍使用默认上限
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+    client.send_array(&["MSET", "a", "1", "b", "2"]).await;
+    assert_eq!(client.read_line().await, "+OK\r\n");
+
+    limited.send_array(&["MSET", "a", "1", "b", "2"]).await;
+    let line = limited.read_line().await;
+    assert!(
+        line.starts_with("-ERR Protocol error: array length exceeds limit"),
+        "got {:?}",
+        line
+    );
+
+    // 非法取值回退到默认上限
+    let bad_guard = set_env("REDUST_MAX_MULTIBULK_LEN", "lots");
+    let (bad_addr, bad_shutdown, bad_handle) = spawn_server().await;
+    let mut bad = TestClient::connect(bad_addr).await;
+    bad.send_array(&["MSET", "a", "1", "b", "2"]).await;
+    assert_eq!(bad.read_line().await, "+OK\r\n");
+    drop(bad_guard);
+
+    for (shutdown, handle) in [
+        (limited_shutdown, limited_handle),
+        (shutdown, handle),
+        (bad_shutdown, bad_handle),
+    ] {
+        shutdown.send(()).unwrap();
+        handle.await.unwrap().unwrap();
+    }
+}
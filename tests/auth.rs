@@ -43,6 +43,9 @@ async fn read_mixed_array(reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>
             out.push(buf);
         } else if line.starts_with(':') {
             out.push(line[1..line.len() - 2].as_bytes().to_vec());
+        } else if line == "*0\r\n" {
+            // 空的嵌套数组（如 HELLO 回复中的 modules）
+            out.push(Vec::new());
         } else {
             panic!("unexpected element header: {}", line);
         }
@@ -53,6 +56,7 @@ async fn read_mixed_array(reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>
 #[tokio::test]
 async fn auth_not_enabled_but_command_sent() {
     let _lock = ENV_LOCK.lock().unwrap();
+    let _persist_guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
     let _guard = remove_env("REDUST_AUTH_PASSWORD");
 
     let (addr, shutdown, handle) = spawn_server().await;
@@ -106,6 +110,7 @@ PING\r
 #[tokio::test]
 async fn auth_required_blocks_commands_until_authenticated() {
     let _lock = ENV_LOCK.lock().unwrap();
+    let _persist_guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
     let _guard = set_env("REDUST_AUTH_PASSWORD", "secret");
 
     let (addr, shutdown, handle) = spawn_server().await;
@@ -250,6 +255,7 @@ foo\r
 #[tokio::test]
 async fn pubsub_requires_auth_when_enabled() {
     let _lock = ENV_LOCK.lock().unwrap();
+    let _persist_guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
     let _guard = set_env("REDUST_AUTH_PASSWORD", "secret");
 
     let (addr, shutdown, handle) = spawn_server().await;
@@ -331,3 +337,128 @@ async fn pubsub_requires_auth_when_enabled() {
     shutdown.send(()).unwrap();
     handle.await.unwrap().unwrap();
 }
+
+#[tokio::test]
+async fn subscription_commands_require_auth_when_enabled() {
+    let _lock = ENV_LOCK.lock().unwrap();
+    let _persist_guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+    let _guard = set_env("REDUST_AUTH_PASSWORD", "secret");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    // 所有订阅类命令都不在未认证白名单内，均应返回 NOAUTH
+    let commands: [&[u8]; 6] = [
+        b"*2\r\n$9\r\nSUBSCRIBE\r\n$3\r\nfoo\r\n",
+        b"*2\r\n$11\r\nUNSUBSCRIBE\r\n$3\r\nfoo\r\n",
+        b"*2\r\n$10\r\nPSUBSCRIBE\r\n$2\r\nf*\r\n",
+        b"*2\r\n$12\r\nPUNSUBSCRIBE\r\n$2\r\nf*\r\n",
+        b"*2\r\n$10\r\nSSUBSCRIBE\r\n$3\r\nfoo\r\n",
+        b"*2\r\n$12\r\nSUNSUBSCRIBE\r\n$3\r\nfoo\r\n",
+    ];
+    for command in commands {
+        write_half.write_all(command).await.unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        assert_eq!(line, "-NOAUTH Authentication required\r\n");
+    }
+
+    // 被拒绝的 SUBSCRIBE 不会让连接进入订阅模式，普通命令的回复格式不变
+    write_half
+        .write_all(b"*1\r\n$4\r\nPING\r\n")
+        .await
+        .unwrap();
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "+PONG\r\n");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn hello_auth_authenticates_connection() {
+    let _lock = ENV_LOCK.lock().unwrap();
+    let _persist_guard = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+    let _guard = set_env("REDUST_AUTH_PASSWORD", "secret");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    // 未认证且不带 AUTH 的 HELLO -> NOAUTH
+    write_half
+        .write_all(b"*2\r\n$5\r\nHELLO\r\n$1\r\n2\r\n")
+        .await
+        .unwrap();
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("-NOAUTH HELLO must be called with the client already authenticated"), "{:?}", line);
+
+    // 错误密码 -> WRONGPASS，连接仍未认证
+    write_half
+        .write_all(b"*5\r\n$5\r\nHELLO\r\n$1\r\n2\r\n$4\r\nAUTH\r\n$7\r\ndefault\r\n$5\r\nwrong\r\n")
+        .await
+        .unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "-WRONGPASS invalid username-password pair or user is disabled\r\n");
+
+    // 不支持的协议版本先于认证检查；尚无 RESP3 编码器，HELLO 3 同样返回 NOPROTO
+    for protover in [&b"3"[..], b"4"] {
+        let mut req = b"*2\r\n$5\r\nHELLO\r\n$1\r\n".to_vec();
+        req.extend_from_slice(protover);
+        req.extend_from_slice(b"\r\n");
+        write_half.write_all(&req).await.unwrap();
+        line.clear();
+        reader.read_line(&mut line).await.unwrap();
+        assert_eq!(line, "-NOPROTO unsupported protocol version\r\n");
+    }
+    write_half
+        .write_all(b"*7\r\n$5\r\nHELLO\r\n$1\r\n3\r\n$4\r\nAUTH\r\n$7\r\ndefault\r\n$6\r\nsecret\r\n$7\r\nSETNAME\r\n$3\r\napp\r\n")
+        .await
+        .unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "-NOPROTO unsupported protocol version\r\n");
+
+    // HELLO 2 AUTH default secret SETNAME app -> 认证成功并返回服务端信息
+    write_half
+        .write_all(b"*7\r\n$5\r\nHELLO\r\n$1\r\n2\r\n$4\r\nAUTH\r\n$7\r\ndefault\r\n$6\r\nsecret\r\n$7\r\nSETNAME\r\n$3\r\napp\r\n")
+        .await
+        .unwrap();
+    let hello = read_mixed_array(&mut reader).await;
+    assert_eq!(hello.len(), 14);
+    assert_eq!(hello[0], b"server");
+    assert_eq!(hello[4], b"proto");
+    assert_eq!(hello[5], b"2");
+    assert_eq!(hello[12], b"modules");
+
+    // 认证后普通命令可用
+    write_half
+        .write_all(b"*2\r\n$3\r\nGET\r\n$13\r\nhello-missing\r\n")
+        .await
+        .unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "$-1\r\n");
+
+    write_half
+        .write_all(b"*2\r\n$6\r\nCLIENT\r\n$7\r\nGETNAME\r\n")
+        .await
+        .unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "$3\r\n");
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "app\r\n");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
@@ -14,6 +14,7 @@ async fn spawn_server() -> (
     oneshot::Sender<()>,
     tokio::task::JoinHandle<tokio::io::Result<()>>,
 ) {
+    std::env::set_var("REDUST_DISABLE_PERSISTENCE", "1");
     let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind server");
     let addr = listener.local_addr().expect("local addr");
     let (tx, rx) = oneshot::channel();
@@ -148,3 +149,166 @@ async fn auth_is_per_connection() {
     shutdown.send(()).unwrap();
     handle.await.unwrap().unwrap();
 }
+
+#[tokio::test]
+async fn acl_reports_default_user_without_password() {
+    let _lock = ENV_LOCK.lock().unwrap();
+    let _auth_guard = remove_env("REDUST_AUTH_PASSWORD");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["ACL", "WHOAMI"]).await;
+    assert_eq!(client.read_line().await, "$7\r\n");
+    assert_eq!(client.read_line().await, "default\r\n");
+
+    client.send_array(&["ACL", "LIST"]).await;
+    assert_eq!(client.read_line().await, "*1\r\n");
+    let _len = client.read_line().await;
+    let line = client.read_line().await;
+    assert!(line.starts_with("user default on nopass"), "got: {}", line);
+
+    // GETUSER default：flags 中包含 nopass，passwords 为空
+    client.send_array(&["ACL", "GETUSER", "default"]).await;
+    assert_eq!(client.read_line().await, "*12\r\n");
+    assert_eq!(client.read_line().await, "$5\r\n");
+    assert_eq!(client.read_line().await, "flags\r\n");
+    assert_eq!(client.read_line().await, "*2\r\n");
+    assert_eq!(client.read_line().await, "$2\r\n");
+    assert_eq!(client.read_line().await, "on\r\n");
+    assert_eq!(client.read_line().await, "$6\r\n");
+    assert_eq!(client.read_line().await, "nopass\r\n");
+    assert_eq!(client.read_line().await, "$9\r\n");
+    assert_eq!(client.read_line().await, "passwords\r\n");
+    assert_eq!(client.read_line().await, "*0\r\n");
+    for _ in 0..6 {
+        let _ = client.read_line().await;
+        let _ = client.read_line().await;
+    }
+    assert_eq!(client.read_line().await, "$9\r\n");
+    assert_eq!(client.read_line().await, "selectors\r\n");
+    assert_eq!(client.read_line().await, "*0\r\n");
+
+    // 不存在的用户返回 nil
+    client.send_array(&["ACL", "GETUSER", "alice"]).await;
+    assert_eq!(client.read_line().await, "$-1\r\n");
+
+    client.send_array(&["ACL", "SETUSER", "alice"]).await;
+    let line = client.read_line().await;
+    assert!(line.starts_with("-ERR unknown ACL subcommand"), "got: {}", line);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn acl_list_hides_configured_password() {
+    let _lock = ENV_LOCK.lock().unwrap();
+    let _auth_guard = set_env("REDUST_AUTH_PASSWORD", "secret");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["AUTH", "secret"]).await;
+    assert_eq!(client.read_line().await, "+OK\r\n");
+
+    client.send_array(&["ACL", "LIST"]).await;
+    assert_eq!(client.read_line().await, "*1\r\n");
+    let _len = client.read_line().await;
+    let line = client.read_line().await;
+    // 与 Redis 相同的格式：# 后跟密码的 SHA-256（64 个十六进制字符）
+    assert!(
+        line.starts_with(
+            "user default on #2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b"
+        ),
+        "got: {}",
+        line
+    );
+    assert!(!line.contains("nopass"), "got: {}", line);
+    assert!(!line.contains("secret"), "got: {}", line);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn auth_with_username_accepts_default_user_only() {
+    let _lock = ENV_LOCK.lock().unwrap();
+    let _auth_guard = set_env("REDUST_AUTH_PASSWORD", "secret");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    // 用户名错误，即使密码正确也拒绝
+    client.send_array(&["AUTH", "alice", "secret"]).await;
+    let line = client.read_line().await;
+    assert!(line.starts_with("-WRONGPASS"), "got: {}", line);
+
+    client.send_array(&["SET", "k", "v"]).await;
+    let line = client.read_line().await;
+    assert!(line.starts_with("-NOAUTH"), "got: {}", line);
+
+    // default 用户 + 错误密码
+    client.send_array(&["AUTH", "default", "wrong"]).await;
+    let line = client.read_line().await;
+    assert!(line.starts_with("-WRONGPASS"), "got: {}", line);
+
+    // default 用户 + 正确密码
+    client.send_array(&["AUTH", "default", "secret"]).await;
+    assert_eq!(client.read_line().await, "+OK\r\n");
+
+    client.send_array(&["SET", "k", "v"]).await;
+    assert_eq!(client.read_line().await, "+OK\r\n");
+
+    // 单参数形式继续可用
+    let mut other = TestClient::connect(addr).await;
+    other.send_array(&["AUTH", "secret"]).await;
+    assert_eq!(other.read_line().await, "+OK\r\n");
+
+    // 参数过多
+    other.send_array(&["AUTH", "default", "secret", "extra"]).await;
+    let line = other.read_line().await;
+    assert!(line.starts_with("-ERR wrong number of arguments"), "got: {}", line);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn config_set_requirepass_enables_auth_for_new_connections() {
+    let _lock = ENV_LOCK.lock().unwrap();
+    let _auth_guard = remove_env("REDUST_AUTH_PASSWORD");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut admin = TestClient::connect(addr).await;
+
+    admin.send_array(&["CONFIG", "SET", "requirepass", "runtime"]).await;
+    assert_eq!(admin.read_line().await, "+OK\r\n");
+
+    // 已有连接保持已认证状态
+    admin.send_array(&["SET", "k", "v"]).await;
+    assert_eq!(admin.read_line().await, "+OK\r\n");
+
+    // 新连接需要 AUTH
+    let mut client = TestClient::connect(addr).await;
+    client.send_array(&["GET", "k"]).await;
+    let line = client.read_line().await;
+    assert!(line.starts_with("-NOAUTH"), "got: {}", line);
+
+    client.send_array(&["AUTH", "runtime"]).await;
+    assert_eq!(client.read_line().await, "+OK\r\n");
+    client.send_array(&["GET", "k"]).await;
+    assert_eq!(client.read_line().await, "$1\r\n");
+    assert_eq!(client.read_line().await, "v\r\n");
+
+    // 置空后关闭认证
+    admin.send_array(&["CONFIG", "SET", "requirepass", ""]).await;
+    assert_eq!(admin.read_line().await, "+OK\r\n");
+    let mut client = TestClient::connect(addr).await;
+    client.send_array(&["GET", "k"]).await;
+    assert_eq!(client.read_line().await, "$1\r\n");
+    assert_eq!(client.read_line().await, "v\r\n");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
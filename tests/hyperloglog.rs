@@ -256,3 +256,197 @@ async fn test_type_command_for_hll() {
 
     let _ = shutdown.send(());
 }
+
+#[tokio::test]
+async fn test_pfdebug_getreg_and_encoding() {
+    let (addr, shutdown, _handle) = spawn_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    let mut args = vec!["PFADD".to_string(), "debughll".to_string()];
+    for i in 0..100 {
+        args.push(format!("element_{}", i));
+    }
+    let parts: Vec<&str> = args.iter().map(String::as_str).collect();
+    send_array(&mut write_half, &parts).await;
+    assert_eq!(read_line(&mut reader).await, ":1\r\n");
+
+    send_array(&mut write_half, &["PFDEBUG", "ENCODING", "debughll"]).await;
+    assert_eq!(read_line(&mut reader).await, "+sparse\r\n");
+
+    send_array(&mut write_half, &["PFDEBUG", "GETREG", "debughll"]).await;
+    assert_eq!(read_line(&mut reader).await, "*16384\r\n");
+    let mut registers = Vec::with_capacity(16384);
+    for _ in 0..16384 {
+        let line = read_line(&mut reader).await;
+        let value: u8 = line
+            .strip_prefix(':')
+            .and_then(|v| v.trim_end().parse().ok())
+            .unwrap_or_else(|| panic!("unexpected register reply {:?}", line));
+        assert!(value <= 63, "register out of 6-bit range: {}", value);
+        registers.push(value);
+    }
+
+    // 100 个元素最多点亮 100 个寄存器，哈希冲突只会让个别元素落到同一寄存器
+    let nonzero = registers.iter().filter(|&&r| r != 0).count();
+    assert!((95..=100).contains(&nonzero), "nonzero registers = {}", nonzero);
+
+    // 寄存器几乎没有冲突，估算结果应非常接近真实基数
+    send_array(&mut write_half, &["PFCOUNT", "debughll"]).await;
+    let count: i64 = read_line(&mut reader).await[1..].trim_end().parse().unwrap();
+    assert!((98..=102).contains(&count), "count = {}", count);
+
+    send_array(&mut write_half, &["PFDEBUG", "GETREG", "missinghll"]).await;
+    assert!(read_line(&mut reader).await.starts_with("-ERR"));
+
+    send_array(&mut write_half, &["SET", "plainkey", "v"]).await;
+    let _ = read_line(&mut reader).await;
+    send_array(&mut write_half, &["PFDEBUG", "GETREG", "plainkey"]).await;
+    assert!(read_line(&mut reader).await.starts_with("-WRONGTYPE"));
+
+    send_array(&mut write_half, &["PFDEBUG", "NOPE", "debughll"]).await;
+    assert!(read_line(&mut reader).await.starts_with("-ERR"));
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test]
+async fn test_sparse_to_dense_transition_keeps_estimates() {
+    let (addr, shutdown, _handle) = spawn_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    send_array(&mut write_half, &["CONFIG", "GET", "hll-sparse-max-bytes"]).await;
+    assert_eq!(read_line(&mut reader).await, "*2\r\n");
+    let _ = read_line(&mut reader).await;
+    let _ = read_line(&mut reader).await;
+    let _ = read_line(&mut reader).await;
+    assert_eq!(read_line(&mut reader).await, "3000\r\n");
+
+    async fn add_range(
+        writer: &mut tokio::net::tcp::OwnedWriteHalf,
+        reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>,
+        key: &str,
+        range: std::ops::Range<usize>,
+    ) {
+        let mut args = vec!["PFADD".to_string(), key.to_string()];
+        args.extend(range.map(|i| format!("member:{}", i)));
+        let parts: Vec<&str> = args.iter().map(String::as_str).collect();
+        send_array(writer, &parts).await;
+        let _ = read_line(reader).await;
+    }
+
+    async fn pfcount(
+        writer: &mut tokio::net::tcp::OwnedWriteHalf,
+        reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>,
+        keys: &[&str],
+    ) -> f64 {
+        let mut parts = vec!["PFCOUNT"];
+        parts.extend_from_slice(keys);
+        send_array(writer, &parts).await;
+        read_line(reader).await[1..].trim_end().parse().unwrap()
+    }
+
+    async fn encoding(
+        writer: &mut tokio::net::tcp::OwnedWriteHalf,
+        reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>,
+        key: &str,
+    ) -> String {
+        send_array(writer, &["PFDEBUG", "ENCODING", key]).await;
+        read_line(reader).await
+    }
+
+    // 默认 3000 字节约 1000 个非零寄存器：900 个元素保持稀疏，3000 个元素升级为密集
+    add_range(&mut write_half, &mut reader, "small", 0..900).await;
+    assert_eq!(encoding(&mut write_half, &mut reader, "small").await, "+sparse\r\n");
+    add_range(&mut write_half, &mut reader, "big", 0..3000).await;
+    assert_eq!(encoding(&mut write_half, &mut reader, "big").await, "+dense\r\n");
+
+    // 3 倍标准误差（约 2.43%）以内
+    for (keys, actual) in [
+        (&["small"][..], 900.0),
+        (&["big"][..], 3000.0),
+        (&["small", "big"][..], 3000.0),
+    ] {
+        let estimate = pfcount(&mut write_half, &mut reader, keys).await;
+        assert!(
+            (estimate - actual).abs() / actual <= 0.0243,
+            "{:?}: estimate {} vs actual {}",
+            keys,
+            estimate,
+            actual
+        );
+    }
+
+    // 稀疏与密集混合合并
+    add_range(&mut write_half, &mut reader, "other", 2500..3500).await;
+    send_array(&mut write_half, &["PFMERGE", "merged", "small", "big", "other"]).await;
+    assert_eq!(read_line(&mut reader).await, "+OK\r\n");
+    assert_eq!(encoding(&mut write_half, &mut reader, "merged").await, "+dense\r\n");
+    let estimate = pfcount(&mut write_half, &mut reader, &["merged"]).await;
+    assert!((estimate - 3500.0).abs() / 3500.0 <= 0.0243, "merged estimate {}", estimate);
+
+    send_array(&mut write_half, &["CONFIG", "SET", "hll-sparse-max-bytes", "abc"]).await;
+    assert!(read_line(&mut reader).await.starts_with("-ERR"));
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test]
+async fn test_pfcount_is_stable_and_fast_after_noop_pfadds() {
+    let (addr, shutdown, _handle) = spawn_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    let mut args = vec!["PFADD".to_string(), "hot".to_string()];
+    args.extend((0..5000).map(|i| format!("visitor:{}", i)));
+    let parts: Vec<&str> = args.iter().map(String::as_str).collect();
+    send_array(&mut write_half, &parts).await;
+    assert_eq!(read_line(&mut reader).await, ":1\r\n");
+    let mut args = vec!["PFADD".to_string(), "cold".to_string()];
+    args.extend((0..100).map(|i| format!("other:{}", i)));
+    let parts: Vec<&str> = args.iter().map(String::as_str).collect();
+    send_array(&mut write_half, &parts).await;
+    assert_eq!(read_line(&mut reader).await, ":1\r\n");
+
+    send_array(&mut write_half, &["PFCOUNT", "hot"]).await;
+    let first = read_line(&mut reader).await;
+
+    // 大量不修改寄存器的 PFADD 之后，PFCOUNT 结果不变且直接命中缓存
+    let started = std::time::Instant::now();
+    for i in 0..500 {
+        let member = format!("visitor:{}", i * 7 % 5000);
+        send_array(&mut write_half, &["PFADD", "hot", &member]).await;
+        assert_eq!(read_line(&mut reader).await, ":0\r\n");
+        send_array(&mut write_half, &["PFCOUNT", "hot"]).await;
+        assert_eq!(read_line(&mut reader).await, first);
+    }
+    assert!(
+        started.elapsed() < std::time::Duration::from_secs(5),
+        "500 PFADD/PFCOUNT round trips took {:?}",
+        started.elapsed()
+    );
+
+    // 多 key PFCOUNT 计算并集，但不会修改或缓存到任何输入 key
+    send_array(&mut write_half, &["PFCOUNT", "hot", "cold"]).await;
+    let union = read_line(&mut reader).await;
+    assert_ne!(union, first);
+    send_array(&mut write_half, &["PFCOUNT", "hot"]).await;
+    assert_eq!(read_line(&mut reader).await, first);
+    send_array(&mut write_half, &["PFCOUNT", "cold"]).await;
+    assert_eq!(read_line(&mut reader).await, ":100\r\n");
+
+    // 真正修改寄存器后估算随之更新
+    let mut args = vec!["PFADD".to_string(), "hot".to_string()];
+    args.extend((5000..6000).map(|i| format!("visitor:{}", i)));
+    let parts: Vec<&str> = args.iter().map(String::as_str).collect();
+    send_array(&mut write_half, &parts).await;
+    assert_eq!(read_line(&mut reader).await, ":1\r\n");
+    send_array(&mut write_half, &["PFCOUNT", "hot"]).await;
+    assert_ne!(read_line(&mut reader).await, first);
+
+    let _ = shutdown.send(());
+}
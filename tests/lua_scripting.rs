@@ -293,3 +293,501 @@ async fn test_eval_boolean_conversion() {
 
     let _ = shutdown.send(());
 }
+
+#[tokio::test]
+async fn test_eval_ro_rejects_write_commands() {
+    let (addr, shutdown, _handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_command(&["SET", "ro:key", "original"]).await;
+    assert_eq!(client.read_simple_string().await, "OK");
+
+    // 只读脚本中的写命令报错，key 保持不变
+    client
+        .send_command(&["EVAL_RO", "return redis.call('SET', KEYS[1], 'changed')", "1", "ro:key"])
+        .await;
+    let err = client.read_error().await;
+    assert!(
+        err.contains("ERR Write commands are not allowed from read-only scripts"),
+        "unexpected error: {}",
+        err
+    );
+    client.send_command(&["GET", "ro:key"]).await;
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("original"));
+
+    // pcall 把错误作为返回值交给脚本
+    client
+        .send_command(&["EVAL_RO", "return redis.pcall('DEL', KEYS[1])", "1", "ro:key"])
+        .await;
+    let err = client.read_error().await;
+    assert!(err.contains("Write commands are not allowed"), "unexpected error: {}", err);
+
+    // 读命令正常执行
+    client
+        .send_command(&["EVAL_RO", "return redis.call('GET', KEYS[1])", "1", "ro:key"])
+        .await;
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("original"));
+
+    // EVALSHA_RO 对同一脚本同样生效，而普通 EVALSHA 允许写入
+    let script = "return redis.call('SET', KEYS[1], ARGV[1])";
+    client.send_command(&["SCRIPT", "LOAD", script]).await;
+    let sha1 = client.read_bulk_string().await.unwrap();
+    client
+        .send_command(&["EVALSHA_RO", &sha1, "1", "ro:key", "changed"])
+        .await;
+    let err = client.read_error().await;
+    assert!(err.contains("Write commands are not allowed"), "unexpected error: {}", err);
+    client
+        .send_command(&["EVALSHA", &sha1, "1", "ro:key", "changed"])
+        .await;
+    assert_eq!(client.read_simple_string().await, "OK");
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test]
+async fn test_script_exists_edge_cases() {
+    let (addr, shutdown, _handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_command(&["SCRIPT", "LOAD", "return 'hi'"]).await;
+    let sha1 = client.read_bulk_string().await.unwrap();
+    let upper = sha1.to_uppercase();
+
+    // SHA1 不区分大小写；长度不对或非十六进制的参数只是不存在，不报错
+    client
+        .send_command(&["SCRIPT", "EXISTS", &upper, "abc", "not-a-sha", &sha1, &sha1])
+        .await;
+    assert_eq!(client.read_array_len().await, 5);
+    for expected in [1, 0, 0, 1, 1] {
+        assert_eq!(client.read_integer().await, expected);
+    }
+
+    client.send_command(&["EVALSHA", &upper, "0"]).await;
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("hi"));
+    client.send_command(&["EVALSHA_RO", &upper, "0"]).await;
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("hi"));
+
+    // 40 位但含非十六进制字符的摘要找不到脚本
+    let malformed = format!("{}g", &sha1[..39]);
+    client.send_command(&["SCRIPT", "EXISTS", &malformed]).await;
+    assert_eq!(client.read_array_len().await, 1);
+    assert_eq!(client.read_integer().await, 0);
+    client.send_command(&["EVALSHA", &malformed, "0"]).await;
+    assert_eq!(
+        client.read_error().await,
+        "NOSCRIPT No matching script. Please use EVAL."
+    );
+
+    // 缺少 sha1 参数
+    client.send_command(&["SCRIPT", "EXISTS"]).await;
+    let err = client.read_error().await;
+    assert!(err.starts_with("ERR"), "unexpected error: {}", err);
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test]
+async fn test_error_and_status_reply_helpers() {
+    let (addr, shutdown, _handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_command(&["EVAL", "return redis.error_reply('boom')", "0"]).await;
+    assert_eq!(client.read_line().await, "-boom\r\n");
+
+    client.send_command(&["EVAL", "return redis.status_reply('FINE')", "0"]).await;
+    assert_eq!(client.read_line().await, "+FINE\r\n");
+
+    // 直接返回带 err / ok 字段的表，遵循 Redis 的约定
+    client.send_command(&["EVAL", "return {ok='GOOD'}", "0"]).await;
+    assert_eq!(client.read_simple_string().await, "GOOD");
+    client.send_command(&["EVAL", "return {err='ERR custom failure'}", "0"]).await;
+    assert_eq!(client.read_error().await, "ERR custom failure");
+
+    // 嵌套在数组中的状态与错误回复
+    client
+        .send_command(&[
+            "EVAL",
+            "return {1, redis.status_reply('OK'), redis.error_reply('ERR inner')}",
+            "0",
+        ])
+        .await;
+    assert_eq!(client.read_array_len().await, 3);
+    assert_eq!(client.read_integer().await, 1);
+    assert_eq!(client.read_simple_string().await, "OK");
+    assert_eq!(client.read_error().await, "ERR inner");
+
+    // 回复中的换行被替换，连接保持同步
+    client
+        .send_command(&["EVAL", "return redis.error_reply('line1\\nline2')", "0"])
+        .await;
+    assert_eq!(client.read_line().await, "-line1 line2\r\n");
+    client.send_command(&["EVAL", "return 7", "0"]).await;
+    assert_eq!(client.read_integer().await, 7);
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test]
+async fn test_sha1hex_matches_script_load() {
+    let (addr, shutdown, _handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_command(&["EVAL", "return redis.sha1hex('')", "0"]).await;
+    assert_eq!(
+        client.read_bulk_string().await.as_deref(),
+        Some("da39a3ee5e6b4b0d3255bfef95601890afd80709")
+    );
+
+    // 与 SCRIPT LOAD 使用同一种摘要
+    client.send_command(&["SCRIPT", "LOAD", "return 1"]).await;
+    let loaded = client.read_bulk_string().await.unwrap();
+    client
+        .send_command(&["EVAL", "return redis.sha1hex(ARGV[1])", "0", "return 1"])
+        .await;
+    assert_eq!(client.read_bulk_string().await, Some(loaded));
+
+    client.send_command(&["EVAL", "return redis.sha1hex()", "0"]).await;
+    let err = client.read_error().await;
+    assert!(err.contains("wrong number of arguments"), "unexpected error: {}", err);
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test]
+async fn test_cjson_encode_decode_roundtrip() {
+    let (addr, shutdown, _handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    let doc = r#"{"a":[1,2,3],"b":"x","c":{"d":true,"e":null},"f":1.5}"#;
+    client
+        .send_command(&["EVAL", "return cjson.encode(cjson.decode(ARGV[1]))", "0", doc])
+        .await;
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some(doc));
+
+    // 解码后的表可以像普通 Lua 表一样访问
+    client
+        .send_command(&[
+            "EVAL",
+            "local t = cjson.decode(ARGV[1]); return {t.a[2], t.b, tostring(t.c.e == cjson.null)}",
+            "0",
+            doc,
+        ])
+        .await;
+    assert_eq!(client.read_array_len().await, 3);
+    assert_eq!(client.read_integer().await, 2);
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("x"));
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("true"));
+
+    // 连续整数键的表编码为数组，其余为对象
+    client
+        .send_command(&[
+            "EVAL",
+            "return cjson.encode({list = {'x', cjson.null, 3}, empty = {}})",
+            "0",
+        ])
+        .await;
+    assert_eq!(
+        client.read_bulk_string().await.as_deref(),
+        Some(r#"{"empty":{},"list":["x",null,3]}"#)
+    );
+
+    client
+        .send_command(&["EVAL", "return cjson.decode('{not json')", "0"])
+        .await;
+    let err = client.read_error().await;
+    assert!(err.starts_with("ERR cjson.decode"), "unexpected error: {}", err);
+
+    client
+        .send_command(&["EVAL", "return cjson.encode({f = function() end})", "0"])
+        .await;
+    let err = client.read_error().await;
+    assert!(err.contains("Cannot serialise"), "unexpected error: {}", err);
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test]
+async fn test_pcall_catches_errors_and_numkeys_validation() {
+    let (addr, shutdown, _handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_command(&["SET", "notnum", "abc"]).await;
+    assert_eq!(client.read_simple_string().await, "OK");
+
+    // pcall 把错误作为 {err=...} 表返回，脚本继续执行
+    let script = "local r = redis.pcall('INCR', KEYS[1]); \
+                  redis.call('SET', KEYS[2], 'after'); \
+                  return {r.err, redis.call('GET', KEYS[2])}";
+    client
+        .send_command(&["EVAL", script, "2", "notnum", "marker"])
+        .await;
+    assert_eq!(client.read_array_len().await, 2);
+    assert_eq!(
+        client.read_bulk_string().await.as_deref(),
+        Some("ERR value is not an integer or out of range")
+    );
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("after"));
+
+    // 直接返回 pcall 的结果即得到错误回复
+    client
+        .send_command(&["EVAL", "return redis.pcall('INCR', KEYS[1])", "1", "notnum"])
+        .await;
+    assert_eq!(
+        client.read_error().await,
+        "ERR value is not an integer or out of range"
+    );
+
+    // call 出错则中止脚本，后续写入不会发生
+    client
+        .send_command(&[
+            "EVAL",
+            "redis.call('INCR', KEYS[1]); redis.call('SET', KEYS[2], 'never'); return 1",
+            "2",
+            "notnum",
+            "untouched",
+        ])
+        .await;
+    assert_eq!(
+        client.read_error().await,
+        "ERR value is not an integer or out of range"
+    );
+    client.send_command(&["EXISTS", "untouched"]).await;
+    assert_eq!(client.read_integer().await, 0);
+
+    // #KEYS 与 numkeys 一致，其余参数进入 ARGV
+    client
+        .send_command(&["EVAL", "return {#KEYS, #ARGV}", "2", "k1", "k2", "a1"])
+        .await;
+    assert_eq!(client.read_array_len().await, 2);
+    assert_eq!(client.read_integer().await, 2);
+    assert_eq!(client.read_integer().await, 1);
+
+    client.send_command(&["EVAL", "return 1", "3", "k1", "k2"]).await;
+    assert_eq!(
+        client.read_error().await,
+        "ERR Number of keys can't be greater than number of args"
+    );
+    client.send_command(&["EVALSHA_RO", "abc", "-1"]).await;
+    assert_eq!(client.read_error().await, "ERR Number of keys can't be negative");
+    client.send_command(&["EVAL", "return 1", "x"]).await;
+    assert_eq!(
+        client.read_error().await,
+        "ERR value is not an integer or out of range"
+    );
+
+    let _ = shutdown.send(());
+}
+
+// 多线程运行时下脚本与其他连接的命令才可能真正并发
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_scripts_are_atomic_against_concurrent_commands() {
+    let (addr, shutdown, _handle) = spawn_server().await;
+
+    const SCRIPT_RUNS: i64 = 20;
+    const SCRIPT_INCRS: i64 = 200;
+    const CLIENT_INCRS: i64 = 2000;
+
+    // 脚本用 GET + SET 做非原子的读-改-写；若其他连接的 INCR 插入其中就会丢失更新
+    let script = "for i = 1, tonumber(ARGV[1]) do \
+                    local v = tonumber(redis.call('GET', KEYS[1]) or '0'); \
+                    redis.call('SET', KEYS[1], v + 1) \
+                  end \
+                  return 1";
+
+    let scripter = tokio::spawn(async move {
+        let mut client = TestClient::connect(addr).await;
+        let incrs = SCRIPT_INCRS.to_string();
+        for _ in 0..SCRIPT_RUNS {
+            client
+                .send_command(&["EVAL", script, "1", "atomic:counter", &incrs])
+                .await;
+            assert_eq!(client.read_integer().await, 1);
+        }
+    });
+
+    let incrementer = tokio::spawn(async move {
+        let mut client = TestClient::connect(addr).await;
+        for _ in 0..CLIENT_INCRS {
+            client.send_command(&["INCR", "atomic:counter"]).await;
+            client.read_integer().await;
+        }
+    });
+
+    scripter.await.unwrap();
+    incrementer.await.unwrap();
+
+    let mut client = TestClient::connect(addr).await;
+    client.send_command(&["GET", "atomic:counter"]).await;
+    let total: i64 = client.read_bulk_string().await.unwrap().parse().unwrap();
+    assert_eq!(total, SCRIPT_RUNS * SCRIPT_INCRS + CLIENT_INCRS);
+
+    let _ = shutdown.send(());
+}
+
+// 持有共享锁的命令不能因为客户端不读回复而一直占着锁，否则 EVAL 及排在它后面的连接都会卡住
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_slow_reader_does_not_block_scripts() {
+    let (addr, shutdown, _handle) = spawn_server().await;
+
+    // 约 16MB 的列表，回复远大于 socket 缓冲区
+    let mut client = TestClient::connect(addr).await;
+    let item = "x".repeat(8192);
+    for _ in 0..20 {
+        let mut args = vec!["RPUSH", "slow:list"];
+        args.extend(std::iter::repeat(item.as_str()).take(100));
+        client.send_command(&args).await;
+        client.read_integer().await;
+    }
+
+    // 发送 LRANGE 后不读取回复
+    let mut stalled = TestClient::connect(addr).await;
+    stalled.send_command(&["LRANGE", "slow:list", "0", "-1"]).await;
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    let reply = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        client.send_command(&["EVAL", "return 1", "0"]).await;
+        client.read_integer().await
+    })
+    .await
+    .expect("EVAL blocked by a client that does not read its replies");
+    assert_eq!(reply, 1);
+
+    drop(stalled);
+    let _ = shutdown.send(());
+}
+
+#[tokio::test]
+async fn test_function_load_fcall_list_flush() {
+    let (addr, shutdown, _handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    let library = "#!lua name=mylib\n\
+                   redis.register_function('myset', function(keys, args) \
+                       return redis.call('SET', keys[1], args[1]) end)\n\
+                   redis.register_function{function_name='myget', \
+                       callback=function(keys) return redis.call('GET', keys[1]) end, \
+                       flags={'no-writes'}}";
+    client.send_command(&["FUNCTION", "LOAD", library]).await;
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("mylib"));
+
+    client.send_command(&["FCALL", "myset", "1", "fkey", "fval"]).await;
+    assert_eq!(client.read_simple_string().await, "OK");
+    client.send_command(&["FCALL_RO", "myget", "1", "fkey"]).await;
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("fval"));
+
+    // 没有 no-writes 标志的函数不能通过 FCALL_RO 调用
+    client.send_command(&["FCALL_RO", "myset", "1", "fkey", "x"]).await;
+    assert_eq!(
+        client.read_error().await,
+        "ERR Can not execute a script with write flag using *_ro command."
+    );
+
+    // 重复加载需要 REPLACE；缺少元数据或未注册函数都会报错
+    client.send_command(&["FUNCTION", "LOAD", library]).await;
+    assert_eq!(client.read_error().await, "ERR Library 'mylib' already exists");
+    client.send_command(&["FUNCTION", "LOAD", "REPLACE", library]).await;
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("mylib"));
+    client.send_command(&["FUNCTION", "LOAD", "return 1"]).await;
+    assert_eq!(client.read_error().await, "ERR Missing library metadata");
+    client.send_command(&["FUNCTION", "LOAD", "#!lua name=empty\nlocal x = 1"]).await;
+    assert_eq!(client.read_error().await, "ERR No functions registered");
+    client
+        .send_command(&[
+            "FUNCTION",
+            "LOAD",
+            "#!lua name=other\nredis.register_function('myget', function() return 1 end)",
+        ])
+        .await;
+    assert_eq!(client.read_error().await, "ERR Function myget already exists");
+
+    client.send_command(&["FUNCTION", "LIST", "WITHCODE"]).await;
+    assert_eq!(client.read_array_len().await, 1);
+    assert_eq!(client.read_array_len().await, 8);
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("library_name"));
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("mylib"));
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("engine"));
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("LUA"));
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("functions"));
+    assert_eq!(client.read_array_len().await, 2);
+    for (name, flags) in [("myset", vec![]), ("myget", vec!["no-writes"])] {
+        assert_eq!(client.read_array_len().await, 6);
+        assert_eq!(client.read_bulk_string().await.as_deref(), Some("name"));
+        assert_eq!(client.read_bulk_string().await.as_deref(), Some(name));
+        assert_eq!(client.read_bulk_string().await.as_deref(), Some("description"));
+        assert_eq!(client.read_bulk_string().await, None);
+        assert_eq!(client.read_bulk_string().await.as_deref(), Some("flags"));
+        assert_eq!(client.read_array_len().await, flags.len() as i64);
+        for flag in flags {
+            assert_eq!(client.read_bulk_string().await.as_deref(), Some(flag));
+        }
+    }
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("library_code"));
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some(library));
+
+    client.send_command(&["FUNCTION", "FLUSH"]).await;
+    assert_eq!(client.read_simple_string().await, "OK");
+    client.send_command(&["FUNCTION", "LIST"]).await;
+    assert_eq!(client.read_array_len().await, 0);
+    client.send_command(&["FCALL", "myset", "1", "fkey", "fval"]).await;
+    assert_eq!(client.read_error().await, "ERR Function not found");
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_busy_script_can_be_killed() {
+    let (addr, shutdown, _handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+    let mut other = TestClient::connect(addr).await;
+
+    client
+        .send_command(&["CONFIG", "SET", "busy-reply-threshold", "200"])
+        .await;
+    assert_eq!(client.read_simple_string().await, "OK");
+    client.send_command(&["CONFIG", "GET", "lua-time-limit"]).await;
+    assert_eq!(client.read_array_len().await, 2);
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("lua-time-limit"));
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("200"));
+
+    other.send_command(&["SCRIPT", "KILL"]).await;
+    assert_eq!(
+        other.read_error().await,
+        "NOTBUSY No scripts in execution right now."
+    );
+
+    client.send_command(&["EVAL", "while true do end", "0"]).await;
+    tokio::time::sleep(std::time::Duration::from_millis(400)).await;
+
+    // 超过阈值后其他连接的命令立即返回 BUSY，而不是一直等待
+    other.send_command(&["GET", "busy:key"]).await;
+    let err = other.read_error().await;
+    assert!(err.starts_with("BUSY "), "unexpected error: {}", err);
+    other.send_command(&["EVAL", "return 1", "0"]).await;
+    let err = other.read_error().await;
+    assert!(err.starts_with("BUSY "), "unexpected error: {}", err);
+
+    other.send_command(&["SCRIPT", "KILL"]).await;
+    assert_eq!(other.read_simple_string().await, "OK");
+    let err = client.read_error().await;
+    assert!(
+        err.starts_with("ERR Script killed by user with SCRIPT KILL"),
+        "unexpected error: {}",
+        err
+    );
+
+    other.send_command(&["SET", "busy:key", "1"]).await;
+    assert_eq!(other.read_simple_string().await, "OK");
+
+    client
+        .send_command(&["CONFIG", "SET", "lua-time-limit", "abc"])
+        .await;
+    let err = client.read_error().await;
+    assert!(err.starts_with("ERR Invalid argument"), "unexpected error: {}", err);
+    client
+        .send_command(&["CONFIG", "SET", "lua-time-limit", "5000"])
+        .await;
+    assert_eq!(client.read_simple_string().await, "OK");
+
+    let _ = shutdown.send(());
+}
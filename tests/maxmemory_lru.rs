@@ -1,5 +1,5 @@
 use std::net::SocketAddr;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::oneshot;
 
@@ -12,6 +12,7 @@ async fn spawn_server() -> (
     oneshot::Sender<()>,
     tokio::task::JoinHandle<tokio::io::Result<()>>,
 ) {
+    std::env::set_var("REDUST_DISABLE_PERSISTENCE", "1");
     let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind server");
     let addr = listener.local_addr().expect("local addr");
     let (tx, rx) = oneshot::channel();
@@ -61,6 +62,20 @@ impl TestClient {
         assert_eq!(line, "+OK\r\n");
     }
 
+    async fn used_memory(&mut self) -> u64 {
+        self.send_array(&["INFO", "memory"]).await;
+        let header = self.read_simple_line().await;
+        let len: usize = header[1..header.len() - 2].parse().unwrap();
+        let mut body = vec![0u8; len + 2];
+        self.reader.read_exact(&mut body).await.unwrap();
+        let body = String::from_utf8(body).unwrap();
+        body.lines()
+            .find_map(|l| l.strip_prefix("used_memory:"))
+            .expect("used_memory field")
+            .parse()
+            .unwrap()
+    }
+
     async fn dbsize(&mut self) -> i64 {
         self.send_array(&["DBSIZE"]).await;
         let line = self.read_simple_line().await;
@@ -94,3 +109,222 @@ async fn basic_maxmemory_eviction_smoke() {
     shutdown.send(()).unwrap();
     handle.await.unwrap().unwrap();
 }
+
+#[tokio::test]
+async fn debug_set_used_memory_drives_eviction_deterministically() {
+    let _lock = ENV_LOCK.lock().unwrap();
+    let _guard = set_env("REDUST_MAXMEMORY_BYTES", "1000");
+    let _persistence = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    // 10 个 key，每个估算 14 字节（带 db 前缀的 4 字节 key + 10 字节 value），远低于上限
+    for i in 0..10 {
+        client.set(&format!("k{}", i), "vvvvvvvvvv").await;
+    }
+    assert_eq!(client.dbsize().await, 10);
+
+    // 伪造用量到 1100：DEBUG 本身不淘汰，下一次写入才触发
+    client.send_array(&["DEBUG", "SET-USED-MEMORY", "1100"]).await;
+    assert_eq!(client.read_simple_line().await, "+OK\r\n");
+    assert_eq!(client.dbsize().await, 10);
+
+    // 写入 6 字节后用量为 1106，需要淘汰 8 个旧 key（112 字节）才能回到 1000 以内
+    client.set("k10", "x").await;
+    assert_eq!(client.dbsize().await, 3);
+    client.send_array(&["EXISTS", "k10"]).await;
+    assert_eq!(client.read_simple_line().await, ":1\r\n");
+
+    client.send_array(&["DEBUG", "SET-USED-MEMORY", "-1"]).await;
+    assert!(client.read_simple_line().await.starts_with("-ERR"));
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn config_set_maxmemory_applies_at_runtime() {
+    let _lock = ENV_LOCK.lock().unwrap();
+    let _guard = set_env("REDUST_MAXMEMORY_BYTES", "0");
+    let _persistence = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    for i in 0..10 {
+        client.set(&format!("k{}", i), "vvvvvvvvvv").await;
+    }
+    client.send_array(&["CONFIG", "GET", "maxmemory"]).await;
+    assert_eq!(client.read_simple_line().await, "*2\r\n");
+    assert_eq!(client.read_simple_line().await, "$9\r\n");
+    assert_eq!(client.read_simple_line().await, "maxmemory\r\n");
+    assert_eq!(client.read_simple_line().await, "$1\r\n");
+    assert_eq!(client.read_simple_line().await, "0\r\n");
+
+    // 调低上限后立即按策略淘汰，不必等下一次写命令
+    client.send_array(&["CONFIG", "SET", "maxmemory", "100"]).await;
+    assert_eq!(client.read_simple_line().await, "+OK\r\n");
+    assert!(client.dbsize().await < 10);
+    assert!(client.used_memory().await <= 100);
+    client.send_array(&["CONFIG", "GET", "maxmemory"]).await;
+    assert_eq!(client.read_simple_line().await, "*2\r\n");
+    assert_eq!(client.read_simple_line().await, "$9\r\n");
+    assert_eq!(client.read_simple_line().await, "maxmemory\r\n");
+    assert_eq!(client.read_simple_line().await, "$3\r\n");
+    assert_eq!(client.read_simple_line().await, "100\r\n");
+
+    client.send_array(&["CONFIG", "SET", "maxmemory", "lots"]).await;
+    assert!(client.read_simple_line().await.starts_with("-ERR Invalid argument"));
+
+    // 0 取消上限
+    client.send_array(&["CONFIG", "SET", "maxmemory", "0"]).await;
+    assert_eq!(client.read_simple_line().await, "+OK\r\n");
+    let before = client.dbsize().await;
+    for i in 0..10 {
+        client.set(&format!("big{}", i), &"x".repeat(100)).await;
+    }
+    assert_eq!(client.dbsize().await, before + 10);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn eviction_picks_least_recently_used_key() {
+    let _lock = ENV_LOCK.lock().unwrap();
+    let _guard = set_env("REDUST_MAXMEMORY_BYTES", "1000");
+    let _persistence = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    // 5 个 key 不超过采样数，淘汰时全部参与比较，结果是确定的
+    for key in ["a", "b", "c", "d", "e"] {
+        client.set(key, "vvvvvvvvvv").await;
+    }
+    for key in ["a", "b"] {
+        client.send_array(&["GET", key]).await;
+        assert_eq!(client.read_simple_line().await, "$10\r\n");
+        assert_eq!(client.read_simple_line().await, "vvvvvvvvvv\r\n");
+    }
+
+    // 超出 10 字节，写入前只需淘汰一个 key：最久未访问的是 c
+    client.send_array(&["DEBUG", "SET-USED-MEMORY", "1010"]).await;
+    assert_eq!(client.read_simple_line().await, "+OK\r\n");
+    client.set("f", "vvvvvvvvvv").await;
+    assert_eq!(client.dbsize().await, 5);
+    client.send_array(&["EXISTS", "c"]).await;
+    assert_eq!(client.read_simple_line().await, ":0\r\n");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn append_growth_past_maxmemory_rejects_next_write_with_oom() {
+    let _lock = ENV_LOCK.lock().unwrap();
+    let _guard = set_env("REDUST_MAXMEMORY_BYTES", "200");
+    let _policy = set_env("REDUST_MAXMEMORY_POLICY", "noeviction");
+    let _persistence = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["CONFIG", "GET", "maxmemory-policy"]).await;
+    assert_eq!(client.read_simple_line().await, "*2\r\n");
+    client.read_simple_line().await;
+    client.read_simple_line().await;
+    client.read_simple_line().await;
+    assert_eq!(client.read_simple_line().await, "noeviction\r\n");
+
+    // key 带 db 前缀估算为 3 字节：追加 4 次后为 203 字节，执行前仍未超限所以都成功
+    let chunk = "x".repeat(50);
+    for i in 1..=4 {
+        client.send_array(&["APPEND", "k", &chunk]).await;
+        assert_eq!(client.read_simple_line().await, format!(":{}\r\n", i * 50));
+    }
+
+    // 原地增长已计入用量，下一次写入被拒绝且不修改数据
+    client.send_array(&["APPEND", "k", "y"]).await;
+    assert!(client.read_simple_line().await.starts_with("-OOM "));
+    client.send_array(&["SET", "other", "v"]).await;
+    assert!(client.read_simple_line().await.starts_with("-OOM "));
+    client.send_array(&["STRLEN", "k"]).await;
+    assert_eq!(client.read_simple_line().await, ":200\r\n");
+
+    // 删除不受限制，释放后可以继续写入
+    client.send_array(&["DEL", "k"]).await;
+    assert_eq!(client.read_simple_line().await, ":1\r\n");
+    client.set("other", "v").await;
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn setrange_padding_past_maxmemory_returns_oom() {
+    let _lock = ENV_LOCK.lock().unwrap();
+    let _guard = set_env("REDUST_MAXMEMORY_BYTES", "200");
+    let _policy = set_env("REDUST_MAXMEMORY_POLICY", "noeviction");
+    let _persistence = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    // key 带 db 前缀估算为 3 字节：补齐到 151 字节后用量为 154
+    client.send_array(&["SETRANGE", "k", "150", "x"]).await;
+    assert_eq!(client.read_simple_line().await, ":151\r\n");
+
+    // 恰好补齐到 200 字节的用量仍然允许
+    client.send_array(&["SETRANGE", "k", "196", "x"]).await;
+    assert_eq!(client.read_simple_line().await, ":197\r\n");
+
+    // 再多一个字节就超限：分配前拒绝，原值不变
+    client.send_array(&["SETRANGE", "k", "197", "x"]).await;
+    assert!(client.read_simple_line().await.starts_with("-OOM "));
+    client.send_array(&["STRLEN", "k"]).await;
+    assert_eq!(client.read_simple_line().await, ":197\r\n");
+
+    // allkeys-lru 下淘汰其他 key 也放不下的 value 同样拒绝
+    client
+        .send_array(&["CONFIG", "SET", "maxmemory-policy", "allkeys-lru"])
+        .await;
+    assert_eq!(client.read_simple_line().await, "+OK\r\n");
+    client.send_array(&["SETRANGE", "big", "100000", "x"]).await;
+    assert!(client.read_simple_line().await.starts_with("-OOM "));
+    client.send_array(&["EXISTS", "big"]).await;
+    assert_eq!(client.read_simple_line().await, ":0\r\n");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn setbit_growth_is_counted_in_used_memory() {
+    let _lock = ENV_LOCK.lock().unwrap();
+    let _persistence = set_env("REDUST_DISABLE_PERSISTENCE", "1");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    let before = client.used_memory().await;
+
+    // 第 799 位位于第 100 个字节：新 key 计入 4 字节 key（带 db 前缀）+ 100 字节补齐后的值
+    client.send_array(&["SETBIT", "bm", "799", "1"]).await;
+    assert_eq!(client.read_simple_line().await, ":0\r\n");
+    assert_eq!(client.used_memory().await, before + 104);
+
+    // 再向后扩展 100 个字节
+    client.send_array(&["SETBIT", "bm", "1599", "1"]).await;
+    assert_eq!(client.read_simple_line().await, ":0\r\n");
+    assert_eq!(client.used_memory().await, before + 204);
+
+    client.send_array(&["GETBIT", "bm", "799"]).await;
+    assert_eq!(client.read_simple_line().await, ":1\r\n");
+    client.send_array(&["GETBIT", "bm", "800"]).await;
+    assert_eq!(client.read_simple_line().await, ":0\r\n");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
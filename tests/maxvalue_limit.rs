@@ -14,6 +14,7 @@ async fn spawn_server() -> (
     oneshot::Sender<()>,
     tokio::task::JoinHandle<tokio::io::Result<()>>,
 ) {
+    std::env::set_var("REDUST_DISABLE_PERSISTENCE", "1");
     let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind server");
     let addr = listener.local_addr().expect("local addr");
     let (tx, rx) = oneshot::channel();
@@ -131,3 +132,74 @@ async fn mset_respects_limit() {
     shutdown.send(()).unwrap();
     handle.await.unwrap().unwrap();
 }
+
+#[tokio::test]
+async fn zadd_member_respects_limit() {
+    let _lock = ENV_LOCK.lock().unwrap();
+    let _guard = set_env("REDUST_MAXVALUE_BYTES", "8");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client
+        .send_array(&["ZADD", "z", "1", "ok", "2", "123456789"])
+        .await;
+    let line = client.read_simple_line().await;
+    assert!(line.starts_with("-ERR value exceeds REDUST_MAXVALUE_BYTES"));
+
+    // 整条 ZADD 被拒绝，合法成员也不应写入
+    client.send_array(&["ZCARD", "z"]).await;
+    let line = client.read_simple_line().await;
+    assert_eq!(line, ":0\r\n");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn hset_field_respects_limit() {
+    let _lock = ENV_LOCK.lock().unwrap();
+    let _guard = set_env("REDUST_MAXVALUE_BYTES", "8");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["HSET", "h", "123456789", "v"]).await;
+    let line = client.read_simple_line().await;
+    assert!(line.starts_with("-ERR value exceeds REDUST_MAXVALUE_BYTES"));
+
+    client.send_array(&["HLEN", "h"]).await;
+    let line = client.read_simple_line().await;
+    assert_eq!(line, ":0\r\n");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn setbit_padding_respects_limit() {
+    let _lock = ENV_LOCK.lock().unwrap();
+    let _guard = set_env("REDUST_MAXVALUE_BYTES", "8");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    // 第 63 位落在第 8 个字节，补齐后恰好 8 字节
+    client.send_array(&["SETBIT", "maxvalue_bits", "63", "1"]).await;
+    let line = client.read_simple_line().await;
+    assert_eq!(line, ":0\r\n");
+
+    // 巨大偏移需要补齐数百 MB，直接拒绝且不修改原值
+    client
+        .send_array(&["SETBIT", "maxvalue_bits", "4000000000", "1"])
+        .await;
+    let line = client.read_simple_line().await;
+    assert!(line.starts_with("-ERR value exceeds REDUST_MAXVALUE_BYTES"));
+
+    client.send_array(&["STRLEN", "maxvalue_bits"]).await;
+    let line = client.read_simple_line().await;
+    assert_eq!(line, ":8\r\n");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
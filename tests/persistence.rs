@@ -58,6 +58,49 @@ impl TestClient {
         self.reader.read_line(&mut line).await.unwrap();
         line
     }
+
+    async fn read_bulk(&mut self) -> String {
+        let header = self.read_simple_line().await;
+        assert!(header.starts_with('$'), "unexpected header {:?}", header);
+        let len: usize = header[1..].trim_end().parse().unwrap();
+        let mut buf = vec![0u8; len + 2];
+        self.reader.read_exact(&mut buf).await.unwrap();
+        buf.truncate(len);
+        String::from_utf8(buf).unwrap()
+    }
+
+    /// 读取一个完整回复的原始文本（数组递归读取），便于前后对比
+    async fn read_raw_reply(&mut self) -> String {
+        let header = self.read_simple_line().await;
+        let mut out = header.clone();
+        match header.as_bytes()[0] {
+            b'$' => {
+                let len: i64 = header[1..].trim_end().parse().unwrap();
+                if len >= 0 {
+                    let mut buf = vec![0u8; len as usize + 2];
+                    self.reader.read_exact(&mut buf).await.unwrap();
+                    out.push_str(&String::from_utf8_lossy(&buf));
+                }
+            }
+            b'*' => {
+                let n: i64 = header[1..].trim_end().parse().unwrap();
+                for _ in 0..n.max(0) {
+                    out.push_str(&Box::pin(self.read_raw_reply()).await);
+                }
+            }
+            _ => {}
+        }
+        out
+    }
+
+    async fn info_field(&mut self, field: &str) -> String {
+        self.send_array(&["INFO"]).await;
+        let info = self.read_bulk().await;
+        let prefix = format!("{}:", field);
+        info.lines()
+            .find_map(|l| l.strip_prefix(&prefix).map(|v| v.to_string()))
+            .unwrap_or_else(|| panic!("INFO missing {}", field))
+    }
 }
 
 #[tokio::test]
@@ -114,9 +157,11 @@ async fn aof_everysec_persists_state() {
     let path = tmp.join(format!("redust_aof_{}.aof", rand::random::<u64>()));
     let path_str = path.to_string_lossy().to_string();
     let _ = std::fs::remove_file(&path);
+    // SAVE 仍写 RDB 路径，指向临时目录以免在仓库根目录留下 redust.rdb
+    let rdb = tmp.join(format!("redust_aof_{}.rdb", rand::random::<u64>()));
     std::env::set_var("REDUST_AOF_ENABLED", "1");
     std::env::set_var("REDUST_AOF_PATH", &path_str);
-    std::env::remove_var("REDUST_RDB_PATH");
+    std::env::set_var("REDUST_RDB_PATH", rdb.to_string_lossy().to_string());
 
     let (addr, shutdown, handle) = spawn_server().await;
     let mut client = TestClient::connect(addr).await;
@@ -152,6 +197,459 @@ async fn aof_everysec_persists_state() {
     handle2.await.unwrap().unwrap();
 
     let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&rdb);
+    std::env::remove_var("REDUST_AOF_ENABLED");
+    std::env::remove_var("REDUST_AOF_PATH");
+    std::env::remove_var("REDUST_RDB_PATH");
+}
+
+#[tokio::test]
+async fn save_resets_changes_since_last_save() {
+    let _guard = persistence_lock();
+    let tmp = std::env::temp_dir();
+    let path = tmp.join(format!("redust_dirty_{}.rdb", rand::random::<u64>()));
+    let path_str = path.to_string_lossy().to_string();
+    std::env::set_var("REDUST_RDB_PATH", &path_str);
+    std::env::remove_var("REDUST_AOF_ENABLED");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    assert_eq!(client.info_field("rdb_changes_since_last_save").await, "0");
+    assert_eq!(client.info_field("rdb_bgsave_in_progress").await, "0");
+
+    client.send_array(&["SET", "d1", "v"]).await;
+    let _ = client.read_simple_line().await;
+    client.send_array(&["RPUSH", "d2", "a", "b"]).await;
+    let _ = client.read_simple_line().await;
+    assert_eq!(client.info_field("rdb_changes_since_last_save").await, "2");
+
+    client.send_array(&["SAVE"]).await;
+    assert_eq!(client.read_simple_line().await, "+OK\r\n");
+    assert_eq!(client.info_field("rdb_changes_since_last_save").await, "0");
+
+    client.send_array(&["DEL", "d1"]).await;
+    let _ = client.read_simple_line().await;
+    assert_eq!(client.info_field("rdb_changes_since_last_save").await, "1");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+
+    let _ = std::fs::remove_file(&path);
+    std::env::remove_var("REDUST_RDB_PATH");
+}
+
+#[tokio::test]
+async fn save_is_rejected_while_another_save_runs() {
+    let _guard = persistence_lock();
+    let tmp = std::env::temp_dir();
+    let path = tmp.join(format!("redust_save_overlap_{}.rdb", rand::random::<u64>()));
+    let path_str = path.to_string_lossy().to_string();
+    std::env::set_var("REDUST_RDB_PATH", &path_str);
+    std::env::remove_var("REDUST_AOF_ENABLED");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    // 足够多的数据让 BGSAVE 持续一段时间
+    let mut filler = String::new();
+    for i in 0..50_000 {
+        let key = format!("overlap:{}", i);
+        filler.push_str(&format!("*3\r\n$3\r\nSET\r\n${}\r\n{}\r\n$1\r\nv\r\n", key.len(), key));
+    }
+    client.writer.write_all(filler.as_bytes()).await.unwrap();
+    for _ in 0..50_000 {
+        assert_eq!(client.read_simple_line().await, "+OK\r\n");
+    }
+
+    // BGSAVE 与 SAVE 在同一次写入中发出，SAVE 到达时后台保存一定还在进行
+    client
+        .writer
+        .write_all(b"*1\r\n$6\r\nBGSAVE\r\n*1\r\n$4\r\nSAVE\r\n")
+        .await
+        .unwrap();
+    assert_eq!(client.read_simple_line().await, "+Background saving started\r\n");
+    assert_eq!(
+        client.read_simple_line().await,
+        "-ERR Background save already in progress\r\n"
+    );
+
+    let mut done = false;
+    for _ in 0..250 {
+        if client.info_field("rdb_bgsave_in_progress").await == "0" {
+            done = true;
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert!(done, "BGSAVE did not finish");
+    // 只有一次保存扣减了 dirty，计数不会回绕
+    assert_eq!(client.info_field("rdb_changes_since_last_save").await, "0");
+
+    client.send_array(&["SAVE"]).await;
+    assert_eq!(client.read_simple_line().await, "+OK\r\n");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+
+    let _ = std::fs::remove_file(&path);
+    std::env::remove_var("REDUST_RDB_PATH");
+}
+
+#[tokio::test]
+async fn slow_reader_does_not_hold_off_save() {
+    let _guard = persistence_lock();
+    let tmp = std::env::temp_dir();
+    let path = tmp.join(format!("redust_slow_reader_{}.rdb", rand::random::<u64>()));
+    std::env::set_var("REDUST_RDB_PATH", path.to_string_lossy().to_string());
+    std::env::remove_var("REDUST_AOF_ENABLED");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    // 16MB 的 value，GETDEL 的回复远大于 socket 缓冲区
+    let big = "x".repeat(16 * 1024 * 1024);
+    for key in ["slow:a", "slow:b"] {
+        client.send_array(&["SET", key, &big]).await;
+        assert_eq!(client.read_simple_line().await, "+OK\r\n");
+    }
+
+    // 写命令与 EXEC 的回复都不读取：闸门不能一直被它们占着
+    let mut stalled = TestClient::connect(addr).await;
+    stalled.send_array(&["GETDEL", "slow:a"]).await;
+    stalled.send_array(&["MULTI"]).await;
+    stalled.send_array(&["GETDEL", "slow:b"]).await;
+    stalled.send_array(&["EXEC"]).await;
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    let reply = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        client.send_array(&["SAVE"]).await;
+        client.read_simple_line().await
+    })
+    .await
+    .expect("SAVE blocked by a client that does not read its replies");
+    assert_eq!(reply, "+OK\r\n");
+
+    drop(stalled);
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+
+    let _ = std::fs::remove_file(&path);
+    std::env::remove_var("REDUST_RDB_PATH");
+}
+
+#[tokio::test]
+async fn aof_snapshots_do_not_reset_changes_since_last_save() {
+    let _guard = persistence_lock();
+    let tmp = std::env::temp_dir();
+    let aof = tmp.join(format!("redust_aof_dirty_{}.aof", rand::random::<u64>()));
+    let rdb = tmp.join(format!("redust_aof_dirty_{}.rdb", rand::random::<u64>()));
+    std::env::set_var("REDUST_AOF_ENABLED", "1");
+    std::env::set_var("REDUST_AOF_PATH", aof.to_string_lossy().to_string());
+    std::env::set_var("REDUST_RDB_PATH", rdb.to_string_lossy().to_string());
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["SET", "ad1", "v"]).await;
+    assert_eq!(client.read_simple_line().await, "+OK\r\n");
+    client.send_array(&["SET", "ad2", "v"]).await;
+    assert_eq!(client.read_simple_line().await, "+OK\r\n");
+
+    // 等待至少一次 everysec 快照落盘
+    let mut written = false;
+    for _ in 0..60 {
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        if std::fs::metadata(&aof).map(|m| m.len() > 0).unwrap_or(false) {
+            written = true;
+            break;
+        }
+    }
+    assert!(written, "AOF snapshot was not written");
+    // AOF 快照不算 RDB 保存：变更数保持不变，LASTSAVE 也不更新
+    assert_eq!(client.info_field("rdb_changes_since_last_save").await, "2");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+
+    let _ = std::fs::remove_file(&aof);
+    let _ = std::fs::remove_file(&rdb);
     std::env::remove_var("REDUST_AOF_ENABLED");
     std::env::remove_var("REDUST_AOF_PATH");
+    std::env::remove_var("REDUST_RDB_PATH");
+}
+
+#[tokio::test]
+async fn save_point_triggers_background_save_after_write() {
+    let _guard = persistence_lock();
+    let tmp = std::env::temp_dir();
+    let path = tmp.join(format!("redust_savepoint_{}.rdb", rand::random::<u64>()));
+    let path_str = path.to_string_lossy().to_string();
+    let _ = std::fs::remove_file(&path);
+    std::env::set_var("REDUST_RDB_PATH", &path_str);
+    std::env::set_var("REDUST_RDB_SAVE", "1 1");
+    std::env::remove_var("REDUST_AOF_ENABLED");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["CONFIG", "GET", "save"]).await;
+    assert_eq!(client.read_simple_line().await, "*2\r\n");
+    assert_eq!(client.read_bulk().await, "save");
+    assert_eq!(client.read_bulk().await, "1 1");
+
+    // 没有写入时不会触发保存
+    tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+    assert!(!path.exists());
+
+    client.send_array(&["SET", "sp", "v"]).await;
+    let _ = client.read_simple_line().await;
+
+    let mut saved = false;
+    for _ in 0..40 {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        if path.exists() {
+            saved = true;
+            break;
+        }
+    }
+    assert!(saved, "save point 1 1 should trigger a save");
+    assert_eq!(client.info_field("rdb_changes_since_last_save").await, "0");
+
+    std::env::remove_var("REDUST_RDB_SAVE");
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+
+    let _ = std::fs::remove_file(&path);
+    std::env::remove_var("REDUST_RDB_PATH");
+}
+
+#[tokio::test]
+async fn debug_reload_preserves_all_types() {
+    let _guard = persistence_lock();
+    let tmp = std::env::temp_dir();
+    let path = tmp.join(format!("redust_reload_{}.rdb", rand::random::<u64>()));
+    let path_str = path.to_string_lossy().to_string();
+    let _ = std::fs::remove_file(&path);
+    std::env::set_var("REDUST_RDB_PATH", &path_str);
+    std::env::remove_var("REDUST_AOF_ENABLED");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    let setup: [&[&str]; 7] = [
+        &["SET", "r:str", "hello"],
+        &["RPUSH", "r:list", "a", "b", "c"],
+        &["SADD", "r:set", "x", "y"],
+        &["HSET", "r:hash", "f1", "v1"],
+        &["HSET", "r:hash", "f2", "v2"],
+        &["ZADD", "r:zset", "1.5", "m1", "2", "m2"],
+        &["PFADD", "r:hll", "e1", "e2", "e3"],
+    ];
+    for cmd in setup {
+        client.send_array(cmd).await;
+        let _ = client.read_simple_line().await;
+    }
+
+    let queries: [&[&str]; 8] = [
+        &["GET", "r:str"],
+        &["LRANGE", "r:list", "0", "-1"],
+        &["SCARD", "r:set"],
+        &["SISMEMBER", "r:set", "x"],
+        &["SISMEMBER", "r:set", "y"],
+        &["HMGET", "r:hash", "f1", "f2"],
+        &["ZRANGE", "r:zset", "0", "-1", "WITHSCORES"],
+        &["PFCOUNT", "r:hll"],
+    ];
+    let mut before = Vec::new();
+    for q in queries {
+        client.send_array(q).await;
+        before.push(client.read_raw_reply().await);
+    }
+
+    client.send_array(&["DEBUG", "RELOAD"]).await;
+    assert_eq!(client.read_simple_line().await, "+OK\r\n");
+
+    let mut after = Vec::new();
+    for q in queries {
+        client.send_array(q).await;
+        after.push(client.read_raw_reply().await);
+    }
+    assert_eq!(before, after);
+    client.send_array(&["DBSIZE"]).await;
+    assert_eq!(client.read_simple_line().await, ":6\r\n");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+
+    let _ = std::fs::remove_file(&path);
+    std::env::remove_var("REDUST_RDB_PATH");
+}
+
+// 多线程运行时下其他连接的写命令才可能真正落在保存与重新加载之间
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn debug_reload_does_not_drop_concurrent_writes() {
+    let _guard = persistence_lock();
+    let tmp = std::env::temp_dir();
+    let path = tmp.join(format!("redust_reload_race_{}.rdb", rand::random::<u64>()));
+    std::env::set_var("REDUST_RDB_PATH", path.to_string_lossy().to_string());
+    std::env::remove_var("REDUST_AOF_ENABLED");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    const INCRS: usize = 2000;
+    let writer = tokio::spawn(async move {
+        let mut client = TestClient::connect(addr).await;
+        for _ in 0..INCRS {
+            client.send_array(&["INCR", "reload:counter"]).await;
+            assert!(client.read_simple_line().await.starts_with(':'));
+        }
+    });
+
+    let mut client = TestClient::connect(addr).await;
+    while !writer.is_finished() {
+        client.send_array(&["DEBUG", "RELOAD"]).await;
+        assert_eq!(client.read_simple_line().await, "+OK\r\n");
+    }
+    writer.await.unwrap();
+
+    client.send_array(&["GET", "reload:counter"]).await;
+    assert_eq!(client.read_simple_line().await, "$4\r\n");
+    assert_eq!(client.read_simple_line().await, format!("{}\r\n", INCRS));
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+
+    let _ = std::fs::remove_file(&path);
+    std::env::remove_var("REDUST_RDB_PATH");
+}
+
+#[tokio::test]
+async fn shutdown_command_stops_server_with_optional_save() {
+    let _guard = persistence_lock();
+    let tmp = std::env::temp_dir();
+    let path = tmp.join(format!("redust_shutdown_{}.rdb", rand::random::<u64>()));
+    let path_str = path.to_string_lossy().to_string();
+    let _ = std::fs::remove_file(&path);
+    std::env::set_var("REDUST_RDB_PATH", &path_str);
+    std::env::remove_var("REDUST_AOF_ENABLED");
+
+    // NOSAVE：不回复、直接断开，服务退出且不写快照
+    let (addr, _shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+    client.send_array(&["SET", "skey", "lost"]).await;
+    let _ = client.read_simple_line().await;
+    client.send_array(&["SHUTDOWN", "NOSAVE"]).await;
+    assert_eq!(client.read_simple_line().await, "");
+    tokio::time::timeout(std::time::Duration::from_secs(5), handle)
+        .await
+        .expect("server should stop after SHUTDOWN NOSAVE")
+        .unwrap()
+        .unwrap();
+    assert!(!path.exists());
+    assert!(TcpStream::connect(addr).await.is_err());
+
+    // 默认（启用持久化时）先保存再退出，重启后数据仍在
+    let (addr, _shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+    client.send_array(&["SET", "skey", "kept"]).await;
+    let _ = client.read_simple_line().await;
+    client.send_array(&["SHUTDOWN", "SAVE", "NOSAVE"]).await;
+    assert_eq!(client.read_simple_line().await, "-ERR syntax error\r\n");
+    client.send_array(&["SHUTDOWN"]).await;
+    assert_eq!(client.read_simple_line().await, "");
+    tokio::time::timeout(std::time::Duration::from_secs(5), handle)
+        .await
+        .expect("server should stop after SHUTDOWN")
+        .unwrap()
+        .unwrap();
+    assert!(path.exists());
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+    client.send_array(&["GET", "skey"]).await;
+    assert_eq!(client.read_bulk().await, "kept");
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+
+    let _ = std::fs::remove_file(&path);
+    std::env::remove_var("REDUST_RDB_PATH");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn bgsave_snapshot_is_consistent_under_concurrent_writes() {
+    let _guard = persistence_lock();
+    let tmp = std::env::temp_dir();
+    let path = tmp.join(format!("redust_bgsave_race_{}.rdb", rand::random::<u64>()));
+    let path_str = path.to_string_lossy().to_string();
+    let _ = std::fs::remove_file(&path);
+    std::env::set_var("REDUST_RDB_PATH", &path_str);
+    std::env::remove_var("REDUST_AOF_ENABLED");
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    // 填充一些数据，让快照拷贝有可观的耗时
+    let mut filler = String::new();
+    for i in 0..20_000 {
+        let key = format!("filler:{}", i);
+        filler.push_str(&format!("*3\r\n$3\r\nSET\r\n${}\r\n{}\r\n$1\r\nv\r\n", key.len(), key));
+    }
+    client.writer.write_all(filler.as_bytes()).await.unwrap();
+    for _ in 0..20_000 {
+        assert_eq!(client.read_simple_line().await, "+OK\r\n");
+    }
+
+    // 写入方不停地成对修改 key：MSET 同时写 a/b，事务中同时 INCR c/d；
+    // 任何时刻保存下来的快照里，每一对的值都必须相同
+    let stop = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let writer_stop = stop.clone();
+    let writer = tokio::spawn(async move {
+        let mut w = TestClient::connect(addr).await;
+        let mut i = 0u64;
+        while !writer_stop.load(std::sync::atomic::Ordering::Relaxed) {
+            i += 1;
+            let n = i.to_string();
+            w.send_array(&["MSET", "a", &n, "b", &n]).await;
+            assert_eq!(w.read_simple_line().await, "+OK\r\n");
+            w.send_array(&["MULTI"]).await;
+            w.send_array(&["INCR", "c"]).await;
+            w.send_array(&["INCR", "d"]).await;
+            w.send_array(&["EXEC"]).await;
+            assert_eq!(w.read_simple_line().await, "+OK\r\n");
+            assert_eq!(w.read_simple_line().await, "+QUEUED\r\n");
+            assert_eq!(w.read_simple_line().await, "+QUEUED\r\n");
+            let _ = w.read_raw_reply().await;
+        }
+        i
+    });
+
+    for _ in 0..5 {
+        client.send_array(&["BGSAVE"]).await;
+        assert_eq!(client.read_simple_line().await, "+Background saving started\r\n");
+        let mut done = false;
+        for _ in 0..100 {
+            if client.info_field("rdb_bgsave_in_progress").await == "0" {
+                done = true;
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert!(done, "BGSAVE did not finish");
+
+        let restored = redust::storage::Storage::default();
+        restored.load_rdb(&path).unwrap();
+        assert_eq!(restored.get("0:a"), restored.get("0:b"));
+        assert_eq!(restored.get("0:c"), restored.get("0:d"));
+        assert_eq!(restored.get("0:filler:19999").as_deref(), Some(&b"v"[..]));
+    }
+
+    stop.store(true, std::sync::atomic::Ordering::Relaxed);
+    assert!(writer.await.unwrap() > 0);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+
+    let _ = std::fs::remove_file(&path);
+    std::env::remove_var("REDUST_RDB_PATH");
 }
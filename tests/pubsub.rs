@@ -114,6 +114,38 @@ async fn subscribe_and_receive_publish() {
     handle.await.unwrap().unwrap();
 }
 
+#[tokio::test]
+async fn binary_payloads_are_delivered_byte_for_byte() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut sub = RespClient::connect(addr).await;
+    let mut psub = RespClient::connect(addr).await;
+    let mut pub_client = RespClient::connect(addr).await;
+
+    sub.send_array(&[b"SUBSCRIBE", b"bin"]).await;
+    let _ = sub.read_array().await;
+    psub.send_array(&[b"PSUBSCRIBE", b"b*"]).await;
+    let _ = psub.read_array().await;
+
+    // 内嵌 CRLF、NUL 以及非 UTF-8 字节，任何字符串转换都会破坏长度或内容
+    let payload: &[u8] = b"a\r\nb\0c\xff\xfe\r\n";
+    pub_client.send_array(&[b"PUBLISH", b"bin", payload]).await;
+    assert_eq!(pub_client.read_integer().await, 2);
+
+    let message = sub.read_array().await;
+    assert_eq!(message[0], b"message");
+    assert_eq!(message[1], b"bin");
+    assert_eq!(message[2], payload);
+
+    let pmessage = psub.read_array().await;
+    assert_eq!(pmessage[0], b"pmessage");
+    assert_eq!(pmessage[2], b"bin");
+    assert_eq!(pmessage[3], payload);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
 #[tokio::test]
 async fn ssubscribe_and_spublish() {
     let (addr, shutdown, handle) = spawn_server().await;
@@ -147,6 +179,37 @@ async fn ssubscribe_and_spublish() {
     handle.await.unwrap().unwrap();
 }
 
+#[tokio::test]
+async fn psubscribe_count_includes_shard_subscriptions() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut sub = RespClient::connect(addr).await;
+
+    sub.send_array(&[b"SSUBSCRIBE", b"shard"]).await;
+    let ack = sub.read_array().await;
+    assert_eq!(ack[2], b"1");
+
+    // 计数与 SUBSCRIBE 一致：频道、模式与分片频道之和
+    sub.send_array(&[b"PSUBSCRIBE", b"news.*"]).await;
+    let ack = sub.read_array().await;
+    assert_eq!(ack[0], b"psubscribe");
+    assert_eq!(ack[2], b"2");
+
+    sub.send_array(&[b"PUNSUBSCRIBE"]).await;
+    let ack = sub.read_array().await;
+    assert_eq!(ack[0], b"punsubscribe");
+    assert_eq!(ack[2], b"1");
+
+    // 仍有分片订阅，连接保持订阅模式
+    sub.send_array(&[b"GET", b"k"]).await;
+    let mut line = String::new();
+    sub.reader.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("-ERR only (P)SUBSCRIBE"), "{:?}", line);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
 #[tokio::test]
 async fn unsubscribe_leaves_sub_mode() {
     let (addr, shutdown, handle) = spawn_server().await;
@@ -311,7 +374,7 @@ async fn pubsub_introspection_counts_channels_and_patterns() {
     let numpat = client.read_integer().await;
     assert_eq!(numpat, 1);
 
-    // Shard introspection currently reuses active channels / zeros
+    // 分片频道单独统计：只有 sub3 的 SSUBSCRIBE alpha
     client
         .send_array(&[b"PUBSUB", b"SHARDCHANNELS", b"a*"])
         .await;
@@ -504,3 +567,438 @@ async fn slow_subscriber_drops_old_messages_but_stays_subscribed() {
     shutdown.send(()).unwrap();
     handle.await.unwrap().unwrap();
 }
+
+#[tokio::test]
+async fn keyevent_expired_is_published_by_active_expiration() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut sub = RespClient::connect(addr).await;
+    let mut client = RespClient::connect(addr).await;
+
+    client
+        .send_array(&[b"CONFIG", b"SET", b"notify-keyspace-events", b"Ex"])
+        .await;
+    let mut line = String::new();
+    client.reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "+OK\r\n");
+
+    sub.send_array(&[b"SUBSCRIBE", b"__keyevent@0__:expired"])
+        .await;
+    let _ = sub.read_array().await;
+
+    client
+        .send_array(&[b"SET", b"volatile", b"v", b"PX", b"50"])
+        .await;
+    line.clear();
+    client.reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "+OK\r\n");
+
+    // 不再访问该 key，只能由后台主动过期任务删除并发布事件
+    let msg = tokio::time::timeout(Duration::from_secs(3), sub.read_array())
+        .await
+        .expect("expired event should be delivered");
+    assert_eq!(msg[0], b"message");
+    assert_eq!(msg[1], b"__keyevent@0__:expired");
+    assert_eq!(msg[2], b"volatile");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn getdel_and_getex_publish_generic_events() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut sub = RespClient::connect(addr).await;
+    let mut client = RespClient::connect(addr).await;
+
+    client
+        .send_array(&[b"CONFIG", b"SET", b"notify-keyspace-events", b"Kg"])
+        .await;
+    let mut line = String::new();
+    client.reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "+OK\r\n");
+
+    sub.send_array(&[b"SUBSCRIBE", b"__keyspace@0__:gx"]).await;
+    let _ = sub.read_array().await;
+
+    // GETEX 不带选项、PERSIST 无 TTL 的 key 都不改变 TTL，不发布事件
+    let commands: [&[&[u8]]; 6] = [
+        &[b"SET", b"gx", b"v"],
+        &[b"GETEX", b"gx"],
+        &[b"GETEX", b"gx", b"PERSIST"],
+        &[b"GETEX", b"gx", b"EX", b"100"],
+        &[b"GETEX", b"gx", b"PERSIST"],
+        &[b"GETDEL", b"gx"],
+    ];
+    for parts in commands {
+        client.send_array(parts).await;
+        // 回复为 +OK 或 $1 v，读到首行后按需读掉 bulk 内容
+        line.clear();
+        client.reader.read_line(&mut line).await.unwrap();
+        if line.starts_with('$') {
+            line.clear();
+            client.reader.read_line(&mut line).await.unwrap();
+        }
+    }
+
+    for event in [&b"expire"[..], b"persist", b"del"] {
+        let msg = tokio::time::timeout(Duration::from_secs(3), sub.read_array())
+            .await
+            .expect("keyspace event should be delivered");
+        assert_eq!(msg[1], b"__keyspace@0__:gx");
+        assert_eq!(msg[2], event);
+    }
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn rename_publishes_rename_from_and_rename_to() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut client = RespClient::connect(addr).await;
+    let mut sub = RespClient::connect(addr).await;
+
+    client
+        .send_array(&[b"CONFIG", b"SET", b"notify-keyspace-events", b"Eg"])
+        .await;
+    let mut line = String::new();
+    client.reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "+OK\r\n");
+
+    sub.send_array(&[b"PSUBSCRIBE", b"__keyevent@0__:rename_*"]).await;
+    let _ = sub.read_array().await;
+
+    for parts in [&[&b"SET"[..], b"rn:a", b"v"][..], &[b"RENAME", b"rn:a", b"rn:b"]] {
+        client.send_array(parts).await;
+        line.clear();
+        client.reader.read_line(&mut line).await.unwrap();
+        assert_eq!(line, "+OK\r\n");
+    }
+
+    for (event, key) in [(&b"rename_from"[..], &b"rn:a"[..]), (b"rename_to", b"rn:b")] {
+        let msg = tokio::time::timeout(Duration::from_secs(3), sub.read_array())
+            .await
+            .expect("rename event should be delivered");
+        assert_eq!(msg[0], b"pmessage");
+        assert_eq!(msg[2], [&b"__keyevent@0__:"[..], event].concat());
+        assert_eq!(msg[3], key);
+    }
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn flushdb_publishes_del_for_each_key_of_that_db_only() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut client = RespClient::connect(addr).await;
+    let mut sub = RespClient::connect(addr).await;
+
+    client
+        .send_array(&[b"CONFIG", b"SET", b"notify-keyspace-events", b"Eg"])
+        .await;
+    let mut line = String::new();
+    client.reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "+OK\r\n");
+
+    sub.send_array(&[b"PSUBSCRIBE", b"__keyevent@*__:del"]).await;
+    let _ = sub.read_array().await;
+
+    for parts in [
+        &[&b"SET"[..], b"db0:keep", b"v"][..],
+        &[b"SELECT", b"1"],
+        &[b"SET", b"fd:a", b"v"],
+        &[b"SET", b"fd:b", b"v"],
+        &[b"FLUSHDB"],
+    ] {
+        client.send_array(parts).await;
+        line.clear();
+        client.reader.read_line(&mut line).await.unwrap();
+        assert_eq!(line, "+OK\r\n");
+    }
+
+    let mut keys = Vec::new();
+    for _ in 0..2 {
+        let msg = tokio::time::timeout(Duration::from_secs(3), sub.read_array())
+            .await
+            .expect("del event should be delivered");
+        assert_eq!(msg[0], b"pmessage");
+        assert_eq!(msg[2], b"__keyevent@1__:del");
+        keys.push(msg[3].clone());
+    }
+    keys.sort();
+    assert_eq!(keys, vec![b"fd:a".to_vec(), b"fd:b".to_vec()]);
+
+    // DB0 未被清空，也不应收到它的事件
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), sub.read_array())
+            .await
+            .is_err(),
+        "FLUSHDB must not publish events for other databases"
+    );
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+async fn pubsub_dropped(client: &mut RespClient) -> u64 {
+    client.send_array(&[b"INFO"]).await;
+    let mut header = String::new();
+    client.reader.read_line(&mut header).await.unwrap();
+    let len: usize = header[1..header.len() - 2].parse().unwrap();
+    let mut buf = vec![0u8; len + 2];
+    client.reader.read_exact(&mut buf).await.unwrap();
+    let info = String::from_utf8(buf).unwrap();
+    info.lines()
+        .find_map(|l| l.strip_prefix("pubsub_messages_dropped:"))
+        .expect("INFO missing pubsub_messages_dropped")
+        .trim()
+        .parse()
+        .unwrap()
+}
+
+async fn config_set_pubsub_buffer(client: &mut RespClient, size: &[u8]) {
+    client
+        .send_array(&[b"CONFIG", b"SET", b"pubsub-buffer", size])
+        .await;
+    let mut line = String::new();
+    client.reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "+OK\r\n");
+}
+
+/// 不读回复地连续发送一批 PUBLISH，再统一读取回复，制造突发流量
+async fn publish_burst(client: &mut RespClient, channel: &[u8], n: usize) {
+    for i in 0..n {
+        let payload = format!("m{}", i);
+        client
+            .send_array(&[b"PUBLISH", channel, payload.as_bytes()])
+            .await;
+    }
+    for _ in 0..n {
+        let _ = client.read_integer().await;
+    }
+}
+
+#[tokio::test]
+async fn large_pubsub_buffer_absorbs_burst_without_drops() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut sub = RespClient::connect(addr).await;
+    let mut pub_client = RespClient::connect(addr).await;
+
+    // 缓冲区只影响之后新建的频道，因此先配置再订阅
+    config_set_pubsub_buffer(&mut pub_client, b"4096").await;
+    pub_client
+        .send_array(&[b"CONFIG", b"GET", b"pubsub-buffer"])
+        .await;
+    assert_eq!(
+        pub_client.read_array().await,
+        vec![b"pubsub-buffer".to_vec(), b"4096".to_vec()]
+    );
+
+    sub.send_array(&[b"SUBSCRIBE", b"burst"]).await;
+    let _ = sub.read_array().await;
+
+    let n = 2000;
+    publish_burst(&mut pub_client, b"burst", n).await;
+
+    for i in 0..n {
+        let msg = sub.read_array().await;
+        assert_eq!(msg[2], format!("m{}", i).into_bytes());
+    }
+    assert_eq!(pubsub_dropped(&mut pub_client).await, 0);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn small_pubsub_buffer_triggers_overflow_strategy() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut sub = RespClient::connect(addr).await;
+    let mut pub_client = RespClient::connect(addr).await;
+
+    pub_client
+        .send_array(&[b"CONFIG", b"SET", b"pubsub-buffer", b"0"])
+        .await;
+    let mut line = String::new();
+    pub_client.reader.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("-ERR Invalid argument '0'"), "{:?}", line);
+
+    config_set_pubsub_buffer(&mut pub_client, b"1").await;
+    sub.send_array(&[b"SUBSCRIBE", b"tiny"]).await;
+    let _ = sub.read_array().await;
+
+    publish_burst(&mut pub_client, b"tiny", 2000).await;
+    pub_client
+        .send_array(&[b"PUBLISH", b"tiny", b"final"])
+        .await;
+    let _ = pub_client.read_integer().await;
+
+    // 默认 drop 策略：旧消息被丢弃并计数，订阅者仍能收到之后的消息
+    loop {
+        let msg = sub.read_array().await;
+        if msg[2] == b"final" {
+            break;
+        }
+    }
+    assert!(pubsub_dropped(&mut pub_client).await > 0);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn per_channel_order_is_preserved_across_multiple_subscriptions() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut sub = RespClient::connect(addr).await;
+    let mut pub_client = RespClient::connect(addr).await;
+
+    // 足够大的缓冲区，排除溢出丢消息对顺序断言的干扰
+    config_set_pubsub_buffer(&mut pub_client, b"4096").await;
+    sub.send_array(&[b"SUBSCRIBE", b"left", b"right"]).await;
+    let _ = sub.read_array().await;
+    let _ = sub.read_array().await;
+
+    // 交替向两个频道发布；同一连接上两个订阅的消息汇入同一个推送队列
+    let n = 500;
+    for i in 0..n {
+        let channel: &[u8] = if i % 2 == 0 { b"left" } else { b"right" };
+        let payload = format!("m{}", i);
+        pub_client
+            .send_array(&[b"PUBLISH", channel, payload.as_bytes()])
+            .await;
+    }
+    for _ in 0..n {
+        assert_eq!(pub_client.read_integer().await, 1);
+    }
+
+    let mut left = Vec::new();
+    let mut right = Vec::new();
+    for _ in 0..n {
+        let msg = sub.read_array().await;
+        let seq: usize = String::from_utf8(msg[2][1..].to_vec()).unwrap().parse().unwrap();
+        match msg[1].as_slice() {
+            b"left" => left.push(seq),
+            b"right" => right.push(seq),
+            other => panic!("unexpected channel {:?}", other),
+        }
+    }
+
+    // 特征测试：每个频道内严格按发布顺序且不丢消息；
+    // 跨频道的交错顺序取决于转发任务的调度，这里刻意不做断言
+    assert_eq!(left, (0..n).step_by(2).collect::<Vec<_>>());
+    assert_eq!(right, (1..n).step_by(2).collect::<Vec<_>>());
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn numpat_counts_distinct_patterns_not_subscribers() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut sub1 = RespClient::connect(addr).await;
+    let mut sub2 = RespClient::connect(addr).await;
+    let mut client = RespClient::connect(addr).await;
+
+    sub1.send_array(&[b"PSUBSCRIBE", b"news.*"]).await;
+    let _ = sub1.read_array().await;
+    sub2.send_array(&[b"PSUBSCRIBE", b"news.*"]).await;
+    let _ = sub2.read_array().await;
+
+    // 两个客户端订阅同一模式：NUMPAT 为 1 而不是 2
+    client.send_array(&[b"PUBSUB", b"NUMPAT"]).await;
+    assert_eq!(client.read_integer().await, 1);
+
+    sub2.send_array(&[b"PSUBSCRIBE", b"sports.*"]).await;
+    let _ = sub2.read_array().await;
+    client.send_array(&[b"PUBSUB", b"NUMPAT"]).await;
+    assert_eq!(client.read_integer().await, 2);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn channels_and_shard_channels_are_listed_separately() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut sub = RespClient::connect(addr).await;
+    let mut ssub = RespClient::connect(addr).await;
+    let mut client = RespClient::connect(addr).await;
+
+    sub.send_array(&[b"SUBSCRIBE", b"plain"]).await;
+    let _ = sub.read_array().await;
+    ssub.send_array(&[b"SSUBSCRIBE", b"sharded"]).await;
+    let _ = ssub.read_array().await;
+
+    client.send_array(&[b"PUBSUB", b"CHANNELS"]).await;
+    assert_eq!(client.read_array().await, vec![b"plain".to_vec()]);
+    client.send_array(&[b"PUBSUB", b"SHARDCHANNELS"]).await;
+    assert_eq!(client.read_array().await, vec![b"sharded".to_vec()]);
+
+    // 退订后频道立即从 CHANNELS 中消失，分片频道不受影响
+    sub.send_array(&[b"UNSUBSCRIBE", b"plain"]).await;
+    let _ = sub.read_array().await;
+    client.send_array(&[b"PUBSUB", b"CHANNELS"]).await;
+    assert!(client.read_array().await.is_empty());
+    client.send_array(&[b"PUBSUB", b"NUMSUB", b"plain"]).await;
+    assert_eq!(
+        client.read_array().await,
+        vec![b"plain".to_vec(), b"0".to_vec()]
+    );
+    client.send_array(&[b"PUBSUB", b"SHARDCHANNELS"]).await;
+    assert_eq!(client.read_array().await, vec![b"sharded".to_vec()]);
+
+    ssub.send_array(&[b"SUNSUBSCRIBE", b"sharded"]).await;
+    let _ = ssub.read_array().await;
+    client.send_array(&[b"PUBSUB", b"SHARDCHANNELS"]).await;
+    assert!(client.read_array().await.is_empty());
+    client.send_array(&[b"PUBSUB", b"CHANNELS"]).await;
+    assert!(client.read_array().await.is_empty());
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn numsub_reports_zero_for_cleaned_and_unknown_channels_in_request_order() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut sub = RespClient::connect(addr).await;
+    let mut client = RespClient::connect(addr).await;
+
+    sub.send_array(&[b"SUBSCRIBE", b"gone", b"live"]).await;
+    let _ = sub.read_array().await;
+    let _ = sub.read_array().await;
+    sub.send_array(&[b"UNSUBSCRIBE", b"gone"]).await;
+    let _ = sub.read_array().await;
+
+    // gone 已被清理、never 从未出现：都返回 0；回复顺序与请求顺序一致
+    client
+        .send_array(&[b"PUBSUB", b"NUMSUB", b"zeta", b"gone", b"live", b"never"])
+        .await;
+    assert_eq!(
+        client.read_array().await,
+        vec![
+            b"zeta".to_vec(),
+            b"0".to_vec(),
+            b"gone".to_vec(),
+            b"0".to_vec(),
+            b"live".to_vec(),
+            b"1".to_vec(),
+            b"never".to_vec(),
+            b"0".to_vec(),
+        ]
+    );
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
@@ -0,0 +1,101 @@
+// REDUST_PUBSUB_OVERFLOW 在接受连接时读取，放在独立的测试二进制中，
+// 避免影响 tests/pubsub.rs 中依赖默认 drop 策略的用例
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+use tokio::time::timeout;
+
+use redust::server::serve;
+
+async fn spawn_server() -> (
+    SocketAddr,
+    oneshot::Sender<()>,
+    tokio::task::JoinHandle<tokio::io::Result<()>>,
+) {
+    std::env::set_var("REDUST_DISABLE_PERSISTENCE", "1");
+    std::env::set_var("REDUST_PUBSUB_OVERFLOW", "disconnect");
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind server");
+    let addr = listener.local_addr().expect("local addr");
+    let (tx, rx) = oneshot::channel();
+    let handle = tokio::spawn(async move {
+        serve(listener, async move {
+            let _ = rx.await;
+        })
+        .await
+    });
+    (addr, tx, handle)
+}
+
+async fn send_array(stream: &mut TcpStream, parts: &[&[u8]]) {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(format!("*{}\r\n", parts.len()).as_bytes());
+    for p in parts {
+        buf.extend_from_slice(format!("${}\r\n", p.len()).as_bytes());
+        buf.extend_from_slice(p);
+        buf.extend_from_slice(b"\r\n");
+    }
+    stream.write_all(&buf).await.unwrap();
+}
+
+#[tokio::test]
+async fn lagging_subscriber_gets_error_before_disconnect() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut pub_client = TcpStream::connect(addr).await.unwrap();
+    send_array(&mut pub_client, &[b"CONFIG", b"SET", b"pubsub-buffer", b"1"]).await;
+    let mut ok = [0u8; 5];
+    pub_client.read_exact(&mut ok).await.unwrap();
+    assert_eq!(&ok, b"+OK\r\n");
+
+    let sub = TcpStream::connect(addr).await.unwrap();
+    let mut sub = BufReader::new(sub);
+    send_array(sub.get_mut(), &[b"SUBSCRIBE", b"flood"]).await;
+    // *3 / $9 subscribe / $5 flood / :1
+    for _ in 0..6 {
+        let mut line = String::new();
+        sub.read_line(&mut line).await.unwrap();
+    }
+
+    // 不读回复地连续发布，使容量为 1 的频道缓冲区溢出
+    let n = 2000;
+    let mut burst = Vec::new();
+    for i in 0..n {
+        let payload = format!("m{}", i);
+        burst.extend_from_slice(
+            format!("*3\r\n$7\r\nPUBLISH\r\n$5\r\nflood\r\n${}\r\n{}\r\n", payload.len(), payload)
+                .as_bytes(),
+        );
+    }
+    pub_client.write_all(&burst).await.unwrap();
+
+    // 订阅者先收到溢出前转发的消息，然后是错误行，最后连接被关闭
+    let error_line = timeout(Duration::from_secs(5), async {
+        loop {
+            let mut line = String::new();
+            if sub.read_line(&mut line).await.unwrap() == 0 {
+                return None;
+            }
+            if line.starts_with('-') {
+                return Some(line);
+            }
+        }
+    })
+    .await
+    .expect("subscriber was not disconnected");
+    assert_eq!(
+        error_line.as_deref(),
+        Some("-ERR Pub/Sub message buffer overflow, disconnecting\r\n")
+    );
+    let mut rest = Vec::new();
+    let read = timeout(Duration::from_secs(5), sub.read_to_end(&mut rest))
+        .await
+        .expect("socket not closed after overflow error")
+        .unwrap();
+    assert_eq!(read, 0);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use redust::storage::Storage;
+use redust::storage::{RdbCompression, Storage};
 
 fn temp_path(name: &str) -> PathBuf {
     let mut p = std::env::temp_dir();
@@ -18,12 +18,12 @@ async fn rdb_basic_roundtrip_via_storage() {
 
     storage.set("foo".to_string(), b"bar".to_vec());
     storage
-        .lpush("mylist", &vec!["a".to_string(), "b".to_string()])
+        .lpush("mylist", &[b"a".to_vec(), b"b".to_vec()])
         .unwrap();
     storage
-        .sadd("myset", &vec!["x".to_string(), "y".to_string()])
+        .sadd("myset", &[b"x".to_vec(), b"y".to_vec()])
         .unwrap();
-    storage.hset("myhash", "field", "val".to_string()).unwrap();
+    storage.hset("myhash", "field", b"val".to_vec()).unwrap();
 
     storage.set("ttl_key".to_string(), b"tv".to_vec());
     let _ = storage.expire_seconds("ttl_key", 10);
@@ -38,14 +38,14 @@ async fn rdb_basic_roundtrip_via_storage() {
 
     let lvals = restored.lrange("mylist", 0, -1).unwrap();
     // Redis 语义：LPUSH mylist a b -> 列表内容为 ["b", "a"]
-    assert_eq!(lvals, vec!["b".to_string(), "a".to_string()]);
+    assert_eq!(lvals, vec![b"b".to_vec(), b"a".to_vec()]);
 
     let mut svals = restored.smembers("myset").unwrap();
     svals.sort();
-    assert_eq!(svals, vec!["x".to_string(), "y".to_string()]);
+    assert_eq!(svals, vec![b"x".to_vec(), b"y".to_vec()]);
 
     let hvals = restored.hgetall("myhash").unwrap();
-    assert_eq!(hvals, vec![("field".to_string(), "val".to_string())]);
+    assert_eq!(hvals, vec![("field".to_string(), b"val".to_vec())]);
 
     let ttl = restored.ttl_seconds("ttl_key");
     assert!(ttl > 0 && ttl <= 10);
@@ -82,3 +82,262 @@ async fn rdb_load_from_missing_file_is_noop() {
 
     assert_eq!(storage.keys("*").len(), 0);
 }
+
+#[tokio::test]
+async fn rdb_preserves_absolute_expiry_across_reload() {
+    let storage = Storage::default();
+    storage.set("px_key".to_string(), b"v".to_vec());
+    assert!(storage.expire_millis("px_key", 2_000));
+    storage.rpush("px_list", &[b"a".to_vec()]).unwrap();
+    assert!(storage.expire_millis("px_list", 5_000));
+    storage.set("no_ttl".to_string(), b"v".to_vec());
+
+    let path = temp_path("abs_ttl");
+    storage.save_rdb(&path).unwrap();
+
+    // 保存之后经过一段时间再加载：剩余 TTL 应该扣除这段时间，而不是被重置
+    std::thread::sleep(std::time::Duration::from_millis(600));
+
+    let restored = Storage::default();
+    restored.load_rdb(&path).unwrap();
+
+    let pttl = restored.pttl_millis("px_key");
+    assert!(pttl > 0 && pttl <= 1_400, "unexpected pttl {}", pttl);
+    assert!(pttl >= 1_000, "unexpected pttl {}", pttl);
+
+    let list_pttl = restored.pttl_millis("px_list");
+    assert!(
+        list_pttl > 3_000 && list_pttl <= 4_400,
+        "unexpected pttl {}",
+        list_pttl
+    );
+
+    assert_eq!(restored.pttl_millis("no_ttl"), -1);
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn rdb_skips_keys_that_expired_while_offline() {
+    let storage = Storage::default();
+    storage.set("short".to_string(), b"v".to_vec());
+    assert!(storage.expire_millis("short", 50));
+    storage.set("long".to_string(), b"v".to_vec());
+
+    let path = temp_path("offline_expiry");
+    storage.save_rdb(&path).unwrap();
+
+    std::thread::sleep(std::time::Duration::from_millis(120));
+
+    let restored = Storage::default();
+    restored.load_rdb(&path).unwrap();
+
+    // 过期记录被跳过，但其后的记录仍能正确解析
+    assert_eq!(restored.get("short"), None);
+    assert_eq!(restored.get("long").as_deref(), Some("v".as_bytes()));
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn rdb_checksum_mismatch_fails_and_quarantines() {
+    let storage = Storage::default();
+    storage.set("foo".to_string(), b"bar".to_vec());
+
+    let path = temp_path("corrupt");
+    storage.save_rdb(&path).unwrap();
+
+    let mut bytes = std::fs::read(&path).unwrap();
+    assert_eq!(&bytes[..8], b"REDUST01");
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0xff;
+    std::fs::write(&path, &bytes).unwrap();
+
+    let restored = Storage::default();
+    let err = restored.load_rdb(&path).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(restored.keys("*").len(), 0);
+
+    // 损坏文件被挪到 <path>.corrupt，原路径不再存在
+    let quarantine = Storage::rdb_quarantine_path(&path);
+    assert!(!path.exists());
+    assert_eq!(std::fs::read(&quarantine).unwrap(), bytes);
+
+    let _ = std::fs::remove_file(&quarantine);
+}
+
+#[tokio::test]
+async fn rdb_failed_load_keeps_existing_data() {
+    let other = Storage::default();
+    other.set("other".to_string(), b"v".to_vec());
+    let path = temp_path("keep");
+    other.save_rdb(&path).unwrap();
+    let mut bytes = std::fs::read(&path).unwrap();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0xff;
+    std::fs::write(&path, &bytes).unwrap();
+
+    // 加载失败时不清空现有数据，之后的保存不会用空数据集覆盖完好的快照
+    let storage = Storage::default();
+    storage.set("keep".to_string(), b"me".to_vec());
+    assert!(storage.load_rdb(&path).is_err());
+    assert_eq!(storage.get("keep").as_deref(), Some("me".as_bytes()));
+    assert_eq!(storage.get("other"), None);
+
+    let _ = std::fs::remove_file(Storage::rdb_quarantine_path(&path));
+}
+
+#[tokio::test]
+async fn rdb_truncated_file_fails_and_quarantines() {
+    let storage = Storage::default();
+    storage.set("foo".to_string(), b"bar".to_vec());
+
+    let path = temp_path("truncated");
+    storage.save_rdb(&path).unwrap();
+
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &bytes[..bytes.len() - 3]).unwrap();
+
+    let restored = Storage::default();
+    assert!(restored.load_rdb(&path).is_err());
+    assert!(!path.exists());
+
+    let _ = std::fs::remove_file(Storage::rdb_quarantine_path(&path));
+}
+
+#[tokio::test]
+async fn rdb_failed_save_leaves_existing_snapshot_untouched() {
+    let storage = Storage::default();
+    storage.set("good".to_string(), b"v1".to_vec());
+
+    let path = temp_path("atomic");
+    storage.save_rdb(&path).unwrap();
+    let original = std::fs::read(&path).unwrap();
+
+    // 在临时文件路径上放一个目录，使临时文件无法创建，模拟保存失败
+    let tmp = Storage::rdb_temp_path(&path);
+    std::fs::create_dir(&tmp).unwrap();
+
+    storage.set("good".to_string(), b"v2".to_vec());
+    storage.set("extra".to_string(), b"x".to_vec());
+    assert!(storage.save_rdb(&path).is_err());
+
+    assert_eq!(std::fs::read(&path).unwrap(), original);
+
+    let restored = Storage::default();
+    restored.load_rdb(&path).unwrap();
+    assert_eq!(restored.get("good").as_deref(), Some("v1".as_bytes()));
+    assert_eq!(restored.get("extra"), None);
+
+    let _ = std::fs::remove_dir(&tmp);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn rdb_save_does_not_leave_temp_file() {
+    let storage = Storage::default();
+    storage.set("k".to_string(), b"v".to_vec());
+
+    let path = temp_path("no_tmp");
+    storage.save_rdb(&path).unwrap();
+
+    assert!(path.exists());
+    assert!(!Storage::rdb_temp_path(&path).exists());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn rdb_zstd_compression_roundtrip_is_identical_and_smaller() {
+    let storage = Storage::default();
+    for i in 0..500 {
+        storage.set(
+            format!("key:{}", i),
+            format!("value-{}-{}", i, "x".repeat(64)).into_bytes(),
+        );
+    }
+    let items: Vec<Vec<u8>> = (0..200).map(|i| format!("item-{}", i).into_bytes()).collect();
+    storage.rpush("biglist", &items).unwrap();
+    storage.hset("h", "f", b"v".to_vec()).unwrap();
+    assert!(storage.expire_seconds("h", 100));
+
+    let plain_path = temp_path("plain");
+    let zstd_path = temp_path("zstd");
+    storage.save_rdb(&plain_path).unwrap();
+    storage
+        .save_rdb_with_compression(&zstd_path, RdbCompression::Zstd)
+        .unwrap();
+
+    let plain_len = std::fs::metadata(&plain_path).unwrap().len();
+    let zstd_len = std::fs::metadata(&zstd_path).unwrap().len();
+    assert!(
+        zstd_len < plain_len,
+        "compressed {} >= plain {}",
+        zstd_len,
+        plain_len
+    );
+
+    // load_rdb 通过 magic 自动识别压缩格式
+    let from_plain = Storage::default();
+    from_plain.load_rdb(&plain_path).unwrap();
+    let from_zstd = Storage::default();
+    from_zstd.load_rdb(&zstd_path).unwrap();
+
+    let mut plain_keys = from_plain.keys("*");
+    let mut zstd_keys = from_zstd.keys("*");
+    plain_keys.sort();
+    zstd_keys.sort();
+    assert_eq!(plain_keys.len(), 502);
+    assert_eq!(plain_keys, zstd_keys);
+    for i in 0..500 {
+        let k = format!("key:{}", i);
+        assert_eq!(from_plain.get(&k), from_zstd.get(&k));
+    }
+    assert_eq!(
+        from_zstd.lrange("biglist", 0, -1).unwrap(),
+        from_plain.lrange("biglist", 0, -1).unwrap()
+    );
+    assert_eq!(
+        from_zstd.hgetall("h").unwrap(),
+        vec![("f".to_string(), b"v".to_vec())]
+    );
+    assert!(from_zstd.ttl_seconds("h") > 0);
+
+    let _ = std::fs::remove_file(&plain_path);
+    let _ = std::fs::remove_file(&zstd_path);
+}
+
+#[tokio::test]
+async fn rdb_keeps_sparse_hyperloglog_sparse() {
+    let storage = Storage::default();
+    let small: Vec<Vec<u8>> = (0..20).map(|i| format!("e{}", i).into_bytes()).collect();
+    storage.pfadd("small", &small).unwrap();
+    let large: Vec<Vec<u8>> = (0..5000).map(|i| format!("e{}", i).into_bytes()).collect();
+    storage.pfadd("large", &large).unwrap();
+
+    let only_sparse = Storage::default();
+    only_sparse.pfadd("small", &small).unwrap();
+    let sparse_path = temp_path("hll_sparse");
+    only_sparse.save_rdb(&sparse_path).unwrap();
+    // 稀疏表示按原样保存，不再展开成 16384 个寄存器
+    assert!(std::fs::metadata(&sparse_path).unwrap().len() < 1024);
+
+    let path = temp_path("hll");
+    storage.save_rdb(&path).unwrap();
+    let restored = Storage::default();
+    restored.load_rdb(&path).unwrap();
+
+    assert_eq!(
+        restored.pfcount(&["small".to_string()]).unwrap(),
+        storage.pfcount(&["small".to_string()]).unwrap()
+    );
+    assert_eq!(
+        restored.pfcount(&["large".to_string()]).unwrap(),
+        storage.pfcount(&["large".to_string()]).unwrap()
+    );
+    // 编码不变，内存估算也与保存前一致
+    assert_eq!(restored.approximate_used_memory(), storage.approximate_used_memory());
+
+    let _ = std::fs::remove_file(&sparse_path);
+    let _ = std::fs::remove_file(&path);
+}
@@ -121,7 +121,12 @@ impl TestClient {
             owned.push(c.to_string());
         }
         let refs: Vec<&str> = owned.iter().map(|s| s.as_str()).collect();
-        self.send_array(&refs).await;
+        self.scan_raw(&refs).await
+    }
+
+    /// 发送任意 SCAN 参数并解析回复
+    async fn scan_raw(&mut self, parts: &[&str]) -> (u64, Vec<String>) {
+        self.send_array(parts).await;
 
         // *2
         let mut outer = String::new();
@@ -243,6 +248,98 @@ async fn scan_basic_iteration_with_match_and_count() {
     handle.await.unwrap().unwrap();
 }
 
+#[tokio::test]
+async fn scan_selective_match_completes_full_iteration() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut client = TestClient::connect(addr).await;
+
+    // 大量不匹配的 key 中夹杂少量匹配 key，其中部分与不匹配 key 排序相邻
+    for i in 0..300 {
+        let k = format!("noise:{:03}", i);
+        client.send_array(&["SET", &k, "x"]).await;
+        let _ = client.read_simple_line().await;
+    }
+    let expected = vec!["needle:1", "needle:2", "needle:20"];
+    for k in ["needle:1", "needle:2", "needle:20", "needle", "noise:050:needle"] {
+        client.send_array(&["SET", k, "x"]).await;
+        let _ = client.read_simple_line().await;
+    }
+
+    // COUNT 限制每次检查的 key 数：会出现空批次，但持续迭代一定以 cursor 0 结束
+    let total_keys = 305u64;
+    let count = 7u64;
+    let mut cursor = 0u64;
+    let mut seen = Vec::new();
+    let mut calls = 0u64;
+    let mut empty_batches = 0;
+    loop {
+        let (next, keys) = client.scan(cursor, Some("needle:*"), Some(count)).await;
+        calls += 1;
+        if keys.is_empty() {
+            empty_batches += 1;
+        }
+        seen.extend(keys);
+        if next == 0 {
+            break;
+        }
+        assert!(next > cursor, "cursor must move forward: {} -> {}", cursor, next);
+        cursor = next;
+        assert!(calls <= total_keys.div_ceil(count), "SCAN did not terminate");
+    }
+    assert!(empty_batches > 0);
+
+    seen.sort();
+    assert_eq!(seen, expected);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn scan_type_filter_is_case_insensitive_and_ignores_unknown_types() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut client = TestClient::connect(addr).await;
+
+    for cmd in [
+        &["SET", "t:str", "v"][..],
+        &["RPUSH", "t:list", "a"][..],
+        &["SADD", "t:set", "a"][..],
+        &["ZADD", "t:zset", "1", "a"][..],
+        &["HSET", "t:hash", "f", "v"][..],
+    ] {
+        client.send_array(cmd).await;
+        let _ = client.read_simple_line().await;
+    }
+
+    for (type_name, expected) in [
+        ("string", "t:str"),
+        ("LIST", "t:list"),
+        ("Set", "t:set"),
+        ("ZSET", "t:zset"),
+        ("hash", "t:hash"),
+    ] {
+        let (next, keys) = client.scan_raw(&["SCAN", "0", "TYPE", type_name]).await;
+        assert_eq!(next, 0);
+        assert_eq!(keys, vec![expected.to_string()], "TYPE {}", type_name);
+    }
+
+    // 未知类型名不报错，迭代正常结束且没有任何匹配
+    let (next, keys) = client
+        .scan_raw(&["SCAN", "0", "MATCH", "t:*", "TYPE", "bogus"])
+        .await;
+    assert_eq!(next, 0);
+    assert!(keys.is_empty());
+    // stream 是 Redis 已知类型，只是这里没有这种 key
+    let (next, keys) = client.scan_raw(&["SCAN", "0", "TYPE", "stream"]).await;
+    assert_eq!(next, 0);
+    assert!(keys.is_empty());
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
 #[tokio::test]
 async fn scan_argument_and_integer_errors() {
     let (addr, shutdown, handle) = spawn_server().await;
@@ -267,6 +364,11 @@ async fn scan_argument_and_integer_errors() {
     let line = client.read_simple_line().await;
     assert_eq!(line, "-ERR value is not an integer or out of range\r\n");
 
+    // COUNT 0 无法推进扫描 -> syntax error（与 Redis 一致）
+    client.send_array(&["SCAN", "0", "COUNT", "0"]).await;
+    let line = client.read_simple_line().await;
+    assert_eq!(line, "-ERR syntax error\r\n");
+
     // 未知选项 -> syntax error
     client.send_array(&["SCAN", "0", "FOO", "bar"]).await;
     let line = client.read_simple_line().await;
@@ -393,6 +495,103 @@ async fn hscan_basic_roundtrip() {
     handle.await.unwrap().unwrap();
 }
 
+#[tokio::test]
+async fn hscan_novalues_pages_fields_with_cursor_and_count() {
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    for i in 0..1000 {
+        let f = format!("f{:04}", i);
+        client.send_array(&["HSET", "bighash", &f, "v"]).await;
+    }
+    for _ in 0..1000 {
+        assert_eq!(client.read_simple_line().await, ":1\r\n");
+    }
+
+    let mut seen = Vec::new();
+    let mut cursor = "0".to_string();
+    let mut rounds = 0;
+    loop {
+        client
+            .send_array(&["HSCAN", "bighash", &cursor, "MATCH", "*", "COUNT", "10", "NOVALUES"])
+            .await;
+        let mut outer = String::new();
+        client.reader.read_line(&mut outer).await.unwrap();
+        assert_eq!(outer, "*2\r\n");
+        cursor = client.read_bulk_string().await.unwrap();
+        let fields = client.read_array_of_bulk().await;
+        // 每批最多 COUNT 个 field，且不夹带 value
+        assert!(fields.len() <= 10, "batch too large: {}", fields.len());
+        assert!(fields.iter().all(|f| f.starts_with('f')), "unexpected {:?}", fields);
+        if rounds == 0 {
+            assert_eq!(fields.len(), 10);
+            assert_ne!(cursor, "0");
+        }
+        seen.extend(fields);
+        rounds += 1;
+        if cursor == "0" {
+            break;
+        }
+    }
+
+    assert_eq!(rounds, 100);
+    seen.sort();
+    seen.dedup();
+    assert_eq!(seen.len(), 1000);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn hscan_in_transaction_matches_direct_dispatch() {
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    for i in 0..25 {
+        let f = format!("f{:02}", i);
+        let v = format!("v{}", i);
+        client.send_array(&["HSET", "h", &f, &v]).await;
+        assert_eq!(client.read_simple_line().await, ":1\r\n");
+    }
+
+    async fn read_scan_reply(client: &mut TestClient) -> (String, Vec<String>) {
+        let mut outer = String::new();
+        client.reader.read_line(&mut outer).await.unwrap();
+        assert_eq!(outer, "*2\r\n");
+        let cursor = client.read_bulk_string().await.unwrap();
+        (cursor, client.read_array_of_bulk().await)
+    }
+
+    // 两条执行路径都应走 hash 处理逻辑：相同的游标推进与 COUNT 分批
+    for args in [
+        &["HSCAN", "h", "0", "COUNT", "10"][..],
+        &["HSCAN", "h", "10", "COUNT", "10", "NOVALUES"],
+        &["HSCAN", "h", "20", "MATCH", "f2*", "COUNT", "10"],
+    ] {
+        client.send_array(args).await;
+        let direct = read_scan_reply(&mut client).await;
+
+        client.send_array(&["MULTI"]).await;
+        assert_eq!(client.read_simple_line().await, "+OK\r\n");
+        client.send_array(args).await;
+        assert_eq!(client.read_simple_line().await, "+QUEUED\r\n");
+        client.send_array(&["EXEC"]).await;
+        assert_eq!(client.read_simple_line().await, "*1\r\n");
+        let queued = read_scan_reply(&mut client).await;
+
+        assert_eq!(direct, queued, "{:?}", args);
+    }
+
+    client.send_array(&["HSCAN", "h", "0", "COUNT", "10"]).await;
+    let (cursor, flat) = read_scan_reply(&mut client).await;
+    assert_eq!(cursor, "10");
+    assert_eq!(flat.len(), 20);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
 #[tokio::test]
 async fn zscan_wrongtype_returns_error() {
     let (addr, shutdown, handle) = spawn_server().await;
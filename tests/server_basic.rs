@@ -586,6 +586,12 @@ async fn prometheus_metrics_exporter_basic() {
     assert!(buf.contains("redust_connected_clients"));
     assert!(buf.contains("redust_total_commands_processed"));
     assert!(buf.contains("redust_keyspace_keys"));
+    assert!(buf.contains("redust_keyspace_hits_total"));
+    assert!(buf.contains("redust_keyspace_misses_total"));
+    assert!(buf.contains(&format!(
+        "redust_listener_info{{addr=\"{}\",family=\"ipv4\"}} 1",
+        addr
+    )));
 
     shutdown.send(()).unwrap();
     handle.await.unwrap().unwrap();
@@ -731,6 +737,115 @@ async fn lists_extended_commands() {
     handle.await.unwrap().unwrap();
 }
 
+#[tokio::test]
+async fn linsert_sentinels_and_insert_positions() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    let cases: [(&[&str], &str); 8] = [
+        // key 不存在 -> 0，且不会创建 key
+        (&["LINSERT", "nolist", "BEFORE", "a", "x"], ":0\r\n"),
+        (&["EXISTS", "nolist"], ":0\r\n"),
+        (&["RPUSH", "ins", "a", "b", "c"], ":3\r\n"),
+        // pivot 不存在 -> -1，列表不变
+        (&["LINSERT", "ins", "AFTER", "zz", "x"], ":-1\r\n"),
+        // 成功插入返回新长度
+        (&["LINSERT", "ins", "before", "b", "x"], ":4\r\n"),
+        (&["LINSERT", "ins", "AFTER", "c", "y"], ":5\r\n"),
+        (&["LINSERT", "ins", "MIDDLE", "a", "x"], "-ERR syntax error\r\n"),
+        (&["SET", "str", "v"], "+OK\r\n"),
+    ];
+    for (parts, expected) in cases {
+        send_array(&mut write_half, parts).await;
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        assert_eq!(line, expected, "{:?}", parts);
+    }
+
+    send_array(&mut write_half, &["LINSERT", "str", "BEFORE", "a", "x"]).await;
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("-WRONGTYPE"), "{}", line);
+
+    send_array(&mut write_half, &["LRANGE", "ins", "0", "-1"]).await;
+    let mut header = String::new();
+    reader.read_line(&mut header).await.unwrap();
+    assert_eq!(header, "*5\r\n");
+    let mut items = Vec::new();
+    for _ in 0..5 {
+        let mut len = String::new();
+        let mut item = String::new();
+        reader.read_line(&mut len).await.unwrap();
+        reader.read_line(&mut item).await.unwrap();
+        items.push(item.trim_end().to_string());
+    }
+    assert_eq!(items, vec!["a", "x", "b", "c", "y"]);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn lpos_rank_count_and_maxlen() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    // 下标:          0    1    2    3    4    5    6
+    send_array(&mut write_half, &["RPUSH", "lp", "a", "b", "c", "a", "b", "c", "a"]).await;
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, ":7\r\n");
+
+    // 每个回复都读成一行文本，数组回复把元素拼接起来，便于比较
+    let cases: [(&[&str], &str); 14] = [
+        (&["LPOS", "lp", "a"], ":0"),
+        (&["LPOS", "lp", "a", "RANK", "2"], ":3"),
+        // 负数 RANK 从表尾开始找，返回的仍是从表头计数的下标
+        (&["LPOS", "lp", "a", "RANK", "-1"], ":6"),
+        (&["LPOS", "lp", "b", "RANK", "-2"], ":1"),
+        (&["LPOS", "lp", "a", "RANK", "4"], "$-1"),
+        // COUNT 0 返回全部匹配
+        (&["LPOS", "lp", "a", "COUNT", "0"], "*3 :0 :3 :6"),
+        (&["LPOS", "lp", "a", "COUNT", "2", "RANK", "-1"], "*2 :6 :3"),
+        (&["LPOS", "lp", "zz", "COUNT", "0"], "*0"),
+        // MAXLEN 限制比较的元素个数
+        (&["LPOS", "lp", "c", "MAXLEN", "2"], "$-1"),
+        (&["LPOS", "lp", "a", "COUNT", "0", "MAXLEN", "2"], "*1 :0"),
+        (&["LPOS", "lp", "c", "RANK", "-1", "MAXLEN", "2"], ":5"),
+        (&["LPOS", "missing", "a"], "$-1"),
+        (
+            &["LPOS", "lp", "a", "RANK", "0"],
+            "-ERR RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the end of the list",
+        ),
+        (&["LPOS", "lp", "a", "COUNT", "-1"], "-ERR COUNT can't be negative"),
+    ];
+    for (parts, expected) in cases {
+        send_array(&mut write_half, parts).await;
+        let mut header = String::new();
+        reader.read_line(&mut header).await.unwrap();
+        let mut got = header.trim_end().to_string();
+        if let Some(n) = got.strip_prefix('*') {
+            let n: usize = n.parse().unwrap();
+            for _ in 0..n {
+                let mut item = String::new();
+                reader.read_line(&mut item).await.unwrap();
+                got.push(' ');
+                got.push_str(item.trim_end());
+            }
+        }
+        assert_eq!(got, expected, "{:?}", parts);
+    }
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
 #[tokio::test]
 async fn hashes_basic_behaviour() {
     let (addr, shutdown, handle) = spawn_server().await;
@@ -903,6 +1018,116 @@ async fn hashes_basic_behaviour() {
     handle.await.unwrap().unwrap();
 }
 
+#[tokio::test]
+async fn hashes_binary_values_roundtrip() {
+    use tokio::io::AsyncReadExt;
+
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    // value 含 NUL 与非 UTF-8 高位字节
+    let value: &[u8] = b"a\x00b\xff\xfe\r\nz";
+    let mut req = b"*4\r\n$4\r\nHSET\r\n$5\r\nbinhf\r\n$1\r\nf\r\n".to_vec();
+    req.extend_from_slice(format!("${}\r\n", value.len()).as_bytes());
+    req.extend_from_slice(value);
+    req.extend_from_slice(b"\r\n");
+    write_half.write_all(&req).await.unwrap();
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, ":1\r\n");
+
+    let expected_bulk = {
+        let mut b = format!("${}\r\n", value.len()).into_bytes();
+        b.extend_from_slice(value);
+        b.extend_from_slice(b"\r\n");
+        b
+    };
+
+    let cases: [(&[u8], Vec<u8>); 4] = [
+        (
+            b"*3\r\n$4\r\nHGET\r\n$5\r\nbinhf\r\n$1\r\nf\r\n",
+            expected_bulk.clone(),
+        ),
+        (b"*2\r\n$5\r\nHVALS\r\n$5\r\nbinhf\r\n", {
+            let mut b = b"*1\r\n".to_vec();
+            b.extend_from_slice(&expected_bulk);
+            b
+        }),
+        (b"*2\r\n$7\r\nHGETALL\r\n$5\r\nbinhf\r\n", {
+            let mut b = b"*2\r\n$1\r\nf\r\n".to_vec();
+            b.extend_from_slice(&expected_bulk);
+            b
+        }),
+        (
+            b"*4\r\n$5\r\nHMGET\r\n$5\r\nbinhf\r\n$1\r\nf\r\n$7\r\nmissing\r\n",
+            {
+                let mut b = b"*2\r\n".to_vec();
+                b.extend_from_slice(&expected_bulk);
+                b.extend_from_slice(b"$-1\r\n");
+                b
+            },
+        ),
+    ];
+    for (request, expected) in cases {
+        write_half.write_all(request).await.unwrap();
+        let mut buf = vec![0u8; expected.len()];
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, expected);
+    }
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn hgetall_and_smembers_order_is_stable() {
+    use tokio::io::AsyncReadExt;
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    let fields = ["zeta", "alpha", "mid", "beta", "omega", "gamma"];
+    for f in fields {
+        send_array(&mut write_half, &["HSET", "orderh", f, &f.to_uppercase()]).await;
+        send_array(&mut write_half, &["SADD", "orders", f]).await;
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        line.clear();
+        reader.read_line(&mut line).await.unwrap();
+    }
+
+    // 按 field / 成员字节序返回，与插入顺序无关
+    let mut sorted = fields.to_vec();
+    sorted.sort();
+    let mut expected_h = format!("*{}\r\n", sorted.len() * 2);
+    let mut expected_s = format!("*{}\r\n", sorted.len());
+    for f in &sorted {
+        let v = f.to_uppercase();
+        expected_h.push_str(&format!("${}\r\n{}\r\n${}\r\n{}\r\n", f.len(), f, v.len(), v));
+        expected_s.push_str(&format!("${}\r\n{}\r\n", f.len(), f));
+    }
+
+    for _ in 0..5 {
+        send_array(&mut write_half, &["HGETALL", "orderh"]).await;
+        let mut buf = vec![0u8; expected_h.len()];
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), expected_h);
+
+        send_array(&mut write_half, &["SMEMBERS", "orders"]).await;
+        let mut buf = vec![0u8; expected_s.len()];
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), expected_s);
+    }
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
 #[tokio::test]
 async fn info_basic_fields() {
     let (addr, shutdown, handle) = spawn_server().await;
@@ -914,7 +1139,7 @@ async fn info_basic_fields() {
 
     let mut buf = String::new();
     // 读取若干行，直到 EOF 或已经包含我们关心的 Keyspace 行
-    for _ in 0..32 {
+    for _ in 0..48 {
         let mut line = String::new();
         let n = reader.read_line(&mut line).await.unwrap();
         if n == 0 {
@@ -1287,6 +1512,145 @@ async fn sets_union_behaviour() {
     handle.await.unwrap().unwrap();
 }
 
+#[tokio::test]
+async fn set_store_with_empty_result_deletes_destination() {
+    let (addr, shutdown, handle) = spawn_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    async fn reply(
+        reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>,
+        writer: &mut tokio::net::tcp::OwnedWriteHalf,
+        parts: &[&str],
+    ) -> String {
+        send_array(writer, parts).await;
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        line
+    }
+
+    reply(&mut reader, &mut write_half, &["SADD", "es:a", "1", "2"]).await;
+    reply(&mut reader, &mut write_half, &["SADD", "es:b", "3", "4"]).await;
+
+    // 目标 key 原本有数据（甚至是其他类型），空结果会把它删除
+    for (store, dest_init) in [
+        (["SINTERSTORE", "es:dest", "es:a", "es:b"], &["SADD", "es:dest", "old"][..]),
+        (["SDIFFSTORE", "es:dest", "es:a", "es:a"], &["SET", "es:dest", "old"][..]),
+        (["SUNIONSTORE", "es:dest", "es:none1", "es:none2"], &["RPUSH", "es:dest", "old"][..]),
+    ] {
+        reply(&mut reader, &mut write_half, dest_init).await;
+        assert_eq!(reply(&mut reader, &mut write_half, &store).await, ":0\r\n");
+        assert_eq!(
+            reply(&mut reader, &mut write_half, &["TYPE", "es:dest"]).await,
+            "+none\r\n",
+            "{} should delete the destination",
+            store[0]
+        );
+        assert_eq!(
+            reply(&mut reader, &mut write_half, &["EXISTS", "es:dest"]).await,
+            ":0\r\n"
+        );
+    }
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn copy_deep_copies_every_type() {
+    let (addr, shutdown, handle) = spawn_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    // 读取一条完整回复（含嵌套数组），按原始 RESP 文本返回
+    async fn reply(
+        reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>,
+        writer: &mut tokio::net::tcp::OwnedWriteHalf,
+        parts: &[&str],
+    ) -> String {
+        send_array(writer, parts).await;
+        let mut out = String::new();
+        let mut pending = 1i64;
+        while pending > 0 {
+            let mut line = String::new();
+            reader.read_line(&mut line).await.unwrap();
+            pending -= 1;
+            if let Some(n) = line.strip_prefix('*') {
+                pending += n.trim_end().parse::<i64>().unwrap().max(0);
+            } else if line.starts_with('$') && !line.starts_with("$-1") {
+                reader.read_line(&mut line).await.unwrap();
+            }
+            out.push_str(&line);
+        }
+        out
+    }
+
+    // list：COPY 之后修改源 key，副本不受影响
+    reply(&mut reader, &mut write_half, &["RPUSH", "cp:list", "a", "b"]).await;
+    assert_eq!(reply(&mut reader, &mut write_half, &["COPY", "cp:list", "cp:list2"]).await, ":1\r\n");
+    reply(&mut reader, &mut write_half, &["LPUSH", "cp:list", "z"]).await;
+    reply(&mut reader, &mut write_half, &["LSET", "cp:list", "1", "changed"]).await;
+    assert_eq!(
+        reply(&mut reader, &mut write_half, &["LRANGE", "cp:list2", "0", "-1"]).await,
+        "*2\r\n$1\r\na\r\n$1\r\nb\r\n"
+    );
+
+    // hash
+    reply(&mut reader, &mut write_half, &["HSET", "cp:hash", "f", "v"]).await;
+    assert_eq!(reply(&mut reader, &mut write_half, &["COPY", "cp:hash", "cp:hash2"]).await, ":1\r\n");
+    reply(&mut reader, &mut write_half, &["HSET", "cp:hash", "f", "new"]).await;
+    reply(&mut reader, &mut write_half, &["HSET", "cp:hash", "g", "v"]).await;
+    assert_eq!(reply(&mut reader, &mut write_half, &["HGET", "cp:hash2", "f"]).await, "$1\r\nv\r\n");
+    assert_eq!(reply(&mut reader, &mut write_half, &["HLEN", "cp:hash2"]).await, ":1\r\n");
+
+    // zset
+    reply(&mut reader, &mut write_half, &["ZADD", "cp:zset", "1", "m"]).await;
+    assert_eq!(reply(&mut reader, &mut write_half, &["COPY", "cp:zset", "cp:zset2"]).await, ":1\r\n");
+    reply(&mut reader, &mut write_half, &["ZINCRBY", "cp:zset", "5", "m"]).await;
+    reply(&mut reader, &mut write_half, &["ZADD", "cp:zset", "2", "n"]).await;
+    assert_eq!(reply(&mut reader, &mut write_half, &["ZSCORE", "cp:zset2", "m"]).await, "$1\r\n1\r\n");
+    assert_eq!(reply(&mut reader, &mut write_half, &["ZCARD", "cp:zset2"]).await, ":1\r\n");
+
+    // set 与 string
+    reply(&mut reader, &mut write_half, &["SADD", "cp:set", "x"]).await;
+    assert_eq!(reply(&mut reader, &mut write_half, &["COPY", "cp:set", "cp:set2"]).await, ":1\r\n");
+    reply(&mut reader, &mut write_half, &["SADD", "cp:set", "y"]).await;
+    assert_eq!(reply(&mut reader, &mut write_half, &["SCARD", "cp:set2"]).await, ":1\r\n");
+    reply(&mut reader, &mut write_half, &["SET", "cp:str", "v"]).await;
+    assert_eq!(reply(&mut reader, &mut write_half, &["COPY", "cp:str", "cp:str2"]).await, ":1\r\n");
+    reply(&mut reader, &mut write_half, &["APPEND", "cp:str", "more"]).await;
+    assert_eq!(reply(&mut reader, &mut write_half, &["GET", "cp:str2"]).await, "$1\r\nv\r\n");
+
+    // 目标已存在时需要 REPLACE；源不存在返回 0；源与目标相同报错
+    assert_eq!(reply(&mut reader, &mut write_half, &["COPY", "cp:str", "cp:list2"]).await, ":0\r\n");
+    assert_eq!(
+        reply(&mut reader, &mut write_half, &["COPY", "cp:str", "cp:list2", "REPLACE"]).await,
+        ":1\r\n"
+    );
+    assert_eq!(reply(&mut reader, &mut write_half, &["TYPE", "cp:list2"]).await, "+string\r\n");
+    assert_eq!(reply(&mut reader, &mut write_half, &["COPY", "cp:none", "cp:x"]).await, ":0\r\n");
+    assert_eq!(
+        reply(&mut reader, &mut write_half, &["COPY", "cp:str", "cp:str"]).await,
+        "-ERR source and destination objects are the same\r\n"
+    );
+
+    // DB 选项把副本写入其他库，TTL 一并复制
+    reply(&mut reader, &mut write_half, &["EXPIRE", "cp:str", "100"]).await;
+    assert_eq!(
+        reply(&mut reader, &mut write_half, &["COPY", "cp:str", "cp:str", "DB", "1"]).await,
+        ":1\r\n"
+    );
+    reply(&mut reader, &mut write_half, &["SELECT", "1"]).await;
+    assert_eq!(reply(&mut reader, &mut write_half, &["GET", "cp:str"]).await, "$5\r\nvmore\r\n");
+    let ttl = reply(&mut reader, &mut write_half, &["TTL", "cp:str"]).await;
+    assert!(ttl == ":100\r\n" || ttl == ":99\r\n", "{:?}", ttl);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
 #[tokio::test]
 async fn sets_store_and_random_commands() {
     let (addr, shutdown, handle) = spawn_server().await;
@@ -1479,6 +1843,106 @@ async fn sets_store_and_random_commands() {
     handle.await.unwrap().unwrap();
 }
 
+#[tokio::test]
+async fn srandmember_and_spop_are_uniform() {
+    use std::collections::HashMap;
+    use tokio::io::AsyncReadExt;
+
+    async fn read_members(
+        reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>,
+    ) -> Vec<String> {
+        let mut header = String::new();
+        reader.read_line(&mut header).await.unwrap();
+        assert!(header.starts_with('*'), "unexpected header {:?}", header);
+        let n: usize = header[1..].trim_end().parse().unwrap();
+        let mut out = Vec::with_capacity(n);
+        for _ in 0..n {
+            let mut bulk = String::new();
+            reader.read_line(&mut bulk).await.unwrap();
+            let len: usize = bulk[1..].trim_end().parse().unwrap();
+            let mut buf = vec![0u8; len + 2];
+            reader.read_exact(&mut buf).await.unwrap();
+            buf.truncate(len);
+            out.push(String::from_utf8(buf).unwrap());
+        }
+        out
+    }
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    let members = ["a", "b", "c", "d", "e"];
+    let mut sadd = vec!["SADD", "uni"];
+    sadd.extend(members);
+    send_array(&mut write_half, &sadd).await;
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, ":5\r\n");
+
+    // 期望次数上下各放宽 25%，约为统计波动（标准差 3%~4%）的 6 倍以上，但能抓住明显偏向
+    let assert_uniform = |counts: &HashMap<String, usize>, expected: usize, what: &str| {
+        for m in members {
+            let c = counts.get(m).copied().unwrap_or(0);
+            assert!(
+                c * 4 >= expected * 3 && c * 4 <= expected * 5,
+                "{}: member {} seen {} times, expected about {}",
+                what,
+                m,
+                c,
+                expected
+            );
+        }
+    };
+
+    // 负数 count：有放回抽样，6000 次中每个成员约 1200 次
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for _ in 0..6 {
+        send_array(&mut write_half, &["SRANDMEMBER", "uni", "-1000"]).await;
+        let got = read_members(&mut reader).await;
+        assert_eq!(got.len(), 1000);
+        for m in got {
+            *counts.entry(m).or_default() += 1;
+        }
+    }
+    assert_uniform(&counts, 1200, "SRANDMEMBER -1000");
+
+    // 正数 count：两两不同，每个成员被选中的概率为 2/5
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for _ in 0..1000 {
+        send_array(&mut write_half, &["SRANDMEMBER", "uni", "2"]).await;
+        let got = read_members(&mut reader).await;
+        assert_eq!(got.len(), 2);
+        assert_ne!(got[0], got[1]);
+        for m in got {
+            *counts.entry(m).or_default() += 1;
+        }
+    }
+    assert_uniform(&counts, 400, "SRANDMEMBER 2");
+
+    // SPOP count：弹出后放回，重复抽样
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for _ in 0..1000 {
+        send_array(&mut write_half, &["SPOP", "uni", "2"]).await;
+        let got = read_members(&mut reader).await;
+        assert_eq!(got.len(), 2);
+        let mut readd = vec!["SADD", "uni"];
+        readd.extend(got.iter().map(|s| s.as_str()));
+        send_array(&mut write_half, &readd).await;
+        line.clear();
+        reader.read_line(&mut line).await.unwrap();
+        assert_eq!(line, ":2\r\n");
+        for m in got {
+            *counts.entry(m).or_default() += 1;
+        }
+    }
+    assert_uniform(&counts, 400, "SPOP 2");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
 #[tokio::test]
 async fn lists_pop_behaviour() {
     let (addr, shutdown, handle) = spawn_server().await;
@@ -1646,6 +2110,63 @@ async fn sets_basic_behaviour() {
     handle.await.unwrap().unwrap();
 }
 
+#[tokio::test]
+async fn sets_binary_members_roundtrip() {
+    use tokio::io::AsyncReadExt;
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    // 成员含非法 UTF-8 字节与 NUL
+    let member: &[u8] = b"a\xff\x00\xc3";
+    let bulk = {
+        let mut b = format!("${}\r\n", member.len()).into_bytes();
+        b.extend_from_slice(member);
+        b.extend_from_slice(b"\r\n");
+        b
+    };
+
+    let mut req = b"*3\r\n$4\r\nSADD\r\n$6\r\nbinset\r\n".to_vec();
+    req.extend_from_slice(&bulk);
+    write_half.write_all(&req).await.unwrap();
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, ":1\r\n");
+
+    let mut req = b"*3\r\n$9\r\nSISMEMBER\r\n$6\r\nbinset\r\n".to_vec();
+    req.extend_from_slice(&bulk);
+    write_half.write_all(&req).await.unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, ":1\r\n");
+
+    let mut expected = b"*1\r\n".to_vec();
+    expected.extend_from_slice(&bulk);
+    write_half
+        .write_all(b"*2\r\n$8\r\nSMEMBERS\r\n$6\r\nbinset\r\n")
+        .await
+        .unwrap();
+    let mut buf = vec![0u8; expected.len()];
+    reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, expected);
+
+    // SSCAN MATCH 按字节匹配，非 UTF-8 成员同样可以命中
+    let mut expected = b"*2\r\n$1\r\n0\r\n*1\r\n".to_vec();
+    expected.extend_from_slice(&bulk);
+    write_half
+        .write_all(b"*5\r\n$5\r\nSSCAN\r\n$6\r\nbinset\r\n$1\r\n0\r\n$5\r\nMATCH\r\n$2\r\na*\r\n")
+        .await
+        .unwrap();
+    let mut buf = vec![0u8; expected.len()];
+    reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, expected);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
 #[tokio::test]
 async fn lists_basic_behaviour() {
     let (addr, shutdown, handle) = spawn_server().await;
@@ -1749,6 +2270,102 @@ async fn lists_basic_behaviour() {
     handle.await.unwrap().unwrap();
 }
 
+#[tokio::test]
+async fn lists_binary_elements_roundtrip() {
+    use tokio::io::AsyncReadExt;
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    let elems: [&[u8]; 2] = [b"\x00\x01", b"x\xff\r\ny"];
+    let bulks: Vec<Vec<u8>> = elems
+        .iter()
+        .map(|e| {
+            let mut b = format!("${}\r\n", e.len()).into_bytes();
+            b.extend_from_slice(e);
+            b.extend_from_slice(b"\r\n");
+            b
+        })
+        .collect();
+
+    // RPUSH binlist <elem0> <elem1> -> 2
+    let mut req = b"*4\r\n$5\r\nRPUSH\r\n$7\r\nbinlist\r\n".to_vec();
+    for b in &bulks {
+        req.extend_from_slice(b);
+    }
+    write_half.write_all(&req).await.unwrap();
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, ":2\r\n");
+
+    // LRANGE binlist 0 -1 原样返回两个元素
+    let mut expected = b"*2\r\n".to_vec();
+    for b in &bulks {
+        expected.extend_from_slice(b);
+    }
+    send_array(&mut write_half, &["LRANGE", "binlist", "0", "-1"]).await;
+    let mut buf = vec![0u8; expected.len()];
+    reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, expected);
+
+    // LPOP / RPOP 同样二进制安全
+    send_array(&mut write_half, &["LPOP", "binlist"]).await;
+    let mut buf = vec![0u8; bulks[0].len()];
+    reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, bulks[0]);
+
+    send_array(&mut write_half, &["RPOP", "binlist"]).await;
+    let mut buf = vec![0u8; bulks[1].len()];
+    reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, bulks[1]);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn type_reports_lowercase_redis_names() {
+    let (addr, shutdown, handle) = spawn_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    let setup: [&[&str]; 6] = [
+        &["SET", "t:str", "v"],
+        &["RPUSH", "t:list", "a"],
+        &["SADD", "t:set", "a"],
+        &["HSET", "t:hash", "f", "v"],
+        &["ZADD", "t:zset", "1", "a"],
+        &["PFADD", "t:hll", "a"],
+    ];
+    for cmd in setup {
+        send_array(&mut write_half, cmd).await;
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+    }
+
+    // HyperLogLog 与 Redis 一致报告为 string
+    for (key, expected) in [
+        ("t:str", "+string\r\n"),
+        ("t:list", "+list\r\n"),
+        ("t:set", "+set\r\n"),
+        ("t:hash", "+hash\r\n"),
+        ("t:zset", "+zset\r\n"),
+        ("t:hll", "+string\r\n"),
+        ("t:missing", "+none\r\n"),
+    ] {
+        send_array(&mut write_half, &["TYPE", key]).await;
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        assert_eq!(line, expected, "TYPE {}", key);
+    }
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
 #[tokio::test]
 async fn multiple_clients_share_storage() {
     let (addr, shutdown, handle) = spawn_server().await;
@@ -1804,6 +2421,73 @@ async fn continues_after_unknown_command() {
     handle.await.unwrap().unwrap();
 }
 
+#[tokio::test]
+async fn protocol_error_replies_then_closes_connection() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    for malformed in [
+        &b"*abc\r\n$4\r\nPING\r\n"[..],
+        &b"*1\r\n+PING\r\n"[..],
+        &b"*1\r\n$x\r\nPING\r\n"[..],
+    ] {
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (read_half, mut write_half) = stream.into_split();
+        let mut reader = BufReader::new(read_half);
+
+        write_half.write_all(malformed).await.unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        assert!(
+            line.starts_with("-ERR Protocol error: "),
+            "unexpected reply {:?} for {:?}",
+            line,
+            malformed
+        );
+
+        // 帧已失去同步，服务端回复后关闭连接，后续命令不会再被执行
+        let _ = write_half.write_all(b"*1\r\n$4\r\nPING\r\n").await;
+        line.clear();
+        let n = reader.read_line(&mut line).await.unwrap_or(0);
+        assert_eq!(n, 0, "connection should be closed, got {:?}", line);
+    }
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn oversized_array_header_is_rejected_without_allocation() {
+    let (addr, shutdown, handle) = spawn_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    // 只发送声称十亿个元素的数组头，服务端应直接拒绝而不是按该长度分配
+    write_half.write_all(b"*1000000000\r\n").await.unwrap();
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    assert!(
+        line.starts_with("-ERR Protocol error: array length exceeds limit"),
+        "unexpected reply {:?}",
+        line
+    );
+    line.clear();
+    let n = reader.read_line(&mut line).await.unwrap_or(0);
+    assert_eq!(n, 0, "connection should be closed, got {:?}", line);
+
+    // 其他连接不受影响
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+    write_half.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "+PONG\r\n");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
 #[tokio::test]
 async fn handles_quit_and_connection_close() {
     let (addr, shutdown, handle) = spawn_server().await;
@@ -1846,3 +2530,598 @@ async fn performance_ping_round_trips() {
     shutdown.send(()).unwrap();
     handle.await.unwrap().unwrap();
 }
+
+#[tokio::test]
+async fn small_bulk_replies_are_not_delayed() {
+    use tokio::io::AsyncReadExt;
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    // bulk 回复由多次小写入组成；若未关闭 Nagle，配合客户端延迟 ACK 每次往返会多出约 40ms
+    let iterations = 50;
+    let start = Instant::now();
+    for _ in 0..iterations {
+        send_array(&mut write_half, &["ECHO", "hello"]).await;
+        let mut buf = [0u8; 11];
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"$5\r\nhello\r\n");
+    }
+    let elapsed = start.elapsed();
+    assert!(
+        elapsed < Duration::from_millis(500),
+        "ECHO loop took too long: {:?}",
+        elapsed
+    );
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn performance_large_array_replies() {
+    use tokio::io::AsyncReadExt;
+
+    let (addr, shutdown, handle) = spawn_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    // 一次 RPUSH 写入 10k 个元素
+    let count = 10_000;
+    let items: Vec<String> = (0..count).map(|i| format!("item{:05}", i)).collect();
+    let mut parts = vec!["RPUSH", "biglist"];
+    parts.extend(items.iter().map(|s| s.as_str()));
+    send_array(&mut write_half, &parts).await;
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, format!(":{}\r\n", count));
+
+    // 每个元素编码为 "$9\r\nitemNNNNN\r\n"，共 15 字节
+    let header = format!("*{}\r\n", count);
+    let reply_len = header.len() + count * 15;
+    let iterations = 20;
+    let start = Instant::now();
+    for _ in 0..iterations {
+        send_array(&mut write_half, &["LRANGE", "biglist", "0", "-1"]).await;
+        let mut buf = vec![0u8; reply_len];
+        reader.read_exact(&mut buf).await.unwrap();
+        assert!(buf.starts_with(header.as_bytes()));
+        assert!(buf.ends_with(b"$9\r\nitem09999\r\n"));
+    }
+    let elapsed = start.elapsed();
+    assert!(
+        elapsed < Duration::from_secs(2),
+        "LRANGE 10k loop took too long: {:?}",
+        elapsed
+    );
+
+    // MGET 10k 个不存在的 key：每个元素为 "$-1\r\n"
+    let mut parts = vec!["MGET"];
+    parts.extend(items.iter().map(|s| s.as_str()));
+    let reply_len = header.len() + count * 5;
+    let start = Instant::now();
+    for _ in 0..iterations {
+        send_array(&mut write_half, &parts).await;
+        let mut buf = vec![0u8; reply_len];
+        reader.read_exact(&mut buf).await.unwrap();
+        assert!(buf.starts_with(header.as_bytes()));
+        assert!(buf.ends_with(b"$-1\r\n"));
+    }
+    let elapsed = start.elapsed();
+    assert!(
+        elapsed < Duration::from_secs(2),
+        "MGET 10k loop took too long: {:?}",
+        elapsed
+    );
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn serves_over_ipv6_loopback() {
+    std::env::set_var("REDUST_DISABLE_PERSISTENCE", "1");
+    // 部分环境未启用 IPv6，绑定失败时跳过
+    let listener = match TcpListener::bind("[::1]:0").await {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("skipping ipv6 test: {}", e);
+            return;
+        }
+    };
+    let addr = listener.local_addr().unwrap();
+    assert!(addr.is_ipv6());
+    let (tx, rx) = oneshot::channel::<()>();
+    let handle = tokio::spawn(async move {
+        serve(listener, async move {
+            let _ = rx.await;
+        })
+        .await
+    });
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let local = stream.local_addr().unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    write_half.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "+PONG\r\n");
+
+    // CLIENT LIST 中的 IPv6 地址带方括号：addr=[::1]:port
+    send_array(&mut write_half, &["CLIENT", "LIST"]).await;
+    let mut header = String::new();
+    reader.read_line(&mut header).await.unwrap();
+    let mut info = String::new();
+    reader.read_line(&mut info).await.unwrap();
+    assert!(
+        info.contains(&format!("addr=[::1]:{}", local.port())),
+        "got: {}",
+        info
+    );
+
+    tx.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn time_returns_seconds_and_microseconds() {
+    let (addr, shutdown, handle) = spawn_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    send_array(&mut write_half, &["TIME"]).await;
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "*2\r\n");
+    let mut values = Vec::new();
+    for _ in 0..2 {
+        let mut header = String::new();
+        reader.read_line(&mut header).await.unwrap();
+        assert!(header.starts_with('$'), "unexpected header {:?}", header);
+        let mut value = String::new();
+        reader.read_line(&mut value).await.unwrap();
+        values.push(value.trim_end().parse::<u64>().unwrap());
+    }
+    let local = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    assert!(values[0].abs_diff(local) <= 2, "server {} vs local {}", values[0], local);
+    assert!(values[1] < 1_000_000);
+
+    send_array(&mut write_half, &["TIME", "extra"]).await;
+    let mut err = String::new();
+    reader.read_line(&mut err).await.unwrap();
+    assert!(err.starts_with("-ERR wrong number of arguments"), "unexpected {:?}", err);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn info_run_id_is_stable_and_lolwut_replies() {
+    let (addr, shutdown, handle) = spawn_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    let mut run_ids = Vec::new();
+    for _ in 0..2 {
+        send_array(&mut write_half, &["INFO", "server"]).await;
+        let mut header = String::new();
+        reader.read_line(&mut header).await.unwrap();
+        let len: usize = header.trim_start_matches('$').trim_end().parse().unwrap();
+        let mut body = vec![0u8; len + 2];
+        tokio::io::AsyncReadExt::read_exact(&mut reader, &mut body)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body).unwrap();
+        let run_id = body
+            .lines()
+            .find_map(|l| l.strip_prefix("run_id:"))
+            .expect("INFO should contain run_id")
+            .to_string();
+        assert_eq!(run_id.len(), 40);
+        assert!(run_id.bytes().all(|b| b.is_ascii_hexdigit()));
+        run_ids.push(run_id);
+    }
+    assert_eq!(run_ids[0], run_ids[1]);
+
+    send_array(&mut write_half, &["LOLWUT"]).await;
+    let mut header = String::new();
+    reader.read_line(&mut header).await.unwrap();
+    assert!(header.starts_with('$'), "unexpected header {:?}", header);
+    let mut banner = String::new();
+    reader.read_line(&mut banner).await.unwrap();
+    assert!(banner.starts_with("Redust ver. "), "unexpected banner {:?}", banner);
+    let mut rest = String::new();
+    reader.read_line(&mut rest).await.unwrap();
+    assert_eq!(rest, "\r\n");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn object_idletime_tracks_last_access() {
+    let (addr, shutdown, handle) = spawn_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    async fn reply(
+        reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>,
+        writer: &mut tokio::net::tcp::OwnedWriteHalf,
+        parts: &[&str],
+    ) -> String {
+        send_array(writer, parts).await;
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        line
+    }
+
+    assert_eq!(reply(&mut reader, &mut write_half, &["SET", "idle:a", "1"]).await, "+OK\r\n");
+    assert_eq!(reply(&mut reader, &mut write_half, &["SET", "idle:b", "1"]).await, "+OK\r\n");
+    tokio::time::sleep(Duration::from_millis(2100)).await;
+
+    assert_eq!(reply(&mut reader, &mut write_half, &["GET", "idle:a"]).await, "$1\r\n");
+    let mut value = String::new();
+    reader.read_line(&mut value).await.unwrap();
+
+    assert_eq!(
+        reply(&mut reader, &mut write_half, &["OBJECT", "IDLETIME", "idle:a"]).await,
+        ":0\r\n"
+    );
+    // OBJECT IDLETIME 本身不刷新访问时间
+    for _ in 0..2 {
+        let line = reply(&mut reader, &mut write_half, &["OBJECT", "IDLETIME", "idle:b"]).await;
+        let idle: i64 = line.trim_start_matches(':').trim_end().parse().unwrap();
+        assert!(idle >= 2, "idle:b should be idle for at least 2s, got {}", idle);
+    }
+    assert_eq!(
+        reply(&mut reader, &mut write_half, &["OBJECT", "IDLETIME", "idle:missing"]).await,
+        "$-1\r\n"
+    );
+    let err = reply(&mut reader, &mut write_half, &["OBJECT", "NOPE", "idle:a"]).await;
+    assert!(err.starts_with("-ERR Unknown subcommand"), "unexpected {:?}", err);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn object_encoding_follows_list_length_and_listpack_threshold() {
+    let (addr, shutdown, handle) = spawn_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    async fn reply(
+        reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>,
+        writer: &mut tokio::net::tcp::OwnedWriteHalf,
+        parts: &[&str],
+    ) -> String {
+        send_array(writer, parts).await;
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        if line.starts_with('$') && line != "$-1\r\n" {
+            let mut value = String::new();
+            reader.read_line(&mut value).await.unwrap();
+            return value;
+        }
+        line
+    }
+
+    assert_eq!(
+        reply(&mut reader, &mut write_half, &["DEBUG", "LISTPACK-ENTRIES", "4"]).await,
+        "+OK\r\n"
+    );
+    assert_eq!(
+        reply(&mut reader, &mut write_half, &["RPUSH", "enc:list", "a", "b", "c", "d"]).await,
+        ":4\r\n"
+    );
+    assert_eq!(
+        reply(&mut reader, &mut write_half, &["OBJECT", "ENCODING", "enc:list"]).await,
+        "listpack\r\n"
+    );
+    assert_eq!(reply(&mut reader, &mut write_half, &["RPUSH", "enc:list", "e"]).await, ":5\r\n");
+    assert_eq!(
+        reply(&mut reader, &mut write_half, &["OBJECT", "ENCODING", "enc:list"]).await,
+        "quicklist\r\n"
+    );
+
+    // 调高阈值（别名 QUICKLIST-PACKED-THRESHOLD）后立即对已有 key 生效
+    assert_eq!(
+        reply(&mut reader, &mut write_half, &["DEBUG", "QUICKLIST-PACKED-THRESHOLD", "8"]).await,
+        "+OK\r\n"
+    );
+    assert_eq!(
+        reply(&mut reader, &mut write_half, &["OBJECT", "ENCODING", "enc:list"]).await,
+        "listpack\r\n"
+    );
+
+    assert_eq!(reply(&mut reader, &mut write_half, &["SET", "enc:int", "12345"]).await, "+OK\r\n");
+    assert_eq!(
+        reply(&mut reader, &mut write_half, &["OBJECT", "ENCODING", "enc:int"]).await,
+        "int\r\n"
+    );
+    assert_eq!(
+        reply(&mut reader, &mut write_half, &["OBJECT", "ENCODING", "enc:missing"]).await,
+        "$-1\r\n"
+    );
+    let err = reply(&mut reader, &mut write_half, &["DEBUG", "LISTPACK-ENTRIES", "-1"]).await;
+    assert!(err.starts_with("-ERR value is out of range"), "unexpected {:?}", err);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn object_encoding_moves_sets_from_intset_to_listpack_and_hashtable() {
+    let (addr, shutdown, handle) = spawn_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    async fn reply(
+        reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>,
+        writer: &mut tokio::net::tcp::OwnedWriteHalf,
+        parts: &[&str],
+    ) -> String {
+        send_array(writer, parts).await;
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        if line.starts_with('$') && line != "$-1\r\n" {
+            let mut value = String::new();
+            reader.read_line(&mut value).await.unwrap();
+            return value;
+        }
+        line
+    }
+
+    for (param, value) in [("set-max-intset-entries", "3"), ("set-max-listpack-entries", "4")] {
+        assert_eq!(
+            reply(&mut reader, &mut write_half, &["CONFIG", "SET", param, value]).await,
+            "+OK\r\n"
+        );
+    }
+    send_array(&mut write_half, &["CONFIG", "GET", "set-max-*"]).await;
+    let mut lines = Vec::new();
+    for _ in 0..9 {
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        lines.push(line);
+    }
+    assert_eq!(
+        lines.concat(),
+        "*4\r\n$22\r\nset-max-intset-entries\r\n$1\r\n3\r\n$24\r\nset-max-listpack-entries\r\n$1\r\n4\r\n"
+    );
+
+    assert_eq!(reply(&mut reader, &mut write_half, &["SADD", "enc:s", "1", "2", "-3"]).await, ":3\r\n");
+    assert_eq!(
+        reply(&mut reader, &mut write_half, &["OBJECT", "ENCODING", "enc:s"]).await,
+        "intset\r\n"
+    );
+    // 非整数成员（含非规范写法 "007"）使 set 离开 intset
+    assert_eq!(reply(&mut reader, &mut write_half, &["SADD", "enc:s", "007"]).await, ":1\r\n");
+    assert_eq!(
+        reply(&mut reader, &mut write_half, &["OBJECT", "ENCODING", "enc:s"]).await,
+        "listpack\r\n"
+    );
+    assert_eq!(reply(&mut reader, &mut write_half, &["SADD", "enc:s", "x"]).await, ":1\r\n");
+    assert_eq!(
+        reply(&mut reader, &mut write_half, &["OBJECT", "ENCODING", "enc:s"]).await,
+        "hashtable\r\n"
+    );
+
+    // 全是整数但超过 set-max-intset-entries 时同样不再是 intset
+    assert_eq!(
+        reply(&mut reader, &mut write_half, &["SADD", "enc:n", "1", "2", "3", "4"]).await,
+        ":4\r\n"
+    );
+    assert_eq!(
+        reply(&mut reader, &mut write_half, &["OBJECT", "ENCODING", "enc:n"]).await,
+        "listpack\r\n"
+    );
+    let err = reply(&mut reader, &mut write_half, &["CONFIG", "SET", "set-max-intset-entries", "-1"]).await;
+    assert!(err.starts_with("-ERR Invalid argument"), "unexpected {:?}", err);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn debug_object_and_memory_usage_share_serialized_length() {
+    let (addr, shutdown, handle) = spawn_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    async fn reply(
+        reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>,
+        writer: &mut tokio::net::tcp::OwnedWriteHalf,
+        parts: &[&str],
+    ) -> String {
+        send_array(writer, parts).await;
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        line
+    }
+
+    reply(&mut reader, &mut write_half, &["RPUSH", "sl:list", "a", "bcd"]).await;
+    reply(&mut reader, &mut write_half, &["HSET", "sl:hash", "f", "vv"]).await;
+    reply(&mut reader, &mut write_half, &["HSET", "sl:hash", "ff", "v"]).await;
+    reply(&mut reader, &mut write_half, &["SADD", "sl:set", "x", "yz"]).await;
+    reply(&mut reader, &mut write_half, &["ZADD", "sl:zset", "1", "m", "2", "nn"]).await;
+    reply(&mut reader, &mut write_half, &["SET", "sl:str", "hello"]).await;
+    reply(&mut reader, &mut write_half, &["PFADD", "sl:hll", "a"]).await;
+
+    // 长度前缀 4 字节：list/set 4+Σ(4+len)，hash 4+Σ(4+f+4+v)，zset 4+Σ(8+4+member)，
+    // 稀疏 HyperLogLog 4+3×非零寄存器数
+    let expected = [
+        ("sl:list", 4 + (4 + 1) + (4 + 3)),
+        ("sl:hash", 4 + (4 + 1 + 4 + 2) + (4 + 2 + 4 + 1)),
+        ("sl:set", 4 + (4 + 1) + (4 + 2)),
+        ("sl:zset", 4 + (8 + 4 + 1) + (8 + 4 + 2)),
+        ("sl:str", 4 + 5),
+        ("sl:hll", 4 + 3),
+    ];
+    for (key, len) in expected {
+        let info = reply(&mut reader, &mut write_half, &["DEBUG", "OBJECT", key]).await;
+        assert!(
+            info.contains(&format!(" serializedlength:{} ", len)),
+            "unexpected DEBUG OBJECT reply for {}: {:?}",
+            key,
+            info
+        );
+        // MEMORY USAGE = key 长度 + 同一个 serializedlength
+        assert_eq!(
+            reply(&mut reader, &mut write_half, &["MEMORY", "USAGE", key]).await,
+            format!(":{}\r\n", key.len() + len)
+        );
+    }
+
+    assert_eq!(
+        reply(&mut reader, &mut write_half, &["MEMORY", "USAGE", "sl:list", "SAMPLES", "0"]).await,
+        format!(":{}\r\n", "sl:list".len() + 16)
+    );
+    assert_eq!(
+        reply(&mut reader, &mut write_half, &["MEMORY", "USAGE", "sl:missing"]).await,
+        "$-1\r\n"
+    );
+    assert_eq!(
+        reply(&mut reader, &mut write_half, &["DEBUG", "OBJECT", "sl:missing"]).await,
+        "-ERR no such key\r\n"
+    );
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn role_and_replicaof_no_one() {
+    let (addr, shutdown, handle) = spawn_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    send_array(&mut write_half, &["ROLE"]).await;
+    let mut reply = String::new();
+    for _ in 0..5 {
+        reader.read_line(&mut reply).await.unwrap();
+    }
+    assert_eq!(reply, "*3\r\n$6\r\nmaster\r\n:0\r\n*0\r\n");
+
+    for cmd in ["REPLICAOF", "SLAVEOF"] {
+        send_array(&mut write_half, &[cmd, "NO", "ONE"]).await;
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        assert_eq!(line, "+OK\r\n");
+
+        send_array(&mut write_half, &[cmd, "127.0.0.1", "6379"]).await;
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        assert_eq!(line, "-ERR replication not supported\r\n");
+    }
+
+    send_array(&mut write_half, &["INFO", "replication"]).await;
+    let mut header = String::new();
+    reader.read_line(&mut header).await.unwrap();
+    let len: usize = header.trim_start_matches('$').trim_end().parse().unwrap();
+    let mut body = vec![0u8; len + 2];
+    tokio::io::AsyncReadExt::read_exact(&mut reader, &mut body)
+        .await
+        .unwrap();
+    let body = String::from_utf8(body).unwrap();
+    assert!(body.contains("# Replication\r\nrole:master\r\n"), "{}", body);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn wait_and_failover_reply_predictably_without_replicas() {
+    let (addr, shutdown, handle) = spawn_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    let cases: [(&[&str], &str); 5] = [
+        (&["FAILOVER"], "-ERR FAILOVER requires connected replicas.\r\n"),
+        (
+            &["FAILOVER", "TO", "127.0.0.1", "6380"],
+            "-ERR FAILOVER requires connected replicas.\r\n",
+        ),
+        // 没有副本可等待：立即返回 0，而不是阻塞到超时
+        (&["WAIT", "1", "0"], ":0\r\n"),
+        (&["WAIT", "0", "-1"], "-ERR timeout is negative\r\n"),
+        (
+            &["WAIT", "1", "abc"],
+            "-ERR timeout is not an integer or out of range\r\n",
+        ),
+    ];
+    for (cmd, expected) in cases {
+        send_array(&mut write_half, cmd).await;
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        assert_eq!(line, expected, "{:?}", cmd);
+    }
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn cluster_stubs_report_cluster_disabled() {
+    let (addr, shutdown, handle) = spawn_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+
+    send_array(&mut write_half, &["CLUSTER", "INFO"]).await;
+    let mut reply = String::new();
+    for _ in 0..3 {
+        reader.read_line(&mut reply).await.unwrap();
+    }
+    assert_eq!(reply, "$19\r\ncluster_enabled:0\r\n\r\n");
+
+    send_array(&mut write_half, &["CLUSTER", "MYID"]).await;
+    let mut header = String::new();
+    reader.read_line(&mut header).await.unwrap();
+    assert_eq!(header, "$40\r\n");
+    let mut id = String::new();
+    reader.read_line(&mut id).await.unwrap();
+    assert!(id.trim_end().bytes().all(|b| b.is_ascii_hexdigit()));
+
+    for sub in ["SLOTS", "shards"] {
+        send_array(&mut write_half, &["CLUSTER", sub]).await;
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        assert_eq!(line, "*0\r\n");
+    }
+
+    for cmd in [
+        &["CLUSTER", "ADDSLOTS", "1"][..],
+        &["CLUSTER", "MEET", "127.0.0.1", "7000"],
+        &["CLUSTER", "RESET"],
+    ] {
+        send_array(&mut write_half, cmd).await;
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        assert_eq!(line, "-ERR This instance has cluster support disabled\r\n");
+    }
+
+    send_array(&mut write_half, &["CLUSTER", "INFO", "extra"]).await;
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("-ERR Unknown subcommand"), "{}", line);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
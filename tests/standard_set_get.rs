@@ -11,6 +11,8 @@ async fn spawn_server() -> (
     oneshot::Sender<()>,
     tokio::task::JoinHandle<tokio::io::Result<()>>,
 ) {
+    // 不加载工作目录下的 redust.rdb，避免残留数据（如 foo）影响 APPEND 等断言
+    std::env::set_var("REDUST_DISABLE_PERSISTENCE", "1");
     let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind server");
     let addr = listener.local_addr().expect("local addr");
     let (tx, rx) = oneshot::channel();
@@ -41,6 +43,27 @@ async fn mset_and_mget_roundtrip() {
     handle.await.unwrap().unwrap();
 }
 
+#[tokio::test]
+async fn mget_returns_nil_for_non_string_keys() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["LPUSH", "l", "x"]).await;
+    assert_eq!(client.read_simple_line().await, ":1\r\n");
+    client.send_array(&["HSET", "h", "f", "v"]).await;
+    assert_eq!(client.read_simple_line().await, ":1\r\n");
+    client.send_array(&["SET", "s", "v"]).await;
+    assert_eq!(client.read_simple_line().await, "+OK\r\n");
+
+    // 与 Redis 一致：非 string 类型的 key 在 MGET 中按 nil 返回，不报 WRONGTYPE
+    let values = client.mget(&["l", "s", "missing", "h"]).await;
+    assert_eq!(values, vec![None, Some("v".to_string()), None, None]);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
 #[tokio::test]
 async fn set_with_exat_and_pxat_options() {
     use std::time::{SystemTime, UNIX_EPOCH};
@@ -113,6 +136,39 @@ async fn setrange_does_not_truncate_tail() {
     handle.await.unwrap().unwrap();
 }
 
+#[tokio::test]
+async fn setrange_rejects_lengths_past_proto_max_bulk_len() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut client = TestClient::connect(addr).await;
+
+    // 512MB 上限：offset + len 超过 536870912 即报错，不分配也不创建 key
+    for offset in ["536870912", "9223372036854775807"] {
+        client.send_array(&["SETRANGE", "huge", offset, "x"]).await;
+        assert_eq!(
+            client.read_simple_line().await,
+            "-ERR string exceeds maximum allowed size (proto-max-bulk-len)\r\n"
+        );
+    }
+    // 超出 i64 的偏移（如 usize::MAX）在解析阶段报错
+    client
+        .send_array(&["SETRANGE", "huge", "18446744073709551615", "x"])
+        .await;
+    assert_eq!(
+        client.read_simple_line().await,
+        "-ERR value is not an integer or out of range\r\n"
+    );
+
+    // 空 value 不检查长度，也不创建 key
+    client.send_array(&["SETRANGE", "huge", "9223372036854775807", ""]).await;
+    assert_eq!(client.read_simple_line().await, ":0\r\n");
+    client.send_array(&["EXISTS", "huge"]).await;
+    assert_eq!(client.read_simple_line().await, ":0\r\n");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
 #[tokio::test]
 async fn msetnx_atomicity_and_existing_keys() {
     let (addr, shutdown, handle) = spawn_server().await;
@@ -189,6 +245,52 @@ async fn incrbyfloat_roundtrip_and_error_cases() {
     handle.await.unwrap().unwrap();
 }
 
+#[tokio::test]
+async fn incrbyfloat_formatting_and_overflow() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut client = TestClient::connect(addr).await;
+
+    // 结果不使用科学计数法，也不带尾随的 0
+    client.send_array(&["INCRBYFLOAT", "fmt", "3.0e3"]).await;
+    assert_eq!(client.read_bulk().await.as_deref(), Some("3000"));
+
+    client.set("fmt2", "10.5").await;
+    client.send_array(&["INCRBYFLOAT", "fmt2", "0.1"]).await;
+    assert_eq!(client.read_bulk().await.as_deref(), Some("10.6"));
+
+    // 第二次相加溢出为 Infinity：报错且保留原值
+    client.send_array(&["INCRBYFLOAT", "big", "1e308"]).await;
+    let first = client.read_bulk().await.unwrap();
+    assert!(first.starts_with("1000000000") && !first.contains('e'));
+    client.send_array(&["INCRBYFLOAT", "big", "1e308"]).await;
+    assert_eq!(
+        client.read_simple_line().await,
+        "-ERR increment would produce NaN or Infinity\r\n"
+    );
+    assert_eq!(client.get("big").await.as_deref(), Some(first.as_str()));
+
+    // HINCRBYFLOAT 使用相同的格式与错误
+    client.send_array(&["HINCRBYFLOAT", "hf", "f", "3.0e3"]).await;
+    assert_eq!(client.read_bulk().await.as_deref(), Some("3000"));
+    client.send_array(&["HINCRBYFLOAT", "hf", "f", "1.7e308"]).await;
+    let _ = client.read_bulk().await;
+    client.send_array(&["HINCRBYFLOAT", "hf", "f", "1.7e308"]).await;
+    assert_eq!(
+        client.read_simple_line().await,
+        "-ERR increment would produce NaN or Infinity\r\n"
+    );
+
+    // 类型不符时返回 WRONGTYPE
+    client.send_array(&["RPUSH", "flist", "a"]).await;
+    let _ = client.read_simple_line().await;
+    client.send_array(&["INCRBYFLOAT", "flist", "1"]).await;
+    assert!(client.read_simple_line().await.starts_with("-WRONGTYPE"));
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
 #[tokio::test]
 async fn getrange_and_setrange_roundtrip() {
     let (addr, shutdown, handle) = spawn_server().await;
@@ -322,6 +424,15 @@ async fn set_with_get_option() {
     let v2 = client.get("foo").await;
     assert_eq!(v2.as_deref(), Some("baz"));
 
+    // 旧值不是字符串时 SET ... GET 返回 WRONGTYPE，且不覆盖原有 list
+    client.send_array(&["LPUSH", "k", "x"]).await;
+    let _ = client.read_simple_line().await; // :1
+    client.send_array(&["SET", "k", "v", "GET"]).await;
+    let line = client.read_simple_line().await;
+    assert!(line.starts_with("-WRONGTYPE"), "unexpected {:?}", line);
+    client.send_array(&["LLEN", "k"]).await;
+    assert_eq!(client.read_simple_line().await, ":1\r\n");
+
     shutdown.send(()).unwrap();
     handle.await.unwrap().unwrap();
 }
@@ -388,6 +499,10 @@ async fn getdel_basic_behaviour_and_wrongtype() {
     let line = client.read_simple_line().await;
     assert!(line.starts_with("-WRONGTYPE"));
 
+    // 类型不符时不删除原 key
+    client.send_array(&["LLEN", "list"]).await;
+    assert_eq!(client.read_simple_line().await, ":1\r\n");
+
     shutdown.send(()).unwrap();
     handle.await.unwrap().unwrap();
 }
@@ -441,6 +556,85 @@ async fn getex_updates_ttl_and_persist_clears_it() {
     handle.await.unwrap().unwrap();
 }
 
+#[tokio::test]
+async fn non_positive_expire_times_are_rejected() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut client = TestClient::connect(addr).await;
+
+    let cases: [&[&str]; 7] = [
+        &["SET", "k", "v", "EX", "0"],
+        &["SET", "k", "v", "PX", "0"],
+        &["SET", "k", "v", "EXAT", "0"],
+        &["SET", "k", "v", "PXAT", "-5"],
+        &["SET", "k", "v", "EX", "-1"],
+        &["SETEX", "k", "0", "v"],
+        &["PSETEX", "k", "0", "v"],
+    ];
+    for parts in cases {
+        client.send_array(parts).await;
+        let expected = format!(
+            "-ERR invalid expire time in '{}' command\r\n",
+            parts[0].to_ascii_lowercase()
+        );
+        assert_eq!(client.read_simple_line().await, expected, "{:?}", parts);
+    }
+    client.send_array(&["SETEX", "k", "-1", "v"]).await;
+    assert_eq!(
+        client.read_simple_line().await,
+        "-ERR invalid expire time in 'setex' command\r\n"
+    );
+
+    // 被拒绝的命令不会写入 key
+    assert_eq!(client.get("k").await, None);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn zero_ttl_deletes_for_expire_but_errors_for_set_family() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut client = TestClient::connect(addr).await;
+
+    // EXPIRE/PEXPIRE 的非正数 TTL：key 存在时立刻删除并返回 1
+    for parts in [
+        &["EXPIRE", "k", "0"][..],
+        &["PEXPIRE", "k", "0"],
+        &["EXPIRE", "k", "-5"],
+    ] {
+        client.send_array(&["SET", "k", "v"]).await;
+        assert_eq!(client.read_simple_line().await, "+OK\r\n");
+        client.send_array(parts).await;
+        assert_eq!(client.read_simple_line().await, ":1\r\n", "{:?}", parts);
+        assert_eq!(client.get("k").await, None, "{:?}", parts);
+    }
+
+    // GETEX 的 EX/PX 与 SET 一样拒绝非正数，key 的值与 TTL 都保持不变
+    client.send_array(&["SET", "k", "v"]).await;
+    assert_eq!(client.read_simple_line().await, "+OK\r\n");
+    for parts in [
+        &["GETEX", "k", "EX", "0"][..],
+        &["GETEX", "k", "PX", "0"],
+        &["GETEX", "k", "EX", "-1"],
+    ] {
+        client.send_array(parts).await;
+        assert_eq!(
+            client.read_simple_line().await,
+            "-ERR invalid expire time in 'getex' command\r\n",
+            "{:?}",
+            parts
+        );
+    }
+    assert_eq!(client.get("k").await.as_deref(), Some("v"));
+    client.send_array(&["TTL", "k"]).await;
+    assert_eq!(client.read_simple_line().await, ":-1\r\n");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
 #[tokio::test]
 async fn command_argument_and_integer_errors_match_redis() {
     let (addr, shutdown, handle) = spawn_server().await;
@@ -489,12 +683,12 @@ async fn command_argument_and_integer_errors_match_redis() {
     let line = client.read_simple_line().await;
     assert_eq!(line, "-ERR syntax error\r\n");
 
-    // EXAT/PXAT 参数为非整数（或负数）时，返回整数错误
+    // EXAT/PXAT 参数为负数时返回过期时间错误，非整数时返回整数错误
     client
         .send_array(&["SET", "foo", "bar", "EXAT", "-1"])
         .await;
     let line = client.read_simple_line().await;
-    assert_eq!(line, "-ERR value is not an integer or out of range\r\n");
+    assert_eq!(line, "-ERR invalid expire time in 'set' command\r\n");
 
     client
         .send_array(&["SET", "foo", "bar", "PXAT", "notint"])
@@ -666,6 +860,78 @@ async fn setex_and_psetex_roundtrip() {
     handle.await.unwrap().unwrap();
 }
 
+#[tokio::test]
+async fn del_and_unlink_do_not_count_expired_keys() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["SET", "ephemeral", "v", "PX", "20"]).await;
+    assert_eq!(client.read_simple_line().await, "+OK\r\n");
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+    // 已逻辑过期但尚未被清理的 key 对 DEL 而言不存在
+    client.send_array(&["DEL", "ephemeral"]).await;
+    assert_eq!(client.read_simple_line().await, ":0\r\n");
+
+    client.send_array(&["SET", "ephemeral", "v", "PX", "20"]).await;
+    assert_eq!(client.read_simple_line().await, "+OK\r\n");
+    client.set("live", "v").await;
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+    client.send_array(&["UNLINK", "ephemeral", "live", "missing"]).await;
+    assert_eq!(client.read_simple_line().await, ":1\r\n");
+    // 过期 key 已被一并清理
+    assert_eq!(client.dbsize().await, 0);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn incr_error_precedence_and_strict_integer_parsing() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut client = TestClient::connect(addr).await;
+
+    // 类型错误优先于数值解析错误
+    client.send_array(&["RPUSH", "list", "1"]).await;
+    assert_eq!(client.read_simple_line().await, ":1\r\n");
+    client.send_array(&["INCR", "list"]).await;
+    assert_eq!(
+        client.read_simple_line().await,
+        "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n"
+    );
+    assert!(client.incrby("list", 5).await.unwrap_err().starts_with("-WRONGTYPE"));
+
+    // 与 Redis 一样不裁剪空白，也不接受 `+` 号和前导 0
+    for bad in ["abc", " 10", "10 ", "  10  ", "+10", "010", "-0", ""] {
+        client.set("n", bad).await;
+        client.send_array(&["INCR", "n"]).await;
+        assert_eq!(
+            client.read_simple_line().await,
+            "-ERR value is not an integer or out of range\r\n",
+            "INCR on {:?}",
+            bad
+        );
+        assert_eq!(client.get("n").await.as_deref(), Some(bad));
+    }
+
+    client.set("n", "-10").await;
+    assert_eq!(client.incrby("n", 3).await, Ok(-7));
+    client.set("n", "0").await;
+    assert_eq!(client.incrby("n", 1).await, Ok(1));
+
+    client.set("n", &i64::MAX.to_string()).await;
+    assert_eq!(
+        client.incrby("n", 1).await,
+        Err("-ERR increment or decrement would overflow\r\n".to_string())
+    );
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
 #[tokio::test]
 async fn incrby_and_decrby_roundtrip() {
     let (addr, shutdown, handle) = spawn_server().await;
@@ -714,7 +980,17 @@ async fn append_and_strlen_roundtrip() {
 
     client.send_array(&["APPEND", "list", "x"]).await;
     let line = client.read_simple_line().await;
-    assert!(line.starts_with("-WRONGTYPE"));
+    assert_eq!(
+        line,
+        "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n"
+    );
+    client.send_array(&["LLEN", "list"]).await;
+    assert_eq!(client.read_simple_line().await, ":1\r\n");
+
+    // 空后缀也会创建 key，返回 0
+    assert_eq!(client.append("empty", "").await, 0);
+    client.send_array(&["EXISTS", "empty"]).await;
+    assert_eq!(client.read_simple_line().await, ":1\r\n");
 
     client.send_array(&["STRLEN", "list"]).await;
     let line2 = client.read_simple_line().await;
@@ -1013,7 +1289,6 @@ impl TestClient {
 
 #[tokio::test]
 async fn basic_dbsize_behaviour() {
-    std::env::set_var("REDUST_DISABLE_PERSISTENCE", "1");
     let (addr, _shutdown, _handle) = spawn_server().await;
 
     let mut client = TestClient::connect(addr).await;
@@ -1145,6 +1420,42 @@ async fn ttl_and_expire_roundtrip() {
     handle.await.unwrap().unwrap();
 }
 
+#[tokio::test]
+async fn expire_missing_zero_and_negative_ttl() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut client = TestClient::connect(addr).await;
+
+    // key 不存在 -> 0
+    for cmd in ["EXPIRE", "PEXPIRE"] {
+        for ttl in ["10", "0", "-5"] {
+            client.send_array(&[cmd, "missing", ttl]).await;
+            assert_eq!(client.read_simple_line().await, ":0\r\n", "{} missing {}", cmd, ttl);
+        }
+    }
+
+    // TTL 为 0 或负数：删除 key 并返回 1
+    for (cmd, ttl) in [("EXPIRE", "0"), ("EXPIRE", "-1"), ("PEXPIRE", "0"), ("PEXPIRE", "-100")] {
+        client.set("foo", "bar").await;
+        client.send_array(&[cmd, "foo", ttl]).await;
+        assert_eq!(client.read_simple_line().await, ":1\r\n", "{} foo {}", cmd, ttl);
+        assert_eq!(client.get("foo").await, None);
+        client.send_array(&["TTL", "foo"]).await;
+        assert_eq!(client.read_simple_line().await, ":-2\r\n");
+    }
+
+    // 已经过期的 key 视为不存在，即使 TTL <= 0 也返回 0
+    client.set("foo", "bar").await;
+    client.send_array(&["PEXPIRE", "foo", "20"]).await;
+    assert_eq!(client.read_simple_line().await, ":1\r\n");
+    tokio::time::sleep(std::time::Duration::from_millis(60)).await;
+    client.send_array(&["EXPIRE", "foo", "0"]).await;
+    assert_eq!(client.read_simple_line().await, ":0\r\n");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
 #[tokio::test]
 async fn standard_set_overwrite() {
     let (addr, shutdown, handle) = spawn_server().await;
@@ -157,6 +157,43 @@ async fn multi_exec_basic() {
     handle.await.unwrap().unwrap();
 }
 
+/// 测试 EXEC 中某条命令出错时，错误作为数组元素返回，其余命令继续执行
+#[tokio::test]
+async fn exec_reports_runtime_errors_inline_and_continues() {
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["MULTI"]).await;
+    assert_eq!(client.read_simple_line().await, "+OK\r\n");
+    for cmd in [
+        &["SET", "k", "v"][..],
+        &["INCR", "k"][..],
+        &["GET", "k"][..],
+        &["SET", "after", "1"][..],
+    ] {
+        client.send_array(cmd).await;
+        assert_eq!(client.read_simple_line().await, "+QUEUED\r\n");
+    }
+
+    client.send_array(&["EXEC"]).await;
+    assert_eq!(client.read_simple_line().await, "*4\r\n");
+    assert_eq!(client.read_simple_line().await, "+OK\r\n");
+    // INCR 在执行期才失败（k 不是整数），不会中止事务
+    assert_eq!(
+        client.read_simple_line().await,
+        "-ERR value is not an integer or out of range\r\n"
+    );
+    assert_eq!(client.read_bulk_string().await, Some("v".to_string()));
+    assert_eq!(client.read_simple_line().await, "+OK\r\n");
+
+    // 出错之后的命令确实已经执行
+    client.send_array(&["GET", "after"]).await;
+    assert_eq!(client.read_bulk_string().await, Some("1".to_string()));
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
 /// 测试 DISCARD 取消事务
 #[tokio::test]
 async fn multi_discard() {
@@ -611,3 +648,156 @@ async fn watch_detects_key_expiration() {
     shutdown.send(()).unwrap();
     handle.await.unwrap().unwrap();
 }
+
+/// 测试 RENAME 覆盖其他类型的目标 key、保留 TTL，并使 WATCH 源或目标的事务失败
+#[tokio::test]
+async fn rename_over_other_type_keeps_ttl_and_invalidates_watchers() {
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut watch_src = TestClient::connect(addr).await;
+    let mut watch_dst = TestClient::connect(addr).await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["SET", "rn:src", "v", "EX", "100"]).await;
+    assert_eq!(client.read_simple_line().await, "+OK\r\n");
+    client.send_array(&["RPUSH", "rn:dst", "a", "b"]).await;
+    assert_eq!(client.read_simple_line().await, ":2\r\n");
+
+    for (watcher, key) in [(&mut watch_src, "rn:src"), (&mut watch_dst, "rn:dst")] {
+        watcher.send_array(&["WATCH", key]).await;
+        assert_eq!(watcher.read_simple_line().await, "+OK\r\n");
+        watcher.send_array(&["MULTI"]).await;
+        assert_eq!(watcher.read_simple_line().await, "+OK\r\n");
+        watcher.send_array(&["SET", "rn:other", "x"]).await;
+        assert_eq!(watcher.read_simple_line().await, "+QUEUED\r\n");
+    }
+
+    client.send_array(&["RENAME", "rn:src", "rn:dst"]).await;
+    assert_eq!(client.read_simple_line().await, "+OK\r\n");
+
+    client.send_array(&["TYPE", "rn:dst"]).await;
+    assert_eq!(client.read_simple_line().await, "+string\r\n");
+    client.send_array(&["GET", "rn:dst"]).await;
+    assert_eq!(client.read_bulk_string().await, Some("v".to_string()));
+    client.send_array(&["TTL", "rn:dst"]).await;
+    let ttl = client.read_simple_line().await;
+    assert!(ttl == ":100\r\n" || ttl == ":99\r\n", "TTL should be preserved, got {:?}", ttl);
+    client.send_array(&["EXISTS", "rn:src"]).await;
+    assert_eq!(client.read_simple_line().await, ":0\r\n");
+
+    for watcher in [&mut watch_src, &mut watch_dst] {
+        watcher.send_array(&["EXEC"]).await;
+        assert_eq!(watcher.read_simple_line().await, "*-1\r\n");
+    }
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+/// 测试其他连接 GETDEL 被 WATCH 的 key 后 EXEC 失败
+#[tokio::test]
+async fn watch_detects_getdel_from_other_connection() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut client1 = TestClient::connect(addr).await;
+    let mut client2 = TestClient::connect(addr).await;
+
+    client1.send_array(&["SET", "gd", "v1"]).await;
+    let _ = client1.read_simple_line().await;
+
+    client1.send_array(&["WATCH", "gd"]).await;
+    assert_eq!(client1.read_simple_line().await, "+OK\r\n");
+
+    client1.send_array(&["MULTI"]).await;
+    let _ = client1.read_simple_line().await;
+    client1.send_array(&["SET", "gd", "v2"]).await;
+    assert_eq!(client1.read_simple_line().await, "+QUEUED\r\n");
+
+    // client2: GETDEL 删除 key
+    client2.send_array(&["GETDEL", "gd"]).await;
+    assert_eq!(client2.read_bulk_string().await, Some("v1".to_string()));
+
+    client1.send_array(&["EXEC"]).await;
+    let mut header = String::new();
+    client1.reader.read_line(&mut header).await.unwrap();
+    assert_eq!(header, "*-1\r\n", "EXEC should return null after GETDEL");
+
+    // 事务未执行，key 仍然不存在
+    client1.send_array(&["GET", "gd"]).await;
+    assert_eq!(client1.read_bulk_string().await, None);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn watch_is_invalidated_by_flushdb_and_flushall() {
+    let (addr, shutdown, handle) = spawn_server().await;
+
+    let mut client1 = TestClient::connect(addr).await;
+    let mut client2 = TestClient::connect(addr).await;
+
+    for flush in ["FLUSHDB", "FLUSHALL"] {
+        client1.send_array(&["SET", "fl", "v1"]).await;
+        assert_eq!(client1.read_simple_line().await, "+OK\r\n");
+
+        client1.send_array(&["WATCH", "fl"]).await;
+        assert_eq!(client1.read_simple_line().await, "+OK\r\n");
+        client1.send_array(&["MULTI"]).await;
+        assert_eq!(client1.read_simple_line().await, "+OK\r\n");
+        client1.send_array(&["SET", "fl", "v2"]).await;
+        assert_eq!(client1.read_simple_line().await, "+QUEUED\r\n");
+
+        client2.send_array(&[flush]).await;
+        assert_eq!(client2.read_simple_line().await, "+OK\r\n");
+
+        client1.send_array(&["EXEC"]).await;
+        let mut header = String::new();
+        client1.reader.read_line(&mut header).await.unwrap();
+        assert_eq!(header, "*-1\r\n", "EXEC should abort after {}", flush);
+
+        client1.send_array(&["GET", "fl"]).await;
+        assert_eq!(client1.read_bulk_string().await, None);
+    }
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+/// 测试 MULTI 中的 SUBSCRIBE 立即被拒绝，且不影响事务中其他命令
+#[tokio::test]
+async fn subscribe_inside_multi_is_rejected_without_aborting() {
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["MULTI"]).await;
+    let line = client.read_simple_line().await;
+    assert_eq!(line, "+OK\r\n");
+
+    client.send_array(&["SET", "sub_k", "v"]).await;
+    let line = client.read_simple_line().await;
+    assert_eq!(line, "+QUEUED\r\n");
+
+    client.send_array(&["SUBSCRIBE", "ch"]).await;
+    let line = client.read_simple_line().await;
+    assert_eq!(line, "-ERR SUBSCRIBE is not allowed in transactions\r\n");
+
+    client.send_array(&["PSUBSCRIBE", "ch.*"]).await;
+    let line = client.read_simple_line().await;
+    assert_eq!(line, "-ERR PSUBSCRIBE is not allowed in transactions\r\n");
+
+    // EXEC 仍然执行已入队的命令
+    client.send_array(&["EXEC"]).await;
+    let mut header = String::new();
+    client.reader.read_line(&mut header).await.unwrap();
+    assert_eq!(header, "*1\r\n");
+    let line = client.read_simple_line().await;
+    assert_eq!(line, "+OK\r\n");
+
+    // 连接没有进入订阅模式，普通命令仍可执行
+    client.send_array(&["GET", "sub_k"]).await;
+    let val = client.read_bulk_string().await;
+    assert_eq!(val, Some("v".to_string()));
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
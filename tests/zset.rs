@@ -182,6 +182,115 @@ async fn zincrby_updates_scores() {
     handle.await.unwrap().unwrap();
 }
 
+#[tokio::test]
+async fn zadd_nx_and_xx() {
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["ZADD", "zf", "1", "a"]).await;
+    assert_eq!(client.read_simple_line().await, ":1\r\n");
+
+    // NX：只新增 b，已存在的 a 分数不变
+    client.send_array(&["ZADD", "zf", "NX", "5", "a", "2", "b"]).await;
+    assert_eq!(client.read_simple_line().await, ":1\r\n");
+    client.send_array(&["ZSCORE", "zf", "a"]).await;
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("1"));
+
+    // XX：只更新 a，不新增 c
+    client.send_array(&["ZADD", "zf", "XX", "5", "a", "3", "c"]).await;
+    assert_eq!(client.read_simple_line().await, ":0\r\n");
+    client.send_array(&["ZSCORE", "zf", "a"]).await;
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("5"));
+    client.send_array(&["ZSCORE", "zf", "c"]).await;
+    assert_eq!(client.read_bulk_string().await, None);
+
+    // XX 不会创建 key
+    client.send_array(&["ZADD", "zf:missing", "XX", "1", "a"]).await;
+    assert_eq!(client.read_simple_line().await, ":0\r\n");
+    client.send_array(&["EXISTS", "zf:missing"]).await;
+    assert_eq!(client.read_simple_line().await, ":0\r\n");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn zadd_gt_lt_and_ch() {
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["ZADD", "zg", "5", "a", "5", "b"]).await;
+    assert_eq!(client.read_simple_line().await, ":2\r\n");
+
+    // GT：a 降分被忽略，b 升分生效，新成员 c 照常新增；CH 统计新增与变化
+    client
+        .send_array(&["ZADD", "zg", "GT", "CH", "1", "a", "7", "b", "2", "c"])
+        .await;
+    assert_eq!(client.read_simple_line().await, ":2\r\n");
+    client.send_array(&["ZRANGE", "zg", "0", "-1", "WITHSCORES"]).await;
+    assert_eq!(
+        client.read_array_of_bulk().await,
+        vec!["c", "2", "a", "5", "b", "7"]
+    );
+
+    // LT：只降分；不带 CH 时只返回新增数
+    client.send_array(&["ZADD", "zg", "LT", "9", "a", "6", "b"]).await;
+    assert_eq!(client.read_simple_line().await, ":0\r\n");
+    client.send_array(&["ZSCORE", "zg", "b"]).await;
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("6"));
+
+    // CH：分数不变的成员不计入
+    client.send_array(&["ZADD", "zg", "CH", "6", "b", "3", "c"]).await;
+    assert_eq!(client.read_simple_line().await, ":1\r\n");
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn zadd_incr_and_invalid_combinations() {
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    client.send_array(&["ZADD", "zi", "INCR", "2", "a"]).await;
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("2"));
+    client.send_array(&["ZADD", "zi", "INCR", "1.5", "a"]).await;
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("3.5"));
+
+    // 被 NX/XX/GT 跳过时返回 nil
+    client.send_array(&["ZADD", "zi", "NX", "INCR", "1", "a"]).await;
+    assert_eq!(client.read_bulk_string().await, None);
+    client.send_array(&["ZADD", "zi", "XX", "INCR", "1", "b"]).await;
+    assert_eq!(client.read_bulk_string().await, None);
+    client.send_array(&["ZADD", "zi", "GT", "INCR", "-1", "a"]).await;
+    assert_eq!(client.read_bulk_string().await, None);
+    client.send_array(&["ZSCORE", "zi", "a"]).await;
+    assert_eq!(client.read_bulk_string().await.as_deref(), Some("3.5"));
+
+    client.send_array(&["ZADD", "zi", "INCR", "1", "a", "1", "b"]).await;
+    assert_eq!(
+        client.read_simple_line().await,
+        "-ERR INCR option supports a single increment-element pair\r\n"
+    );
+    client.send_array(&["ZADD", "zi", "NX", "XX", "1", "a"]).await;
+    assert_eq!(
+        client.read_simple_line().await,
+        "-ERR XX and NX options at the same time are not compatible\r\n"
+    );
+    for combo in [["NX", "GT"], ["NX", "LT"], ["GT", "LT"]] {
+        client
+            .send_array(&["ZADD", "zi", combo[0], combo[1], "1", "a"])
+            .await;
+        assert_eq!(
+            client.read_simple_line().await,
+            "-ERR GT, LT, and/or NX options at the same time are not compatible\r\n"
+        );
+    }
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
 #[tokio::test]
 async fn zscan_roundtrip_and_wrongtype() {
     let (addr, shutdown, handle) = spawn_server().await;
@@ -243,6 +352,41 @@ async fn zscan_roundtrip_and_wrongtype() {
     handle.await.unwrap().unwrap();
 }
 
+#[tokio::test]
+async fn zadd_binary_member_roundtrip() {
+    let (addr, shutdown, handle) = spawn_server().await;
+    let mut client = TestClient::connect(addr).await;
+
+    // member 含 NUL 与非 UTF-8 字节，score 仍为 f64
+    let member: &[u8] = b"m\x00\xff\xfe";
+    let mut bulk = format!("${}\r\n", member.len()).into_bytes();
+    bulk.extend_from_slice(member);
+    bulk.extend_from_slice(b"\r\n");
+
+    let mut req = b"*4\r\n$4\r\nZADD\r\n$4\r\nbinz\r\n$3\r\n1.5\r\n".to_vec();
+    req.extend_from_slice(&bulk);
+    client.writer.write_all(&req).await.unwrap();
+    assert_eq!(client.read_simple_line().await, ":1\r\n");
+
+    let mut req = b"*3\r\n$6\r\nZSCORE\r\n$4\r\nbinz\r\n".to_vec();
+    req.extend_from_slice(&bulk);
+    client.writer.write_all(&req).await.unwrap();
+    assert_eq!(client.read_bulk_string().await, Some("1.5".to_string()));
+
+    client
+        .send_array(&["ZRANGE", "binz", "0", "-1", "WITHSCORES"])
+        .await;
+    let mut expected = b"*2\r\n".to_vec();
+    expected.extend_from_slice(&bulk);
+    expected.extend_from_slice(b"$3\r\n1.5\r\n");
+    let mut buf = vec![0u8; expected.len()];
+    client.reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, expected);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
 /// 测试 ZSET 持久化（RDB save/load）
 #[tokio::test]
 async fn zset_persistence_roundtrip() {
@@ -254,8 +398,8 @@ async fn zset_persistence_roundtrip() {
 
     // 创建 storage 并添加 ZSET 数据
     let storage = Storage::new(None);
-    assert!(storage.zadd("0:myzset", &[(1.5, "a".to_string()), (2.5, "b".to_string()), (3.0, "c".to_string())]).is_ok());
-    assert!(storage.zadd("0:anotherzset", &[(100.0, "x".to_string())]).is_ok());
+    assert!(storage.zadd("0:myzset", &[(1.5, b"a".to_vec()), (2.5, b"b".to_vec()), (3.0, b"c".to_vec())], Default::default()).is_ok());
+    assert!(storage.zadd("0:anotherzset", &[(100.0, b"x".to_vec())], Default::default()).is_ok());
 
     // 保存 RDB
     storage.save_rdb(&rdb_path).expect("save_rdb should succeed");
@@ -265,16 +409,16 @@ async fn zset_persistence_roundtrip() {
     storage2.load_rdb(&rdb_path).expect("load_rdb should succeed");
 
     // 验证 ZSET 数据已恢复
-    let score_a = storage2.zscore("0:myzset", "a").unwrap();
+    let score_a = storage2.zscore("0:myzset", b"a").unwrap();
     assert_eq!(score_a, Some(1.5), "score of 'a' should be 1.5");
 
-    let score_b = storage2.zscore("0:myzset", "b").unwrap();
+    let score_b = storage2.zscore("0:myzset", b"b").unwrap();
     assert_eq!(score_b, Some(2.5), "score of 'b' should be 2.5");
 
-    let score_c = storage2.zscore("0:myzset", "c").unwrap();
+    let score_c = storage2.zscore("0:myzset", b"c").unwrap();
     assert_eq!(score_c, Some(3.0), "score of 'c' should be 3.0");
 
-    let score_x = storage2.zscore("0:anotherzset", "x").unwrap();
+    let score_x = storage2.zscore("0:anotherzset", b"x").unwrap();
     assert_eq!(score_x, Some(100.0), "score of 'x' should be 100.0");
 
     // ZCARD 验证